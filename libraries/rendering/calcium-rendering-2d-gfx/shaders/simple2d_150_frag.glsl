@@ -14,13 +14,21 @@ out vec4 Target0;
 const uint MODE_COLOR = 0u;
 const uint MODE_TEXTURE = 1u;
 const uint MODE_MASK = 2u;
+const uint MODE_PREMULTIPLIED = 4u;
 
 void main() {
-    if (u_mode == MODE_COLOR) {
-        Target0 = f_color;
-    } else if (u_mode == MODE_TEXTURE) {
-        Target0 = texture(u_texture, f_uv).rgba * f_color;
-    } else if (u_mode == MODE_MASK) {
-        Target0 = vec4(f_color.rgb, texture(u_texture, f_uv).r) * f_color;
+    // Premultiplied alpha blending needs the vertex color to be premultiplied as well
+    vec4 color = f_color;
+    if ((u_mode & MODE_PREMULTIPLIED) != 0u) {
+        color.rgb *= color.a;
+    }
+    uint mode = u_mode & ~MODE_PREMULTIPLIED;
+
+    if (mode == MODE_COLOR) {
+        Target0 = color;
+    } else if (mode == MODE_TEXTURE) {
+        Target0 = texture(u_texture, f_uv).rgba * color;
+    } else if (mode == MODE_MASK) {
+        Target0 = vec4(color.rgb, texture(u_texture, f_uv).r) * color;
     }
 }
@@ -0,0 +1,22 @@
+#version 150 core
+
+uniform Transform {
+    mat4 u_transform;
+};
+
+in vec2 v_corner;
+
+in vec2 i_position;
+in vec2 i_size;
+in vec2 i_uv_min;
+in vec2 i_uv_max;
+in vec4 i_color;
+
+out vec2 f_uv;
+out vec4 f_color;
+
+void main() {
+    f_uv = mix(i_uv_min, i_uv_max, v_corner);
+    f_color = i_color;
+    gl_Position = u_transform * vec4(i_position + i_size * v_corner, 0.0, 1.0);
+}
@@ -1,6 +1,8 @@
 extern crate cgmath;
 #[macro_use]
 extern crate gfx;
+#[macro_use]
+extern crate slog;
 extern crate calcium_rendering;
 extern crate calcium_rendering_2d;
 extern crate calcium_rendering_gfx;
@@ -1,25 +1,36 @@
+use std::sync::{Arc};
+
 use gfx::{Device, Factory};
+use gfx::handle::{RenderTargetView};
 
-use calcium_rendering::{Renderer};
-use calcium_rendering_gfx::{GfxRendererRaw};
+use calcium_rendering::{Renderer, Error};
+use calcium_rendering::raw::{RawAccess};
+use calcium_rendering::texture::{Texture};
+use calcium_rendering_gfx::{GfxRendererRaw, ColorFormat};
 use calcium_rendering_2d::{Renderer2D};
 use calcium_rendering_2d::raw::{Renderer2DTargetRaw};
 
 use {GfxRenderer2DRaw};
 
-pub struct GfxRenderer2DTargetRaw {
+pub struct GfxRenderer2DTargetRaw<D: Device + 'static> {
     clear: bool,
+    color_view: Option<RenderTargetView<D::Resources, ColorFormat>>,
 }
 
-impl GfxRenderer2DTargetRaw {
+impl<D: Device + 'static> GfxRenderer2DTargetRaw<D> {
     pub fn is_clear(&self) -> bool {
         self.clear
     }
+
+    /// The view this target renders to, or None if it renders to the renderer's color view.
+    pub fn color_view(&self) -> Option<&RenderTargetView<D::Resources, ColorFormat>> {
+        self.color_view.as_ref()
+    }
 }
 
 impl<D: Device + 'static, F: Factory<D::Resources> + 'static>
     Renderer2DTargetRaw<GfxRendererRaw<D, F>, GfxRenderer2DRaw<D, F>>
-    for GfxRenderer2DTargetRaw
+    for GfxRenderer2DTargetRaw<D>
 {
     fn new(
         clear: bool,
@@ -28,6 +39,24 @@ impl<D: Device + 'static, F: Factory<D::Resources> + 'static>
     ) -> Self {
         GfxRenderer2DTargetRaw {
             clear,
+            color_view: None,
         }
     }
+
+    fn new_texture(
+        clear: bool,
+        texture: &Arc<Texture<GfxRendererRaw<D, F>>>,
+        _renderer: &Renderer<GfxRendererRaw<D, F>>,
+        _simple2d_renderer: &Renderer2D<GfxRendererRaw<D, F>, GfxRenderer2DRaw<D, F>>,
+    ) -> Result<Self, Error> {
+        let color_view = texture.raw().render_target_view.clone()
+            .ok_or_else(|| Error::Unsupported(
+                "Texture was not built as a render target".into()
+            ))?;
+
+        Ok(GfxRenderer2DTargetRaw {
+            clear,
+            color_view: Some(color_view),
+        })
+    }
 }
@@ -1,18 +1,28 @@
+use std::collections::{HashMap};
+use std::mem::{size_of};
 use std::sync::{Arc};
 
-use cgmath::{Vector2};
-use gfx::{self, Device, Factory, VertexBuffer, ConstantBuffer};
-use gfx::handle::{Sampler, Buffer};
+use cgmath::{Vector2, Point2};
+use gfx::{
+    self, Device, Factory, VertexBuffer, InstanceBuffer, ConstantBuffer, Primitive, Slice,
+    IndexBuffer,
+};
+use gfx::buffer::{Role};
+use gfx::handle::{Buffer, RenderTargetView};
+use gfx::memory::{Usage, Bind};
 use gfx::pso::{PipelineState};
 use gfx::pso::resource::{RawShaderResource};
-use gfx::traits::{FactoryExt};
-use gfx::texture::{SamplerInfo, FilterMethod, WrapMode};
+use gfx::state::{Rasterizer, Blend, BlendChannel, Equation, Factor, BlendValue};
+use gfx::traits::{FactoryExt, Pod};
 
 use calcium_rendering::raw::{RawAccess};
-use calcium_rendering::texture::{Texture, SampleMode};
+use calcium_rendering::texture::{Texture, PixelFormat};
 use calcium_rendering::{Error, Frame, Renderer};
 use calcium_rendering_gfx::{GfxRendererRaw, ColorFormat};
-use calcium_rendering_2d::render_data::{ShaderMode, RenderData, RenderSet};
+use calcium_rendering_2d::render_data::{
+    ShaderMode, RenderData, RenderSet, RenderBatch, BlendMode, Rectangle, Shader2D, Material,
+    QUAD_INDICES, BLEND_MODES,
+};
 use calcium_rendering_2d::raw::{Renderer2DRaw};
 use calcium_rendering_2d::{Renderer2DTarget};
 
@@ -25,6 +35,18 @@ gfx_defines!{
         color: [f32; 4] = "v_color",
     }
 
+    vertex QuadCorner {
+        corner: [f32; 2] = "v_corner",
+    }
+
+    vertex Instance {
+        position: [f32; 2] = "i_position",
+        size: [f32; 2] = "i_size",
+        uv_min: [f32; 2] = "i_uv_min",
+        uv_max: [f32; 2] = "i_uv_max",
+        color: [f32; 4] = "i_color",
+    }
+
     constant Transform {
         transform: [[f32; 4]; 4] = "u_transform",
     }
@@ -33,45 +55,139 @@ gfx_defines!{
         mode: u32 = "u_mode",
     }
 
+    constant Parameters {
+        parameter0: [f32; 4] = "u_parameter0",
+        parameter1: [f32; 4] = "u_parameter1",
+        parameter2: [f32; 4] = "u_parameter2",
+        parameter3: [f32; 4] = "u_parameter3",
+        parameter4: [f32; 4] = "u_parameter4",
+        parameter5: [f32; 4] = "u_parameter5",
+        parameter6: [f32; 4] = "u_parameter6",
+        parameter7: [f32; 4] = "u_parameter7",
+    }
+
     pipeline pipe {
         vbuf: VertexBuffer<Vertex> = (),
         transform: ConstantBuffer<Transform> = "Transform",
         mode: ConstantBuffer<Mode> = "Mode",
         texture: RawShaderResource = "u_texture",
         texture_sampler: ::gfx::pso::resource::Sampler = "u_texture",
+        scissor: gfx::Scissor = (),
+        out: gfx::BlendTarget<ColorFormat> = (
+            "Target0", gfx::state::MASK_ALL, gfx::preset::blend::ALPHA
+        ),
+    }
+
+    pipeline pipe_instanced {
+        vbuf: VertexBuffer<QuadCorner> = (),
+        instances: InstanceBuffer<Instance> = (),
+        transform: ConstantBuffer<Transform> = "Transform",
+        mode: ConstantBuffer<Mode> = "Mode",
+        texture: RawShaderResource = "u_texture",
+        texture_sampler: ::gfx::pso::resource::Sampler = "u_texture",
+        scissor: gfx::Scissor = (),
+        out: gfx::BlendTarget<ColorFormat> = (
+            "Target0", gfx::state::MASK_ALL, gfx::preset::blend::ALPHA
+        ),
+    }
+
+    pipeline pipe_custom {
+        vbuf: VertexBuffer<Vertex> = (),
+        transform: ConstantBuffer<Transform> = "Transform",
+        parameters: ConstantBuffer<Parameters> = "Parameters",
+        texture0: RawShaderResource = "u_texture0",
+        texture0_sampler: ::gfx::pso::resource::Sampler = "u_texture0",
+        texture1: RawShaderResource = "u_texture1",
+        texture1_sampler: ::gfx::pso::resource::Sampler = "u_texture1",
+        texture2: RawShaderResource = "u_texture2",
+        texture2_sampler: ::gfx::pso::resource::Sampler = "u_texture2",
+        texture3: RawShaderResource = "u_texture3",
+        texture3_sampler: ::gfx::pso::resource::Sampler = "u_texture3",
+        scissor: gfx::Scissor = (),
         out: gfx::BlendTarget<ColorFormat> = (
             "Target0", gfx::state::MASK_ALL, gfx::preset::blend::ALPHA
         ),
     }
 }
 
+/// Set on top of the shader mode ID, to premultiply the vertex color for premultiplied alpha.
+const MODE_PREMULTIPLIED: usize = 4;
+
+/// The corners of the quad all instances share, in the order used by `QUAD_INDICES`.
+const QUAD_CORNERS: [QuadCorner; 4] = [
+    QuadCorner { corner: [0.0, 0.0] },
+    QuadCorner { corner: [0.0, 1.0] },
+    QuadCorner { corner: [1.0, 0.0] },
+    QuadCorner { corner: [1.0, 1.0] },
+];
+
 pub struct GfxRenderer2DRaw<D: Device + 'static, F: Factory<D::Resources> + 'static> {
-    pso: PipelineState<D::Resources, pipe::Meta>,
+    /// A pipeline for every blend mode by `BlendMode::index`, blending is part of the pipeline
+    /// state in gfx.
+    psos: Vec<PipelineState<D::Resources, pipe::Meta>>,
+    instanced_psos: Vec<PipelineState<D::Resources, pipe_instanced::Meta>>,
+    /// The pipelines for every registered custom shader, by the shader's ID.
+    custom_psos: HashMap<usize, Vec<PipelineState<D::Resources, pipe_custom::Meta>>>,
+    quad_buffer: Buffer<D::Resources, QuadCorner>,
+    quad_slice: Slice<D::Resources>,
     dummy_texture: Arc<Texture<GfxRendererRaw<D, F>>>,
     mode_buffers: Vec<Buffer<D::Resources, Mode>>,
 
-    linear_sampler: Sampler<D::Resources>,
-    nearest_sampler: Sampler<D::Resources>,
+    /// Buffers re-used by every batch, rather than creating new ones for every draw.
+    vertex_buffer: ReusableBuffer<D, Vertex>,
+    index_buffer: ReusableBuffer<D, u32>,
+    instance_buffer: ReusableBuffer<D, Instance>,
 }
 
 impl<D: Device + 'static, F: Factory<D::Resources> + 'static> GfxRenderer2DRaw<D, F> {
     pub fn new(
         renderer: &mut Renderer<GfxRendererRaw<D, F>>
     ) -> Result<Self, Error> {
-        let pso = renderer.raw_mut().factory_mut().create_pipeline_simple(
-            include_bytes!("../shaders/simple2d_150_vert.glsl"),
-            include_bytes!("../shaders/simple2d_150_frag.glsl"),
-            pipe::new()
-        ).unwrap();
+        let (psos, instanced_psos, quad_buffer, quad_slice) = {
+            let factory = renderer.raw_mut().factory_mut();
+            let shader_set = factory.create_shader_set(
+                include_bytes!("../shaders/simple2d_150_vert.glsl"),
+                include_bytes!("../shaders/simple2d_150_frag.glsl"),
+            ).unwrap();
+            let instanced_shader_set = factory.create_shader_set(
+                include_bytes!("../shaders/simple2d_instanced_150_vert.glsl"),
+                include_bytes!("../shaders/simple2d_150_frag.glsl"),
+            ).unwrap();
+
+            let psos = BLEND_MODES.iter().map(|mode| {
+                let mut init = pipe::new();
+                init.out.2 = blend_for_mode(*mode);
+
+                factory.create_pipeline_state(
+                    &shader_set, Primitive::TriangleList, Rasterizer::new_fill(), init
+                ).unwrap()
+            }).collect();
+            let instanced_psos = BLEND_MODES.iter().map(|mode| {
+                let mut init = pipe_instanced::new();
+                init.out.2 = blend_for_mode(*mode);
+
+                factory.create_pipeline_state(
+                    &instanced_shader_set, Primitive::TriangleList, Rasterizer::new_fill(), init
+                ).unwrap()
+            }).collect();
+
+            // All instances are drawn using the same quad
+            let (quad_buffer, quad_slice) = factory.create_vertex_buffer_with_slice(
+                &QUAD_CORNERS, &QUAD_INDICES[..]
+            );
+
+            (psos, instanced_psos, quad_buffer, quad_slice)
+        };
 
         let dummy_texture = Texture::new()
-            .from_bytes(vec![255u8; 8*8], Vector2::new(8, 8), false)
+            .from_bytes(vec![255u8; 8*8], Vector2::new(8, 8), PixelFormat::R8)
             .as_single_channel()
             .build(renderer)?;
 
-        // Create pre-made buffers for the shader modes
+        // Create pre-made buffers for the shader modes, and the same modes with the vertex color
+        //  premultiplied
         let mut mode_buffers = Vec::new();
-        for i in 0..3 {
+        for i in 0..(MODE_PREMULTIPLIED as u32 * 2) {
             let mode = Mode {
                 mode: i
             };
@@ -80,41 +196,29 @@ impl<D: Device + 'static, F: Factory<D::Resources> + 'static> GfxRenderer2DRaw<D
             mode_buffers.push(mode_buffer);
         }
 
-        // Create the samplers for the two sample modes
-        let linear_sampler = renderer.raw_mut().factory_mut().create_sampler(SamplerInfo::new(
-            FilterMethod::Trilinear,
-            WrapMode::Clamp,
-        ));
-        let nearest_sampler = renderer.raw_mut().factory_mut().create_sampler(SamplerInfo::new(
-            FilterMethod::Scale,
-            WrapMode::Clamp,
-        ));
-
         Ok(GfxRenderer2DRaw {
-            pso,
+            psos,
+            instanced_psos,
+            custom_psos: HashMap::new(),
+            quad_buffer,
+            quad_slice,
             dummy_texture,
             mode_buffers,
-
-            linear_sampler,
-            nearest_sampler,
+            vertex_buffer: ReusableBuffer::new(Role::Vertex),
+            index_buffer: ReusableBuffer::new(Role::Index),
+            instance_buffer: ReusableBuffer::new(Role::Vertex),
         })
     }
 
-    fn sampler_for_mode(&self, sample_mode: SampleMode) -> &Sampler<D::Resources> {
-        match sample_mode {
-            SampleMode::Linear => &self.linear_sampler,
-            SampleMode::Nearest => &self.nearest_sampler,
-        }
-    }
-
     fn render_set(
         &mut self,
         set: &RenderSet<GfxRendererRaw<D, F>>,
-        frame: &mut Frame<GfxRendererRaw<D, F>>,
+        size: Vector2<u32>,
+        color_view: &RenderTargetView<D::Resources, ColorFormat>,
         renderer: &mut Renderer<GfxRendererRaw<D, F>>,
     ) {
         // Create a projection matrix that just matches coordinates to pixels
-        let proj = set.projection.to_matrix(frame.raw().size());
+        let proj = set.to_matrix(size);
         let transform = Transform {
             transform: proj.into()
         };
@@ -122,57 +226,214 @@ impl<D: Device + 'static, F: Factory<D::Resources> + 'static> GfxRenderer2DRaw<D
         renderer.raw_mut().encoder_mut().update_buffer(&transform_buffer, &[transform], 0).unwrap();
 
         // Go over all batches
-        for batch in &set.batches {
-            // Create a big mesh of all the rectangles we got told to draw this batch
-            let mut vertices = Vec::new();
-            for vertex in &batch.vertices {
-                vertices.push(Vertex {
-                    position: vertex.position.into(),
-                    uv: vertex.uv.into(),
-                    color: vertex.color.into(),
-                });
+        for batch in set.sorted_batches() {
+            // Limit drawing to the clip rectangle, GL's scissor starts at the bottom left
+            let scissor = set.scissor(batch, size).unwrap_or_else(|| Rectangle::new(
+                Point2::new(0, 0), Point2::new(size.x, size.y),
+            ));
+            let scissor_size = scissor.size();
+            if scissor_size.x == 0 || scissor_size.y == 0 {
+                continue
             }
+            let scissor = gfx::Rect {
+                x: scissor.min.x as u16,
+                y: (size.y - scissor.max.y) as u16,
+                w: scissor_size.x as u16,
+                h: scissor_size.y as u16,
+            };
 
-            // Create an actual VBO from it
-            let (vertex_buffer, slice) = renderer.raw_mut().factory_mut()
-                .create_vertex_buffer_with_slice(
-                    &vertices, ()
+            // Custom shaders have their own pipelines
+            if let ShaderMode::Custom(ref material) = batch.mode {
+                self.render_custom_batch(
+                    batch, material, &transform_buffer, scissor, color_view, renderer
                 );
+                continue
+            }
 
             // Get the mode ID this batch has and a texture to render
             // TODO: Figure out a way to avoid having to have a dummy texture
-            let (mode_id, texture, sampler) = match &batch.mode {
-                &ShaderMode::Color =>
-                    (0, &self.dummy_texture, &self.linear_sampler),
-                &ShaderMode::Texture(ref texture) =>
-                    (1, texture, self.sampler_for_mode(texture.raw().sample_mode)),
-                &ShaderMode::Mask(ref texture) =>
-                    (2, texture, self.sampler_for_mode(texture.raw().sample_mode)),
+            let (mode_id, texture) = match &batch.mode {
+                &ShaderMode::Color => (0, &self.dummy_texture),
+                &ShaderMode::Texture(ref texture) => (1, texture),
+                &ShaderMode::Mask(ref texture) => (2, texture),
+                &ShaderMode::Custom(_) => unreachable!(),
             };
 
             // Get the matching buffer for this shader mode
+            let mode_id = if batch.blend_mode == BlendMode::PremultipliedAlpha {
+                mode_id | MODE_PREMULTIPLIED
+            } else {
+                mode_id
+            };
             let mode_buffer = &self.mode_buffers[mode_id];
 
-            // Gather together all the data we need to render
-            let data = pipe::Data {
-                vbuf: vertex_buffer,
-                transform: transform_buffer.clone(),
-                mode: mode_buffer.clone(),
-                texture: texture.raw().view.raw().clone(),
-                texture_sampler: sampler.clone(),
-                out: renderer.raw().color_view().clone(),
-            };
+            if batch.vertices.len() != 0 {
+                // Create a big mesh of all the rectangles we got told to draw this batch
+                let mut vertices = Vec::new();
+                for vertex in &batch.vertices {
+                    vertices.push(Vertex {
+                        position: vertex.position.into(),
+                        uv: vertex.uv.into(),
+                        color: vertex.color.into(),
+                    });
+                }
+
+                // Upload it to the VBO, with an index buffer if the batch has indices, skipping
+                //  triangles with indices outside of the vertices
+                let vertex_buffer = self.vertex_buffer.upload(&vertices, renderer);
+                let indices = batch.valid_indices();
+                let slice = if let Some(ref indices) = indices {
+                    Slice {
+                        start: 0,
+                        end: indices.len() as u32,
+                        base_vertex: 0,
+                        instances: None,
+                        buffer: IndexBuffer::Index32(self.index_buffer.upload(indices, renderer)),
+                    }
+                } else {
+                    Slice {
+                        start: 0,
+                        end: vertices.len() as u32,
+                        base_vertex: 0,
+                        instances: None,
+                        buffer: IndexBuffer::Auto,
+                    }
+                };
+
+                // Gather together all the data we need to render
+                let data = pipe::Data {
+                    vbuf: vertex_buffer,
+                    transform: transform_buffer.clone(),
+                    mode: mode_buffer.clone(),
+                    texture: texture.raw().view.raw().clone(),
+                    texture_sampler: texture.raw().sampler.clone(),
+                    scissor,
+                    out: color_view.clone(),
+                };
+
+                // Add the draw to the encoder, using the pipeline for the batch's blend mode
+                let pso = &self.psos[batch.blend_mode.index()];
+                renderer.raw_mut().encoder_mut().draw(&slice, pso, &data);
+                renderer.stats_mut().draw_calls += 1;
+                renderer.stats_mut().bytes_uploaded +=
+                    vertices.len() * size_of::<Vertex>() +
+                    indices.map(|i| i.len() * size_of::<u32>()).unwrap_or(0);
+            }
+
+            if batch.instances.len() != 0 {
+                let instances: Vec<_> = batch.instances.iter().map(|instance| Instance {
+                    position: instance.position.into(),
+                    size: instance.size.into(),
+                    uv_min: instance.uv.min.into(),
+                    uv_max: instance.uv.max.into(),
+                    color: instance.color.into(),
+                }).collect();
+                let instance_buffer = self.instance_buffer.upload(&instances, renderer);
+
+                // Draw the shared quad once for every instance
+                let mut slice = self.quad_slice.clone();
+                slice.instances = Some((instances.len() as u32, 0));
 
-            // Finally, add the draw to the encoder
-            renderer.raw_mut().encoder_mut().draw(&slice, &self.pso, &data);
+                let data = pipe_instanced::Data {
+                    vbuf: self.quad_buffer.clone(),
+                    instances: instance_buffer,
+                    transform: transform_buffer.clone(),
+                    mode: mode_buffer.clone(),
+                    texture: texture.raw().view.raw().clone(),
+                    texture_sampler: texture.raw().sampler.clone(),
+                    scissor,
+                    out: color_view.clone(),
+                };
+
+                let pso = &self.instanced_psos[batch.blend_mode.index()];
+                renderer.raw_mut().encoder_mut().draw(&slice, pso, &data);
+                renderer.stats_mut().draw_calls += 1;
+                renderer.stats_mut().bytes_uploaded += instances.len() * size_of::<Instance>();
+            }
         }
     }
+
+    fn render_custom_batch(
+        &mut self,
+        batch: &RenderBatch<GfxRendererRaw<D, F>>,
+        material: &Material<GfxRendererRaw<D, F>>,
+        transform_buffer: &Buffer<D::Resources, Transform>,
+        scissor: gfx::Rect,
+        color_view: &RenderTargetView<D::Resources, ColorFormat>,
+        renderer: &mut Renderer<GfxRendererRaw<D, F>>,
+    ) {
+        // Shaders that haven't been registered don't have pipelines to draw with
+        let psos = match self.custom_psos.get(&material.shader.id()) {
+            Some(psos) => psos,
+            None => {
+                warn!(
+                    renderer.log(), "Skipping batch, custom shader isn't registered";
+                    "shader" => &material.shader.name
+                );
+                return
+            },
+        };
+
+        // Custom shaders don't have an instanced version, so instances are expanded
+        let vertices: Vec<_> = batch.triangle_vertices().iter().map(|vertex| Vertex {
+            position: vertex.position.into(),
+            uv: vertex.uv.into(),
+            color: vertex.color.into(),
+        }).collect();
+        if vertices.len() == 0 {
+            return
+        }
+
+        let p = &material.parameters;
+        let parameters = Parameters {
+            parameter0: p[0].into(), parameter1: p[1].into(),
+            parameter2: p[2].into(), parameter3: p[3].into(),
+            parameter4: p[4].into(), parameter5: p[5].into(),
+            parameter6: p[6].into(), parameter7: p[7].into(),
+        };
+
+        let vertex_buffer = self.vertex_buffer.upload(&vertices, renderer);
+        let slice = Slice {
+            start: 0,
+            end: vertices.len() as u32,
+            base_vertex: 0,
+            instances: None,
+            buffer: IndexBuffer::Auto,
+        };
+        let parameters_buffer = renderer.raw_mut().factory_mut().create_constant_buffer(1);
+        renderer.raw_mut().encoder_mut()
+            .update_buffer(&parameters_buffer, &[parameters], 0).unwrap();
+
+        // Textures the material doesn't set still need something bound
+        let texture = |i: usize| material.textures.get(i).unwrap_or(&self.dummy_texture).raw();
+        let data = pipe_custom::Data {
+            vbuf: vertex_buffer,
+            transform: transform_buffer.clone(),
+            parameters: parameters_buffer,
+            texture0: texture(0).view.raw().clone(),
+            texture0_sampler: texture(0).sampler.clone(),
+            texture1: texture(1).view.raw().clone(),
+            texture1_sampler: texture(1).sampler.clone(),
+            texture2: texture(2).view.raw().clone(),
+            texture2_sampler: texture(2).sampler.clone(),
+            texture3: texture(3).view.raw().clone(),
+            texture3_sampler: texture(3).sampler.clone(),
+            scissor,
+            out: color_view.clone(),
+        };
+
+        let pso = &psos[batch.blend_mode.index()];
+        renderer.raw_mut().encoder_mut().draw(&slice, pso, &data);
+        renderer.stats_mut().draw_calls += 1;
+        renderer.stats_mut().bytes_uploaded +=
+            vertices.len() * size_of::<Vertex>() + size_of::<Parameters>();
+    }
 }
 
 impl<D: Device + 'static, F: Factory<D::Resources> + 'static>
     Renderer2DRaw<GfxRendererRaw<D, F>> for GfxRenderer2DRaw<D, F>
 {
-    type RenderTargetRaw = GfxRenderer2DTargetRaw;
+    type RenderTargetRaw = GfxRenderer2DTargetRaw<D>;
 
     fn render(
         &mut self,
@@ -181,14 +442,119 @@ impl<D: Device + 'static, F: Factory<D::Resources> + 'static>
         render_target: &mut Renderer2DTarget<GfxRendererRaw<D, F>, Self>,
         renderer: &mut Renderer<GfxRendererRaw<D, F>>,
     ) {
+        // Find out what we're rendering to, the window or a texture
+        let size = render_target.size(frame.raw().size());
+        let color_view = render_target.raw.color_view().cloned()
+            .unwrap_or_else(|| renderer.raw().color_view().clone());
+
         // Clear if we were told to clear
         if render_target.raw.is_clear() {
-            let color_view = renderer.raw().color_view().clone();
             renderer.raw_mut().encoder_mut().clear(&color_view, [0.0, 0.0, 0.0, 1.0]);
         }
 
-        for set in &data.render_sets {
-            self.render_set(set, frame, renderer);
+        for set in data.sorted_sets() {
+            self.render_set(set, size, &color_view, renderer);
         }
     }
+
+    fn register_shader(
+        &mut self, shader: &Arc<Shader2D>, renderer: &mut Renderer<GfxRendererRaw<D, F>>,
+    ) -> Result<(), Error> {
+        let glsl = shader.glsl_150.as_ref().ok_or_else(|| Error::Unsupported(format!(
+            "Shader \"{}\" has no GLSL 150 version, which the gfx backend needs", shader.name
+        )))?;
+        let vertex = glsl.vertex.as_ref()
+            .map(|v| v.as_bytes())
+            .unwrap_or(&include_bytes!("../shaders/simple2d_150_vert.glsl")[..]);
+
+        let factory = renderer.raw_mut().factory_mut();
+        let shader_set = factory.create_shader_set(vertex, glsl.fragment.as_bytes())
+            .map_err(|e| Error::Platform(format!(
+                "Failed to compile shader \"{}\": {:?}", shader.name, e
+            )))?;
+
+        let mut psos = Vec::new();
+        for mode in &BLEND_MODES {
+            let mut init = pipe_custom::new();
+            init.out.2 = blend_for_mode(*mode);
+
+            let pso = factory.create_pipeline_state(
+                &shader_set, Primitive::TriangleList, Rasterizer::new_fill(), init
+            ).map_err(|e| Error::Platform(format!(
+                "Failed to create pipeline for shader \"{}\": {:?}", shader.name, e
+            )))?;
+            psos.push(pso);
+        }
+
+        self.custom_psos.insert(shader.id(), psos);
+        Ok(())
+    }
+}
+
+/// A buffer that's re-used for every draw instead of creating a new one, it's only re-created
+/// when it's too small for the data.
+struct ReusableBuffer<D: Device, T> {
+    buffer: Option<Buffer<D::Resources, T>>,
+    capacity: usize,
+    role: Role,
+}
+
+impl<D: Device + 'static, T: Pod> ReusableBuffer<D, T> {
+    fn new(role: Role) -> Self {
+        ReusableBuffer {
+            buffer: None,
+            capacity: 0,
+            role,
+        }
+    }
+
+    /// Uploads data to the start of the buffer, growing it if needed, and returns the buffer.
+    fn upload<F: Factory<D::Resources> + 'static>(
+        &mut self, data: &[T], renderer: &mut Renderer<GfxRendererRaw<D, F>>,
+    ) -> Buffer<D::Resources, T> {
+        if self.buffer.is_none() || self.capacity < data.len() {
+            // Grow to a power of two, so a slowly growing batch doesn't re-create it every frame
+            let capacity = data.len().next_power_of_two();
+            let buffer = renderer.raw_mut().factory_mut()
+                .create_buffer(capacity, self.role, Usage::Dynamic, Bind::empty())
+                .unwrap();
+            self.buffer = Some(buffer);
+            self.capacity = capacity;
+        }
+
+        let buffer = self.buffer.as_ref().unwrap();
+        renderer.raw_mut().encoder_mut().update_buffer(buffer, data, 0).unwrap();
+        buffer.clone()
+    }
+}
+
+fn blend_for_mode(mode: BlendMode) -> Blend {
+    let source_alpha = BlendValue::SourceAlpha;
+    let (color, alpha) = match mode {
+        BlendMode::Alpha => (
+            (Factor::ZeroPlus(source_alpha), Factor::OneMinus(source_alpha)),
+            (Factor::One, Factor::OneMinus(source_alpha)),
+        ),
+        BlendMode::PremultipliedAlpha => (
+            (Factor::One, Factor::OneMinus(source_alpha)),
+            (Factor::One, Factor::OneMinus(source_alpha)),
+        ),
+        BlendMode::Additive => (
+            (Factor::ZeroPlus(source_alpha), Factor::One),
+            (Factor::Zero, Factor::One),
+        ),
+        BlendMode::Multiply => (
+            (Factor::ZeroPlus(BlendValue::DestColor), Factor::Zero),
+            (Factor::Zero, Factor::One),
+        ),
+        BlendMode::Opaque => (
+            (Factor::One, Factor::Zero),
+            (Factor::One, Factor::Zero),
+        ),
+    };
+
+    Blend {
+        color: BlendChannel { equation: Equation::Add, source: color.0, destination: color.1 },
+        alpha: BlendChannel { equation: Equation::Add, source: alpha.0, destination: alpha.1 },
+    }
 }
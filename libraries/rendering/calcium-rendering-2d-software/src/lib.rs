@@ -0,0 +1,13 @@
+extern crate cgmath;
+#[macro_use]
+extern crate slog;
+extern crate calcium_rendering;
+extern crate calcium_rendering_2d;
+extern crate calcium_rendering_software;
+
+mod rasterizer;
+mod render_target;
+mod renderer;
+
+pub use render_target::{SoftwareRenderer2DTargetRaw};
+pub use renderer::{SoftwareRenderer2DRaw};
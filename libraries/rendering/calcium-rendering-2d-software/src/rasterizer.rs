@@ -0,0 +1,90 @@
+use cgmath::{Vector2, Vector4, Point2};
+
+/// A vertex that has already been transformed into target pixel coordinates.
+#[derive(Clone, Debug)]
+pub struct RasterVertex {
+    pub position: Point2<f32>,
+    pub uv: Point2<f32>,
+    pub color: Vector4<f32>,
+}
+
+/// Rasterizes a single triangle, calling the fragment function for every pixel covered by it with
+/// the interpolated UV and color. Pixels are sampled at their centers, with a top-left fill rule
+/// so triangles sharing an edge never both cover the same pixel.
+pub fn rasterize_triangle<F: FnMut(Vector2<u32>, Point2<f32>, Vector4<f32>)>(
+    vertices: [&RasterVertex; 3], target_size: Vector2<u32>, mut fragment: F,
+) {
+    let (v0, mut v1, mut v2) = (vertices[0], vertices[1], vertices[2]);
+
+    // Make sure the triangle is always wound the same way, we don't do any culling
+    let mut area = edge_function(v0.position, v1.position, v2.position);
+    if area == 0.0 {
+        return
+    }
+    if area < 0.0 {
+        ::std::mem::swap(&mut v1, &mut v2);
+        area = -area;
+    }
+
+    // Find the pixels we need to check, limited to the target
+    let min_x = v0.position.x.min(v1.position.x).min(v2.position.x).floor().max(0.0) as u32;
+    let min_y = v0.position.y.min(v1.position.y).min(v2.position.y).floor().max(0.0) as u32;
+    let max_x = (v0.position.x.max(v1.position.x).max(v2.position.x).ceil() as i64)
+        .min(target_size.x as i64);
+    let max_y = (v0.position.y.max(v1.position.y).max(v2.position.y).ceil() as i64)
+        .min(target_size.y as i64);
+
+    let top_left = [
+        is_top_left(v1.position, v2.position),
+        is_top_left(v2.position, v0.position),
+        is_top_left(v0.position, v1.position),
+    ];
+
+    for y in min_y as i64..max_y {
+        for x in min_x as i64..max_x {
+            let center = Point2::new(x as f32 + 0.5, y as f32 + 0.5);
+
+            let weights = [
+                edge_function(v1.position, v2.position, center),
+                edge_function(v2.position, v0.position, center),
+                edge_function(v0.position, v1.position, center),
+            ];
+
+            // Check if this pixel's center is inside of the triangle
+            let inside = weights.iter().zip(top_left.iter())
+                .all(|(w, tl)| *w > 0.0 || (*w == 0.0 && *tl));
+            if !inside {
+                continue
+            }
+
+            // Interpolate the vertex values using the barycentric coordinates
+            let (b0, b1, b2) = (weights[0] / area, weights[1] / area, weights[2] / area);
+            let uv = Point2::new(
+                v0.uv.x * b0 + v1.uv.x * b1 + v2.uv.x * b2,
+                v0.uv.y * b0 + v1.uv.y * b1 + v2.uv.y * b2,
+            );
+            let color = v0.color * b0 + v1.color * b1 + v2.color * b2;
+
+            fragment(Vector2::new(x as u32, y as u32), uv, color);
+        }
+    }
+}
+
+/// How much of the UV space a single pixel of the triangle covers. The 2D renderer has no
+/// perspective, so this is the same everywhere in the triangle, and is used to pick mipmap levels.
+pub fn uv_area_per_pixel(vertices: [&RasterVertex; 3]) -> f32 {
+    let area = edge_function(vertices[0].position, vertices[1].position, vertices[2].position);
+    let uv_area = edge_function(vertices[0].uv, vertices[1].uv, vertices[2].uv);
+    (uv_area / area).abs()
+}
+
+fn edge_function(a: Point2<f32>, b: Point2<f32>, c: Point2<f32>) -> f32 {
+    (c.x - a.x) * (b.y - a.y) - (c.y - a.y) * (b.x - a.x)
+}
+
+/// Checks if an edge of a consistently wound triangle is a top or a left edge, in target pixel
+/// coordinates with Y pointing down.
+fn is_top_left(a: Point2<f32>, b: Point2<f32>) -> bool {
+    let edge = b - a;
+    (edge.y == 0.0 && edge.x < 0.0) || edge.y > 0.0
+}
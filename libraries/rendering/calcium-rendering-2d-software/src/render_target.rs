@@ -0,0 +1,57 @@
+use std::sync::{Arc};
+
+use calcium_rendering::{Renderer, Error};
+use calcium_rendering::raw::{RawAccess};
+use calcium_rendering::texture::{Texture};
+use calcium_rendering_2d::{Renderer2D};
+use calcium_rendering_2d::raw::{Renderer2DTargetRaw};
+use calcium_rendering_software::{SoftwareRendererRaw};
+
+use {SoftwareRenderer2DRaw};
+
+pub struct SoftwareRenderer2DTargetRaw {
+    clear: bool,
+    texture: Option<Arc<Texture<SoftwareRendererRaw>>>,
+}
+
+impl SoftwareRenderer2DTargetRaw {
+    pub fn is_clear(&self) -> bool {
+        self.clear
+    }
+
+    /// The texture this target renders to, or None if it renders to the framebuffer.
+    pub fn texture(&self) -> Option<&Arc<Texture<SoftwareRendererRaw>>> {
+        self.texture.as_ref()
+    }
+}
+
+impl Renderer2DTargetRaw<SoftwareRendererRaw, SoftwareRenderer2DRaw>
+    for SoftwareRenderer2DTargetRaw
+{
+    fn new(
+        clear: bool,
+        _renderer: &Renderer<SoftwareRendererRaw>,
+        _simple2d_renderer: &Renderer2D<SoftwareRendererRaw, SoftwareRenderer2DRaw>,
+    ) -> Self {
+        SoftwareRenderer2DTargetRaw {
+            clear,
+            texture: None,
+        }
+    }
+
+    fn new_texture(
+        clear: bool,
+        texture: &Arc<Texture<SoftwareRendererRaw>>,
+        _renderer: &Renderer<SoftwareRendererRaw>,
+        _simple2d_renderer: &Renderer2D<SoftwareRendererRaw, SoftwareRenderer2DRaw>,
+    ) -> Result<Self, Error> {
+        if !texture.raw().is_render_target() {
+            return Err(Error::Unsupported("Texture was not built as a render target".into()))
+        }
+
+        Ok(SoftwareRenderer2DTargetRaw {
+            clear,
+            texture: Some(texture.clone()),
+        })
+    }
+}
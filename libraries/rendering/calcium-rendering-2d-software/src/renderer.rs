@@ -0,0 +1,233 @@
+use std::sync::{Arc};
+
+use cgmath::{Vector4, Point2, Matrix4};
+use slog::{Logger};
+
+use calcium_rendering::raw::{RawAccess};
+use calcium_rendering::{Renderer, Error, Frame};
+use calcium_rendering::texture::{Texture};
+use calcium_rendering_2d::render_data::{
+    RenderBatch, ShaderMode, RenderData, RenderSet, DrawVertex, BlendMode, Shader2D,
+    CpuShaderInput, Rectangle, clip_triangles, project_point,
+};
+use calcium_rendering_2d::raw::{Renderer2DRaw};
+use calcium_rendering_2d::{Renderer2DTarget};
+use calcium_rendering_software::{SoftwareRendererRaw, PixelBuffer};
+
+use rasterizer::{self, RasterVertex};
+use {SoftwareRenderer2DTargetRaw};
+
+pub struct SoftwareRenderer2DRaw {
+    log: Logger,
+}
+
+impl SoftwareRenderer2DRaw {
+    pub fn new(renderer: &mut Renderer<SoftwareRendererRaw>) -> Result<Self, Error> {
+        info!(renderer.log(), "Creating simple2d renderer");
+        Ok(SoftwareRenderer2DRaw {
+            log: renderer.log().clone(),
+        })
+    }
+
+    fn render_set(
+        &mut self,
+        set: &RenderSet<SoftwareRendererRaw>,
+        target: &mut PixelBuffer,
+    ) {
+        let transform = set.to_matrix(target.size());
+
+        for batch in set.sorted_batches() {
+            let scissor = set.scissor(batch, target.size());
+            render_batch(batch, transform, scissor, target, &self.log);
+        }
+    }
+
+    fn render_to(
+        &mut self, data: &RenderData<SoftwareRendererRaw>, clear: bool, target: &mut PixelBuffer,
+    ) {
+        // Clear if we were told to clear
+        if clear {
+            target.clear(Vector4::new(0.0, 0.0, 0.0, 1.0));
+        }
+
+        for set in data.sorted_sets() {
+            self.render_set(set, target);
+        }
+    }
+}
+
+impl Renderer2DRaw<SoftwareRendererRaw> for SoftwareRenderer2DRaw {
+    type RenderTargetRaw = SoftwareRenderer2DTargetRaw;
+
+    fn render(
+        &mut self,
+        data: &RenderData<SoftwareRendererRaw>,
+        _frame: &mut Frame<SoftwareRendererRaw>,
+        render_target: &mut Renderer2DTarget<SoftwareRendererRaw, Self>,
+        renderer: &mut Renderer<SoftwareRendererRaw>,
+    ) {
+        let clear = render_target.raw.is_clear();
+        if let Some(texture) = render_target.raw.texture() {
+            self.render_to(data, clear, &mut texture.raw().pixels_mut());
+        } else {
+            self.render_to(data, clear, renderer.raw_mut().framebuffer_mut());
+        }
+
+        // Every batch is rasterized in one go, which is as close to a draw call as we get
+        let batches: usize = data.render_sets.iter().map(|s| s.batches.len()).sum();
+        renderer.stats_mut().draw_calls += batches;
+    }
+
+    fn register_shader(
+        &mut self, shader: &Arc<Shader2D>, _renderer: &mut Renderer<SoftwareRendererRaw>,
+    ) -> Result<(), Error> {
+        // Custom shaders are just called directly, as long as there is a CPU version
+        if shader.cpu.is_none() {
+            return Err(Error::Unsupported(format!(
+                "Shader \"{}\" has no CPU version, which the software backend needs",
+                shader.name
+            )))
+        }
+
+        Ok(())
+    }
+}
+
+fn render_batch(
+    batch: &RenderBatch<SoftwareRendererRaw>, transform: Matrix4<f32>,
+    scissor: Option<Rectangle<u32>>, target: &mut PixelBuffer, log: &Logger,
+) {
+    // Only shaders with a CPU version can be registered, but nothing stops a material from using
+    //  one that never was
+    if let ShaderMode::Custom(ref material) = batch.mode {
+        if material.shader.cpu.is_none() {
+            warn!(
+                log, "Skipping batch, custom shader has no CPU version";
+                "shader" => &material.shader.name
+            );
+            return
+        }
+    }
+
+    let target_size = target.size();
+
+    // Transform all vertices to pixel coordinates on the target, there's no benefit to indices
+    //  or instancing on the CPU so those are just expanded
+    let mut vertices: Vec<DrawVertex> = batch.triangle_vertices().iter()
+        .map(|v| DrawVertex::new(project_point(transform, v.position, target_size), v.uv, v.color))
+        .collect();
+
+    // We can't scissor, so instead cut the triangles off at the clip rectangle
+    if let Some(scissor) = scissor {
+        if scissor.min.x == scissor.max.x || scissor.min.y == scissor.max.y {
+            return
+        }
+
+        let scissor = Rectangle::new(scissor.min.cast(), scissor.max.cast());
+        vertices = clip_triangles(&vertices, &scissor);
+    }
+
+    // The built-in shader modes premultiply the vertex color for premultiplied alpha blending,
+    //  custom shaders have to do this themselves
+    let premultiply = match batch.mode {
+        ShaderMode::Custom(_) => false,
+        _ => batch.blend_mode == BlendMode::PremultipliedAlpha,
+    };
+
+    for triangle in vertices.chunks(3) {
+        if triangle.len() != 3 {
+            break
+        }
+
+        let triangle = [
+            to_raster_vertex(&triangle[0]),
+            to_raster_vertex(&triangle[1]),
+            to_raster_vertex(&triangle[2]),
+        ];
+        let triangle = [&triangle[0], &triangle[1], &triangle[2]];
+        let uv_area = rasterizer::uv_area_per_pixel(triangle);
+        rasterizer::rasterize_triangle(
+            triangle, target_size,
+            |position, uv, color| {
+                let color = if premultiply {
+                    Vector4::new(color.x * color.w, color.y * color.w, color.z * color.w, color.w)
+                } else {
+                    color
+                };
+                let source = shade(&batch.mode, uv, uv_area, color);
+                let destination = target.pixel(position);
+                target.set_pixel(position, blend(batch.blend_mode, source, destination));
+            }
+        );
+    }
+}
+
+fn to_raster_vertex(vertex: &DrawVertex) -> RasterVertex {
+    RasterVertex {
+        position: vertex.position,
+        uv: vertex.uv,
+        color: vertex.color,
+    }
+}
+
+/// The CPU equivalent of the simple2d fragment shader.
+fn shade(
+    mode: &ShaderMode<SoftwareRendererRaw>, uv: Point2<f32>, uv_area: f32, color: Vector4<f32>,
+) -> Vector4<f32> {
+    match *mode {
+        ShaderMode::Color => color,
+        ShaderMode::Texture(ref texture) => {
+            let sample = sample(texture, uv, uv_area);
+            mul_element_wise(sample, color)
+        },
+        ShaderMode::Mask(ref texture) => {
+            let sample = sample(texture, uv, uv_area);
+            mul_element_wise(Vector4::new(color.x, color.y, color.z, sample.x), color)
+        },
+        ShaderMode::Custom(ref material) => {
+            // Batches with shaders without a CPU version are skipped before getting here
+            let sampler = |index: usize, uv: Point2<f32>| material.textures.get(index)
+                .map(|texture| sample(texture, uv, uv_area))
+                .unwrap_or(Vector4::new(0.0, 0.0, 0.0, 0.0));
+            let input = CpuShaderInput::new(uv, color, &material.parameters, &sampler);
+            material.shader.cpu.as_ref()
+                .map(|shader| shader(&input))
+                .unwrap_or(Vector4::new(0.0, 0.0, 0.0, 0.0))
+        },
+    }
+}
+
+fn sample(
+    texture: &Texture<SoftwareRendererRaw>, uv: Point2<f32>, uv_area: f32,
+) -> Vector4<f32> {
+    // Sampling the texture that's currently being rendered to is undefined on the GPU, here it
+    //  just gives transparent black instead of deadlocking
+    texture.raw().sample(uv, uv_area)
+        .unwrap_or(Vector4::new(0.0, 0.0, 0.0, 0.0))
+}
+
+/// Blends a color onto the target, done in linear color space just like an sRGB framebuffer
+/// would.
+fn blend(mode: BlendMode, source: Vector4<f32>, destination: Vector4<f32>) -> Vector4<f32> {
+    let alpha = source.w.max(0.0).min(1.0);
+    let over_alpha = alpha + destination.w * (1.0 - alpha);
+
+    let color = match mode {
+        BlendMode::Alpha => source * alpha + destination * (1.0 - alpha),
+        BlendMode::PremultipliedAlpha => source + destination * (1.0 - alpha),
+        BlendMode::Additive => source * alpha + destination,
+        BlendMode::Multiply => mul_element_wise(source, destination),
+        BlendMode::Opaque => source,
+    };
+    let alpha = match mode {
+        BlendMode::Alpha | BlendMode::PremultipliedAlpha => over_alpha,
+        BlendMode::Additive | BlendMode::Multiply => destination.w,
+        BlendMode::Opaque => source.w,
+    };
+
+    Vector4::new(color.x, color.y, color.z, alpha)
+}
+
+fn mul_element_wise(a: Vector4<f32>, b: Vector4<f32>) -> Vector4<f32> {
+    Vector4::new(a.x * b.x, a.y * b.y, a.z * b.z, a.w * b.w)
+}
@@ -0,0 +1,476 @@
+//! Renders simple render data on the software backend and compares the result against reference
+//! images in `tests/golden`. To regenerate the reference images after an intended change in
+//! output, run the tests with `UPDATE_GOLDEN=1` and check the new images by hand.
+
+extern crate cgmath;
+extern crate image;
+#[macro_use]
+extern crate slog;
+extern crate calcium_rendering;
+extern crate calcium_rendering_2d;
+extern crate calcium_rendering_software;
+extern crate calcium_rendering_2d_software;
+
+use std::env;
+use std::path::{PathBuf};
+use std::sync::{Arc};
+
+use cgmath::{Vector2, Vector4, Point2};
+use image::{RgbaImage};
+use slog::{Logger, Discard};
+
+use calcium_rendering::{Renderer};
+use calcium_rendering::raw::{RawAccess};
+use calcium_rendering::texture::{Texture, PixelFormat, SampleMode};
+use calcium_rendering_2d::{Renderer2D, Renderer2DTarget};
+use calcium_rendering_2d::render_data::{
+    RenderData, RenderSet, RenderBatch, ShaderMode, UvMode, BlendMode, Projection, Camera,
+    ClipRect, Rectangle, Shader2D, Material, MATERIAL_PARAMETERS,
+};
+use calcium_rendering_software::{SoftwareRendererRaw};
+use calcium_rendering_2d_software::{SoftwareRenderer2DRaw};
+
+type SoftwareRenderer2D = Renderer2D<SoftwareRendererRaw, SoftwareRenderer2DRaw>;
+
+fn create_renderer(
+    size: Vector2<u32>,
+) -> (Renderer<SoftwareRendererRaw>, SoftwareRenderer2D) {
+    let log = Logger::root(Discard, o!());
+    let mut renderer = Renderer::raw_new(SoftwareRendererRaw::new(&log, size), log.clone());
+    let simple2d_renderer = Renderer2D::raw_new(SoftwareRenderer2DRaw::new(&mut renderer).unwrap());
+    (renderer, simple2d_renderer)
+}
+
+fn render(
+    data: &RenderData<SoftwareRendererRaw>,
+    renderer: &mut Renderer<SoftwareRendererRaw>, simple2d_renderer: &mut SoftwareRenderer2D,
+) -> RgbaImage {
+    let mut target = Renderer2DTarget::new(true, renderer, simple2d_renderer);
+    let mut frame = renderer.start_frame();
+    simple2d_renderer.render(data, &mut frame, &mut target, renderer);
+    renderer.finish_frame(frame);
+
+    let framebuffer = renderer.raw().framebuffer();
+    let size = framebuffer.size();
+    RgbaImage::from_raw(size.x, size.y, framebuffer.to_srgba8()).unwrap()
+}
+
+fn full_uv() -> Rectangle<f32> {
+    Rectangle::new(Point2::new(0.0, 0.0), Point2::new(1.0, 1.0))
+}
+
+fn rect(min_x: f32, min_y: f32, max_x: f32, max_y: f32) -> Rectangle<f32> {
+    Rectangle::new(Point2::new(min_x, min_y), Point2::new(max_x, max_y))
+}
+
+fn assert_pixel(image: &RgbaImage, x: u32, y: u32, expected: [u8; 4]) {
+    let actual = image.get_pixel(x, y).data;
+    assert_eq!(actual, expected, "Pixel at {}, {}", x, y);
+}
+
+/// Compares an image against its reference image, allowing a difference of 1 per channel for
+/// rounding.
+fn assert_golden(name: &str, image: &RgbaImage) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests").join("golden").join(format!("{}.png", name));
+
+    if env::var("UPDATE_GOLDEN").is_ok() {
+        image.save(&path).unwrap();
+        return
+    }
+
+    let expected = image::open(&path)
+        .unwrap_or_else(|e| panic!(
+            "Can't open reference image {}, run with UPDATE_GOLDEN=1 to create it: {}",
+            path.display(), e
+        ))
+        .to_rgba();
+    assert_eq!(expected.dimensions(), image.dimensions(), "Size of {}", name);
+
+    for (x, y, pixel) in image.enumerate_pixels() {
+        let expected_pixel = expected.get_pixel(x, y);
+        let matches = pixel.data.iter().zip(expected_pixel.data.iter())
+            .all(|(a, b)| (*a as i32 - *b as i32).abs() <= 1);
+        assert!(
+            matches, "Pixel at {}, {} in {} is {:?}, expected {:?}",
+            x, y, name, pixel.data, expected_pixel.data
+        );
+    }
+}
+
+#[test]
+fn solid_rectangles() {
+    let (mut renderer, mut simple2d_renderer) = create_renderer(Vector2::new(16, 16));
+
+    let mut batch = RenderBatch::new(ShaderMode::Color, UvMode::YDown);
+    batch.push_rectangle(rect(2.0, 2.0, 10.0, 10.0), full_uv(), Vector4::new(1.0, 0.0, 0.0, 1.0));
+    batch.push_rectangle(rect(6.0, 6.0, 14.0, 14.0), full_uv(), Vector4::new(0.0, 0.0, 1.0, 1.0));
+    let mut data = RenderData::new();
+    data.render_sets.push(RenderSet::new(Projection::Pixels, vec!(batch)));
+
+    let image = render(&data, &mut renderer, &mut simple2d_renderer);
+
+    assert_pixel(&image, 0, 0, [0, 0, 0, 255]);
+    assert_pixel(&image, 3, 3, [255, 0, 0, 255]);
+    // Later rectangles are drawn over earlier ones
+    assert_pixel(&image, 7, 7, [0, 0, 255, 255]);
+    assert_pixel(&image, 13, 13, [0, 0, 255, 255]);
+    assert_pixel(&image, 14, 14, [0, 0, 0, 255]);
+    assert_golden("solid_rectangles", &image);
+}
+
+#[test]
+fn alpha_blending() {
+    let (mut renderer, mut simple2d_renderer) = create_renderer(Vector2::new(16, 16));
+
+    let mut background = RenderBatch::new(ShaderMode::Color, UvMode::YDown);
+    background.push_rectangle(
+        rect(0.0, 0.0, 16.0, 16.0), full_uv(), Vector4::new(1.0, 1.0, 1.0, 1.0),
+    );
+    let mut overlay = RenderBatch::new(ShaderMode::Color, UvMode::YDown);
+    overlay.push_rectangle(rect(0.0, 0.0, 8.0, 16.0), full_uv(), Vector4::new(1.0, 0.0, 0.0, 0.5));
+    let mut data = RenderData::new();
+    data.render_sets.push(RenderSet::new(Projection::Pixels, vec!(background, overlay)));
+
+    let image = render(&data, &mut renderer, &mut simple2d_renderer);
+
+    // Blending halfway to red keeps the full red channel, outside the overlay stays white
+    assert_pixel(&image, 12, 8, [255, 255, 255, 255]);
+    let blended = image.get_pixel(4, 8).data;
+    assert_eq!(blended[0], 255);
+    assert!(blended[1] > 0 && blended[1] < 255 && blended[1] == blended[2]);
+    assert_golden("alpha_blending", &image);
+}
+
+#[test]
+fn premultiplied_alpha_premultiplies_vertex_color() {
+    let (mut renderer, mut simple2d_renderer) = create_renderer(Vector2::new(4, 4));
+
+    let mut batch = RenderBatch::new(ShaderMode::Color, UvMode::YDown)
+        .with_blend_mode(BlendMode::PremultipliedAlpha);
+    batch.push_rectangle(rect(0.0, 0.0, 4.0, 4.0), full_uv(), Vector4::new(1.0, 0.0, 0.0, 0.5));
+    let mut data = RenderData::new();
+    data.render_sets.push(RenderSet::new(Projection::Pixels, vec!(batch)));
+
+    let image = render(&data, &mut renderer, &mut simple2d_renderer);
+
+    // Half of red in linear space over black, the same as regular alpha blending would give
+    assert_pixel(&image, 1, 1, [188, 0, 0, 255]);
+}
+
+#[test]
+fn additive_multiply_and_opaque_blending() {
+    let (mut renderer, mut simple2d_renderer) = create_renderer(Vector2::new(12, 4));
+
+    let mut background = RenderBatch::new(ShaderMode::Color, UvMode::YDown);
+    background.push_rectangle(
+        rect(0.0, 0.0, 12.0, 4.0), full_uv(), Vector4::new(0.5, 0.5, 0.5, 1.0),
+    );
+    let mut batches = vec!(background);
+    let modes = [BlendMode::Additive, BlendMode::Multiply, BlendMode::Opaque];
+    let colors = [
+        Vector4::new(1.0, 0.0, 0.0, 1.0),
+        Vector4::new(1.0, 0.0, 0.0, 1.0),
+        Vector4::new(0.0, 0.0, 1.0, 0.5),
+    ];
+    for (i, (mode, color)) in modes.iter().zip(colors.iter()).enumerate() {
+        let x = i as f32 * 4.0;
+        let mut batch = RenderBatch::new(ShaderMode::Color, UvMode::YDown)
+            .with_blend_mode(*mode);
+        batch.push_rectangle(rect(x, 0.0, x + 4.0, 4.0), full_uv(), *color);
+        batches.push(batch);
+    }
+    let mut data = RenderData::new();
+    data.render_sets.push(RenderSet::new(Projection::Pixels, batches));
+
+    let image = render(&data, &mut renderer, &mut simple2d_renderer);
+
+    // Additive adds to the gray in linear space, multiply keeps only the red channel of it, and
+    //  opaque overwrites the gray including its alpha
+    assert_pixel(&image, 1, 1, [255, 188, 188, 255]);
+    assert_pixel(&image, 5, 1, [188, 0, 0, 255]);
+    assert_pixel(&image, 9, 1, [0, 0, 255, 128]);
+    assert_golden("additive_multiply_and_opaque_blending", &image);
+}
+
+#[test]
+fn nearest_texture() {
+    let (mut renderer, mut simple2d_renderer) = create_renderer(Vector2::new(8, 8));
+
+    // A 2x2 texture with a different color in every corner
+    let bytes = vec!(
+        255, 0, 0, 255,  0, 255, 0, 255,
+        0, 0, 255, 255,  255, 255, 255, 255,
+    );
+    let texture = Texture::new()
+        .from_bytes(bytes, Vector2::new(2, 2), PixelFormat::Rgba8)
+        .with_sample_mode(SampleMode::Nearest)
+        .build(&mut renderer).unwrap();
+
+    let mut batch = RenderBatch::new(ShaderMode::Texture(texture), UvMode::YDown);
+    batch.push_rectangle(rect(0.0, 0.0, 8.0, 8.0), full_uv(), Vector4::new(1.0, 1.0, 1.0, 1.0));
+    let mut data = RenderData::new();
+    data.render_sets.push(RenderSet::new(Projection::Pixels, vec!(batch)));
+
+    let image = render(&data, &mut renderer, &mut simple2d_renderer);
+
+    assert_pixel(&image, 1, 1, [255, 0, 0, 255]);
+    assert_pixel(&image, 6, 1, [0, 255, 0, 255]);
+    assert_pixel(&image, 1, 6, [0, 0, 255, 255]);
+    assert_pixel(&image, 6, 6, [255, 255, 255, 255]);
+    assert_golden("nearest_texture", &image);
+}
+
+#[test]
+fn minified_texture_uses_mipmaps() {
+    let (mut renderer, mut simple2d_renderer) = create_renderer(Vector2::new(4, 4));
+
+    // A 4x4 black and white checkerboard, drawn at half size so every pixel covers 2x2 texels
+    let bytes: Vec<u8> = (0..16)
+        .flat_map(|i| {
+            let value = if (i % 4 + i / 4) % 2 == 0 { 0 } else { 255 };
+            vec!(value, value, value, 255)
+        })
+        .collect();
+    let texture = Texture::new()
+        .from_bytes(bytes, Vector2::new(4, 4), PixelFormat::Rgba8)
+        .with_sample_mode(SampleMode::Nearest)
+        .generate_mipmaps()
+        .build(&mut renderer).unwrap();
+
+    let mut batch = RenderBatch::new(ShaderMode::Texture(texture), UvMode::YDown);
+    batch.push_rectangle(rect(0.0, 0.0, 2.0, 2.0), full_uv(), Vector4::new(1.0, 1.0, 1.0, 1.0));
+    let mut data = RenderData::new();
+    data.render_sets.push(RenderSet::new(Projection::Pixels, vec!(batch)));
+
+    let image = render(&data, &mut renderer, &mut simple2d_renderer);
+
+    // Half of white in linear space, instead of whichever texel nearest sampling would hit
+    assert_pixel(&image, 0, 0, [188, 188, 188, 255]);
+    assert_pixel(&image, 1, 1, [188, 188, 188, 255]);
+    assert_pixel(&image, 2, 2, [0, 0, 0, 255]);
+}
+
+#[test]
+fn mask_texture() {
+    let (mut renderer, mut simple2d_renderer) = create_renderer(Vector2::new(8, 8));
+
+    // A 2x2 mask with only the top left and bottom right corners set
+    let texture = Texture::new()
+        .from_bytes(vec!(255, 0, 0, 255), Vector2::new(2, 2), PixelFormat::R8)
+        .as_single_channel()
+        .with_sample_mode(SampleMode::Nearest)
+        .build(&mut renderer).unwrap();
+
+    let mut batch = RenderBatch::new(ShaderMode::Mask(texture), UvMode::YDown);
+    batch.push_rectangle(rect(0.0, 0.0, 8.0, 8.0), full_uv(), Vector4::new(0.0, 1.0, 0.0, 1.0));
+    let mut data = RenderData::new();
+    data.render_sets.push(RenderSet::new(Projection::Pixels, vec!(batch)));
+
+    let image = render(&data, &mut renderer, &mut simple2d_renderer);
+
+    // The mask only gives the alpha, the color comes from the vertices
+    assert_pixel(&image, 1, 1, [0, 255, 0, 255]);
+    assert_pixel(&image, 6, 1, [0, 0, 0, 255]);
+    assert_pixel(&image, 1, 6, [0, 0, 0, 255]);
+    assert_pixel(&image, 6, 6, [0, 255, 0, 255]);
+    assert_golden("mask_texture", &image);
+}
+
+#[test]
+fn camera_projection() {
+    let (mut renderer, mut simple2d_renderer) = create_renderer(Vector2::new(16, 16));
+
+    // 4 pixels per unit on a 16 pixel target shows 4 by 4 units around the camera, with Y up
+    let camera = Camera::new(4.0, Point2::new(1.0, 0.0));
+    let mut batch = RenderBatch::new(ShaderMode::Color, UvMode::YUp);
+    batch.push_rectangle(rect(0.0, 0.0, 2.0, 1.0), full_uv(), Vector4::new(1.0, 0.0, 0.0, 1.0));
+    let mut data = RenderData::new();
+    data.render_sets.push(RenderSet::new(Projection::Camera(camera), vec!(batch)));
+
+    let image = render(&data, &mut renderer, &mut simple2d_renderer);
+
+    // The rectangle ends up above the center, around the camera's X position
+    assert_pixel(&image, 4, 5, [255, 0, 0, 255]);
+    assert_pixel(&image, 11, 5, [255, 0, 0, 255]);
+    assert_pixel(&image, 4, 9, [0, 0, 0, 255]);
+    assert_pixel(&image, 12, 5, [0, 0, 0, 255]);
+    assert_pixel(&image, 4, 3, [0, 0, 0, 255]);
+    assert_golden("camera_projection", &image);
+}
+
+#[test]
+fn y_up_full_texture() {
+    let (mut renderer, mut simple2d_renderer) = create_renderer(Vector2::new(8, 8));
+
+    let bytes = vec!(
+        255, 0, 0, 255,  0, 255, 0, 255,
+        0, 0, 255, 255,  255, 255, 255, 255,
+    );
+    let texture = Texture::new()
+        .from_bytes(bytes, Vector2::new(2, 2), PixelFormat::Rgba8)
+        .with_sample_mode(SampleMode::Nearest)
+        .build(&mut renderer).unwrap();
+
+    let mut batch = RenderBatch::new(ShaderMode::Texture(texture), UvMode::YUp);
+    batch.push_rectangle_full_texture(rect(0.0, 0.0, 8.0, 8.0));
+    let mut data = RenderData::new();
+    data.render_sets.push(RenderSet::new(Projection::Pixels, vec!(batch)));
+
+    let image = render(&data, &mut renderer, &mut simple2d_renderer);
+
+    // With Y up the UVs start at the other corner, so on a Y down projection the texture is
+    //  drawn turned around
+    assert_pixel(&image, 1, 1, [255, 255, 255, 255]);
+    assert_pixel(&image, 6, 1, [0, 0, 255, 255]);
+    assert_pixel(&image, 1, 6, [0, 255, 0, 255]);
+    assert_pixel(&image, 6, 6, [255, 0, 0, 255]);
+    assert_golden("y_up_full_texture", &image);
+}
+
+#[test]
+fn clipped_batch() {
+    let (mut renderer, mut simple2d_renderer) = create_renderer(Vector2::new(16, 16));
+
+    let mut batch = RenderBatch::new(ShaderMode::Color, UvMode::YDown)
+        .with_clip(ClipRect::Pixels(rect(4.0, 4.0, 12.0, 8.0)));
+    batch.push_rectangle(rect(0.0, 0.0, 16.0, 16.0), full_uv(), Vector4::new(0.0, 1.0, 0.0, 1.0));
+    let mut data = RenderData::new();
+    data.render_sets.push(RenderSet::new(Projection::Pixels, vec!(batch)));
+
+    let image = render(&data, &mut renderer, &mut simple2d_renderer);
+
+    assert_pixel(&image, 3, 5, [0, 0, 0, 255]);
+    assert_pixel(&image, 4, 4, [0, 255, 0, 255]);
+    assert_pixel(&image, 11, 7, [0, 255, 0, 255]);
+    assert_pixel(&image, 12, 7, [0, 0, 0, 255]);
+    assert_pixel(&image, 8, 8, [0, 0, 0, 255]);
+    assert_golden("clipped_batch", &image);
+}
+
+fn solid_texture(
+    renderer: &mut Renderer<SoftwareRendererRaw>, color: [u8; 4],
+) -> Arc<Texture<SoftwareRendererRaw>> {
+    Texture::new()
+        .from_bytes(color.to_vec(), Vector2::new(1, 1), PixelFormat::Rgba8)
+        .with_sample_mode(SampleMode::Nearest)
+        .build(renderer).unwrap()
+}
+
+#[test]
+fn cpu_shader_gets_parameters() {
+    let (mut renderer, mut simple2d_renderer) = create_renderer(Vector2::new(8, 8));
+
+    // Combines the vertex color with parameters from both ends of the parameter list
+    let shader = Arc::new(Shader2D::new("parameters").with_cpu(|input| Vector4::new(
+        input.parameters[0].x, input.color.y, input.parameters[MATERIAL_PARAMETERS - 1].z, 1.0,
+    )));
+    simple2d_renderer.register_shader(&shader, &mut renderer).unwrap();
+    let material = Arc::new(Material::new(shader)
+        .with_float(0, 1.0)
+        .with_vector(MATERIAL_PARAMETERS - 1, Vector4::new(0.0, 0.0, 1.0, 0.0)));
+
+    let mut batch = RenderBatch::new(ShaderMode::Custom(material), UvMode::YDown);
+    batch.push_rectangle(rect(0.0, 0.0, 4.0, 8.0), full_uv(), Vector4::new(0.0, 0.0, 0.0, 1.0));
+    batch.push_rectangle(rect(4.0, 0.0, 8.0, 8.0), full_uv(), Vector4::new(0.0, 1.0, 0.0, 1.0));
+    let mut data = RenderData::new();
+    data.render_sets.push(RenderSet::new(Projection::Pixels, vec!(batch)));
+
+    let image = render(&data, &mut renderer, &mut simple2d_renderer);
+
+    assert_pixel(&image, 1, 4, [255, 0, 255, 255]);
+    assert_pixel(&image, 6, 4, [255, 255, 255, 255]);
+    assert_golden("cpu_shader_gets_parameters", &image);
+}
+
+#[test]
+fn cpu_shader_samples_textures() {
+    let (mut renderer, mut simple2d_renderer) = create_renderer(Vector2::new(8, 8));
+
+    // Takes red from texture 0 and green from texture 1, texture 2 isn't set so sampling it
+    //  gives zero and the blue channel stays empty
+    let shader = Arc::new(Shader2D::new("textures").with_cpu(|input| {
+        let unset = input.sample(2, input.uv);
+        Vector4::new(
+            input.sample(0, input.uv).x, input.sample(1, input.uv).y, unset.x + unset.w, 1.0,
+        )
+    }));
+    simple2d_renderer.register_shader(&shader, &mut renderer).unwrap();
+    let material = Arc::new(Material::new(shader)
+        .with_texture(solid_texture(&mut renderer, [255, 0, 0, 255]))
+        .with_texture(solid_texture(&mut renderer, [0, 255, 0, 255])));
+
+    let mut batch = RenderBatch::new(ShaderMode::Custom(material), UvMode::YDown);
+    batch.push_rectangle(rect(0.0, 0.0, 8.0, 8.0), full_uv(), Vector4::new(1.0, 1.0, 1.0, 1.0));
+    let mut data = RenderData::new();
+    data.render_sets.push(RenderSet::new(Projection::Pixels, vec!(batch)));
+
+    let image = render(&data, &mut renderer, &mut simple2d_renderer);
+
+    assert_pixel(&image, 4, 4, [255, 255, 0, 255]);
+    assert_golden("cpu_shader_samples_textures", &image);
+}
+
+#[test]
+fn cpu_shader_output_is_not_premultiplied() {
+    let (mut renderer, mut simple2d_renderer) = create_renderer(Vector2::new(8, 8));
+
+    let shader = Arc::new(Shader2D::new("passthrough").with_cpu(|input| input.color));
+    simple2d_renderer.register_shader(&shader, &mut renderer).unwrap();
+    let material = Arc::new(Material::new(shader));
+
+    // The built-in shader premultiplies the vertex color, a custom shader is responsible for
+    //  its own output so it's blended as-is
+    let half_red = Vector4::new(1.0, 0.0, 0.0, 0.5);
+    let mut custom = RenderBatch::new(ShaderMode::Custom(material), UvMode::YDown)
+        .with_blend_mode(BlendMode::PremultipliedAlpha);
+    custom.push_rectangle(rect(0.0, 0.0, 4.0, 8.0), full_uv(), half_red);
+    let mut color = RenderBatch::new(ShaderMode::Color, UvMode::YDown)
+        .with_blend_mode(BlendMode::PremultipliedAlpha);
+    color.push_rectangle(rect(4.0, 0.0, 8.0, 8.0), full_uv(), half_red);
+    let mut data = RenderData::new();
+    data.render_sets.push(RenderSet::new(Projection::Pixels, vec!(custom, color)));
+
+    let image = render(&data, &mut renderer, &mut simple2d_renderer);
+
+    assert_pixel(&image, 1, 4, [255, 0, 0, 255]);
+    assert_pixel(&image, 6, 4, [188, 0, 0, 255]);
+    assert_golden("cpu_shader_output_is_not_premultiplied", &image);
+}
+
+#[test]
+fn custom_shader_without_cpu_version_is_skipped() {
+    let (mut renderer, mut simple2d_renderer) = create_renderer(Vector2::new(4, 4));
+
+    // This shader can't be registered on this backend, but a material can still use it
+    let shader = Arc::new(Shader2D::new("gpu_only").with_glsl_150(None, String::new()));
+    let material = Arc::new(Material::new(shader));
+
+    let mut custom = RenderBatch::new(ShaderMode::Custom(material), UvMode::YDown);
+    custom.push_rectangle(rect(0.0, 0.0, 4.0, 4.0), full_uv(), Vector4::new(1.0, 0.0, 0.0, 1.0));
+    let mut color = RenderBatch::new(ShaderMode::Color, UvMode::YDown);
+    color.push_rectangle(rect(2.0, 0.0, 4.0, 4.0), full_uv(), Vector4::new(0.0, 1.0, 0.0, 1.0));
+    let mut data = RenderData::new();
+    data.render_sets.push(RenderSet::new(Projection::Pixels, vec!(custom, color)));
+
+    let image = render(&data, &mut renderer, &mut simple2d_renderer);
+
+    assert_pixel(&image, 1, 1, [0, 0, 0, 255]);
+    assert_pixel(&image, 3, 1, [0, 255, 0, 255]);
+}
+
+#[test]
+fn render_target_requires_render_target_texture() {
+    let (mut renderer, simple2d_renderer) = create_renderer(Vector2::new(4, 4));
+
+    let texture = Texture::new()
+        .from_bytes(vec![0u8; 4*4*4], Vector2::new(4, 4), PixelFormat::Rgba8)
+        .build(&mut renderer).unwrap();
+    let target = Renderer2DTarget::from_texture(true, texture, &renderer, &simple2d_renderer);
+    assert!(target.is_err());
+
+    let target = Renderer2DTarget::new_texture(
+        true, Vector2::new(4, 4), &mut renderer, &simple2d_renderer,
+    );
+    assert!(target.is_ok());
+}
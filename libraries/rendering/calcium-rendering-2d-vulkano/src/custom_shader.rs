@@ -0,0 +1,206 @@
+use std::borrow::{Cow};
+use std::ffi::{CStr};
+use std::sync::{Arc};
+use std::vec::{IntoIter};
+
+use vulkano::descriptor::descriptor::{
+    DescriptorDesc, DescriptorDescTy, DescriptorBufferDesc, DescriptorImageDesc,
+    DescriptorImageDescDimensions, DescriptorImageDescArray, ShaderStages,
+};
+use vulkano::descriptor::pipeline_layout::{PipelineLayoutDesc, PipelineLayoutDescPcRange};
+use vulkano::format::{Format};
+use vulkano::framebuffer::{RenderPassAbstract, Subpass};
+use vulkano::pipeline::{GraphicsPipeline, GraphicsPipelineAbstract};
+use vulkano::pipeline::blend::{AttachmentBlend};
+use vulkano::pipeline::shader::{
+    ShaderModule, ShaderInterfaceDef, ShaderInterfaceDefEntry, GraphicsShaderType,
+};
+
+use calcium_rendering::{Renderer, Error, CalciumErrorMappable};
+use calcium_rendering::raw::{RawAccess};
+use calcium_rendering_2d::render_data::{Shader2D, MATERIAL_TEXTURES};
+use calcium_rendering_vulkano::{VulkanoRendererRaw};
+use calcium_rendering_vulkano_shaders::{simple2d_vs};
+
+use {VkVertex};
+
+/// The shader modules created for a registered custom shader.
+pub struct CustomShader {
+    vertex: Option<Arc<ShaderModule>>,
+    fragment: Arc<ShaderModule>,
+}
+
+impl CustomShader {
+    pub fn new(shader: &Shader2D, renderer: &Renderer<VulkanoRendererRaw>) -> Result<Self, Error> {
+        let spirv = shader.spirv().ok_or_else(|| Error::Unsupported(format!(
+            "Shader \"{}\" has no SPIR-V version, which the vulkano backend needs", shader.name
+        )))?;
+        let device = renderer.raw().device().clone();
+
+        // Vulkano can't check the SPIR-V itself, `Shader2D::with_spirv` is unsafe so whoever set
+        //  it is responsible for it matching the interface documented on Shader2D
+        let vertex = if let Some(ref vertex) = spirv.vertex {
+            Some(unsafe { ShaderModule::new(device.clone(), vertex) }.map_platform_err()?)
+        } else {
+            None
+        };
+        let fragment = unsafe { ShaderModule::new(device, &spirv.fragment) }.map_platform_err()?;
+
+        Ok(CustomShader {
+            vertex,
+            fragment,
+        })
+    }
+
+    /// Creates a pipeline for this shader in a render pass. If the shader doesn't have its own
+    /// vertex shader, the given built-in one is used.
+    pub fn create_pipeline(
+        &self, blend: AttachmentBlend, vs: &simple2d_vs::Shader,
+        render_pass: &Arc<RenderPassAbstract + Send + Sync>,
+        renderer: &Renderer<VulkanoRendererRaw>,
+    ) -> Result<Arc<GraphicsPipelineAbstract + Send + Sync>, Error> {
+        let main = CStr::from_bytes_with_nul(b"main\0").unwrap();
+        let subpass = Subpass::from(render_pass.clone(), 0).unwrap();
+        let device = renderer.raw().device().clone();
+
+        let fs = unsafe { self.fragment.graphics_entry_point(
+            main, FragmentInput, FragmentOutput, CustomLayout, GraphicsShaderType::Fragment
+        ) };
+
+        // The entry points have different types, so both need their own builder
+        let pipeline = if let Some(ref vertex) = self.vertex {
+            let custom_vs = unsafe { vertex.graphics_entry_point(
+                main, VertexInput, FragmentInput, CustomLayout, GraphicsShaderType::Vertex
+            ) };
+
+            Arc::new(GraphicsPipeline::start()
+                .vertex_input_single_buffer::<VkVertex>()
+                .triangle_list()
+                .viewports_dynamic_scissors_dynamic(1)
+                .vertex_shader(custom_vs, ())
+                .fragment_shader(fs, ())
+                .blend_collective(blend)
+                .cull_mode_disabled()
+                .render_pass(subpass)
+                .build(device).map_platform_err()?
+            ) as Arc<GraphicsPipelineAbstract + Send + Sync>
+        } else {
+            Arc::new(GraphicsPipeline::start()
+                .vertex_input_single_buffer::<VkVertex>()
+                .triangle_list()
+                .viewports_dynamic_scissors_dynamic(1)
+                .vertex_shader(vs.main_entry_point(), ())
+                .fragment_shader(fs, ())
+                .blend_collective(blend)
+                .cull_mode_disabled()
+                .render_pass(subpass)
+                .build(device).map_platform_err()?
+            ) as Arc<GraphicsPipelineAbstract + Send + Sync>
+        };
+
+        Ok(pipeline)
+    }
+}
+
+/// The vertex data, the same as the built-in vertex shader.
+#[derive(Debug, Copy, Clone)]
+struct VertexInput;
+
+unsafe impl ShaderInterfaceDef for VertexInput {
+    type Iter = IntoIter<ShaderInterfaceDefEntry>;
+
+    fn elements(&self) -> Self::Iter {
+        vec!(
+            entry(0, Format::R32G32Sfloat, "v_position"),
+            entry(1, Format::R32G32Sfloat, "v_uv"),
+            entry(2, Format::R32G32B32A32Sfloat, "v_color"),
+        ).into_iter()
+    }
+}
+
+/// What's passed from the vertex shader to the fragment shader.
+#[derive(Debug, Copy, Clone)]
+struct FragmentInput;
+
+unsafe impl ShaderInterfaceDef for FragmentInput {
+    type Iter = IntoIter<ShaderInterfaceDefEntry>;
+
+    fn elements(&self) -> Self::Iter {
+        vec!(
+            entry(0, Format::R32G32Sfloat, "f_uv"),
+            entry(1, Format::R32G32B32A32Sfloat, "f_color"),
+        ).into_iter()
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+struct FragmentOutput;
+
+unsafe impl ShaderInterfaceDef for FragmentOutput {
+    type Iter = IntoIter<ShaderInterfaceDefEntry>;
+
+    fn elements(&self) -> Self::Iter {
+        vec!(
+            entry(0, Format::R32G32B32A32Sfloat, "o_color"),
+        ).into_iter()
+    }
+}
+
+fn entry(location: u32, format: Format, name: &'static str) -> ShaderInterfaceDefEntry {
+    ShaderInterfaceDefEntry {
+        location: location..location + 1,
+        format,
+        name: Some(Cow::Borrowed(name)),
+    }
+}
+
+/// The descriptors of custom shaders, the matrix at binding 0, the parameters at binding 1 and
+/// the textures after that. Both the vertex and fragment shader can use all of them.
+#[derive(Debug, Copy, Clone)]
+struct CustomLayout;
+
+unsafe impl PipelineLayoutDesc for CustomLayout {
+    fn num_sets(&self) -> usize {
+        1
+    }
+
+    fn num_bindings_in_set(&self, set: usize) -> Option<usize> {
+        if set == 0 { Some(2 + MATERIAL_TEXTURES) } else { None }
+    }
+
+    fn descriptor(&self, set: usize, binding: usize) -> Option<DescriptorDesc> {
+        if set != 0 || binding >= 2 + MATERIAL_TEXTURES {
+            return None
+        }
+
+        let ty = if binding < 2 {
+            DescriptorDescTy::Buffer(DescriptorBufferDesc {
+                dynamic: Some(false),
+                storage: false,
+            })
+        } else {
+            DescriptorDescTy::CombinedImageSampler(DescriptorImageDesc {
+                sampled: true,
+                dimensions: DescriptorImageDescDimensions::TwoDimensional,
+                format: None,
+                multisampled: false,
+                array_layers: DescriptorImageDescArray::NonArrayed,
+            })
+        };
+
+        Some(DescriptorDesc {
+            ty,
+            array_count: 1,
+            stages: ShaderStages { vertex: true, fragment: true, .. ShaderStages::none() },
+            readonly: true,
+        })
+    }
+
+    fn num_push_constants_ranges(&self) -> usize {
+        0
+    }
+
+    fn push_constants_range(&self, _num: usize) -> Option<PipelineLayoutDescPcRange> {
+        None
+    }
+}
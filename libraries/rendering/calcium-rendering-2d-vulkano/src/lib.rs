@@ -8,10 +8,11 @@ extern crate calcium_rendering_2d;
 extern crate calcium_rendering_vulkano;
 extern crate calcium_rendering_vulkano_shaders;
 
+mod custom_shader;
 mod render_target;
 mod renderer;
 mod vertex;
 
 pub use render_target::{VulkanoRenderer2DTargetRaw};
 pub use renderer::{VulkanoRenderer2DRaw};
-pub use vertex::{VkVertex};
+pub use vertex::{VkVertex, VkQuadCorner, VkInstance};
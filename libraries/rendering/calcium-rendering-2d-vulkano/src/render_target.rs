@@ -1,34 +1,86 @@
+use std::collections::{HashMap};
 use std::sync::{Arc};
 
 use vulkano::pipeline::{GraphicsPipeline, GraphicsPipelineAbstract};
-use vulkano::pipeline::vertex::{SingleBufferDefinition};
+use vulkano::pipeline::vertex::{SingleBufferDefinition, OneVertexOneInstanceDefinition};
+use vulkano::pipeline::blend::{AttachmentBlend, BlendOp, BlendFactor};
 use vulkano::framebuffer::{Subpass, RenderPassAbstract, Framebuffer, FramebufferAbstract};
 use vulkano::format::{ClearValue};
 use vulkano::image::swapchain::{SwapchainImage};
 use vulkano::descriptor::descriptor_set::{FixedSizeDescriptorSetsPool};
 
-use calcium_rendering::{Renderer};
+use calcium_rendering::{Renderer, Error, CalciumErrorMappable, WindowId};
 use calcium_rendering::raw::{RawAccess};
+use calcium_rendering::texture::{Texture};
 use calcium_rendering_vulkano::{VulkanoRendererRaw};
 use calcium_rendering_2d::{Renderer2D};
 use calcium_rendering_2d::raw::{Renderer2DTargetRaw};
+use calcium_rendering_2d::render_data::{BlendMode, BLEND_MODES};
+use calcium_rendering_vulkano_shaders::{simple2d_vs};
 
-use {VkVertex, VulkanoRenderer2DRaw};
+use custom_shader::{CustomShader};
+use {VkVertex, VkQuadCorner, VkInstance, VulkanoRenderer2DRaw};
+
+/// A custom shader's pipeline for a blend mode, together with a pool for its descriptor sets so
+/// they don't have to be allocated for every batch.
+pub struct CustomPipeline {
+    pub pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>,
+    pub set_pool: FixedSizeDescriptorSetsPool<Arc<GraphicsPipelineAbstract + Send + Sync>>,
+}
 
 pub struct VulkanoRenderer2DTargetRaw {
     render_pass: Arc<RenderPassAbstract + Send + Sync>,
-    framebuffers: Vec<Arc<FramebufferAbstract + Send + Sync>>,
+    /// The framebuffers for every window rendered to so far, together with the images id they
+    /// were created for.
+    window_framebuffers: HashMap<WindowId, (usize, Vec<Arc<FramebufferAbstract + Send + Sync>>)>,
+    texture_framebuffer: Option<Arc<FramebufferAbstract + Send + Sync>>,
 
-    pipeline: Arc<GraphicsPipelineAbstract + Send + Sync>,
+    /// A pipeline for every blend mode by `BlendMode::index`, blending is part of the pipeline
+    /// state in vulkan.
+    pipelines: Vec<Arc<GraphicsPipelineAbstract + Send + Sync>>,
+    /// The same as pipelines, but for drawing instanced quads.
+    instanced_pipelines: Vec<Arc<GraphicsPipelineAbstract + Send + Sync>>,
     set_pool: FixedSizeDescriptorSetsPool<Arc<GraphicsPipelineAbstract + Send + Sync>>,
+    /// Pipelines for custom shaders by the shader's ID and then `BlendMode::index`, created when
+    /// first used on this target.
+    custom_pipelines: HashMap<usize, Vec<Option<CustomPipeline>>>,
 
     clear: bool,
-    framebuffers_images_id: usize,
 }
 
 impl VulkanoRenderer2DTargetRaw {
-    pub fn pipeline(&self) -> &Arc<GraphicsPipelineAbstract + Send + Sync> {
-        &self.pipeline
+    pub fn pipeline(&self, blend_mode: BlendMode) -> &Arc<GraphicsPipelineAbstract + Send + Sync> {
+        &self.pipelines[blend_mode.index()]
+    }
+
+    pub fn instanced_pipeline(
+        &self, blend_mode: BlendMode,
+    ) -> &Arc<GraphicsPipelineAbstract + Send + Sync> {
+        &self.instanced_pipelines[blend_mode.index()]
+    }
+
+    /// Gets the pipeline for a custom shader with a blend mode, creating it if it hasn't been
+    /// used on this target yet.
+    pub fn custom_pipeline(
+        &mut self, shader_id: usize, shader: &CustomShader, blend_mode: BlendMode,
+        vs: &simple2d_vs::Shader, renderer: &Renderer<VulkanoRendererRaw>,
+    ) -> Result<&mut CustomPipeline, Error> {
+        let render_pass = &self.render_pass;
+        let custom = &mut self.custom_pipelines.entry(shader_id)
+            .or_insert_with(|| BLEND_MODES.iter().map(|_| None).collect())[blend_mode.index()];
+
+        if custom.is_none() {
+            debug!(renderer.log(), "Creating simple2d custom shader pipeline");
+            let pipeline = shader.create_pipeline(
+                blend_for_mode(blend_mode), vs, render_pass, renderer
+            )?;
+            *custom = Some(CustomPipeline {
+                set_pool: FixedSizeDescriptorSetsPool::new(pipeline.clone(), 0),
+                pipeline,
+            });
+        }
+
+        Ok(custom.as_mut().unwrap())
     }
 
     pub fn set_pool_mut(
@@ -37,21 +89,34 @@ impl VulkanoRenderer2DTargetRaw {
         &mut self.set_pool
     }
 
+    /// Gets the framebuffer to render to for an image of a window's swapchain. Returns an error if
+    /// the window has been removed.
     pub fn framebuffer_for(
-        &mut self, image_num: usize, renderer: &Renderer<VulkanoRendererRaw>,
-    ) -> &Arc<FramebufferAbstract + Send + Sync> {
-        // Check if we should update the framebuffers
-        let current_images_id = renderer.raw().swapchain.images_id();
-        if self.framebuffers_images_id != current_images_id {
-            self.framebuffers = create_framebuffers(
-                renderer.raw().swapchain.images(),
-                &self.render_pass,
-            );
-            self.framebuffers_images_id = current_images_id;
+        &mut self, window: WindowId, image_num: usize, renderer: &Renderer<VulkanoRendererRaw>,
+    ) -> Result<&Arc<FramebufferAbstract + Send + Sync>, Error> {
+        // If we're rendering to a texture, there's only one framebuffer
+        if let Some(ref framebuffer) = self.texture_framebuffer {
+            return Ok(framebuffer)
+        }
+
+        // Drop the framebuffers of removed windows, they keep the swapchain images alive
+        self.window_framebuffers.retain(|id, _| renderer.raw().window(*id).is_some());
+
+        // Check if we should create or update the framebuffers for this window
+        let swapchain = &renderer.raw().window(window)
+            .ok_or_else(|| Error::Platform("The frame's window has been removed".into()))?
+            .swapchain;
+        let current_images_id = swapchain.images_id();
+        let outdated = self.window_framebuffers.get(&window)
+            .map(|&(images_id, _)| images_id != current_images_id)
+            .unwrap_or(true);
+        if outdated {
+            let framebuffers = create_framebuffers(swapchain.images(), &self.render_pass);
+            self.window_framebuffers.insert(window, (current_images_id, framebuffers));
         }
 
         // Return the framebuffer for this image_num
-        &self.framebuffers[image_num]
+        Ok(&self.window_framebuffers[&window].1[image_num])
     }
 
     pub fn clear_values(&self) -> Vec<ClearValue> {
@@ -71,84 +136,190 @@ impl Renderer2DTargetRaw<VulkanoRendererRaw, VulkanoRenderer2DRaw>
         renderer: &Renderer<VulkanoRendererRaw>,
         simple2d_renderer: &Renderer2D<VulkanoRendererRaw, VulkanoRenderer2DRaw>,
     ) -> Self {
-        // Set up the render pass for 2D rendering depending on the settings for this target
-        debug!(renderer.log(), "Creating simple2d render pass");
-        #[allow(dead_code)]
-        let render_pass = if clear {
-            Arc::new(single_pass_renderpass!(renderer.raw().device().clone(),
-                attachments: {
-                    color: {
-                        load: Clear,
-                        store: Store,
-                        // TODO: Get this format from a central place that isn't the window
-                        format: ::vulkano::format::Format::B8G8R8A8Srgb,
-                        samples: 1,
-                    }
-                },
-                pass: {
-                    color: [color],
-                    depth_stencil: {}
+        let (render_pass, pipelines, instanced_pipelines, set_pool) =
+            create_pipelines(clear, renderer, simple2d_renderer);
+
+        // Window framebuffers are created once we know what window we're rendering to
+        VulkanoRenderer2DTargetRaw {
+            render_pass,
+            window_framebuffers: HashMap::new(),
+            texture_framebuffer: None,
+
+            pipelines,
+            instanced_pipelines,
+            set_pool,
+            custom_pipelines: HashMap::new(),
+
+            clear,
+        }
+    }
+
+    fn new_texture(
+        clear: bool,
+        texture: &Arc<Texture<VulkanoRendererRaw>>,
+        renderer: &Renderer<VulkanoRendererRaw>,
+        simple2d_renderer: &Renderer2D<VulkanoRendererRaw, VulkanoRenderer2DRaw>,
+    ) -> Result<Self, Error> {
+        let attachment = texture.raw().attachment()
+            .ok_or_else(|| Error::Unsupported(
+                "Texture was not built as a render target".into()
+            ))?;
+
+        let (render_pass, pipelines, instanced_pipelines, set_pool) =
+            create_pipelines(clear, renderer, simple2d_renderer);
+
+        // Create the single framebuffer that renders to the texture
+        let texture_framebuffer = Arc::new(Framebuffer::start(render_pass.clone())
+            .add(attachment.clone()).map_platform_err()?
+            .build().map_platform_err()?
+        ) as Arc<FramebufferAbstract + Send + Sync>;
+
+        Ok(VulkanoRenderer2DTargetRaw {
+            render_pass,
+            window_framebuffers: HashMap::new(),
+            texture_framebuffer: Some(texture_framebuffer),
+
+            pipelines,
+            instanced_pipelines,
+            set_pool,
+            custom_pipelines: HashMap::new(),
+
+            clear,
+        })
+    }
+}
+
+fn create_pipelines(
+    clear: bool,
+    renderer: &Renderer<VulkanoRendererRaw>,
+    simple2d_renderer: &Renderer2D<VulkanoRendererRaw, VulkanoRenderer2DRaw>,
+) -> (
+    Arc<RenderPassAbstract + Send + Sync>,
+    Vec<Arc<GraphicsPipelineAbstract + Send + Sync>>,
+    Vec<Arc<GraphicsPipelineAbstract + Send + Sync>>,
+    FixedSizeDescriptorSetsPool<Arc<GraphicsPipelineAbstract + Send + Sync>>,
+) {
+    // Set up the render pass for 2D rendering depending on the settings for this target
+    debug!(renderer.log(), "Creating simple2d render pass");
+    #[allow(dead_code)]
+    let render_pass = if clear {
+        Arc::new(single_pass_renderpass!(renderer.raw().device().clone(),
+            attachments: {
+                color: {
+                    load: Clear,
+                    store: Store,
+                    // TODO: Get this format from a central place that isn't the window
+                    format: ::vulkano::format::Format::B8G8R8A8Srgb,
+                    samples: 1,
                 }
-            ).unwrap()) as Arc<RenderPassAbstract + Send + Sync>
-        } else {
-            Arc::new(single_pass_renderpass!(renderer.raw().device().clone(),
-                attachments: {
-                    color: {
-                        load: Load,
-                        store: Store,
-                        // TODO: Get this format from a central place that isn't the window
-                        format: ::vulkano::format::Format::B8G8R8A8Srgb,
-                        samples: 1,
-                    }
-                },
-                pass: {
-                    color: [color],
-                    depth_stencil: {}
+            },
+            pass: {
+                color: [color],
+                depth_stencil: {}
+            }
+        ).unwrap()) as Arc<RenderPassAbstract + Send + Sync>
+    } else {
+        Arc::new(single_pass_renderpass!(renderer.raw().device().clone(),
+            attachments: {
+                color: {
+                    load: Load,
+                    store: Store,
+                    // TODO: Get this format from a central place that isn't the window
+                    format: ::vulkano::format::Format::B8G8R8A8Srgb,
+                    samples: 1,
                 }
-            ).unwrap()) as Arc<RenderPassAbstract + Send + Sync>
-        };
+            },
+            pass: {
+                color: [color],
+                depth_stencil: {}
+            }
+        ).unwrap()) as Arc<RenderPassAbstract + Send + Sync>
+    };
 
-        // Set up the pipeline for this target
-        debug!(renderer.log(), "Creating simple2d pipeline");
+    // Set up the pipelines for this target, one for every blend mode
+    debug!(renderer.log(), "Creating simple2d pipelines");
+    let pipelines: Vec<_> = BLEND_MODES.iter().map(|mode| {
         let pipeline = Arc::new(GraphicsPipeline::start()
             .vertex_input_single_buffer()
             .triangle_list()
-            .viewports_dynamic_scissors_irrelevant(1)
+            .viewports_dynamic_scissors_dynamic(1)
 
             // Which shaders to use
             .vertex_shader(simple2d_renderer.raw().vs.main_entry_point(), ())
             .fragment_shader(simple2d_renderer.raw().fs.main_entry_point(), ())
 
-            .blend_alpha_blending()
+            .blend_collective(blend_for_mode(*mode))
             .cull_mode_disabled()
 
             .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
             .build(renderer.raw().device().clone()).unwrap()
         ) as Arc<GraphicsPipeline<SingleBufferDefinition<VkVertex>, _, _>>;
 
-        // Create specialized set pools for more efficient rendering
-        let set_pool = FixedSizeDescriptorSetsPool::new(
-            pipeline.clone() as Arc<GraphicsPipelineAbstract + Send + Sync>,
-            0
-        );
+        pipeline as Arc<GraphicsPipelineAbstract + Send + Sync>
+    }).collect();
+    let instanced_pipelines: Vec<_> = BLEND_MODES.iter().map(|mode| {
+        let pipeline = Arc::new(GraphicsPipeline::start()
+            .vertex_input(OneVertexOneInstanceDefinition::<VkQuadCorner, VkInstance>::new())
+            .triangle_list()
+            .viewports_dynamic_scissors_dynamic(1)
+
+            // Which shaders to use
+            .vertex_shader(simple2d_renderer.raw().instanced_vs.main_entry_point(), ())
+            .fragment_shader(simple2d_renderer.raw().fs.main_entry_point(), ())
+
+            .blend_collective(blend_for_mode(*mode))
+            .cull_mode_disabled()
 
-        // Create the swapchain framebuffers for this render pass
-        let framebuffers = create_framebuffers(
-            renderer.raw().swapchain.images(),
-            &render_pass,
+            .render_pass(Subpass::from(render_pass.clone(), 0).unwrap())
+            .build(renderer.raw().device().clone()).unwrap()
         );
-        let framebuffers_images_id = renderer.raw().swapchain.images_id();
 
-        VulkanoRenderer2DTargetRaw {
-            render_pass,
-            framebuffers,
+        pipeline as Arc<GraphicsPipelineAbstract + Send + Sync>
+    }).collect();
 
-            pipeline,
-            set_pool,
+    // Create specialized set pools for more efficient rendering, all pipelines, including the
+    //  instanced ones, have the same layout so they can share sets
+    let set_pool = FixedSizeDescriptorSetsPool::new(pipelines[0].clone(), 0);
 
-            framebuffers_images_id,
-            clear,
-        }
+    (render_pass, pipelines, instanced_pipelines, set_pool)
+}
+
+fn blend_for_mode(mode: BlendMode) -> AttachmentBlend {
+    let (color, alpha) = match mode {
+        BlendMode::Alpha => (
+            (BlendFactor::SrcAlpha, BlendFactor::OneMinusSrcAlpha),
+            (BlendFactor::One, BlendFactor::OneMinusSrcAlpha),
+        ),
+        BlendMode::PremultipliedAlpha => (
+            (BlendFactor::One, BlendFactor::OneMinusSrcAlpha),
+            (BlendFactor::One, BlendFactor::OneMinusSrcAlpha),
+        ),
+        BlendMode::Additive => (
+            (BlendFactor::SrcAlpha, BlendFactor::One),
+            (BlendFactor::Zero, BlendFactor::One),
+        ),
+        BlendMode::Multiply => (
+            (BlendFactor::DstColor, BlendFactor::Zero),
+            (BlendFactor::Zero, BlendFactor::One),
+        ),
+        BlendMode::Opaque => (
+            (BlendFactor::One, BlendFactor::Zero),
+            (BlendFactor::One, BlendFactor::Zero),
+        ),
+    };
+
+    AttachmentBlend {
+        enabled: true,
+        color_op: BlendOp::Add,
+        color_source: color.0,
+        color_destination: color.1,
+        alpha_op: BlendOp::Add,
+        alpha_source: alpha.0,
+        alpha_destination: alpha.1,
+        mask_red: true,
+        mask_green: true,
+        mask_blue: true,
+        mask_alpha: true,
     }
 }
 
@@ -1,31 +1,55 @@
+use std::collections::{HashMap};
+use std::mem::{size_of};
 use std::sync::{Arc};
 
-use cgmath::{Vector2, Matrix4};
+use cgmath::{Vector2, Point2, Matrix4};
 use vulkano::sync::{GpuFuture};
-use vulkano::pipeline::viewport::{Viewport};
+use vulkano::pipeline::viewport::{Viewport, Scissor};
 use vulkano::command_buffer::{AutoCommandBufferBuilder, DynamicState};
-use vulkano::buffer::{CpuAccessibleBuffer, BufferUsage};
+use vulkano::buffer::{CpuAccessibleBuffer, BufferUsage, BufferAccess};
 use vulkano::buffer::cpu_pool::{CpuBufferPool, CpuBufferPoolSubbuffer};
 use vulkano::memory::pool::{StdMemoryPool};
 
 use calcium_rendering::raw::{RawAccess};
-use calcium_rendering::texture::{Texture};
+use calcium_rendering::texture::{Texture, PixelFormat};
 use calcium_rendering::{Renderer, Error, Frame};
-use calcium_rendering_2d::render_data::{RenderBatch, ShaderMode, RenderData, RenderSet};
+use calcium_rendering_2d::render_data::{
+    RenderBatch, ShaderMode, RenderData, RenderSet, Rectangle, Shader2D, Material, BlendMode,
+    QUAD_INDICES, MATERIAL_PARAMETERS,
+};
 use calcium_rendering_2d::raw::{Renderer2DRaw};
 use calcium_rendering_2d::{Renderer2DTarget};
 use calcium_rendering_vulkano::{VulkanoRendererRaw};
-use calcium_rendering_vulkano_shaders::{simple2d_vs, simple2d_fs};
+use calcium_rendering_vulkano_shaders::{simple2d_vs, simple2d_instanced_vs, simple2d_fs};
 
-use {VkVertex, VulkanoRenderer2DTargetRaw};
+use custom_shader::{CustomShader};
+use {VkVertex, VkQuadCorner, VkInstance, VulkanoRenderer2DTargetRaw};
+
+/// Set on top of the shader mode ID, to premultiply the vertex color for premultiplied alpha.
+const MODE_PREMULTIPLIED: usize = 4;
 
 pub struct VulkanoRenderer2DRaw {
     dummy_texture: Arc<Texture<VulkanoRendererRaw>>,
 
     matrix_pool: CpuBufferPool<simple2d_vs::ty::MatrixData>,
+    /// Pools the per-batch geometry is uploaded to, these re-use their memory between frames and
+    /// grow when needed.
+    vertex_pool: CpuBufferPool<VkVertex>,
+    index_pool: CpuBufferPool<u32>,
+    instance_pool: CpuBufferPool<VkInstance>,
+    /// The parameters of custom shader materials, uploaded for every custom batch.
+    parameters_pool: CpuBufferPool<[[f32; 4]; MATERIAL_PARAMETERS]>,
     mode_buffers: Vec<Arc<CpuAccessibleBuffer<simple2d_fs::ty::ModeData>>>,
 
+    /// The quad all instances share.
+    quad_buffer: Arc<CpuAccessibleBuffer<[VkQuadCorner]>>,
+    quad_index_buffer: Arc<CpuAccessibleBuffer<[u32]>>,
+
+    /// The shader modules for every registered custom shader, by the shader's ID.
+    custom_shaders: HashMap<usize, CustomShader>,
+
     pub vs: simple2d_vs::Shader,
+    pub instanced_vs: simple2d_instanced_vs::Shader,
     pub fs: simple2d_fs::Shader,
 }
 
@@ -33,23 +57,38 @@ impl VulkanoRenderer2DRaw {
     pub fn new(renderer: &mut Renderer<VulkanoRendererRaw>) -> Result<Self, Error> {
         info!(renderer.log(), "Creating simple2d renderer");
         let dummy_texture = Texture::new()
-            .from_bytes(vec![255u8; 8*8], Vector2::new(8, 8), false)
+            .from_bytes(vec![255u8; 8*8], Vector2::new(8, 8), PixelFormat::R8)
             .as_single_channel()
             .build(renderer)?;
 
         // Load in the shaders
         debug!(renderer.log(), "Creating simple2d shaders");
         let vs = simple2d_vs::Shader::load(renderer.raw().device().clone()).unwrap();
+        let instanced_vs =
+            simple2d_instanced_vs::Shader::load(renderer.raw().device().clone()).unwrap();
         let fs = simple2d_fs::Shader::load(renderer.raw().device().clone()).unwrap();
 
         // Set up the CPU buffer pools we'll use to upload various data
         let matrix_pool = CpuBufferPool::new(
             renderer.raw().device().clone(), BufferUsage::uniform_buffer(),
         );
+        let vertex_pool = CpuBufferPool::new(
+            renderer.raw().device().clone(), BufferUsage::vertex_buffer(),
+        );
+        let index_pool = CpuBufferPool::new(
+            renderer.raw().device().clone(), BufferUsage::index_buffer(),
+        );
+        let instance_pool = CpuBufferPool::new(
+            renderer.raw().device().clone(), BufferUsage::vertex_buffer(),
+        );
+        let parameters_pool = CpuBufferPool::new(
+            renderer.raw().device().clone(), BufferUsage::uniform_buffer(),
+        );
 
-        // Create pre-made mode buffers that can be re-used
+        // Create pre-made mode buffers that can be re-used, for the shader modes and the same modes
+        //  with the vertex color premultiplied
         let mut mode_buffers = Vec::new();
-        for mode_id in 0..4 {
+        for mode_id in 0..(MODE_PREMULTIPLIED as u32 * 2) {
             let buffer = CpuAccessibleBuffer::from_data(
                 renderer.raw().device().clone(), BufferUsage::uniform_buffer(),
                 simple2d_fs::ty::ModeData { mode: mode_id }
@@ -57,13 +96,34 @@ impl VulkanoRenderer2DRaw {
             mode_buffers.push(buffer);
         }
 
+        // Create the quad that will be drawn for every instance, the corners match the order
+        //  used by QUAD_INDICES
+        let corners = [[0.0, 0.0], [0.0, 1.0], [1.0, 0.0], [1.0, 1.0]];
+        let quad_buffer = CpuAccessibleBuffer::from_iter(
+            renderer.raw().device().clone(), BufferUsage::vertex_buffer(),
+            corners.iter().map(|c| VkQuadCorner { v_corner: *c })
+        ).unwrap();
+        let quad_index_buffer = CpuAccessibleBuffer::from_iter(
+            renderer.raw().device().clone(), BufferUsage::index_buffer(),
+            QUAD_INDICES.iter().cloned()
+        ).unwrap();
+
         Ok(VulkanoRenderer2DRaw {
             dummy_texture,
 
             matrix_pool,
+            vertex_pool,
+            index_pool,
+            instance_pool,
+            parameters_pool,
             mode_buffers,
 
-            vs, fs,
+            quad_buffer,
+            quad_index_buffer,
+
+            custom_shaders: HashMap::new(),
+
+            vs, instanced_vs, fs,
         })
     }
 
@@ -75,11 +135,12 @@ impl VulkanoRenderer2DRaw {
         render_target: &mut Renderer2DTarget<VulkanoRendererRaw, VulkanoRenderer2DRaw>,
     ) -> AutoCommandBufferBuilder {
         // Create a projection matrix that just matches coordinates to pixels
+        let size = render_target.size(frame.raw().size);
         let proj =
             // OpenGL expectation of clip space is different from Vulkan
             Matrix4::from_nonuniform_scale(1.0, -1.0, 1.0) *
-            // The projection matrix, coming from Projection, is in OpenGL format
-            set.projection.to_matrix(frame.raw().size);
+            // The projection matrix, coming from the render set, is in OpenGL format
+            set.to_matrix(size);
 
         // Create a buffer for the matrix data to be sent over in
         let total_matrix_raw = proj.into();
@@ -90,10 +151,18 @@ impl VulkanoRenderer2DRaw {
         ).unwrap());
 
         // Go over all batches
-        for batch in &set.batches {
+        for batch in set.sorted_batches() {
+            // Limit drawing to the clip rectangle, skipping the batch if nothing would be drawn
+            let scissor = set.scissor(batch, size).unwrap_or_else(|| Rectangle::new(
+                Point2::new(0, 0), Point2::new(size.x, size.y),
+            ));
+            if scissor.min.x == scissor.max.x || scissor.min.y == scissor.max.y {
+                continue
+            }
+
             buffer_builder = self.render_batch(
                 &batch, buffer_builder,
-                frame.raw().size, renderer, render_target,
+                size, scissor, renderer, render_target,
                 &matrix_data_buffer,
             );
         }
@@ -103,26 +172,37 @@ impl VulkanoRenderer2DRaw {
 
     fn render_batch(
         &mut self,
-        batch: &RenderBatch<VulkanoRendererRaw>, builder: AutoCommandBufferBuilder,
-        size: Vector2<u32>,
+        batch: &RenderBatch<VulkanoRendererRaw>, mut builder: AutoCommandBufferBuilder,
+        size: Vector2<u32>, scissor: Rectangle<u32>,
         renderer: &Renderer<VulkanoRendererRaw>,
         render_target: &mut Renderer2DTarget<VulkanoRendererRaw, VulkanoRenderer2DRaw>,
         matrix_data_buffer: &Arc<CpuBufferPoolSubbuffer<simple2d_vs::ty::MatrixData, Arc<StdMemoryPool>>>,
     ) -> AutoCommandBufferBuilder {
-        // Create a big mesh of all the rectangles we got told to draw this batch
-        let mut vertices = Vec::new();
-        for vertex in &batch.vertices {
-            vertices.push(VkVertex {
-                v_position: vertex.position.into(),
-                v_uv: vertex.uv.into(),
-                v_color: vertex.color.into(),
-            });
-        }
+        // TODO: When a lot is being rendered, check the performance impact of doing this here
+        //  instead of in the pipeline.
+        let dynamic_state = DynamicState {
+            viewports: Some(vec!(Viewport {
+                origin: [0.0, 0.0],
+                depth_range: 0.0 .. 1.0,
+                dimensions: [
+                    size.x as f32,
+                    size.y as f32,
+                ],
+            })),
+            scissors: Some(vec!(Scissor {
+                origin: [scissor.min.x as i32, scissor.min.y as i32],
+                dimensions: [scissor.max.x - scissor.min.x, scissor.max.y - scissor.min.y],
+            })),
+            .. DynamicState::none()
+        };
 
-        // Create the final vertex buffer that we'll send over to the GPU for rendering
-        let vertex_buffer = CpuAccessibleBuffer::from_iter(
-            renderer.raw().device().clone(), BufferUsage::all(), vertices.into_iter()
-        ).unwrap();
+        // Custom shaders have their own pipelines and descriptor sets
+        if let ShaderMode::Custom(ref material) = batch.mode {
+            return self.render_custom_batch(
+                batch, material, builder, dynamic_state,
+                renderer, render_target, matrix_data_buffer,
+            )
+        }
 
         // Get the mode ID this batch has and a texture to render
         let (mode_id, image, sampler) = match &batch.mode {
@@ -133,12 +213,18 @@ impl VulkanoRenderer2DRaw {
                 (1, texture.raw().image(), texture.raw().sampler()),
             &ShaderMode::Mask(ref texture) =>
                 (2, texture.raw().image(), texture.raw().sampler()),
+            &ShaderMode::Custom(_) => unreachable!(),
         };
 
         // Get a buffer containing the mode data
+        let mode_id = if batch.blend_mode == BlendMode::PremultipliedAlpha {
+            mode_id | MODE_PREMULTIPLIED
+        } else {
+            mode_id
+        };
         let mode_data_buffer = self.mode_buffers[mode_id].clone();
 
-        // Create the uniform data set to send over
+        // Create the uniform data set to send over, this is shared by both draws
         let set = Arc::new(render_target.raw_mut().set_pool_mut().next()
             .add_buffer(matrix_data_buffer.clone()).unwrap()
             .add_sampled_image(image.clone(), sampler.clone()).unwrap()
@@ -146,23 +232,137 @@ impl VulkanoRenderer2DRaw {
             .build().unwrap()
         );
 
-        // Add the draw command to the command buffer
-        builder.draw(
-            render_target.raw().pipeline().clone(),
-            // TODO: When a lot is being rendered, check the performance impact of doing
-            //  this here instead of in the pipeline.
-            DynamicState {
-                viewports: Some(vec!(Viewport {
-                    origin: [0.0, 0.0],
-                    depth_range: 0.0 .. 1.0,
-                    dimensions: [
-                        size.x as f32,
-                        size.y as f32,
-                    ],
-                })),
-                .. DynamicState::none()
+        if batch.vertices.len() != 0 {
+            // Create a big mesh of all the rectangles we got told to draw this batch
+            let mut vertices = Vec::new();
+            for vertex in &batch.vertices {
+                vertices.push(VkVertex {
+                    v_position: vertex.position.into(),
+                    v_uv: vertex.uv.into(),
+                    v_color: vertex.color.into(),
+                });
+            }
+
+            // Upload the vertices that we'll send over to the GPU for rendering
+            let vertex_buffer = Arc::new(self.vertex_pool.chunk(vertices).unwrap())
+                as Arc<BufferAccess + Send + Sync>;
+
+            // Add the draw command to the command buffer, indexed if the batch has indices,
+            //  skipping triangles with indices outside of the vertices
+            let pipeline = render_target.raw().pipeline(batch.blend_mode).clone();
+            builder = if let Some(indices) = batch.valid_indices() {
+                let index_buffer =
+                    Arc::new(self.index_pool.chunk(indices.iter().cloned()).unwrap());
+
+                builder.draw_indexed(
+                    pipeline, dynamic_state.clone(),
+                    vec!(vertex_buffer), index_buffer,
+                    set.clone(), ()
+                ).unwrap()
+            } else {
+                builder.draw(
+                    pipeline, dynamic_state.clone(),
+                    vec!(vertex_buffer),
+                    set.clone(), ()
+                ).unwrap()
+            };
+        }
+
+        if batch.instances.len() != 0 {
+            let instance_buffer = Arc::new(self.instance_pool.chunk(
+                batch.instances.iter().map(|instance| VkInstance {
+                    i_position: instance.position.into(),
+                    i_size: instance.size.into(),
+                    i_uv_min: instance.uv.min.into(),
+                    i_uv_max: instance.uv.max.into(),
+                    i_color: instance.color.into(),
+                })
+            ).unwrap());
+
+            // Draw the shared quad once for every instance
+            let buffers = vec!(
+                self.quad_buffer.clone() as Arc<BufferAccess + Send + Sync>,
+                instance_buffer as Arc<BufferAccess + Send + Sync>,
+            );
+            builder = builder.draw_indexed(
+                render_target.raw().instanced_pipeline(batch.blend_mode).clone(),
+                dynamic_state,
+                buffers, self.quad_index_buffer.clone(),
+                set, ()
+            ).unwrap();
+        }
+
+        builder
+    }
+
+    fn render_custom_batch(
+        &mut self,
+        batch: &RenderBatch<VulkanoRendererRaw>, material: &Material<VulkanoRendererRaw>,
+        builder: AutoCommandBufferBuilder, dynamic_state: DynamicState,
+        renderer: &Renderer<VulkanoRendererRaw>,
+        render_target: &mut Renderer2DTarget<VulkanoRendererRaw, VulkanoRenderer2DRaw>,
+        matrix_data_buffer:
+            &Arc<CpuBufferPoolSubbuffer<simple2d_vs::ty::MatrixData, Arc<StdMemoryPool>>>,
+    ) -> AutoCommandBufferBuilder {
+        // Shaders that haven't been registered don't have modules to draw with
+        let shader_id = material.shader.id();
+        let shader = match self.custom_shaders.get(&shader_id) {
+            Some(shader) => shader,
+            None => {
+                warn!(
+                    renderer.log(), "Skipping batch, custom shader isn't registered";
+                    "shader" => &material.shader.name
+                );
+                return builder
+            },
+        };
+
+        // Custom shaders don't have an instanced version, so instances are expanded
+        let vertices = batch.triangle_vertices();
+        if vertices.len() == 0 {
+            return builder
+        }
+
+        let custom = match render_target.raw_mut().custom_pipeline(
+            shader_id, shader, batch.blend_mode, &self.vs, renderer
+        ) {
+            Ok(custom) => custom,
+            Err(e) => {
+                error!(renderer.log(), "Failed to create pipeline for shader \"{}\": {}",
+                    material.shader.name, e);
+                return builder
             },
-            vec!(vertex_buffer.clone()),
+        };
+
+        let mut parameters = [[0.0f32; 4]; MATERIAL_PARAMETERS];
+        for (raw, parameter) in parameters.iter_mut().zip(material.parameters.iter()) {
+            *raw = (*parameter).into();
+        }
+        let parameters_buffer = self.parameters_pool.next(parameters).unwrap();
+
+        // Textures the material doesn't set still need something bound
+        let texture = |i: usize| material.textures.get(i).unwrap_or(&self.dummy_texture).raw();
+        let set = Arc::new(custom.set_pool.next()
+            .add_buffer(matrix_data_buffer.clone()).unwrap()
+            .add_buffer(parameters_buffer).unwrap()
+            .add_sampled_image(texture(0).image().clone(), texture(0).sampler().clone()).unwrap()
+            .add_sampled_image(texture(1).image().clone(), texture(1).sampler().clone()).unwrap()
+            .add_sampled_image(texture(2).image().clone(), texture(2).sampler().clone()).unwrap()
+            .add_sampled_image(texture(3).image().clone(), texture(3).sampler().clone()).unwrap()
+            .build().unwrap()
+        );
+
+        let vertex_buffer = Arc::new(self.vertex_pool.chunk(
+            vertices.iter().map(|vertex| VkVertex {
+                v_position: vertex.position.into(),
+                v_uv: vertex.uv.into(),
+                v_color: vertex.color.into(),
+            })
+        ).unwrap()) as Arc<BufferAccess + Send + Sync>;
+
+        builder.draw(
+            custom.pipeline.clone(), dynamic_state,
+            vec!(vertex_buffer),
             set, ()
         ).unwrap()
     }
@@ -178,24 +378,52 @@ impl Renderer2DRaw<VulkanoRendererRaw> for VulkanoRenderer2DRaw {
         render_target: &mut Renderer2DTarget<VulkanoRendererRaw, Self>,
         renderer: &mut Renderer<VulkanoRendererRaw>,
     ) {
+        // The frame's window may have been removed after the frame was started, in which case
+        //  there's nothing to render to
+        let framebuffer = match render_target.raw.framebuffer_for(
+            frame.window(), frame.raw().image_num, renderer
+        ) {
+            Ok(framebuffer) => framebuffer.clone(),
+            Err(error) => {
+                warn!(renderer.log(), "Skipping simple2d render"; "error" => error.to_string());
+                return
+            },
+        };
+
         // Give the renderer an opportunity to insert any commands it had queued up, this is used
         //  to copy textures for example. This always has to be done right before a render pass.
         let future = renderer.raw_mut().submit_queued_commands(frame.raw_mut().future.take().unwrap());
 
         // Start the command buffer, this will contain the draw commands
-        let mut buffer_builder = {
-            let clear_values = render_target.raw.clear_values();
-            let framebuffer = render_target.raw.framebuffer_for(frame.raw().image_num, renderer);
-
-            AutoCommandBufferBuilder::new(
-                    renderer.raw().device().clone(), renderer.raw().graphics_queue().family()
-                ).unwrap()
-                .begin_render_pass(framebuffer.clone(), false, clear_values).unwrap()
-        };
+        let mut buffer_builder = AutoCommandBufferBuilder::new(
+                renderer.raw().device().clone(), renderer.raw().graphics_queue().family()
+            ).unwrap()
+            .begin_render_pass(framebuffer, false, render_target.raw.clear_values()).unwrap();
 
-        // Render all render sets
-        for set in &data.render_sets {
+        // Render all render sets, every batch is a draw with its own vertex buffer and another
+        //  one with its own instance buffer, if it has vertices and instances respectively
+        for set in data.sorted_sets() {
             buffer_builder = self.render_set(set, buffer_builder, frame, renderer, render_target);
+
+            for batch in &set.batches {
+                let stats = renderer.stats_mut();
+                if let ShaderMode::Custom(_) = batch.mode {
+                    // Custom batches are drawn expanded, in a single draw
+                    stats.draw_calls += 1;
+                    stats.bytes_uploaded += batch.vertex_count() * size_of::<VkVertex>() +
+                        size_of::<[[f32; 4]; MATERIAL_PARAMETERS]>();
+                    continue
+                }
+                if batch.vertices.len() != 0 {
+                    stats.draw_calls += 1;
+                    stats.bytes_uploaded += batch.vertices.len() * size_of::<VkVertex>() +
+                        batch.valid_indices().map(|i| i.len() * size_of::<u32>()).unwrap_or(0);
+                }
+                if batch.instances.len() != 0 {
+                    stats.draw_calls += 1;
+                    stats.bytes_uploaded += batch.instances.len() * size_of::<VkInstance>();
+                }
+            }
         }
 
         // Finish the command buffer
@@ -210,4 +438,13 @@ impl Renderer2DRaw<VulkanoRendererRaw> for VulkanoRenderer2DRaw {
         );
         frame.raw_mut().future = Some(future);
     }
+
+    fn register_shader(
+        &mut self, shader: &Arc<Shader2D>, renderer: &mut Renderer<VulkanoRendererRaw>,
+    ) -> Result<(), Error> {
+        // Pipelines depend on the render pass, so they're created by the targets that use them
+        let custom_shader = CustomShader::new(shader, renderer)?;
+        self.custom_shaders.insert(shader.id(), custom_shader);
+        Ok(())
+    }
 }
@@ -5,3 +5,20 @@ pub struct VkVertex {
 }
 
 impl_vertex!(VkVertex, v_position, v_uv, v_color);
+
+/// A corner of the quad shared by all instances, in 0 to 1 range.
+pub struct VkQuadCorner {
+    pub v_corner: [f32; 2],
+}
+
+impl_vertex!(VkQuadCorner, v_corner);
+
+pub struct VkInstance {
+    pub i_position: [f32; 2],
+    pub i_size: [f32; 2],
+    pub i_uv_min: [f32; 2],
+    pub i_uv_max: [f32; 2],
+    pub i_color: [f32; 4],
+}
+
+impl_vertex!(VkInstance, i_position, i_size, i_uv_min, i_uv_max, i_color);
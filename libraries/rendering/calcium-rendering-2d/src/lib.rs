@@ -4,6 +4,7 @@ extern crate screenmath;
 
 pub mod raw;
 pub mod render_data;
+pub mod shapes;
 mod render_target;
 mod renderer;
 
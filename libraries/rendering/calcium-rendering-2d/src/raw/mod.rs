@@ -1,9 +1,11 @@
 use std::any::{Any};
+use std::sync::{Arc};
 
-use calcium_rendering::{Renderer, Frame};
+use calcium_rendering::{Renderer, Frame, Error};
 use calcium_rendering::raw::{RendererRaw};
+use calcium_rendering::texture::{Texture};
 
-use render_data::{RenderData};
+use render_data::{RenderData, Shader2D};
 use {Renderer2DTarget, Renderer2D};
 
 pub trait Renderer2DRaw<R: RendererRaw>: Any + Sized {
@@ -16,12 +18,27 @@ pub trait Renderer2DRaw<R: RendererRaw>: Any + Sized {
         render_target: &mut Renderer2DTarget<R, Self>,
         renderer: &mut Renderer<R>,
     );
+
+    /// Prepares a custom shader so it can be used by materials, returns an unsupported error if
+    /// the shader doesn't have a version this backend can use.
+    fn register_shader(
+        &mut self, shader: &Arc<Shader2D>, renderer: &mut Renderer<R>,
+    ) -> Result<(), Error>;
 }
 
-pub trait Renderer2DTargetRaw<R: RendererRaw, SR: Renderer2DRaw<R>> {
+pub trait Renderer2DTargetRaw<R: RendererRaw, SR: Renderer2DRaw<R>>: Sized {
     fn new(
         clear: bool,
         renderer: &Renderer<R>,
         simple2d_renderer: &Renderer2D<R, SR>,
     ) -> Self;
+
+    /// Creates a target that renders to the given texture. The texture will have been built with
+    /// `as_render_target`.
+    fn new_texture(
+        clear: bool,
+        texture: &Arc<Texture<R>>,
+        renderer: &Renderer<R>,
+        simple2d_renderer: &Renderer2D<R, SR>,
+    ) -> Result<Self, Error>;
 }
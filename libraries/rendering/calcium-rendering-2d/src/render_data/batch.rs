@@ -1,11 +1,14 @@
+use std::borrow::{Cow};
 use std::sync::{Arc};
 
-use cgmath::{Vector4, Point2};
+use cgmath::{Vector2, Vector4, Point2, Matrix3};
 use screenmath::{Rectangle};
 
 use calcium_rendering::raw::{RendererRaw};
 use calcium_rendering::texture::{Texture};
 
+use render_data::{QuadTransform, ClipRect, Material, transform_point, clip_triangles};
+
 /// A render batch that can be drawn by a renderer. Represents the equivalent of a single drawcall.
 pub struct RenderBatch<R: RendererRaw> {
     /// The shader mode in which a render batch will be drawn.
@@ -14,8 +17,31 @@ pub struct RenderBatch<R: RendererRaw> {
     /// Used to determine what UV coordinates for a full texture are.
     pub uv_mode: UvMode,
 
+    /// How the batch's colors are combined with what's already been drawn. Defaults to Alpha.
+    pub blend_mode: BlendMode,
+
     /// The vertices that will be drawn.
     pub vertices: Vec<DrawVertex>,
+
+    /// If set, triangles are made from the vertices at these indices, rather than from the
+    /// vertices in order. This lets quads share vertices.
+    pub indices: Option<Vec<u32>>,
+
+    /// Quads that will be drawn after the vertices, backends draw these in a single instanced
+    /// draw. This is the cheapest way to draw large amounts of rectangles.
+    pub instances: Vec<QuadInstance>,
+
+    /// If set, nothing in this batch is drawn outside of this rectangle. This is combined with
+    /// the render set's clip rectangle.
+    pub clip: Option<ClipRect>,
+
+    /// Batches on a higher layer are drawn over batches on a lower layer in the same render
+    /// set. Defaults to 0.
+    pub layer: i32,
+
+    /// Within a layer, batches with a higher sort key are drawn over batches with a lower sort
+    /// key. Batches without a sort key are drawn first and batches with a NaN sort key last.
+    pub sort_key: Option<f32>,
 }
 
 impl<R: RendererRaw> RenderBatch<R> {
@@ -23,13 +49,56 @@ impl<R: RendererRaw> RenderBatch<R> {
         RenderBatch {
             mode,
             uv_mode,
+            blend_mode: BlendMode::Alpha,
             vertices: Vec::new(),
+            indices: None,
+            instances: Vec::new(),
+            clip: None,
+            layer: 0,
+            sort_key: None,
+        }
+    }
+
+    /// Makes this batch use indices, after this `push_rectangle` adds 4 vertices and 6 indices
+    /// instead of 6 vertices. Vertices already added get indices in their current order, so they
+    /// still get drawn.
+    pub fn indexed(mut self) -> Self {
+        if self.indices.is_none() {
+            self.indices = Some((0..self.vertices.len() as u32).collect());
         }
+        self
+    }
+
+    pub fn with_blend_mode(mut self, value: BlendMode) -> Self {
+        self.blend_mode = value;
+        self
+    }
+
+    pub fn with_clip(mut self, value: ClipRect) -> Self {
+        self.clip = Some(value);
+        self
+    }
+
+    pub fn with_layer(mut self, value: i32) -> Self {
+        self.layer = value;
+        self
+    }
+
+    pub fn with_sort_key(mut self, value: f32) -> Self {
+        self.sort_key = Some(value);
+        self
     }
 
     /// Returns true if this render batch has nothing to be drawn.
     pub fn empty(&self) -> bool {
-        self.vertices.len() == 0
+        self.vertices.len() == 0 && self.instances.len() == 0
+    }
+
+    /// The amount of vertices that will be processed when drawing this batch, counting indexed
+    /// vertices every time they're used.
+    pub fn vertex_count(&self) -> usize {
+        let vertices = self.indices.as_ref().map(|i| i.len()).unwrap_or(self.vertices.len());
+        vertices + self.instances.len() * 6
     }
 
     /// Adds vertices for a rectangle to this render batch.
@@ -37,25 +106,68 @@ impl<R: RendererRaw> RenderBatch<R> {
         &mut self,
         destination: Rectangle<f32>, texture_source: Rectangle<f32>, color: Vector4<f32>,
     ) {
-        let destination_start_end = destination.min_max().cast();
-        let destination_end_start = destination.max_min().cast();
-
-        let uvs = texture_source;
-        let uvs_min_max = uvs.min_max();
-        let uvs_max_min = uvs.max_min();
-        let (tri1_uvs, tri2_uvs) = (
-            [uvs.min, uvs_min_max, uvs_max_min],
-            [uvs.max, uvs_max_min, uvs_min_max],
-        );
+        let corners = [
+            destination.min, destination.min_max(), destination.max_min(), destination.max,
+        ];
+        self.push_quad(corners, texture_source, color);
+    }
 
-        // Add the two triangles for this quad
-        self.vertices.push(DrawVertex::new(destination.min.cast(), tri1_uvs[0], color));
-        self.vertices.push(DrawVertex::new(destination_start_end, tri1_uvs[1], color));
-        self.vertices.push(DrawVertex::new(destination_end_start, tri1_uvs[2], color));
+    /// Adds vertices for a rectangle to this render batch, rotated, scaled and flipped as
+    /// described by the given transform.
+    pub fn push_rectangle_transformed(
+        &mut self,
+        destination: Rectangle<f32>, transform: &QuadTransform,
+        texture_source: Rectangle<f32>, color: Vector4<f32>,
+    ) {
+        let matrix = transform.to_matrix(destination.min);
+        let texture_source = transform.flip_source(texture_source);
+        self.push_rectangle_matrix(matrix, destination, texture_source, color);
+    }
+
+    /// Adds vertices for a rectangle to this render batch, with its corners transformed by a 2D
+    /// affine transform matrix.
+    pub fn push_rectangle_matrix(
+        &mut self,
+        transform: Matrix3<f32>,
+        destination: Rectangle<f32>, texture_source: Rectangle<f32>, color: Vector4<f32>,
+    ) {
+        let corners = [
+            transform_point(transform, destination.min),
+            transform_point(transform, destination.min_max()),
+            transform_point(transform, destination.max_min()),
+            transform_point(transform, destination.max),
+        ];
+        self.push_quad(corners, texture_source, color);
+    }
+
+    /// Adds vertices for a quad with arbitrary corners to this render batch. The corners are in
+    /// the order used by `QUAD_INDICES`, matching the texture source's min, min-max, max-min and
+    /// max corners.
+    pub fn push_quad(
+        &mut self,
+        corners: [Point2<f32>; 4], texture_source: Rectangle<f32>, color: Vector4<f32>,
+    ) {
+        let uvs = [
+            texture_source.min, texture_source.min_max(),
+            texture_source.max_min(), texture_source.max,
+        ];
+
+        // If we're indexed, the two triangles can share the vertices on the diagonal
+        if let Some(ref mut indices) = self.indices {
+            let base = self.vertices.len() as u32;
+            for i in 0..4 {
+                self.vertices.push(DrawVertex::new(corners[i], uvs[i], color));
+            }
 
-        self.vertices.push(DrawVertex::new(destination.max.cast(), tri2_uvs[0], color));
-        self.vertices.push(DrawVertex::new(destination_end_start, tri2_uvs[1], color));
-        self.vertices.push(DrawVertex::new(destination_start_end, tri2_uvs[2], color));
+            indices.extend(QUAD_INDICES.iter().map(|i| base + i));
+            return
+        }
+
+        // Add the two triangles for this quad
+        for i in &QUAD_INDICES {
+            let i = *i as usize;
+            self.vertices.push(DrawVertex::new(corners[i], uvs[i], color));
+        }
     }
 
     /// Adds vertices for a rectangle to this render batch, assuming the entire texture should be
@@ -75,6 +187,74 @@ impl<R: RendererRaw> RenderBatch<R> {
             Vector4::new(1.0, 1.0, 1.0, 1.0),
         );
     }
+
+    /// Adds a list of triangles to this render batch, every 3 vertices make up a triangle.
+    pub fn push_triangles(&mut self, vertices: &[DrawVertex]) {
+        if let Some(ref mut indices) = self.indices {
+            let base = self.vertices.len() as u32;
+            indices.extend((0..vertices.len() as u32).map(|i| base + i));
+        }
+
+        self.vertices.extend_from_slice(vertices);
+    }
+
+    /// Adds an instanced quad to this render batch.
+    pub fn push_instance(
+        &mut self,
+        destination: Rectangle<f32>, texture_source: Rectangle<f32>, color: Vector4<f32>,
+    ) {
+        self.instances.push(QuadInstance::new(destination, texture_source, color));
+    }
+
+    /// Returns the indices of this batch, if it has any, without the triangles that are
+    /// incomplete or use indices outside of the vertices. The indices are only copied if
+    /// triangles have to be skipped.
+    pub fn valid_indices<'a>(&'a self) -> Option<Cow<'a, [u32]>> {
+        self.indices.as_ref().map(|indices| {
+            let valid = |triangle: &[u32]|
+                triangle.len() == 3 && triangle.iter().all(|i| (*i as usize) < self.vertices.len());
+
+            if indices.chunks(3).all(&valid) {
+                Cow::Borrowed(&indices[..])
+            } else {
+                Cow::Owned(indices.chunks(3)
+                    .filter(|t| valid(t))
+                    .flat_map(|t| t.iter().cloned())
+                    .collect())
+            }
+        })
+    }
+
+    /// Returns all triangles in this batch as a flat list of vertices, with the indices and
+    /// instances expanded. Backends that can't draw indexed or instanced geometry can use this.
+    /// Triangles with indices outside of the vertices are skipped.
+    pub fn triangle_vertices(&self) -> Vec<DrawVertex> {
+        let mut vertices = if let Some(indices) = self.valid_indices() {
+            indices.iter().map(|i| self.vertices[*i as usize].clone()).collect()
+        } else {
+            self.vertices.clone()
+        };
+
+        for instance in &self.instances {
+            let corners = instance.vertices();
+            for i in &QUAD_INDICES {
+                vertices.push(corners[*i as usize].clone());
+            }
+        }
+
+        vertices
+    }
+
+    /// Cuts off all triangles in this batch outside of a rectangle in vertex coordinates. This
+    /// is done on the CPU, so it works regardless of backend, but it's much slower than a clip
+    /// rectangle. Indices and instances are expanded into plain vertices.
+    pub fn clip_to(&mut self, clip: &Rectangle<f32>) {
+        self.vertices = clip_triangles(&self.triangle_vertices(), clip);
+        if self.indices.is_some() {
+            self.indices = Some((0..self.vertices.len() as u32).collect());
+        }
+        self.instances.clear();
+    }
 }
 
 impl<R: RendererRaw> Clone for RenderBatch<R> {
@@ -82,9 +262,54 @@ impl<R: RendererRaw> Clone for RenderBatch<R> {
         RenderBatch {
             mode: self.mode.clone(),
             uv_mode: self.uv_mode,
+            blend_mode: self.blend_mode,
             vertices: self.vertices.clone(),
+            indices: self.indices.clone(),
+            instances: self.instances.clone(),
+            clip: self.clip.clone(),
+            layer: self.layer,
+            sort_key: self.sort_key,
+        }
+    }
+}
+
+/// The indices of the two triangles of a quad, into the quad's corners as returned by
+/// `QuadInstance::vertices`.
+pub const QUAD_INDICES: [u32; 6] = [0, 1, 2, 3, 2, 1];
+
+/// A rectangle drawn using instancing, all instances in a batch share the same quad geometry.
+#[derive(Debug, Clone)]
+pub struct QuadInstance {
+    /// The position of the quad's minimum corner, in the same coordinates as vertex positions.
+    pub position: Point2<f32>,
+    /// The size of the quad.
+    pub size: Vector2<f32>,
+    /// The UV values at the quad's minimum and maximum corners.
+    pub uv: Rectangle<f32>,
+    /// The color of the quad, in linear color space.
+    pub color: Vector4<f32>,
+}
+
+impl QuadInstance {
+    pub fn new(destination: Rectangle<f32>, uv: Rectangle<f32>, color: Vector4<f32>) -> Self {
+        QuadInstance {
+            position: destination.min,
+            size: destination.size(),
+            uv,
+            color,
         }
     }
+
+    /// Returns the vertices at this quad's corners, in the order used by `QUAD_INDICES`.
+    pub fn vertices(&self) -> [DrawVertex; 4] {
+        let destination = Rectangle::start_size(self.position, self.size);
+        [
+            DrawVertex::new(destination.min, self.uv.min, self.color),
+            DrawVertex::new(destination.min_max(), self.uv.min_max(), self.color),
+            DrawVertex::new(destination.max_min(), self.uv.max_min(), self.color),
+            DrawVertex::new(destination.max, self.uv.max, self.color),
+        ]
+    }
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -93,6 +318,37 @@ pub enum UvMode {
     YDown,
 }
 
+/// Defines how a render batch's output color is combined with the color already on the target.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum BlendMode {
+    /// Standard alpha blending, the output's color is faded in by its alpha.
+    Alpha,
+    /// Alpha blending for colors that have already been multiplied by their alpha, for example
+    /// from textures built with `premultiply_alpha`. The built-in shader modes multiply the
+    /// vertices' color by its alpha to match, custom shaders have to do this themselves.
+    PremultipliedAlpha,
+    /// Adds the output's color, faded by its alpha, to the target. Useful for lights and glows.
+    Additive,
+    /// Multiplies the target's color by the output's color. Useful for shadows and tinting.
+    Multiply,
+    /// Replaces the target's color, ignoring alpha.
+    Opaque,
+}
+
+impl BlendMode {
+    /// The index of this blend mode in `BLEND_MODES`, backends use this to look up the pipeline
+    /// for a blend mode.
+    pub fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// All blend modes, in the order of `BlendMode::index`.
+pub const BLEND_MODES: [BlendMode; 5] = [
+    BlendMode::Alpha, BlendMode::PremultipliedAlpha, BlendMode::Additive,
+    BlendMode::Multiply, BlendMode::Opaque,
+];
+
 /// Defines how the renderer should draw vertices.
 pub enum ShaderMode<R: RendererRaw> {
     /// Uses only the vertices' colors.
@@ -101,6 +357,9 @@ pub enum ShaderMode<R: RendererRaw> {
     Texture(Arc<Texture<R>>),
     /// Uses the vertices' color's RGB and the texture's Alpha.
     Mask(Arc<Texture<R>>),
+    /// Uses a custom shader, which has to be registered with the renderer first. Instances in
+    /// the batch are expanded into vertices.
+    Custom(Arc<Material<R>>),
 }
 
 impl<R: RendererRaw> Clone for ShaderMode<R> {
@@ -109,6 +368,7 @@ impl<R: RendererRaw> Clone for ShaderMode<R> {
             ShaderMode::Color => ShaderMode::Color,
             ShaderMode::Texture(ref t) => ShaderMode::Texture(t.clone()),
             ShaderMode::Mask(ref t) => ShaderMode::Mask(t.clone()),
+            ShaderMode::Custom(ref m) => ShaderMode::Custom(m.clone()),
         }
     }
 }
@@ -0,0 +1,150 @@
+use cgmath::{Vector2, Vector4, Point2, Matrix4};
+use screenmath::{Rectangle};
+
+use render_data::{DrawVertex};
+
+/// A rectangle outside of which a render set or batch doesn't draw anything.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClipRect {
+    /// A rectangle in the same coordinates as the vertices, transformed by the render set's
+    /// projection. If the projection rotates, this clips to the bounds of the rotated rectangle.
+    Projected(Rectangle<f32>),
+    /// A rectangle in pixels on the render target, starting at the top left.
+    Pixels(Rectangle<f32>),
+}
+
+impl ClipRect {
+    /// Resolves this clip rectangle to pixels on the target, starting at the top left and
+    /// limited to the target's size.
+    pub fn to_pixels(
+        &self, projection: Matrix4<f32>, target_size: Vector2<u32>,
+    ) -> Rectangle<u32> {
+        let rectangle = match *self {
+            ClipRect::Projected(ref rectangle) => {
+                // A rotating projection moves the other two corners outside of the rectangle
+                //  between min and max, so all four are needed for the bounds
+                let corners = [
+                    rectangle.min,
+                    Point2::new(rectangle.max.x, rectangle.min.y),
+                    rectangle.max,
+                    Point2::new(rectangle.min.x, rectangle.max.y),
+                ];
+                let projected: Vec<_> = corners.iter()
+                    .map(|c| project_point(projection, *c, target_size))
+                    .collect();
+                let (min, max) = projected.iter().skip(1).fold(
+                    (projected[0], projected[0]),
+                    |(min, max), p| (
+                        Point2::new(min.x.min(p.x), min.y.min(p.y)),
+                        Point2::new(max.x.max(p.x), max.y.max(p.y)),
+                    ),
+                );
+                Rectangle::new(min, max)
+            },
+            ClipRect::Pixels(ref rectangle) => rectangle.clone(),
+        };
+
+        let clamp = |value: f32, max: u32| value.round().max(0.0).min(max as f32) as u32;
+        let min = Point2::new(
+            clamp(rectangle.min.x, target_size.x), clamp(rectangle.min.y, target_size.y),
+        );
+        let max = Point2::new(
+            clamp(rectangle.max.x, target_size.x).max(min.x),
+            clamp(rectangle.max.y, target_size.y).max(min.y),
+        );
+        Rectangle::new(min, max)
+    }
+}
+
+/// Transforms a point using a projection matrix, into pixels on the target starting at the top
+/// left.
+pub fn project_point(
+    projection: Matrix4<f32>, point: Point2<f32>, target_size: Vector2<u32>,
+) -> Point2<f32> {
+    // Projections are in OpenGL's clip space, with Y pointing up
+    let clip = projection * Vector4::new(point.x, point.y, 0.0, 1.0);
+    let normalized = Vector2::new(clip.x / clip.w, clip.y / clip.w);
+
+    Point2::new(
+        (normalized.x + 1.0) * 0.5 * target_size.x as f32,
+        (1.0 - normalized.y) * 0.5 * target_size.y as f32,
+    )
+}
+
+/// Returns the overlapping part of two pixel rectangles. If they don't overlap, the result has
+/// no area.
+pub fn intersect_pixels(a: &Rectangle<u32>, b: &Rectangle<u32>) -> Rectangle<u32> {
+    let min = Point2::new(a.min.x.max(b.min.x), a.min.y.max(b.min.y));
+    let max = Point2::new(a.max.x.min(b.max.x).max(min.x), a.max.y.min(b.max.y).max(min.y));
+    Rectangle::new(min, max)
+}
+
+/// Clips a list of triangles to a rectangle on the CPU, interpolating the UVs and colors of
+/// vertices created on the rectangle's edges. Used by backends that can't scissor.
+pub fn clip_triangles(vertices: &[DrawVertex], clip: &Rectangle<f32>) -> Vec<DrawVertex> {
+    let mut clipped = Vec::with_capacity(vertices.len());
+
+    for triangle in vertices.chunks(3) {
+        if triangle.len() != 3 {
+            break
+        }
+
+        // Most triangles are either fully inside or fully outside, skip the expensive part
+        let inside = |v: &DrawVertex| clip.contains(v.position);
+        if triangle.iter().all(|v| inside(v)) {
+            clipped.extend_from_slice(triangle);
+            continue
+        }
+
+        // Clip the triangle against every edge of the rectangle in turn
+        let mut polygon = triangle.to_vec();
+        polygon = clip_polygon(&polygon, |p| p.x - clip.min.x);
+        polygon = clip_polygon(&polygon, |p| clip.max.x - p.x);
+        polygon = clip_polygon(&polygon, |p| p.y - clip.min.y);
+        polygon = clip_polygon(&polygon, |p| clip.max.y - p.y);
+
+        // What's left is convex, so it can be turned back into triangles as a fan
+        for i in 1..polygon.len().saturating_sub(1) {
+            clipped.push(polygon[0].clone());
+            clipped.push(polygon[i].clone());
+            clipped.push(polygon[i + 1].clone());
+        }
+    }
+
+    clipped
+}
+
+/// Clips a convex polygon to the side of an edge where the distance function is positive.
+fn clip_polygon<F: Fn(Point2<f32>) -> f32>(
+    polygon: &[DrawVertex], distance: F,
+) -> Vec<DrawVertex> {
+    let mut result = Vec::with_capacity(polygon.len() + 1);
+
+    for i in 0..polygon.len() {
+        let (current, next) = (&polygon[i], &polygon[(i + 1) % polygon.len()]);
+        let current_distance = distance(current.position);
+        let next_distance = distance(next.position);
+
+        if current_distance >= 0.0 {
+            result.push(current.clone());
+        }
+
+        // If the edge crosses the clipping edge, add a vertex where it crosses, vertices exactly
+        //  on the clipping edge are already added as they are
+        if (current_distance > 0.0 && next_distance < 0.0) ||
+            (current_distance < 0.0 && next_distance > 0.0) {
+            let t = current_distance / (current_distance - next_distance);
+            result.push(lerp_vertex(current, next, t));
+        }
+    }
+
+    result
+}
+
+fn lerp_vertex(a: &DrawVertex, b: &DrawVertex, t: f32) -> DrawVertex {
+    DrawVertex::new(
+        a.position + (b.position - a.position) * t,
+        a.uv + (b.uv - a.uv) * t,
+        a.color + (b.color - a.color) * t,
+    )
+}
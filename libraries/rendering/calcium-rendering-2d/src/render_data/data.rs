@@ -1,6 +1,14 @@
+use std::cmp::{Ordering};
+
+use cgmath::{Vector2, Point2, Matrix4, EuclideanSpace, Zero};
+use screenmath::{Rectangle};
+
 use calcium_rendering::raw::{RendererRaw};
 
-use render_data::{RenderBatch, Projection};
+use render_data::{
+    RenderBatch, Projection, ClipRect, intersect_pixels, project_point, unproject_point,
+    area_matrix, float_order,
+};
 
 pub struct RenderData<R: RendererRaw> {
     pub render_sets: Vec<RenderSet<R>>,
@@ -12,11 +20,37 @@ impl<R: RendererRaw> RenderData<R> {
             render_sets: Vec::new(),
         }
     }
+
+    /// Returns the render sets in the order they should be drawn, sorted by layer and then by
+    /// sort key. The sort is stable, so sets that are equal are drawn in the order they were
+    /// added.
+    pub fn sorted_sets(&self) -> Vec<&RenderSet<R>> {
+        let mut sets: Vec<_> = self.render_sets.iter().collect();
+        sets.sort_by(|a, b| draw_order(a.layer, a.sort_key, b.layer, b.sort_key));
+        sets
+    }
 }
 
 pub struct RenderSet<R: RendererRaw> {
     pub projection: Projection,
     pub batches: Vec<RenderBatch<R>>,
+
+    /// If set, nothing in this render set is drawn outside of this rectangle. Batches can
+    /// further limit this with their own clip rectangle.
+    pub clip: Option<ClipRect>,
+
+    /// Render sets on a higher layer are drawn over sets on a lower layer. Defaults to 0.
+    pub layer: i32,
+
+    /// Within a layer, render sets with a higher sort key are drawn over sets with a lower sort
+    /// key. Sets without a sort key are drawn first and sets with a NaN sort key last. For
+    /// example, a top-down game could use the Y coordinate of its objects here.
+    pub sort_key: Option<f32>,
+
+    /// If set, this render set is drawn into this rectangle in pixels on the target, starting at
+    /// the top left, as if it were the whole target. Useful for split-screen and
+    /// picture-in-picture. Nothing is drawn outside of the viewport.
+    pub viewport: Option<Rectangle<f32>>,
 }
 
 impl<R: RendererRaw> RenderSet<R> {
@@ -24,6 +58,129 @@ impl<R: RendererRaw> RenderSet<R> {
         RenderSet {
             projection,
             batches,
+            clip: None,
+            layer: 0,
+            sort_key: None,
+            viewport: None,
         }
     }
+
+    pub fn with_clip(mut self, value: ClipRect) -> Self {
+        self.clip = Some(value);
+        self
+    }
+
+    pub fn with_layer(mut self, value: i32) -> Self {
+        self.layer = value;
+        self
+    }
+
+    pub fn with_sort_key(mut self, value: f32) -> Self {
+        self.sort_key = Some(value);
+        self
+    }
+
+    pub fn with_viewport(mut self, value: Rectangle<f32>) -> Self {
+        self.viewport = Some(value);
+        self
+    }
+
+    /// The size in pixels of the area this render set is drawn into, the viewport if there is
+    /// one or otherwise the whole target. The viewport's size is rounded to whole pixels.
+    pub fn viewport_size(&self, target_size: Vector2<u32>) -> Vector2<u32> {
+        self.rounded_viewport()
+            .map(|v| Vector2::new(v.size().x.round() as u32, v.size().y.round() as u32))
+            .unwrap_or(target_size)
+    }
+
+    /// The viewport with its size rounded to whole pixels. The projection is created for the
+    /// rounded size, so it has to be squashed into an area of that same size.
+    fn rounded_viewport(&self) -> Option<Rectangle<f32>> {
+        self.viewport.as_ref().map(|v| {
+            let size = v.size();
+            let size = Vector2::new(size.x.round().max(1.0), size.y.round().max(1.0));
+            Rectangle::new(v.min, v.min + size)
+        })
+    }
+
+    /// Creates the matrix that transforms this render set's coordinates to the target's clip
+    /// space, including the viewport. Backends should use this rather than the projection's
+    /// matrix.
+    pub fn to_matrix(&self, target_size: Vector2<u32>) -> Matrix4<f32> {
+        let projection = self.projection.to_matrix(self.viewport_size(target_size));
+
+        if let Some(ref viewport) = self.rounded_viewport() {
+            area_matrix(viewport, target_size) * projection
+        } else {
+            projection
+        }
+    }
+
+    /// Converts a point in this render set's coordinates to pixels on the target, starting at
+    /// the top left.
+    pub fn world_to_screen(&self, point: Point2<f32>, target_size: Vector2<u32>) -> Point2<f32> {
+        project_point(self.to_matrix(target_size), point, target_size)
+    }
+
+    /// Converts pixels on the target, starting at the top left, to a point in this render set's
+    /// coordinates. This can be used to find what's under the mouse.
+    pub fn screen_to_world(&self, point: Point2<f32>, target_size: Vector2<u32>) -> Point2<f32> {
+        unproject_point(self.to_matrix(target_size), point, target_size)
+    }
+
+    /// Returns the batches in this set in the order they should be drawn, sorted by layer and
+    /// then by sort key. The sort is stable, so batches that are equal are drawn in the order
+    /// they were added.
+    pub fn sorted_batches(&self) -> Vec<&RenderBatch<R>> {
+        let mut batches: Vec<_> = self.batches.iter().collect();
+        batches.sort_by(|a, b| draw_order(a.layer, a.sort_key, b.layer, b.sort_key));
+        batches
+    }
+
+    /// Resolves the area in pixels a batch in this set is limited to, combining the set's
+    /// viewport, the projection's visible area and the set's and batch's clip rectangles.
+    /// Returns None if there's none of those. If they don't overlap, the returned area is empty
+    /// and the batch doesn't need to be drawn.
+    pub fn scissor(
+        &self, batch: &RenderBatch<R>, target_size: Vector2<u32>,
+    ) -> Option<Rectangle<u32>> {
+        // Projections like a virtual resolution leave bars around what they draw, which are in
+        //  the viewport's pixels if there is one
+        let projection_area = self.projection.visible_area(self.viewport_size(target_size))
+            .map(|area| {
+                let offset = self.viewport.as_ref()
+                    .map(|v| v.min.to_vec())
+                    .unwrap_or(Vector2::zero());
+                ClipRect::Pixels(Rectangle::new(area.min + offset, area.max + offset))
+            });
+
+        if self.viewport.is_none() && projection_area.is_none() &&
+            self.clip.is_none() && batch.clip.is_none() {
+            return None
+        }
+
+        let projection = self.to_matrix(target_size);
+        let mut scissor = Rectangle::new(
+            Point2::new(0, 0), Point2::new(target_size.x, target_size.y),
+        );
+        let viewport = self.rounded_viewport().map(ClipRect::Pixels);
+        let clips = viewport.iter().chain(projection_area.iter())
+            .chain(self.clip.iter()).chain(batch.clip.iter());
+        for clip in clips {
+            scissor = intersect_pixels(&scissor, &clip.to_pixels(projection, target_size));
+        }
+
+        Some(scissor)
+    }
+}
+
+/// Compares the layers and sort keys of two sets or batches. Missing sort keys go before any
+/// sort key and NaN sort keys after all others, so they compare consistently.
+fn draw_order(
+    a_layer: i32, a_key: Option<f32>, b_layer: i32, b_key: Option<f32>,
+) -> Ordering {
+    a_layer.cmp(&b_layer).then_with(|| match (a_key, b_key) {
+        (Some(a), Some(b)) => float_order(a, b),
+        (a, b) => a.is_some().cmp(&b.is_some()),
+    })
 }
@@ -0,0 +1,217 @@
+use std::sync::{Arc};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use cgmath::{Vector4, Point2};
+
+use calcium_rendering::raw::{RendererRaw};
+use calcium_rendering::texture::{Texture};
+
+/// The amount of vec4 parameters every material passes to its shader.
+pub const MATERIAL_PARAMETERS: usize = 8;
+
+/// The maximum amount of textures a material can pass to its shader.
+pub const MATERIAL_TEXTURES: usize = 4;
+
+static NEXT_SHADER_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// A user-defined shader for drawing render batches, with a version of the shader for every
+/// backend. A shader has to be registered using `Renderer2D::register_shader` before it can be
+/// used, registering fails if the shader has no version the backend can use.
+///
+/// Custom shaders use the same vertex data as the built-in shader. Besides the vertices' UV and
+/// color, the fragment shader gets `MATERIAL_PARAMETERS` vec4 parameters and
+/// `MATERIAL_TEXTURES` textures. Textures the material doesn't set shouldn't be sampled.
+///
+/// GLSL 150 vertex shaders get the same inputs as the built-in one and fragment shaders get
+/// the following:
+///
+/// ```glsl
+/// uniform Parameters {
+///     vec4 u_parameter0;
+///     // Up to u_parameter7, in order
+/// };
+/// uniform sampler2D u_texture0; // Up to u_texture3
+///
+/// in vec2 f_uv;
+/// in vec4 f_color;
+///
+/// out vec4 Target0;
+/// ```
+///
+/// SPIR-V vertex shaders get the same inputs as the built-in one and fragment shaders get the
+/// following:
+///
+/// ```glsl
+/// layout(set = 0, binding = 1) uniform ParameterData {
+///     vec4 parameters[8];
+/// } u_parameters;
+/// layout(set = 0, binding = 2) uniform sampler2D u_texture0; // Up to u_texture3 at binding 5
+///
+/// layout(location = 0) in vec2 f_uv;
+/// layout(location = 1) in vec4 f_color;
+///
+/// layout(location = 0) out vec4 o_color;
+/// ```
+pub struct Shader2D {
+    id: usize,
+
+    /// A name to identify this shader by in logs and recordings.
+    pub name: String,
+
+    /// GLSL 150 core shaders, used by the OpenGL backend.
+    pub glsl_150: Option<ShaderPair<String>>,
+
+    /// SPIR-V shaders, used by the Vulkan backend. Only set through `with_spirv`, as the
+    /// backend can't check them.
+    spirv: Option<ShaderPair<Vec<u8>>>,
+
+    /// A function used in place of the fragment shader, used by the software backend.
+    pub cpu: Option<Arc<Fn(&CpuShaderInput) -> Vector4<f32> + Send + Sync>>,
+}
+
+impl Shader2D {
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        Shader2D {
+            id: NEXT_SHADER_ID.fetch_add(1, Ordering::Relaxed),
+            name: name.into(),
+            glsl_150: None,
+            spirv: None,
+            cpu: None,
+        }
+    }
+
+    /// Sets the GLSL 150 core shaders. If no vertex shader is given, the built-in vertex shader
+    /// is used.
+    pub fn with_glsl_150<S: Into<String>>(mut self, vertex: Option<S>, fragment: S) -> Self {
+        self.glsl_150 = Some(ShaderPair {
+            vertex: vertex.map(|v| v.into()),
+            fragment: fragment.into(),
+        });
+        self
+    }
+
+    /// Sets the SPIR-V shaders. If no vertex shader is given, the built-in vertex shader is
+    /// used.
+    ///
+    /// # Safety
+    ///
+    /// The Vulkan backend hands the SPIR-V to the driver as-is, it can't check it. The shaders
+    /// must be valid SPIR-V with a `main` entry point and use exactly the interface documented
+    /// on `Shader2D`. Anything else is undefined behavior in the driver.
+    pub unsafe fn with_spirv(mut self, vertex: Option<Vec<u8>>, fragment: Vec<u8>) -> Self {
+        self.spirv = Some(ShaderPair {
+            vertex,
+            fragment,
+        });
+        self
+    }
+
+    /// Sets the function used in place of the fragment shader on the CPU.
+    pub fn with_cpu<F>(mut self, value: F) -> Self
+        where F: Fn(&CpuShaderInput) -> Vector4<f32> + Send + Sync + 'static
+    {
+        self.cpu = Some(Arc::new(value));
+        self
+    }
+
+    /// The SPIR-V shaders, if set using `with_spirv`.
+    pub fn spirv(&self) -> Option<&ShaderPair<Vec<u8>>> {
+        self.spirv.as_ref()
+    }
+
+    /// A number unique to this shader, backends use this to find what they created for it.
+    pub fn id(&self) -> usize {
+        self.id
+    }
+}
+
+/// A vertex and fragment shader.
+#[derive(Debug, Clone)]
+pub struct ShaderPair<T> {
+    /// The vertex shader, or None to use the built-in vertex shader.
+    pub vertex: Option<T>,
+    pub fragment: T,
+}
+
+/// What a CPU shader function gets for every pixel.
+pub struct CpuShaderInput<'a> {
+    pub uv: Point2<f32>,
+    pub color: Vector4<f32>,
+    pub parameters: &'a [Vector4<f32>; MATERIAL_PARAMETERS],
+    sampler: &'a Fn(usize, Point2<f32>) -> Vector4<f32>,
+}
+
+impl<'a> CpuShaderInput<'a> {
+    /// Creates the input for a pixel, backends provide a function to sample the material's
+    /// textures by index.
+    pub fn new(
+        uv: Point2<f32>, color: Vector4<f32>,
+        parameters: &'a [Vector4<f32>; MATERIAL_PARAMETERS],
+        sampler: &'a Fn(usize, Point2<f32>) -> Vector4<f32>,
+    ) -> Self {
+        CpuShaderInput {
+            uv,
+            color,
+            parameters,
+            sampler,
+        }
+    }
+
+    /// Samples one of the material's textures, in linear color space.
+    pub fn sample(&self, texture: usize, uv: Point2<f32>) -> Vector4<f32> {
+        (self.sampler)(texture, uv)
+    }
+}
+
+/// A custom shader together with the values of its parameters and textures, used to draw
+/// batches with `ShaderMode::Custom`.
+pub struct Material<R: RendererRaw> {
+    pub shader: Arc<Shader2D>,
+    pub parameters: [Vector4<f32>; MATERIAL_PARAMETERS],
+    pub textures: Vec<Arc<Texture<R>>>,
+}
+
+impl<R: RendererRaw> Material<R> {
+    pub fn new(shader: Arc<Shader2D>) -> Self {
+        Material {
+            shader,
+            parameters: [Vector4::new(0.0, 0.0, 0.0, 0.0); MATERIAL_PARAMETERS],
+            textures: Vec::new(),
+        }
+    }
+
+    /// Sets a parameter to a single float, in the parameter's X component. Panics if the index
+    /// isn't below `MATERIAL_PARAMETERS`.
+    pub fn with_float(self, index: usize, value: f32) -> Self {
+        self.with_vector(index, Vector4::new(value, 0.0, 0.0, 0.0))
+    }
+
+    /// Sets a parameter. Panics if the index isn't below `MATERIAL_PARAMETERS`.
+    pub fn with_vector(mut self, index: usize, value: Vector4<f32>) -> Self {
+        assert!(
+            index < MATERIAL_PARAMETERS,
+            "Material parameter {} is out of range, materials have {} parameters",
+            index, MATERIAL_PARAMETERS
+        );
+        self.parameters[index] = value;
+        self
+    }
+
+    /// Adds a texture, the first texture added is texture 0. Panics if the material already
+    /// has `MATERIAL_TEXTURES` textures.
+    pub fn with_texture(mut self, value: Arc<Texture<R>>) -> Self {
+        assert!(self.textures.len() < MATERIAL_TEXTURES, "Too many textures for material");
+        self.textures.push(value);
+        self
+    }
+}
+
+impl<R: RendererRaw> Clone for Material<R> {
+    fn clone(&self) -> Self {
+        Material {
+            shader: self.shader.clone(),
+            parameters: self.parameters,
+            textures: self.textures.clone(),
+        }
+    }
+}
@@ -1,10 +1,39 @@
 mod batch;
+mod clip;
 mod data;
+mod material;
 mod projection;
+mod sprite_batcher;
+mod transform;
 
 pub use self::data::{RenderData, RenderSet};
-pub use self::batch::{RenderBatch, ShaderMode, DrawVertex, UvMode};
-pub use self::projection::{Projection, Camera};
+pub use self::batch::{
+    RenderBatch, ShaderMode, DrawVertex, UvMode, BlendMode, QuadInstance, QUAD_INDICES,
+    BLEND_MODES,
+};
+pub use self::clip::{ClipRect, clip_triangles, intersect_pixels, project_point};
+pub use self::material::{
+    Shader2D, ShaderPair, CpuShaderInput, Material, MATERIAL_PARAMETERS, MATERIAL_TEXTURES,
+};
+pub use self::projection::{
+    Projection, Camera, VirtualResolution, ScalePolicy, unproject_point, area_matrix,
+};
+pub use self::sprite_batcher::{SpriteBatcher, Sprite};
+pub use self::transform::{
+    QuadTransform, TransformStack,
+    affine_translation, affine_rotation, affine_scale, transform_point,
+};
 
 // Re-export screenmath types for convenience
 pub use screenmath::{Rectangle};
+
+use std::cmp::{Ordering};
+
+/// Compares two depths or sort keys, NaN goes after all other values so the ordering stays
+/// total.
+pub(crate) fn float_order(a: f32, b: f32) -> Ordering {
+    match (a.is_nan(), b.is_nan()) {
+        (false, false) => a.partial_cmp(&b).unwrap(),
+        (a_nan, b_nan) => a_nan.cmp(&b_nan),
+    }
+}
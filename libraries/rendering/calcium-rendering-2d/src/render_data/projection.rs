@@ -1,9 +1,19 @@
-use cgmath::{self, Vector2, Point2, Matrix4, Vector3};
+use cgmath::{self, Vector2, Vector4, Point2, Matrix4, Vector3, Rad, SquareMatrix};
+use screenmath::{Rectangle};
+
+use calcium_rendering::{Error};
+
+use render_data::{project_point};
 
 /// Defines how the coordinates in render batches will be translated to the screen.
+#[derive(Debug, Clone)]
 pub enum Projection {
     Pixels,
     Camera(Camera),
+    /// Uses the given matrix as-is, regardless of the size of the target.
+    Matrix(Matrix4<f32>),
+    /// Uses pixel coordinates on a fixed size virtual screen, which is scaled into the target.
+    Virtual(VirtualResolution),
 }
 
 impl Projection {
@@ -19,14 +29,163 @@ impl Projection {
             Projection::Camera(ref camera) => {
                 camera.to_matrix(target_size)
             },
+            Projection::Matrix(matrix) => matrix,
+            Projection::Virtual(ref resolution) => {
+                resolution.to_matrix(target_size)
+            },
+        }
+    }
+
+    /// The area in pixels on the target this projection is limited to, if it is. Anything
+    /// outside of it is not drawn.
+    pub fn visible_area(&self, target_size: Vector2<u32>) -> Option<Rectangle<f32>> {
+        match *self {
+            Projection::Virtual(ref resolution) => Some(resolution.area(target_size)),
+            _ => None,
+        }
+    }
+}
+
+/// Defines how a virtual resolution is scaled to fit into the target.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ScalePolicy {
+    /// Scales to exactly cover the target, the aspect ratio isn't kept.
+    Stretch,
+    /// Scales as large as possible while staying inside the target, adding bars to the sides
+    /// if the aspect ratio doesn't match.
+    Fit,
+    /// Like Fit, but only scales by whole numbers so pixels stay the same size. If the target
+    /// is smaller than the virtual resolution, it's drawn at its normal size.
+    IntegerScale,
+    /// Scales to cover the entire target while keeping the aspect ratio, cutting off the sides
+    /// if the aspect ratio doesn't match.
+    Fill,
+}
+
+/// A fixed size virtual screen that's scaled into the target, useful for pixel-art games.
+#[derive(Debug, Clone)]
+pub struct VirtualResolution {
+    size: Vector2<u32>,
+    pub policy: ScalePolicy,
+}
+
+impl VirtualResolution {
+    /// Creates a new virtual resolution, returns an error if the size is zero in either
+    /// direction, as that can't be scaled into a target.
+    pub fn new(size: Vector2<u32>, policy: ScalePolicy) -> Result<Self, Error> {
+        if size.x == 0 || size.y == 0 {
+            return Err(Error::Unsupported(format!(
+                "Virtual resolution size {}x{} can't be zero", size.x, size.y
+            )))
+        }
+
+        Ok(VirtualResolution {
+            size,
+            policy,
+        })
+    }
+
+    /// The size of the virtual screen, in virtual pixels. This is never zero.
+    pub fn size(&self) -> Vector2<u32> {
+        self.size
+    }
+
+    /// The scale from virtual pixels to pixels on the target.
+    pub fn scale(&self, target_size: Vector2<u32>) -> Vector2<f32> {
+        let target: Vector2<f32> = target_size.cast();
+        let size: Vector2<f32> = self.size.cast();
+        let ratio = Vector2::new(target.x / size.x, target.y / size.y);
+
+        match self.policy {
+            ScalePolicy::Stretch => ratio,
+            ScalePolicy::Fit => {
+                let scale = ratio.x.min(ratio.y);
+                Vector2::new(scale, scale)
+            },
+            ScalePolicy::IntegerScale => {
+                let scale = ratio.x.min(ratio.y).floor().max(1.0);
+                Vector2::new(scale, scale)
+            },
+            ScalePolicy::Fill => {
+                let scale = ratio.x.max(ratio.y);
+                Vector2::new(scale, scale)
+            },
         }
     }
+
+    /// The rectangle in pixels on the target the virtual screen is drawn into, centered on the
+    /// target. With Fill this extends past the target.
+    pub fn area(&self, target_size: Vector2<u32>) -> Rectangle<f32> {
+        let target: Vector2<f32> = target_size.cast();
+        let scale = self.scale(target_size);
+        let size = Vector2::new(self.size.x as f32 * scale.x, self.size.y as f32 * scale.y);
+
+        // Keep the virtual pixels lined up with the target's pixels
+        let offset = (target - size) * 0.5;
+        Rectangle::start_size(Point2::new(offset.x.floor(), offset.y.floor()), size)
+    }
+
+    pub fn to_matrix(&self, target_size: Vector2<u32>) -> Matrix4<f32> {
+        let projection = cgmath::ortho(
+            0.0, self.size.x as f32,
+            self.size.y as f32, 0.0,
+            1.0, -1.0
+        );
+
+        area_matrix(&self.area(target_size), target_size) * projection
+    }
+
+    /// Converts pixels on the virtual screen to pixels on the target, both starting at the top
+    /// left.
+    pub fn virtual_to_screen(
+        &self, point: Point2<f32>, target_size: Vector2<u32>,
+    ) -> Point2<f32> {
+        let area = self.area(target_size);
+        let scale = self.scale(target_size);
+        Point2::new(area.min.x + point.x * scale.x, area.min.y + point.y * scale.y)
+    }
+
+    /// Converts pixels on the target to pixels on the virtual screen, both starting at the top
+    /// left. This can be used to convert the mouse's position. The result is outside of the
+    /// virtual screen if the point is on the bars around it.
+    pub fn screen_to_virtual(
+        &self, point: Point2<f32>, target_size: Vector2<u32>,
+    ) -> Point2<f32> {
+        let area = self.area(target_size);
+        let scale = self.scale(target_size);
+        Point2::new((point.x - area.min.x) / scale.x, (point.y - area.min.y) / scale.y)
+    }
+}
+
+/// Creates a matrix that squashes the full clip space down into an area in pixels on the
+/// target, keeping in mind that clip space's Y points up while pixels' Y points down.
+pub fn area_matrix(area: &Rectangle<f32>, target_size: Vector2<u32>) -> Matrix4<f32> {
+    let target: Vector2<f32> = target_size.cast();
+    let size = area.size();
+    let offset = Vector3::new(
+        (area.min.x * 2.0 + size.x) / target.x - 1.0,
+        1.0 - (area.min.y * 2.0 + size.y) / target.y,
+        0.0,
+    );
+
+    Matrix4::from_translation(offset) *
+        Matrix4::from_nonuniform_scale(size.x / target.x, size.y / target.y, 1.0)
 }
 
-/// A definition of a 2D camera.
+/// A definition of a 2D camera. World coordinates have Y pointing up, and the camera's position
+/// ends up at the center of the target.
+#[derive(Debug, Clone)]
 pub struct Camera {
     pub pixels_per_unit: f32,
     pub position: Point2<f32>,
+
+    /// How much the camera is zoomed in, multiplies pixels_per_unit. Defaults to 1.0. A zoom of
+    /// 0 or less would show an infinite area, so it's treated as a very small positive zoom.
+    pub zoom: f32,
+
+    /// The camera's rotation, counter-clockwise. The world appears rotated the opposite way.
+    /// Defaults to no rotation.
+    pub rotation: Rad<f32>,
 }
 
 impl Camera {
@@ -34,18 +193,86 @@ impl Camera {
         Camera {
             pixels_per_unit,
             position,
+            zoom: 1.0,
+            rotation: Rad(0.0),
         }
     }
 
+    pub fn with_zoom(mut self, value: f32) -> Self {
+        self.zoom = value;
+        self
+    }
+
+    pub fn with_rotation(mut self, value: Rad<f32>) -> Self {
+        self.rotation = value;
+        self
+    }
+
     pub fn to_matrix(&self, target_size: Vector2<u32>) -> Matrix4<f32> {
-        let half_size = target_size.cast() / self.pixels_per_unit / 2.0;
+        // Dividing by zero here would give an infinite size, which can't be projected or inverted
+        let scale = (self.pixels_per_unit * self.zoom).max(::std::f32::EPSILON);
+        let half_size = target_size.cast() / scale / 2.0;
         let projection = cgmath::ortho(
             -half_size.x, half_size.x,
             -half_size.y, half_size.y,
             1.0, -1.0
         );
-        let view = Matrix4::from_translation(Vector3::new(-self.position.x, -self.position.y, 0.0));
+        let view =
+            Matrix4::from_angle_z(-self.rotation) *
+            Matrix4::from_translation(Vector3::new(-self.position.x, -self.position.y, 0.0));
 
         projection * view
     }
+
+    /// Converts a point in world coordinates to pixels on the target, starting at the top left.
+    /// When rendering to a viewport, the target is the viewport.
+    pub fn world_to_screen(&self, point: Point2<f32>, target_size: Vector2<u32>) -> Point2<f32> {
+        project_point(self.to_matrix(target_size), point, target_size)
+    }
+
+    /// Converts pixels on the target, starting at the top left, to a point in world coordinates.
+    /// This can be used to find what's under the mouse. When rendering to a viewport, the target
+    /// is the viewport.
+    pub fn screen_to_world(&self, point: Point2<f32>, target_size: Vector2<u32>) -> Point2<f32> {
+        unproject_point(self.to_matrix(target_size), point, target_size)
+    }
+
+    /// Returns the rectangle in world coordinates that contains everything visible on the
+    /// target. If the camera is rotated this is larger than what's actually visible. Useful for
+    /// skipping anything that won't be visible anyways.
+    pub fn visible_bounds(&self, target_size: Vector2<u32>) -> Rectangle<f32> {
+        let size: Vector2<f32> = target_size.cast();
+        let corners = [
+            Point2::new(0.0, 0.0), Point2::new(size.x, 0.0),
+            Point2::new(0.0, size.y), Point2::new(size.x, size.y),
+        ];
+
+        let mut min = Point2::new(::std::f32::MAX, ::std::f32::MAX);
+        let mut max = Point2::new(::std::f32::MIN, ::std::f32::MIN);
+        for corner in &corners {
+            let point = self.screen_to_world(*corner, target_size);
+            min.x = min.x.min(point.x);
+            min.y = min.y.min(point.y);
+            max.x = max.x.max(point.x);
+            max.y = max.y.max(point.y);
+        }
+
+        Rectangle::new(min, max)
+    }
+}
+
+/// Transforms pixels on the target, starting at the top left, back using a projection matrix.
+/// This is the reverse of `project_point`.
+pub fn unproject_point(
+    projection: Matrix4<f32>, point: Point2<f32>, target_size: Vector2<u32>,
+) -> Point2<f32> {
+    let normalized = Vector2::new(
+        point.x / target_size.x as f32 * 2.0 - 1.0,
+        1.0 - point.y / target_size.y as f32 * 2.0,
+    );
+
+    // A projection that can't be inverted squashes everything flat, there's no way back
+    let inverse = projection.invert().unwrap_or(Matrix4::identity());
+    let result = inverse * Vector4::new(normalized.x, normalized.y, 0.0, 1.0);
+    Point2::new(result.x / result.w, result.y / result.w)
 }
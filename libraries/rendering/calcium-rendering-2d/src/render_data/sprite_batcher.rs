@@ -0,0 +1,218 @@
+use std::sync::{Arc};
+
+use cgmath::{Vector4, Matrix3, SquareMatrix};
+use screenmath::{Rectangle};
+
+use calcium_rendering::raw::{RendererRaw};
+use calcium_rendering::texture::{Texture};
+
+use render_data::{
+    RenderBatch, ShaderMode, UvMode, BlendMode, QuadTransform, TransformStack, float_order,
+};
+
+/// A single sprite draw that can be given to a sprite batcher.
+pub struct Sprite<R: RendererRaw> {
+    /// The shader mode in which this sprite will be drawn.
+    pub mode: ShaderMode<R>,
+
+    /// Where on the target the sprite will be drawn.
+    pub destination: Rectangle<f32>,
+
+    /// The UV rectangle of the texture that will be drawn.
+    pub source: Rectangle<f32>,
+
+    /// The color the sprite will be drawn with, in linear color space.
+    pub color: Vector4<f32>,
+
+    /// Sprites with a higher depth are drawn over sprites with a lower depth. Sprites with the
+    /// same depth are drawn in the order they were added. Sprites with a NaN depth are drawn over
+    /// all other sprites. Defaults to 0.0.
+    pub depth: f32,
+
+    /// Used to determine what UV coordinates for a full texture are. Defaults to YDown.
+    pub uv_mode: UvMode,
+
+    /// How the sprite's colors are combined with what's already been drawn. Defaults to Alpha.
+    pub blend_mode: BlendMode,
+
+    /// The 2D affine transform applied to the sprite's destination. Defaults to identity.
+    pub transform: Matrix3<f32>,
+
+    /// Rotates, scales and flips the sprite relative to its destination, before `transform` is
+    /// applied. This is applied when the sprite is drawn, so it follows changes to the
+    /// destination. Defaults to no change.
+    pub quad_transform: QuadTransform,
+}
+
+impl<R: RendererRaw> Sprite<R> {
+    /// Creates a new white sprite drawing the given source rectangle of a texture.
+    pub fn new(
+        texture: Arc<Texture<R>>, destination: Rectangle<f32>, source: Rectangle<f32>,
+    ) -> Self {
+        Sprite {
+            mode: ShaderMode::Texture(texture),
+            destination,
+            source,
+            color: Vector4::new(1.0, 1.0, 1.0, 1.0),
+            depth: 0.0,
+            uv_mode: UvMode::YDown,
+            blend_mode: BlendMode::Alpha,
+            transform: Matrix3::identity(),
+            quad_transform: QuadTransform::new(),
+        }
+    }
+
+    pub fn with_mode(mut self, value: ShaderMode<R>) -> Self {
+        self.mode = value;
+        self
+    }
+
+    pub fn with_color(mut self, value: Vector4<f32>) -> Self {
+        self.color = value;
+        self
+    }
+
+    pub fn with_depth(mut self, value: f32) -> Self {
+        self.depth = value;
+        self
+    }
+
+    pub fn with_uv_mode(mut self, value: UvMode) -> Self {
+        self.uv_mode = value;
+        self
+    }
+
+    pub fn with_blend_mode(mut self, value: BlendMode) -> Self {
+        self.blend_mode = value;
+        self
+    }
+
+    pub fn with_transform(mut self, value: Matrix3<f32>) -> Self {
+        self.transform = value;
+        self
+    }
+
+    pub fn with_quad_transform(mut self, value: QuadTransform) -> Self {
+        self.quad_transform = value;
+        self
+    }
+
+    /// Returns true if this sprite can be drawn in the same render batch as the given batch.
+    fn fits_in(&self, batch: &RenderBatch<R>) -> bool {
+        self.uv_mode == batch.uv_mode &&
+        self.blend_mode == batch.blend_mode &&
+        match (&self.mode, &batch.mode) {
+            (&ShaderMode::Color, &ShaderMode::Color) => true,
+            (&ShaderMode::Texture(ref a), &ShaderMode::Texture(ref b)) => Arc::ptr_eq(a, b),
+            (&ShaderMode::Mask(ref a), &ShaderMode::Mask(ref b)) => Arc::ptr_eq(a, b),
+            (&ShaderMode::Custom(ref a), &ShaderMode::Custom(ref b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+/// Collects individual sprite draws and turns them into as few render batches as possible.
+///
+/// Sprites are ordered by depth, and consecutive sprites that share the same texture, shader
+/// mode, UV mode and blend mode are merged into a single batch. Sprites are never moved past
+/// each other within the same depth, so overlapping sprites are drawn exactly as if each had its
+/// own batch.
+///
+/// The batcher keeps a transform stack, every added sprite is transformed by the current
+/// transform. This lets nested objects, like a turret on a ship, be placed relative to their
+/// parent.
+pub struct SpriteBatcher<R: RendererRaw> {
+    sprites: Vec<Sprite<R>>,
+    transforms: TransformStack,
+}
+
+impl<R: RendererRaw> SpriteBatcher<R> {
+    pub fn new() -> Self {
+        SpriteBatcher {
+            sprites: Vec::new(),
+            transforms: TransformStack::new(),
+        }
+    }
+
+    /// Returns true if no sprites have been added to this batcher.
+    pub fn empty(&self) -> bool {
+        self.sprites.len() == 0
+    }
+
+    /// Adds a sprite to be drawn, transformed by the current transform.
+    pub fn push(&mut self, mut sprite: Sprite<R>) {
+        sprite.transform = self.transforms.current() * sprite.transform;
+        self.sprites.push(sprite);
+    }
+
+    /// Pushes a transform onto the transform stack, sprites added after this will be transformed
+    /// by it, in the space of any transforms pushed before it.
+    pub fn push_transform(&mut self, transform: Matrix3<f32>) {
+        self.transforms.push(transform);
+    }
+
+    /// Pops the last pushed transform off the transform stack. Returns false if there was no
+    /// pushed transform left to pop.
+    pub fn pop_transform(&mut self) -> bool {
+        self.transforms.pop()
+    }
+
+    /// Gets the combined transform sprites added now will be transformed by.
+    pub fn transform(&self) -> Matrix3<f32> {
+        self.transforms.current()
+    }
+
+    /// Adds a textured sprite to be drawn, this is a shorthand for `push` with a `Sprite`.
+    pub fn push_sprite(
+        &mut self,
+        texture: Arc<Texture<R>>, destination: Rectangle<f32>, source: Rectangle<f32>,
+        color: Vector4<f32>, depth: f32,
+    ) {
+        self.push(Sprite::new(texture, destination, source).with_color(color).with_depth(depth));
+    }
+
+    /// Removes all sprites from this batcher, so it can be re-used for the next frame.
+    pub fn clear(&mut self) {
+        self.sprites.clear();
+    }
+
+    /// Consumes all sprites in this batcher and appends the resulting render batches to the
+    /// given list of batches.
+    pub fn drain_into(&mut self, batches: &mut Vec<RenderBatch<R>>) {
+        // This sort is stable, so sprites with the same depth keep the order they were added in
+        self.sprites.sort_by(|a, b| float_order(a.depth, b.depth));
+
+        let mut current: Option<RenderBatch<R>> = None;
+        for sprite in self.sprites.drain(..) {
+            // If this sprite can't be added to the current batch, we need to start a new one
+            let fits = current.as_ref().map(|b| sprite.fits_in(b)).unwrap_or(false);
+            if !fits {
+                if let Some(batch) = current.take() {
+                    batches.push(batch);
+                }
+                current = Some(
+                    RenderBatch::new(sprite.mode.clone(), sprite.uv_mode)
+                        .with_blend_mode(sprite.blend_mode)
+                );
+            }
+
+            let quad = &sprite.quad_transform;
+            let transform = sprite.transform * quad.to_matrix(sprite.destination.min);
+            let source = quad.flip_source(sprite.source);
+            current.as_mut().unwrap().push_rectangle_matrix(
+                transform, sprite.destination, source, sprite.color
+            );
+        }
+
+        if let Some(batch) = current {
+            batches.push(batch);
+        }
+    }
+
+    /// Consumes the batcher and returns the resulting render batches.
+    pub fn into_batches(mut self) -> Vec<RenderBatch<R>> {
+        let mut batches = Vec::new();
+        self.drain_into(&mut batches);
+        batches
+    }
+}
@@ -0,0 +1,155 @@
+use cgmath::{Vector2, Vector3, Point2, Matrix3, Rad, SquareMatrix};
+use screenmath::{Rectangle};
+
+/// Creates a 2D affine transform matrix that translates by the given offset.
+pub fn affine_translation(offset: Vector2<f32>) -> Matrix3<f32> {
+    Matrix3::new(
+        1.0, 0.0, 0.0,
+        0.0, 1.0, 0.0,
+        offset.x, offset.y, 1.0,
+    )
+}
+
+/// Creates a 2D affine transform matrix that rotates around the origin. With Y pointing down, as
+/// it does with pixel coordinates, positive angles rotate clockwise.
+pub fn affine_rotation(angle: Rad<f32>) -> Matrix3<f32> {
+    let (sin, cos) = angle.0.sin_cos();
+    Matrix3::new(
+        cos, sin, 0.0,
+        -sin, cos, 0.0,
+        0.0, 0.0, 1.0,
+    )
+}
+
+/// Creates a 2D affine transform matrix that scales from the origin.
+pub fn affine_scale(scale: Vector2<f32>) -> Matrix3<f32> {
+    Matrix3::new(
+        scale.x, 0.0, 0.0,
+        0.0, scale.y, 0.0,
+        0.0, 0.0, 1.0,
+    )
+}
+
+/// Applies a 2D affine transform matrix to a point.
+pub fn transform_point(transform: Matrix3<f32>, point: Point2<f32>) -> Point2<f32> {
+    let value = transform * Vector3::new(point.x, point.y, 1.0);
+    Point2::new(value.x, value.y)
+}
+
+/// Describes how a rectangle is transformed when it's drawn.
+#[derive(Debug, Clone)]
+pub struct QuadTransform {
+    /// The point rotation and scaling happen around, relative to the rectangle's minimum corner.
+    /// Defaults to (0, 0).
+    pub origin: Vector2<f32>,
+
+    /// The rotation around the origin. Defaults to no rotation.
+    pub rotation: Rad<f32>,
+
+    /// The scale around the origin. Defaults to (1, 1).
+    pub scale: Vector2<f32>,
+
+    /// Mirrors the texture horizontally within the rectangle.
+    pub flip_x: bool,
+
+    /// Mirrors the texture vertically within the rectangle.
+    pub flip_y: bool,
+}
+
+impl QuadTransform {
+    pub fn new() -> Self {
+        QuadTransform {
+            origin: Vector2::new(0.0, 0.0),
+            rotation: Rad(0.0),
+            scale: Vector2::new(1.0, 1.0),
+            flip_x: false,
+            flip_y: false,
+        }
+    }
+
+    pub fn with_origin(mut self, value: Vector2<f32>) -> Self {
+        self.origin = value;
+        self
+    }
+
+    pub fn with_rotation(mut self, value: Rad<f32>) -> Self {
+        self.rotation = value;
+        self
+    }
+
+    pub fn with_scale(mut self, value: Vector2<f32>) -> Self {
+        self.scale = value;
+        self
+    }
+
+    pub fn with_flip(mut self, flip_x: bool, flip_y: bool) -> Self {
+        self.flip_x = flip_x;
+        self.flip_y = flip_y;
+        self
+    }
+
+    /// Returns the affine transform matrix for a rectangle with the given minimum corner. Flips
+    /// aren't part of this, they're applied to the texture source using `flip_source`.
+    pub fn to_matrix(&self, rectangle_min: Point2<f32>) -> Matrix3<f32> {
+        let pivot = Vector2::new(rectangle_min.x, rectangle_min.y) + self.origin;
+        affine_translation(pivot) *
+            affine_rotation(self.rotation) *
+            affine_scale(self.scale) *
+            affine_translation(-pivot)
+    }
+
+    /// Mirrors a texture source rectangle as described by the flips, so the rectangle it's drawn
+    /// to can stay in place.
+    pub fn flip_source(&self, mut source: Rectangle<f32>) -> Rectangle<f32> {
+        if self.flip_x {
+            ::std::mem::swap(&mut source.min.x, &mut source.max.x);
+        }
+        if self.flip_y {
+            ::std::mem::swap(&mut source.min.y, &mut source.max.y);
+        }
+        source
+    }
+}
+
+/// A stack of 2D affine transforms, used to compose the transforms of nested objects. Every
+/// pushed transform is applied in the space of the transform below it.
+#[derive(Debug, Clone)]
+pub struct TransformStack {
+    stack: Vec<Matrix3<f32>>,
+}
+
+impl TransformStack {
+    /// Creates a new stack, containing only an identity transform.
+    pub fn new() -> Self {
+        TransformStack {
+            stack: vec!(Matrix3::identity()),
+        }
+    }
+
+    /// Gets the combined transform of everything on the stack.
+    pub fn current(&self) -> Matrix3<f32> {
+        *self.stack.last().unwrap()
+    }
+
+    /// Pushes a transform, combining it with the current transform.
+    pub fn push(&mut self, transform: Matrix3<f32>) {
+        let combined = self.current() * transform;
+        self.stack.push(combined);
+    }
+
+    /// Pops the last pushed transform. The base identity transform is never popped, if there's
+    /// nothing else left to pop this returns false.
+    pub fn pop(&mut self) -> bool {
+        if self.stack.len() > 1 {
+            self.stack.pop();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns the amount of transforms pushed on top of the base identity transform.
+    pub fn depth(&self) -> usize {
+        self.stack.len() - 1
+    }
+}
@@ -1,14 +1,21 @@
-use calcium_rendering::{Renderer};
+use std::sync::{Arc};
+
+use cgmath::{Vector2};
+
+use calcium_rendering::{Renderer, Error};
 use calcium_rendering::raw::{RawAccess, RendererRaw};
+use calcium_rendering::texture::{Texture, PixelFormat};
 
 use raw::{Renderer2DRaw, Renderer2DTargetRaw};
 use {Renderer2D};
 
 pub struct Renderer2DTarget<R: RendererRaw, SR: Renderer2DRaw<R>> {
     pub raw: SR::RenderTargetRaw,
+    texture: Option<Arc<Texture<R>>>,
 }
 
 impl<R: RendererRaw, SR: Renderer2DRaw<R>> Renderer2DTarget<R, SR> {
+    /// Creates a render target that renders to the window.
     pub fn new(
         clear: bool,
         renderer: &Renderer<R>,
@@ -21,8 +28,61 @@ impl<R: RendererRaw, SR: Renderer2DRaw<R>> Renderer2DTarget<R, SR> {
 
         Renderer2DTarget {
             raw,
+            texture: None,
         }
     }
+
+    /// Creates a render target that renders to a newly created texture of the given size. After
+    /// rendering, the texture can be retrieved using `texture()` and used in later batches.
+    pub fn new_texture(
+        clear: bool,
+        size: Vector2<u32>,
+        renderer: &mut Renderer<R>,
+        simple2d_renderer: &Renderer2D<R, SR>,
+    ) -> Result<Self, Error> {
+        // Calculate in usize so large sizes don't overflow u32 before we can catch it
+        let byte_count = (size.x as usize).checked_mul(size.y as usize)
+            .and_then(|pixels| pixels.checked_mul(4))
+            .ok_or_else(|| Error::Unsupported(format!(
+                "Render target size {}x{} is too large", size.x, size.y
+            )))?;
+
+        let texture = Texture::new()
+            .from_bytes(vec![0u8; byte_count], size, PixelFormat::Rgba8)
+            .as_render_target()
+            .build(renderer)?;
+
+        Self::from_texture(clear, texture, renderer, simple2d_renderer)
+    }
+
+    /// Creates a render target that renders to an existing texture. The texture needs to have
+    /// been built with `as_render_target`.
+    pub fn from_texture(
+        clear: bool,
+        texture: Arc<Texture<R>>,
+        renderer: &Renderer<R>,
+        simple2d_renderer: &Renderer2D<R, SR>,
+    ) -> Result<Self, Error> {
+        let raw = Renderer2DTargetRaw::new_texture(
+            clear, &texture,
+            renderer, simple2d_renderer
+        )?;
+
+        Ok(Renderer2DTarget {
+            raw,
+            texture: Some(texture),
+        })
+    }
+
+    /// The texture this target renders to, or None if it renders to the window.
+    pub fn texture(&self) -> Option<&Arc<Texture<R>>> {
+        self.texture.as_ref()
+    }
+
+    /// The size of what this target renders to, given the size of the current frame.
+    pub fn size(&self, frame_size: Vector2<u32>) -> Vector2<u32> {
+        self.texture.as_ref().map(|t| t.size()).unwrap_or(frame_size)
+    }
 }
 
 impl<R: RendererRaw, SR: Renderer2DRaw<R>> RawAccess<SR::RenderTargetRaw>
@@ -1,8 +1,11 @@
+use std::sync::{Arc};
+use std::time::{Instant};
+
 use calcium_rendering::raw::{RawAccess, RendererRaw};
-use calcium_rendering::{Renderer, Frame};
+use calcium_rendering::{Renderer, Frame, Error};
 
 use raw::{Renderer2DRaw};
-use render_data::{RenderData};
+use render_data::{RenderData, Shader2D};
 use {Renderer2DTarget};
 
 /// A 2D renderer capable of rendering render batches.
@@ -19,7 +22,8 @@ impl<R: RendererRaw, SR: Renderer2DRaw<R>> Renderer2D<R, SR> {
         }
     }
 
-    /// Renders a collection of rendering data.
+    /// Renders a collection of rendering data. The work done is counted into the renderer's
+    /// frame stats.
     pub fn render(
         &mut self,
         data: &RenderData<R>,
@@ -27,12 +31,30 @@ impl<R: RendererRaw, SR: Renderer2DRaw<R>> Renderer2D<R, SR> {
         render_target: &mut Renderer2DTarget<R, SR>,
         renderer: &mut Renderer<R>,
     ) {
+        let start = Instant::now();
+
         self.raw.render(
             data,
             frame,
             render_target,
             renderer,
         );
+
+        let stats = renderer.stats_mut();
+        stats.render_2d_time += start.elapsed();
+        stats.render_sets += data.render_sets.len();
+        for set in &data.render_sets {
+            stats.batches += set.batches.len();
+            stats.vertices += set.batches.iter().map(|b| b.vertex_count()).sum::<usize>();
+        }
+    }
+
+    /// Registers a custom shader, this needs to be done before any batch using a material with
+    /// the shader is rendered.
+    pub fn register_shader(
+        &mut self, shader: &Arc<Shader2D>, renderer: &mut Renderer<R>,
+    ) -> Result<(), Error> {
+        self.raw.register_shader(shader, renderer)
     }
 }
 
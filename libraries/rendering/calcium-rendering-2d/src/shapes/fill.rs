@@ -0,0 +1,225 @@
+use cgmath::{Vector2, Vector4, Point2, InnerSpace};
+
+use render_data::{DrawVertex};
+use shapes::{vertex, transparent, cross, dedup_points};
+
+/// How far the feathered border's corners are allowed to extend out, relative to the feather,
+/// to avoid long spikes at sharp corners.
+const FEATHER_MITER_LIMIT: f32 = 4.0;
+
+/// Tessellates a filled polygon into a list of triangles.
+pub fn fill(
+    points: &[Point2<f32>], color: Vector4<f32>, feather: f32, vertices: &mut Vec<DrawVertex>,
+) {
+    let mut points = dedup_points(points, true);
+    if points.len() < 3 {
+        return
+    }
+
+    // Make sure the points are in a consistent order, so we know which side is outside
+    if signed_area(&points) < 0.0 {
+        points.reverse();
+    }
+
+    // The solid part of the polygon is inset by half the feather, the faded border then goes
+    //  from there to half the feather outside of the polygon's edge
+    let (inner, outer) = if feather > 0.0 {
+        let normals = vertex_normals(&points);
+        let offset = feather * 0.5;
+        (
+            points.iter().zip(&normals).map(|(p, n)| *p + *n * -offset).collect(),
+            points.iter().zip(&normals).map(|(p, n)| *p + *n * offset).collect(),
+        )
+    } else {
+        (points.clone(), Vec::new())
+    };
+
+    // The triangulation is done on the original points, the inset points are close enough to
+    //  share it without overlapping
+    for triangle in triangulate(&points) {
+        for i in &triangle {
+            vertices.push(vertex(inner[*i], color));
+        }
+    }
+
+    // Add the feathered border along every edge
+    if feather > 0.0 {
+        let edge_color = transparent(color);
+        for i in 0..points.len() {
+            let next = (i + 1) % points.len();
+            vertices.push(vertex(inner[i], color));
+            vertices.push(vertex(inner[next], color));
+            vertices.push(vertex(outer[next], edge_color));
+
+            vertices.push(vertex(inner[i], color));
+            vertices.push(vertex(outer[next], edge_color));
+            vertices.push(vertex(outer[i], edge_color));
+        }
+    }
+}
+
+/// Twice the signed area of a polygon, positive if the points are counter-clockwise with Y
+/// pointing up.
+fn signed_area(points: &[Point2<f32>]) -> f32 {
+    let mut area = 0.0;
+    for i in 0..points.len() {
+        let (a, b) = (points[i], points[(i + 1) % points.len()]);
+        area += a.x * b.y - b.x * a.y;
+    }
+    area
+}
+
+/// Calculates the outwards pointing miter normals of every point of a polygon with a positive
+/// signed area. These are scaled so that offsetting along them moves the edges by the same
+/// distance.
+fn vertex_normals(points: &[Point2<f32>]) -> Vec<Vector2<f32>> {
+    let edge_normal = |a: Point2<f32>, b: Point2<f32>| {
+        let direction = (b - a).normalize();
+        Vector2::new(direction.y, -direction.x)
+    };
+
+    (0..points.len()).map(|i| {
+        let previous = points[(i + points.len() - 1) % points.len()];
+        let next = points[(i + 1) % points.len()];
+        let previous_normal = edge_normal(previous, points[i]);
+        let next_normal = edge_normal(points[i], next);
+
+        let sum = previous_normal + next_normal;
+        if sum.magnitude2() < 0.0001 {
+            return next_normal
+        }
+
+        let direction = sum.normalize();
+        let scale = (1.0 / direction.dot(next_normal)).min(FEATHER_MITER_LIMIT);
+        direction * scale
+    }).collect()
+}
+
+/// Triangulates a simple polygon with a positive signed area using ear clipping, returns
+/// triangles as indices into the points.
+fn triangulate(points: &[Point2<f32>]) -> Vec<[usize; 3]> {
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    let mut triangles = Vec::with_capacity(points.len() - 2);
+
+    while remaining.len() > 3 {
+        let count = remaining.len();
+        let ear = (0..count).find(|i| {
+            let (a, b, c) = (
+                remaining[(i + count - 1) % count], remaining[*i], remaining[(i + 1) % count],
+            );
+            is_ear(points, &remaining, a, b, c)
+        });
+
+        // If there's no ear the polygon intersects itself or is degenerate, just cut off a
+        //  corner and keep going so we still draw something
+        let i = ear.unwrap_or(0);
+        triangles.push([
+            remaining[(i + count - 1) % count], remaining[i], remaining[(i + 1) % count],
+        ]);
+        remaining.remove(i);
+    }
+
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+    triangles
+}
+
+fn is_ear(points: &[Point2<f32>], remaining: &[usize], a: usize, b: usize, c: usize) -> bool {
+    let (pa, pb, pc) = (points[a], points[b], points[c]);
+
+    // Reflex corners can't be ears
+    if cross(pb - pa, pc - pb) <= 0.0 {
+        return false
+    }
+
+    // No other point may be inside of the ear
+    !remaining.iter()
+        .filter(|i| **i != a && **i != b && **i != c)
+        .any(|i| in_triangle(points[*i], pa, pb, pc))
+}
+
+fn in_triangle(p: Point2<f32>, a: Point2<f32>, b: Point2<f32>, c: Point2<f32>) -> bool {
+    cross(b - a, p - a) >= 0.0 && cross(c - b, p - b) >= 0.0 && cross(a - c, p - c) >= 0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{Vector4, Point2};
+    use render_data::{DrawVertex};
+    use shapes::{cross};
+    use super::{fill};
+
+    fn fill_points(points: &[(f32, f32)], feather: f32) -> Vec<DrawVertex> {
+        let points: Vec<_> = points.iter().map(|p| Point2::new(p.0, p.1)).collect();
+        let mut vertices = Vec::new();
+        fill(&points, Vector4::new(1.0, 1.0, 1.0, 1.0), feather, &mut vertices);
+        vertices
+    }
+
+    /// The total area covered by the triangles, and if they're all wound the same way.
+    fn area(vertices: &[DrawVertex]) -> (f32, bool) {
+        let areas: Vec<f32> = vertices.chunks(3)
+            .map(|t| cross(t[1].position - t[0].position, t[2].position - t[0].position) * 0.5)
+            .collect();
+        let same_winding = areas.iter().all(|a| *a >= 0.0) || areas.iter().all(|a| *a <= 0.0);
+        (areas.iter().map(|a| a.abs()).sum(), same_winding)
+    }
+
+    #[test]
+    fn fills_convex_polygon() {
+        let vertices = fill_points(&[(0.0, 0.0), (4.0, 0.0), (4.0, 2.0), (0.0, 2.0)], 0.0);
+
+        assert_eq!(vertices.len(), 6);
+        let (area, same_winding) = area(&vertices);
+        assert!((area - 8.0).abs() < 0.0001);
+        assert!(same_winding);
+    }
+
+    #[test]
+    fn fills_concave_polygon() {
+        // An L shape, the corner at (1, 1) is reflex
+        let vertices = fill_points(
+            &[(0.0, 0.0), (3.0, 0.0), (3.0, 1.0), (1.0, 1.0), (1.0, 3.0), (0.0, 3.0)], 0.0,
+        );
+
+        // Overlapping triangles or ones covering the notch would give a different area
+        assert_eq!(vertices.len(), 4 * 3);
+        let (area, same_winding) = area(&vertices);
+        assert!((area - 5.0).abs() < 0.0001);
+        assert!(same_winding);
+    }
+
+    #[test]
+    fn winding_does_not_matter() {
+        let clockwise = fill_points(&[(0.0, 2.0), (4.0, 2.0), (4.0, 0.0), (0.0, 0.0)], 0.0);
+        assert!((area(&clockwise).0 - 8.0).abs() < 0.0001);
+    }
+
+    #[test]
+    fn skips_degenerate_polygons() {
+        assert!(fill_points(&[], 1.0).is_empty());
+        assert!(fill_points(&[(0.0, 0.0), (1.0, 0.0)], 1.0).is_empty());
+        assert!(fill_points(&[(0.0, 0.0), (1.0, 0.0), (1.0, 0.0), (0.0, 0.0)], 1.0).is_empty());
+    }
+
+    #[test]
+    fn ignores_duplicate_points() {
+        let plain = fill_points(&[(0.0, 0.0), (4.0, 0.0), (4.0, 2.0), (0.0, 2.0)], 1.0);
+        let duplicates = fill_points(
+            &[(0.0, 0.0), (4.0, 0.0), (4.0, 0.0), (4.0, 2.0), (0.0, 2.0), (0.0, 0.0)], 1.0,
+        );
+
+        let positions = |v: &[DrawVertex]| v.iter().map(|v| v.position).collect::<Vec<_>>();
+        assert_eq!(positions(&plain), positions(&duplicates));
+    }
+
+    #[test]
+    fn feathers_edges() {
+        let vertices = fill_points(&[(0.0, 0.0), (4.0, 0.0), (4.0, 2.0), (0.0, 2.0)], 1.0);
+
+        // The inset fill, and two triangles for the border along each edge
+        assert_eq!(vertices.len(), 6 + 4 * 6);
+        // Together they cover the polygon grown by half the feather
+        assert!((area(&vertices).0 - 5.0 * 3.0).abs() < 0.0001);
+        assert!(vertices.iter().any(|v| v.color.w == 0.0));
+    }
+}
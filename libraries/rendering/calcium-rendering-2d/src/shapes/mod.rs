@@ -0,0 +1,299 @@
+//! Tessellation of vector shapes into triangles for render batches.
+//!
+//! Shapes are tessellated using only vertex colors, so they should be added to batches using
+//! `ShaderMode::Color`. Edges are anti-aliased by fading the alpha out over a feathered border,
+//! which looks correct with `BlendMode::Alpha`.
+
+mod fill;
+mod stroke;
+
+use std::f32::consts::{PI};
+
+use cgmath::{Vector2, Vector4, Point2, Rad};
+use screenmath::{Rectangle};
+
+use calcium_rendering::raw::{RendererRaw};
+
+use render_data::{RenderBatch, DrawVertex};
+
+/// The most segments a single arc is split up in, so very large radii or a tiny tolerance can't
+/// create an unbounded amount of vertices.
+const MAX_ARC_SEGMENTS: usize = 1024;
+
+/// How the segments of a stroked line are connected.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum LineJoin {
+    /// Extends the outer edges until they meet, falling back to Bevel beyond the miter limit.
+    Miter,
+    /// Connects the outer edges with a straight edge.
+    Bevel,
+    /// Connects the outer edges with an arc.
+    Round,
+}
+
+/// How the ends of an open stroked line look.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum LineCap {
+    /// Ends the line exactly at its end points.
+    Butt,
+    /// Extends the line past its end points by half its width.
+    Square,
+    /// Ends the line with a half circle around its end points.
+    Round,
+}
+
+/// Describes how lines are stroked.
+#[derive(Debug, Clone)]
+pub struct StrokeStyle {
+    /// The width of the line. Defaults to 1.0.
+    pub width: f32,
+    /// Defaults to Miter.
+    pub join: LineJoin,
+    /// Defaults to Butt.
+    pub cap: LineCap,
+    /// The maximum length of a miter join relative to half the line's width. Defaults to 4.0.
+    pub miter_limit: f32,
+}
+
+impl StrokeStyle {
+    pub fn new(width: f32) -> Self {
+        StrokeStyle {
+            width,
+            join: LineJoin::Miter,
+            cap: LineCap::Butt,
+            miter_limit: 4.0,
+        }
+    }
+
+    pub fn with_join(mut self, value: LineJoin) -> Self {
+        self.join = value;
+        self
+    }
+
+    pub fn with_cap(mut self, value: LineCap) -> Self {
+        self.cap = value;
+        self
+    }
+
+    pub fn with_miter_limit(mut self, value: f32) -> Self {
+        self.miter_limit = value;
+        self
+    }
+}
+
+/// Tessellates vector shapes into triangles and adds them to render batches.
+#[derive(Debug, Clone)]
+pub struct ShapeTessellator {
+    /// The width of the border over which the edges of shapes fade out, centered on the edge.
+    /// Set to 0.0 to disable anti-aliasing. Defaults to 1.0, a single pixel when drawing using
+    /// `Projection::Pixels`.
+    pub feather: f32,
+
+    /// The maximum distance between a curve and the straight segments it's approximated with.
+    /// Defaults to 0.25.
+    pub tolerance: f32,
+}
+
+impl ShapeTessellator {
+    pub fn new() -> Self {
+        ShapeTessellator {
+            feather: 1.0,
+            tolerance: 0.25,
+        }
+    }
+
+    pub fn with_feather(mut self, value: f32) -> Self {
+        self.feather = value;
+        self
+    }
+
+    pub fn with_tolerance(mut self, value: f32) -> Self {
+        self.tolerance = value;
+        self
+    }
+
+    /// Adds a single straight line.
+    pub fn line<R: RendererRaw>(
+        &self, batch: &mut RenderBatch<R>,
+        start: Point2<f32>, end: Point2<f32>, style: &StrokeStyle, color: Vector4<f32>,
+    ) {
+        self.polyline(batch, &[start, end], false, style, color);
+    }
+
+    /// Adds a line going through all given points. If closed, the last point is connected back
+    /// to the first.
+    pub fn polyline<R: RendererRaw>(
+        &self, batch: &mut RenderBatch<R>,
+        points: &[Point2<f32>], closed: bool, style: &StrokeStyle, color: Vector4<f32>,
+    ) {
+        let mut vertices = Vec::new();
+        stroke::stroke(points, closed, style, color, self, &mut vertices);
+        batch.push_triangles(&vertices);
+    }
+
+    /// Adds a filled polygon, the polygon may be concave but should not intersect itself.
+    pub fn fill_polygon<R: RendererRaw>(
+        &self, batch: &mut RenderBatch<R>, points: &[Point2<f32>], color: Vector4<f32>,
+    ) {
+        let mut vertices = Vec::new();
+        fill::fill(points, color, self.feather, &mut vertices);
+        batch.push_triangles(&vertices);
+    }
+
+    /// Adds the outline of a polygon.
+    pub fn stroke_polygon<R: RendererRaw>(
+        &self, batch: &mut RenderBatch<R>,
+        points: &[Point2<f32>], style: &StrokeStyle, color: Vector4<f32>,
+    ) {
+        self.polyline(batch, points, true, style, color);
+    }
+
+    /// Adds a line along a circle's arc, going from the start angle to the end angle. With Y
+    /// pointing down, increasing angles go clockwise starting from the right.
+    pub fn arc<R: RendererRaw>(
+        &self, batch: &mut RenderBatch<R>,
+        center: Point2<f32>, radius: f32, start: Rad<f32>, end: Rad<f32>,
+        style: &StrokeStyle, color: Vector4<f32>,
+    ) {
+        let points = self.arc_points(center, radius, start, end);
+        self.polyline(batch, &points, false, &curve_style(style), color);
+    }
+
+    /// Adds a filled circle.
+    pub fn fill_circle<R: RendererRaw>(
+        &self, batch: &mut RenderBatch<R>,
+        center: Point2<f32>, radius: f32, color: Vector4<f32>,
+    ) {
+        let points = self.circle_points(center, radius);
+        self.fill_polygon(batch, &points, color);
+    }
+
+    /// Adds the outline of a circle.
+    pub fn stroke_circle<R: RendererRaw>(
+        &self, batch: &mut RenderBatch<R>,
+        center: Point2<f32>, radius: f32, style: &StrokeStyle, color: Vector4<f32>,
+    ) {
+        let points = self.circle_points(center, radius);
+        self.polyline(batch, &points, true, &curve_style(style), color);
+    }
+
+    /// Adds a filled rectangle with rounded corners. The radius is limited to half the
+    /// rectangle's smallest side.
+    pub fn fill_rounded_rectangle<R: RendererRaw>(
+        &self, batch: &mut RenderBatch<R>,
+        rectangle: Rectangle<f32>, radius: f32, color: Vector4<f32>,
+    ) {
+        let points = self.rounded_rectangle_points(rectangle, radius);
+        self.fill_polygon(batch, &points, color);
+    }
+
+    /// Adds the outline of a rectangle with rounded corners. The radius is limited to half the
+    /// rectangle's smallest side.
+    pub fn stroke_rounded_rectangle<R: RendererRaw>(
+        &self, batch: &mut RenderBatch<R>,
+        rectangle: Rectangle<f32>, radius: f32, style: &StrokeStyle, color: Vector4<f32>,
+    ) {
+        let points = self.rounded_rectangle_points(rectangle, radius);
+        self.polyline(batch, &points, true, &curve_style(style), color);
+    }
+
+    /// The amount of segments needed to approximate an arc within the tolerance, limited to
+    /// `MAX_ARC_SEGMENTS`.
+    fn arc_segments(&self, radius: f32, angle: f32) -> usize {
+        let tolerance = self.tolerance.max(0.001);
+        if radius <= tolerance {
+            return 1
+        }
+
+        // The step can round down to 0 for radii much larger than the tolerance, the limit then
+        //  also keeps the infinite amount of segments from overflowing
+        let step = 2.0 * (1.0 - tolerance / radius).acos();
+        (angle.abs() / step).ceil().max(1.0).min(MAX_ARC_SEGMENTS as f32) as usize
+    }
+
+    fn arc_points(
+        &self, center: Point2<f32>, radius: f32, start: Rad<f32>, end: Rad<f32>,
+    ) -> Vec<Point2<f32>> {
+        let angle = end.0 - start.0;
+        let segments = self.arc_segments(radius, angle);
+
+        (0..segments + 1).map(|i| {
+            let (sin, cos) = (start.0 + angle * i as f32 / segments as f32).sin_cos();
+            center + Vector2::new(cos, sin) * radius
+        }).collect()
+    }
+
+    fn circle_points(&self, center: Point2<f32>, radius: f32) -> Vec<Point2<f32>> {
+        let mut points = self.arc_points(center, radius, Rad(0.0), Rad(2.0 * PI));
+        // The last point is the same as the first
+        points.pop();
+        points
+    }
+
+    fn rounded_rectangle_points(
+        &self, rectangle: Rectangle<f32>, radius: f32,
+    ) -> Vec<Point2<f32>> {
+        let size = rectangle.size();
+        let radius = radius.min(size.x * 0.5).min(size.y * 0.5).max(0.0);
+        let (min, max) = (rectangle.min, rectangle.max);
+
+        // Go around the corners clockwise, starting at the top left
+        let corners = [
+            (Point2::new(min.x + radius, min.y + radius), PI),
+            (Point2::new(max.x - radius, min.y + radius), PI * 1.5),
+            (Point2::new(max.x - radius, max.y - radius), 0.0),
+            (Point2::new(min.x + radius, max.y - radius), PI * 0.5),
+        ];
+
+        let mut points = Vec::new();
+        for &(center, start) in &corners {
+            points.extend(self.arc_points(
+                center, radius, Rad(start), Rad(start + PI * 0.5)
+            ));
+        }
+        points
+    }
+}
+
+/// Curves are made out of a lot of small segments with slight angles between them, miter joins
+/// are both the cheapest and the most accurate for those.
+fn curve_style(style: &StrokeStyle) -> StrokeStyle {
+    style.clone().with_join(LineJoin::Miter)
+}
+
+/// Creates a shape vertex, shapes don't use UVs.
+fn vertex(position: Point2<f32>, color: Vector4<f32>) -> DrawVertex {
+    DrawVertex::new(position, Point2::new(0.0, 0.0), color)
+}
+
+/// Returns the color with its alpha faded out completely, used for the outside of feathered
+/// edges.
+fn transparent(color: Vector4<f32>) -> Vector4<f32> {
+    Vector4::new(color.x, color.y, color.z, 0.0)
+}
+
+/// The Z component of the cross product of two 2D vectors.
+fn cross(a: Vector2<f32>, b: Vector2<f32>) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+/// Removes points that are the same as the point before them, these would create segments
+/// without a direction.
+fn dedup_points(points: &[Point2<f32>], closed: bool) -> Vec<Point2<f32>> {
+    let mut result: Vec<Point2<f32>> = Vec::with_capacity(points.len());
+    for point in points {
+        if result.last().map(|last| !near(*last, *point)).unwrap_or(true) {
+            result.push(*point);
+        }
+    }
+
+    if closed && result.len() > 1 && near(result[0], *result.last().unwrap()) {
+        result.pop();
+    }
+
+    result
+}
+
+fn near(a: Point2<f32>, b: Point2<f32>) -> bool {
+    (a.x - b.x).abs() < 0.0001 && (a.y - b.y).abs() < 0.0001
+}
@@ -0,0 +1,425 @@
+use std::f32::consts::{PI};
+
+use cgmath::{Vector2, Vector4, Point2, InnerSpace};
+
+use render_data::{DrawVertex};
+use shapes::{
+    StrokeStyle, LineJoin, LineCap, ShapeTessellator, vertex, transparent, cross, dedup_points,
+};
+
+/// How far the inside corner of a join is allowed to extend, relative to half the line's width.
+/// Very sharp turns would otherwise send it off far past the segments.
+const INNER_MITER_LIMIT: f32 = 10.0;
+
+/// Tessellates a stroked line into a list of triangles.
+pub fn stroke(
+    points: &[Point2<f32>], closed: bool, style: &StrokeStyle, color: Vector4<f32>,
+    tessellator: &ShapeTessellator, vertices: &mut Vec<DrawVertex>,
+) {
+    let points = dedup_points(points, closed);
+    if points.len() < 2 {
+        return
+    }
+    let closed = closed && points.len() > 2;
+
+    let stroker = Stroker::new(style, color, tessellator);
+    let count = points.len();
+    let segments = if closed { count } else { count - 1 };
+    let direction = |segment: usize| (points[(segment + 1) % count] - points[segment]).normalize();
+
+    // Start out with plain sections at the start and end of every segment
+    let mut starts = Vec::with_capacity(segments);
+    let mut ends = Vec::with_capacity(segments);
+    for segment in 0..segments {
+        let normal = left_normal(direction(segment));
+        starts.push(stroker.section(points[segment], normal));
+        ends.push(stroker.section(points[(segment + 1) % count], normal));
+    }
+
+    // Connect the segments, this adjusts their sections so they meet up
+    let mut fans = Vec::new();
+    let joins = if closed { 0..count } else { 1..count - 1 };
+    for point in joins {
+        let (a, b) = ((point + segments - 1) % segments, point);
+        stroker.join(
+            points[point], direction(a), direction(b), &mut ends[a], &mut starts[b], &mut fans
+        );
+    }
+
+    if !closed {
+        let first = direction(0);
+        let last = direction(segments - 1);
+        // The start cap faces backwards, so its left side is the segment's right side
+        stroker.cap(points[0], -left_normal(first), -first, &mut fans);
+        stroker.cap(points[count - 1], left_normal(last), last, &mut fans);
+    }
+
+    for segment in 0..segments {
+        stroker.emit_segment(&starts[segment], &ends[segment], vertices);
+    }
+    for fan in &fans {
+        stroker.emit_fan(fan, vertices);
+    }
+}
+
+/// The points at the start or end of a segment, going across the line from left to right.
+#[derive(Clone, Copy)]
+struct Section {
+    left_edge: Point2<f32>,
+    left: Point2<f32>,
+    right: Point2<f32>,
+    right_edge: Point2<f32>,
+}
+
+/// Fills in the gaps at joins and caps. Every pair is a point on the solid part's edge and the
+/// matching point on the feathered border's edge.
+struct Fan {
+    apex: Point2<f32>,
+    pairs: Vec<(Point2<f32>, Point2<f32>)>,
+}
+
+struct Stroker<'a> {
+    style: &'a StrokeStyle,
+    tessellator: &'a ShapeTessellator,
+    /// Half the width of the solid part of the line.
+    solid: f32,
+    /// Half the width of the line including the feathered border.
+    edge: f32,
+    color: Vector4<f32>,
+    feathered: bool,
+}
+
+impl<'a> Stroker<'a> {
+    fn new(
+        style: &'a StrokeStyle, color: Vector4<f32>, tessellator: &'a ShapeTessellator,
+    ) -> Self {
+        let feather = tessellator.feather.max(0.0);
+        let half_width = style.width * 0.5;
+
+        // The feathered border is centered on the line's edge. If the line is thinner than the
+        //  border, there's no room for a solid part, so instead the line is faded out to look
+        //  about as thin as it should be.
+        let (solid, edge, color) = if feather == 0.0 {
+            (half_width, half_width, color)
+        } else if style.width >= feather {
+            (half_width - feather * 0.5, half_width + feather * 0.5, color)
+        } else {
+            let alpha = color.w * style.width / feather;
+            (0.0, feather * 0.5, Vector4::new(color.x, color.y, color.z, alpha))
+        };
+
+        Stroker {
+            style,
+            tessellator,
+            solid,
+            edge,
+            color,
+            feathered: feather > 0.0,
+        }
+    }
+
+    fn section(&self, point: Point2<f32>, normal: Vector2<f32>) -> Section {
+        Section {
+            left_edge: point + normal * self.edge,
+            left: point + normal * self.solid,
+            right: point + normal * -self.solid,
+            right_edge: point + normal * -self.edge,
+        }
+    }
+
+    fn join(
+        &self, point: Point2<f32>, a: Vector2<f32>, b: Vector2<f32>,
+        a_end: &mut Section, b_start: &mut Section, fans: &mut Vec<Fan>,
+    ) {
+        let (a_normal, b_normal) = (left_normal(a), left_normal(b));
+        let turn = cross(a, b);
+
+        // If the segments continue in a straight line, they only need to share their sections
+        if turn.abs() < 0.0001 && a.dot(b) > 0.0 {
+            let section = self.section(point, (a_normal + b_normal).normalize());
+            *a_end = section;
+            *b_start = section;
+            return
+        }
+
+        // The miter goes along the line that splits the angle between the segments, scaled to
+        //  keep the sides at the right distance
+        let sum = a_normal + b_normal;
+        let (miter, ratio) = if sum.magnitude2() < 0.000001 {
+            (Vector2::new(0.0, 0.0), ::std::f32::INFINITY)
+        } else {
+            let miter = sum.normalize();
+            (miter, 1.0 / miter.dot(a_normal))
+        };
+
+        // The side the line turns towards is the inside of the join, the sides of the segments
+        //  overlap there so they can simply meet at the miter
+        let inner_left = turn > 0.0;
+        let outer_sign = if inner_left { -1.0 } else { 1.0 };
+        let inner_miter = miter * -outer_sign * ratio.min(INNER_MITER_LIMIT);
+        let inner = (point + inner_miter * self.solid, point + inner_miter * self.edge);
+        set_side(a_end, inner_left, inner);
+        set_side(b_start, inner_left, inner);
+
+        // On the outside there's a gap between the segments that needs to be filled
+        if self.style.join == LineJoin::Miter && ratio <= self.style.miter_limit {
+            let outer_miter = miter * outer_sign * ratio;
+            let outer = (point + outer_miter * self.solid, point + outer_miter * self.edge);
+            set_side(a_end, !inner_left, outer);
+            set_side(b_start, !inner_left, outer);
+            return
+        }
+
+        let from = a_normal * outer_sign;
+        let to = b_normal * outer_sign;
+        let directions = if self.style.join == LineJoin::Round {
+            let dot = from.dot(to).max(-1.0).min(1.0);
+            let towards = to - from * dot;
+            // If the line turns back on itself, the arc goes around the front of the segment
+            let towards = if towards.magnitude2() < 0.000001 { a } else { towards.normalize() };
+            self.arc(from, towards, dot.acos())
+        } else {
+            vec!(from, to)
+        };
+
+        fans.push(Fan {
+            apex: inner.0,
+            pairs: directions.iter()
+                .map(|d| (point + *d * self.solid, point + *d * self.edge))
+                .collect(),
+        });
+    }
+
+    fn cap(
+        &self, point: Point2<f32>, normal: Vector2<f32>, outwards: Vector2<f32>,
+        fans: &mut Vec<Fan>,
+    ) {
+        let border = self.edge - self.solid;
+        let pairs = match self.style.cap {
+            LineCap::Butt => {
+                // Without feathering the line's end is already in the right place
+                if !self.feathered {
+                    return
+                }
+
+                let extend = outwards * border;
+                vec!(
+                    (point + normal * self.solid, point + normal * self.edge),
+                    (point + normal * self.solid, point + normal * self.edge + extend),
+                    (point + normal * -self.solid, point + normal * -self.edge + extend),
+                    (point + normal * -self.solid, point + normal * -self.edge),
+                )
+            },
+            LineCap::Square => {
+                let (forward, back) = (normal + outwards, outwards - normal);
+                vec!(
+                    (point + normal * self.solid, point + normal * self.edge),
+                    (point + forward * self.solid, point + forward * self.edge),
+                    (point + back * self.solid, point + back * self.edge),
+                    (point + normal * -self.solid, point + normal * -self.edge),
+                )
+            },
+            LineCap::Round => {
+                self.arc(normal, outwards, PI).iter()
+                    .map(|d| (point + *d * self.solid, point + *d * self.edge))
+                    .collect()
+            },
+        };
+
+        fans.push(Fan {
+            apex: point,
+            pairs,
+        });
+    }
+
+    /// Returns directions along an arc, starting at from and rotating towards the given
+    /// perpendicular direction by the given angle.
+    fn arc(&self, from: Vector2<f32>, towards: Vector2<f32>, angle: f32) -> Vec<Vector2<f32>> {
+        let segments = self.tessellator.arc_segments(self.edge, angle);
+        (0..segments + 1).map(|i| {
+            let (sin, cos) = (angle * i as f32 / segments as f32).sin_cos();
+            from * cos + towards * sin
+        }).collect()
+    }
+
+    fn emit_segment(&self, start: &Section, end: &Section, vertices: &mut Vec<DrawVertex>) {
+        let (solid, edge) = (self.color, transparent(self.color));
+
+        quad(vertices, [
+            (start.left, solid), (end.left, solid), (end.right, solid), (start.right, solid),
+        ]);
+
+        if self.feathered {
+            quad(vertices, [
+                (start.left_edge, edge), (end.left_edge, edge),
+                (end.left, solid), (start.left, solid),
+            ]);
+            quad(vertices, [
+                (start.right, solid), (end.right, solid),
+                (end.right_edge, edge), (start.right_edge, edge),
+            ]);
+        }
+    }
+
+    fn emit_fan(&self, fan: &Fan, vertices: &mut Vec<DrawVertex>) {
+        let (solid, edge) = (self.color, transparent(self.color));
+
+        for pair in fan.pairs.windows(2) {
+            vertices.push(vertex(fan.apex, solid));
+            vertices.push(vertex(pair[0].0, solid));
+            vertices.push(vertex(pair[1].0, solid));
+
+            if self.feathered {
+                quad(vertices, [
+                    (pair[0].0, solid), (pair[1].0, solid), (pair[1].1, edge), (pair[0].1, edge),
+                ]);
+            }
+        }
+    }
+}
+
+fn left_normal(direction: Vector2<f32>) -> Vector2<f32> {
+    Vector2::new(-direction.y, direction.x)
+}
+
+/// Replaces the solid and edge point on one side of a section.
+fn set_side(section: &mut Section, left: bool, points: (Point2<f32>, Point2<f32>)) {
+    if left {
+        section.left = points.0;
+        section.left_edge = points.1;
+    } else {
+        section.right = points.0;
+        section.right_edge = points.1;
+    }
+}
+
+fn quad(vertices: &mut Vec<DrawVertex>, corners: [(Point2<f32>, Vector4<f32>); 4]) {
+    for i in &[0, 1, 2, 0, 2, 3] {
+        vertices.push(vertex(corners[*i].0, corners[*i].1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::{PI};
+
+    use cgmath::{Vector4, Point2};
+    use render_data::{DrawVertex};
+    use shapes::{StrokeStyle, LineJoin, LineCap, ShapeTessellator, MAX_ARC_SEGMENTS, cross};
+    use super::{stroke};
+
+    fn stroke_points(
+        points: &[(f32, f32)], closed: bool, style: &StrokeStyle, tessellator: &ShapeTessellator,
+    ) -> Vec<DrawVertex> {
+        let points: Vec<_> = points.iter().map(|p| Point2::new(p.0, p.1)).collect();
+        let mut vertices = Vec::new();
+        stroke(
+            &points, closed, style, Vector4::new(1.0, 1.0, 1.0, 1.0), tessellator, &mut vertices
+        );
+        vertices
+    }
+
+    /// The total area covered by the triangles, overlapping triangles are counted twice.
+    fn area(vertices: &[DrawVertex]) -> f32 {
+        vertices.chunks(3)
+            .map(|t| cross(t[1].position - t[0].position, t[2].position - t[0].position).abs())
+            .sum::<f32>() * 0.5
+    }
+
+    fn line_area(cap: LineCap) -> f32 {
+        let style = StrokeStyle::new(2.0).with_cap(cap);
+        let tessellator = ShapeTessellator::new().with_feather(0.0).with_tolerance(0.001);
+        area(&stroke_points(&[(0.0, 0.0), (10.0, 0.0)], false, &style, &tessellator))
+    }
+
+    fn corner_area(join: LineJoin) -> f32 {
+        let style = StrokeStyle::new(2.0).with_join(join);
+        let tessellator = ShapeTessellator::new().with_feather(0.0).with_tolerance(0.001);
+        let points = [(0.0, 0.0), (10.0, 0.0), (10.0, 10.0)];
+        area(&stroke_points(&points, false, &style, &tessellator))
+    }
+
+    #[test]
+    fn caps_extend_the_line() {
+        assert!((line_area(LineCap::Butt) - 20.0).abs() < 0.0001);
+        assert!((line_area(LineCap::Square) - 24.0).abs() < 0.0001);
+
+        // A half circle on both ends, the segments approximating it cut off a little
+        let round = line_area(LineCap::Round);
+        assert!(round < 20.0 + PI && round > 20.0 + PI - 0.01);
+    }
+
+    #[test]
+    fn joins_fill_the_outside_corner() {
+        // Both segments go from the inner corner to the outer corner, a full 1x1 square on the
+        //  outside for a miter, half of it for a bevel and a quarter circle for round
+        assert!((corner_area(LineJoin::Miter) - 40.0).abs() < 0.0001);
+        assert!((corner_area(LineJoin::Bevel) - 39.5).abs() < 0.0001);
+        let round = corner_area(LineJoin::Round);
+        assert!(round < 39.0 + PI * 0.25 && round > 39.0 + PI * 0.25 - 0.01);
+    }
+
+    #[test]
+    fn sharp_miter_falls_back_to_bevel() {
+        let style = StrokeStyle::new(2.0).with_miter_limit(1.0);
+        let tessellator = ShapeTessellator::new().with_feather(0.0);
+        let points = [(0.0, 0.0), (10.0, 0.0), (10.0, 10.0)];
+        let vertices = stroke_points(&points, false, &style, &tessellator);
+
+        assert!((area(&vertices) - 39.5).abs() < 0.0001);
+    }
+
+    #[test]
+    fn turning_back_stays_finite() {
+        let tessellator = ShapeTessellator::new();
+        for join in &[LineJoin::Miter, LineJoin::Bevel, LineJoin::Round] {
+            let style = StrokeStyle::new(2.0).with_join(*join);
+            let points = [(0.0, 0.0), (10.0, 0.0), (0.0, 0.0)];
+            let vertices = stroke_points(&points, false, &style, &tessellator);
+
+            assert!(!vertices.is_empty(), "{:?}", join);
+            assert!(
+                vertices.iter().all(|v| v.position.x.is_finite() && v.position.y.is_finite()),
+                "{:?}", join
+            );
+            // Nothing may stick out further than the line's width past the turn
+            assert!(vertices.iter().all(|v| v.position.x <= 11.5), "{:?}", join);
+        }
+    }
+
+    #[test]
+    fn skips_degenerate_lines() {
+        let style = StrokeStyle::new(2.0);
+        let tessellator = ShapeTessellator::new();
+
+        assert!(stroke_points(&[], false, &style, &tessellator).is_empty());
+        assert!(stroke_points(&[(1.0, 1.0)], false, &style, &tessellator).is_empty());
+        assert!(stroke_points(&[(1.0, 1.0), (1.0, 1.0)], true, &style, &tessellator).is_empty());
+    }
+
+    #[test]
+    fn ignores_duplicate_points() {
+        let style = StrokeStyle::new(2.0).with_join(LineJoin::Round).with_cap(LineCap::Round);
+        let tessellator = ShapeTessellator::new();
+        let plain = stroke_points(
+            &[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0)], false, &style, &tessellator,
+        );
+        let duplicates = stroke_points(
+            &[(0.0, 0.0), (0.0, 0.0), (10.0, 0.0), (10.0, 0.0), (10.0, 10.0)], false,
+            &style, &tessellator,
+        );
+
+        let positions = |v: &[DrawVertex]| v.iter().map(|v| v.position).collect::<Vec<_>>();
+        assert_eq!(positions(&plain), positions(&duplicates));
+    }
+
+    #[test]
+    fn arc_segments_are_limited() {
+        // The step between segments rounds down to nothing for a radius this large
+        let style = StrokeStyle::new(1.0e9).with_cap(LineCap::Round);
+        let tessellator = ShapeTessellator::new().with_feather(0.0).with_tolerance(0.0);
+        let vertices = stroke_points(&[(0.0, 0.0), (10.0, 0.0)], false, &style, &tessellator);
+
+        assert!(vertices.len() <= 6 + 2 * MAX_ARC_SEGMENTS * 3);
+    }
+}
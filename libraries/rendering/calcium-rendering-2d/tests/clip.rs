@@ -0,0 +1,43 @@
+extern crate cgmath;
+extern crate calcium_rendering_2d;
+
+use cgmath::{Vector2, Point2, Matrix4, SquareMatrix, Deg};
+
+use calcium_rendering_2d::render_data::{ClipRect, Rectangle};
+
+fn rect(min_x: f32, min_y: f32, max_x: f32, max_y: f32) -> Rectangle<f32> {
+    Rectangle::new(Point2::new(min_x, min_y), Point2::new(max_x, max_y))
+}
+
+#[test]
+fn projected_clip_covers_the_projected_rectangle() {
+    let clip = ClipRect::Projected(rect(-0.5, -0.5, 0.5, 0.5));
+
+    let pixels = clip.to_pixels(Matrix4::identity(), Vector2::new(100, 100));
+
+    assert_eq!(pixels.min, Point2::new(25, 25));
+    assert_eq!(pixels.max, Point2::new(75, 75));
+}
+
+#[test]
+fn rotated_projected_clip_covers_all_corners() {
+    // Rotated by 45 degrees, min and max end up straight above each other while the other two
+    //  corners reach out to the sides
+    let clip = ClipRect::Projected(rect(-0.5, -0.5, 0.5, 0.5));
+    let projection = Matrix4::from_angle_z(Deg(45.0));
+
+    let pixels = clip.to_pixels(projection, Vector2::new(100, 100));
+
+    assert_eq!(pixels.min, Point2::new(15, 15));
+    assert_eq!(pixels.max, Point2::new(85, 85));
+}
+
+#[test]
+fn pixels_clip_is_limited_to_the_target() {
+    let clip = ClipRect::Pixels(rect(-10.0, 20.0, 50.0, 200.0));
+
+    let pixels = clip.to_pixels(Matrix4::identity(), Vector2::new(100, 100));
+
+    assert_eq!(pixels.min, Point2::new(0, 20));
+    assert_eq!(pixels.max, Point2::new(50, 100));
+}
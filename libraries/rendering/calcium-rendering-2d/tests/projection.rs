@@ -0,0 +1,221 @@
+extern crate cgmath;
+extern crate calcium_rendering_2d;
+extern crate calcium_rendering_software;
+
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4};
+
+use cgmath::{Vector2, Point2, Rad};
+
+use calcium_rendering_2d::render_data::{
+    VirtualResolution, ScalePolicy, Projection, Camera, RenderSet, RenderBatch, ShaderMode, UvMode,
+    ClipRect, Rectangle, project_point,
+};
+use calcium_rendering_software::{SoftwareRendererRaw};
+
+const POLICIES: [ScalePolicy; 4] = [
+    ScalePolicy::Stretch, ScalePolicy::Fit, ScalePolicy::IntegerScale, ScalePolicy::Fill,
+];
+
+fn assert_near(actual: Point2<f32>, expected: Point2<f32>, message: &str) {
+    assert!(
+        (actual.x - expected.x).abs() < 0.001 && (actual.y - expected.y).abs() < 0.001,
+        "{}: {:?} is not {:?}", message, actual, expected
+    );
+}
+
+#[test]
+fn zero_size_is_rejected() {
+    assert!(VirtualResolution::new(Vector2::new(0, 50), ScalePolicy::Fit).is_err());
+    assert!(VirtualResolution::new(Vector2::new(100, 0), ScalePolicy::Fit).is_err());
+    let resolution = VirtualResolution::new(Vector2::new(100, 50), ScalePolicy::Fit).unwrap();
+    assert_eq!(resolution.size(), Vector2::new(100, 50));
+}
+
+#[test]
+fn policies_place_the_virtual_screen() {
+    let target = Vector2::new(250, 150);
+    let expected = [
+        (ScalePolicy::Stretch, Point2::new(0.0, 0.0), Point2::new(250.0, 150.0)),
+        (ScalePolicy::Fit, Point2::new(0.0, 12.0), Point2::new(250.0, 137.0)),
+        (ScalePolicy::IntegerScale, Point2::new(25.0, 25.0), Point2::new(225.0, 125.0)),
+        (ScalePolicy::Fill, Point2::new(-25.0, 0.0), Point2::new(275.0, 150.0)),
+    ];
+
+    for &(policy, min, max) in &expected {
+        let resolution = VirtualResolution::new(Vector2::new(100, 50), policy).unwrap();
+        let area = resolution.area(target);
+
+        let message = format!("{:?}", policy);
+        assert_near(area.min, min, &message);
+        assert_near(area.max, max, &message);
+    }
+}
+
+#[test]
+fn virtual_and_screen_round_trip() {
+    let points = [
+        Point2::new(0.0, 0.0), Point2::new(100.0, 50.0), Point2::new(12.5, 37.25),
+        // Outside of the virtual screen, on the bars
+        Point2::new(-10.0, 60.0),
+    ];
+
+    for policy in &POLICIES {
+        for target in &[Vector2::new(250, 150), Vector2::new(64, 300), Vector2::new(100, 50)] {
+            let resolution = VirtualResolution::new(Vector2::new(100, 50), *policy).unwrap();
+
+            for point in &points {
+                let screen = resolution.virtual_to_screen(*point, *target);
+                let message = format!("{:?} on {:?}", policy, target);
+                assert_near(resolution.screen_to_virtual(screen, *target), *point, &message);
+            }
+        }
+    }
+}
+
+#[test]
+fn screen_to_virtual_matches_the_projection() {
+    for policy in &POLICIES {
+        let target = Vector2::new(250, 150);
+        let resolution = VirtualResolution::new(Vector2::new(100, 50), *policy).unwrap();
+        let matrix = Projection::Virtual(resolution.clone()).to_matrix(target);
+
+        for point in &[Point2::new(0.0, 0.0), Point2::new(100.0, 50.0), Point2::new(30.0, 5.0)] {
+            let screen = project_point(matrix, *point, target);
+            let message = format!("{:?}", policy);
+            assert_near(resolution.virtual_to_screen(*point, target), screen, &message);
+            assert_near(resolution.screen_to_virtual(screen, target), *point, &message);
+        }
+    }
+}
+
+fn viewport_set(
+    projection: Projection, viewport: Rectangle<f32>,
+) -> RenderSet<SoftwareRendererRaw> {
+    RenderSet::new(projection, Vec::new()).with_viewport(viewport)
+}
+
+#[test]
+fn camera_round_trips_with_zoom_and_rotation() {
+    let target = Vector2::new(320, 200);
+    let camera = Camera::new(16.0, Point2::new(3.0, -2.0))
+        .with_zoom(2.5)
+        .with_rotation(Rad(0.7));
+
+    for point in &[Point2::new(3.0, -2.0), Point2::new(0.0, 0.0), Point2::new(5.5, 1.25)] {
+        let screen = camera.world_to_screen(*point, target);
+        assert_near(camera.screen_to_world(screen, target), *point, "World to screen");
+    }
+    for point in &[Point2::new(0.0, 0.0), Point2::new(320.0, 200.0), Point2::new(17.0, 150.5)] {
+        let world = camera.screen_to_world(*point, target);
+        assert_near(camera.world_to_screen(world, target), *point, "Screen to world");
+    }
+
+    // The camera's position is at the center, and zoom scales the distance from it
+    assert_near(
+        camera.world_to_screen(Point2::new(3.0, -2.0), target), Point2::new(160.0, 100.0),
+        "Camera position",
+    );
+    let unrotated = Camera::new(16.0, Point2::new(3.0, -2.0)).with_zoom(2.5);
+    assert_near(
+        unrotated.world_to_screen(Point2::new(4.0, -2.0), target), Point2::new(200.0, 100.0),
+        "Zoomed unit",
+    );
+}
+
+#[test]
+fn camera_rotation_turns_the_world_the_other_way() {
+    let target = Vector2::new(100, 100);
+    let camera = Camera::new(10.0, Point2::new(0.0, 0.0)).with_rotation(Rad(FRAC_PI_2));
+
+    // With the camera turned counter-clockwise, what was to its right is now below it
+    assert_near(
+        camera.world_to_screen(Point2::new(1.0, 0.0), target), Point2::new(50.0, 60.0),
+        "Rotated point",
+    );
+}
+
+#[test]
+fn zero_zoom_stays_finite() {
+    let target = Vector2::new(100, 100);
+    let camera = Camera::new(10.0, Point2::new(2.0, 3.0)).with_zoom(0.0);
+
+    let matrix = camera.to_matrix(target);
+    let columns: [[f32; 4]; 4] = matrix.into();
+    assert!(columns.iter().all(|c| c.iter().all(|v| v.is_finite())));
+    assert_near(
+        camera.world_to_screen(Point2::new(2.0, 3.0), target), Point2::new(50.0, 50.0),
+        "Camera position",
+    );
+}
+
+#[test]
+fn visible_bounds_cover_rotated_view() {
+    let target = Vector2::new(100, 100);
+    let camera = Camera::new(1.0, Point2::new(10.0, 0.0));
+    let bounds = camera.visible_bounds(target);
+    assert_near(bounds.min, Point2::new(-40.0, -50.0), "Unrotated min");
+    assert_near(bounds.max, Point2::new(60.0, 50.0), "Unrotated max");
+
+    // Turned by 45 degrees, the corners of the target end up on the axes
+    let half_diagonal = 50.0 * 2.0f32.sqrt();
+    let bounds = camera.with_rotation(Rad(FRAC_PI_4)).visible_bounds(target);
+    assert_near(
+        bounds.min, Point2::new(10.0 - half_diagonal, -half_diagonal), "Rotated min",
+    );
+    assert_near(bounds.max, Point2::new(10.0 + half_diagonal, half_diagonal), "Rotated max");
+}
+
+#[test]
+fn render_set_viewport_offsets_the_projection() {
+    let target = Vector2::new(200, 100);
+    let viewport = Rectangle::new(Point2::new(10.0, 20.0), Point2::new(60.0, 70.0));
+    let set = viewport_set(Projection::Pixels, viewport);
+
+    assert_eq!(set.viewport_size(target), Vector2::new(50, 50));
+    assert_near(set.world_to_screen(Point2::new(0.0, 0.0), target), Point2::new(10.0, 20.0), "Min");
+    assert_near(
+        set.world_to_screen(Point2::new(50.0, 50.0), target), Point2::new(60.0, 70.0), "Max",
+    );
+    assert_near(
+        set.screen_to_world(Point2::new(35.0, 45.0), target), Point2::new(25.0, 25.0), "Center",
+    );
+}
+
+#[test]
+fn fractional_viewport_uses_the_rounded_size() {
+    let target = Vector2::new(200, 100);
+    let viewport = Rectangle::new(Point2::new(10.0, 20.0), Point2::new(60.4, 70.4));
+    let set = viewport_set(Projection::Pixels, viewport);
+
+    // One pixel of the projection is one pixel on the target, rather than slightly larger
+    assert_eq!(set.viewport_size(target), Vector2::new(50, 50));
+    assert_near(
+        set.world_to_screen(Point2::new(50.0, 50.0), target), Point2::new(60.0, 70.0), "Max",
+    );
+}
+
+#[test]
+fn scissor_is_limited_to_the_viewport() {
+    let target = Vector2::new(200, 100);
+    let viewport = Rectangle::new(Point2::new(10.0, 20.0), Point2::new(60.0, 70.0));
+    let set = viewport_set(Projection::Pixels, viewport);
+
+    let batch = RenderBatch::new(ShaderMode::Color, UvMode::YDown);
+    let scissor = set.scissor(&batch, target).unwrap();
+    assert_eq!((scissor.min, scissor.max), (Point2::new(10, 20), Point2::new(60, 70)));
+
+    // Projected clip rectangles are in the viewport's coordinates, pixel ones in the target's
+    let batch = RenderBatch::new(ShaderMode::Color, UvMode::YDown)
+        .with_clip(ClipRect::Projected(Rectangle::new(
+            Point2::new(40.0, 0.0), Point2::new(100.0, 10.0),
+        )));
+    let scissor = set.scissor(&batch, target).unwrap();
+    assert_eq!((scissor.min, scissor.max), (Point2::new(50, 20), Point2::new(60, 30)));
+
+    let batch = RenderBatch::new(ShaderMode::Color, UvMode::YDown)
+        .with_clip(ClipRect::Pixels(Rectangle::new(
+            Point2::new(0.0, 0.0), Point2::new(30.0, 30.0),
+        )));
+    let scissor = set.scissor(&batch, target).unwrap();
+    assert_eq!((scissor.min, scissor.max), (Point2::new(10, 20), Point2::new(30, 30)));
+}
@@ -0,0 +1,91 @@
+extern crate cgmath;
+extern crate calcium_rendering_2d;
+extern crate calcium_rendering_software;
+
+use cgmath::{Vector4, Point2};
+
+use calcium_rendering_2d::render_data::{
+    RenderBatch, ShaderMode, UvMode, DrawVertex, Rectangle, QuadTransform,
+};
+use calcium_rendering_software::{SoftwareRendererRaw};
+
+fn batch() -> RenderBatch<SoftwareRendererRaw> {
+    RenderBatch::new(ShaderMode::Color, UvMode::YDown)
+}
+
+fn vertex(x: f32) -> DrawVertex {
+    DrawVertex::new(Point2::new(x, 0.0), Point2::new(0.0, 0.0), Vector4::new(1.0, 1.0, 1.0, 1.0))
+}
+
+fn positions(vertices: &[DrawVertex]) -> Vec<f32> {
+    vertices.iter().map(|v| v.position.x).collect()
+}
+
+fn unit_rect() -> Rectangle<f32> {
+    Rectangle::new(Point2::new(0.0, 0.0), Point2::new(1.0, 1.0))
+}
+
+#[test]
+fn triangle_vertices_expands_indices() {
+    let mut batch = batch();
+    batch.vertices = vec!(vertex(0.0), vertex(1.0), vertex(2.0));
+    batch.indices = Some(vec!(2, 1, 0, 0, 0, 1));
+
+    assert_eq!(positions(&batch.triangle_vertices()), vec!(2.0, 1.0, 0.0, 0.0, 0.0, 1.0));
+}
+
+#[test]
+fn triangle_vertices_skips_out_of_range_indices() {
+    let mut batch = batch();
+    batch.vertices = vec!(vertex(0.0), vertex(1.0), vertex(2.0));
+    batch.indices = Some(vec!(0, 1, 3, 2, 1, 0));
+
+    assert_eq!(positions(&batch.triangle_vertices()), vec!(2.0, 1.0, 0.0));
+}
+
+#[test]
+fn valid_indices_skips_incomplete_and_out_of_range_triangles() {
+    let mut batch = batch();
+    batch.vertices = vec!(vertex(0.0), vertex(1.0), vertex(2.0));
+    assert!(batch.valid_indices().is_none());
+
+    batch.indices = Some(vec!(0, 1, 2, 2, 1, 0));
+    assert_eq!(&*batch.valid_indices().unwrap(), &[0, 1, 2, 2, 1, 0]);
+
+    batch.indices = Some(vec!(0, 1, 3, 2, 1, 0, 0, 1));
+    assert_eq!(&*batch.valid_indices().unwrap(), &[2, 1, 0]);
+}
+
+#[test]
+fn indexed_keeps_earlier_vertices() {
+    let white = Vector4::new(1.0, 1.0, 1.0, 1.0);
+    let mut batch = batch();
+    batch.push_rectangle(unit_rect(), unit_rect(), white);
+    let mut batch = batch.indexed();
+    batch.push_rectangle(unit_rect(), unit_rect(), white);
+
+    // 6 vertices from before indexing, 4 after, and 12 indices covering both rectangles
+    assert_eq!(batch.vertices.len(), 10);
+    assert_eq!(batch.indices.as_ref().unwrap().len(), 12);
+    assert_eq!(batch.triangle_vertices().len(), 12);
+    assert_eq!(batch.vertex_count(), 12);
+}
+
+#[test]
+fn push_rectangle_transformed_flips_texture_in_place() {
+    let white = Vector4::new(1.0, 1.0, 1.0, 1.0);
+    let mut flipped = batch();
+    flipped.push_rectangle_transformed(
+        unit_rect(), &QuadTransform::new().with_flip(true, true), unit_rect(), white,
+    );
+
+    // The same corners as an unflipped rectangle, with the texture mirrored on both axes
+    let mut mirrored = batch();
+    mirrored.push_rectangle(
+        unit_rect(), Rectangle::new(Point2::new(1.0, 1.0), Point2::new(0.0, 0.0)), white,
+    );
+    let corners = |batch: &RenderBatch<SoftwareRendererRaw>| -> Vec<_> {
+        batch.vertices.iter().map(|v| (v.position, v.uv)).collect()
+    };
+    assert_eq!(corners(&flipped), corners(&mirrored));
+}
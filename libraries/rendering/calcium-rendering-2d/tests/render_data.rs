@@ -0,0 +1,68 @@
+extern crate cgmath;
+extern crate calcium_rendering_2d;
+extern crate calcium_rendering_software;
+
+use std::f32;
+
+use cgmath::{Vector4, Point2};
+
+use calcium_rendering_2d::render_data::{
+    RenderData, RenderSet, RenderBatch, ShaderMode, UvMode, DrawVertex, Projection,
+};
+use calcium_rendering_software::{SoftwareRendererRaw};
+
+/// A batch that can be identified by the X position of its only vertex.
+fn batch(id: f32) -> RenderBatch<SoftwareRendererRaw> {
+    let mut batch = RenderBatch::new(ShaderMode::Color, UvMode::YDown);
+    batch.vertices.push(DrawVertex::new(
+        Point2::new(id, 0.0), Point2::new(0.0, 0.0), Vector4::new(1.0, 1.0, 1.0, 1.0),
+    ));
+    batch
+}
+
+fn ids(batches: &[&RenderBatch<SoftwareRendererRaw>]) -> Vec<f32> {
+    batches.iter().map(|b| b.vertices[0].position.x).collect()
+}
+
+#[test]
+fn batches_sort_by_layer_then_key() {
+    let set = RenderSet::new(Projection::Pixels, vec!(
+        batch(0.0).with_layer(1),
+        batch(1.0).with_sort_key(2.0),
+        batch(2.0),
+        batch(3.0).with_sort_key(-1.0),
+        batch(4.0),
+    ));
+
+    // Without a sort key goes first, and equal batches stay in the order they were added
+    assert_eq!(ids(&set.sorted_batches()), vec!(2.0, 4.0, 3.0, 1.0, 0.0));
+}
+
+#[test]
+fn nan_sort_keys_go_last_within_layer() {
+    let set = RenderSet::new(Projection::Pixels, vec!(
+        batch(0.0).with_sort_key(f32::NAN),
+        batch(1.0).with_layer(1),
+        batch(2.0).with_sort_key(1.0),
+        batch(3.0).with_sort_key(f32::NAN),
+        batch(4.0),
+        batch(5.0).with_sort_key(f32::INFINITY),
+    ));
+
+    assert_eq!(ids(&set.sorted_batches()), vec!(4.0, 2.0, 5.0, 0.0, 3.0, 1.0));
+}
+
+#[test]
+fn nan_sort_keys_go_last_for_sets() {
+    let mut data = RenderData::new();
+    data.render_sets.push(RenderSet::new(Projection::Pixels, vec!(batch(0.0)))
+        .with_sort_key(f32::NAN));
+    data.render_sets.push(RenderSet::new(Projection::Pixels, vec!(batch(1.0)))
+        .with_sort_key(3.0));
+    data.render_sets.push(RenderSet::new(Projection::Pixels, vec!(batch(2.0))));
+
+    let order: Vec<_> = data.sorted_sets().iter()
+        .map(|s| s.batches[0].vertices[0].position.x)
+        .collect();
+    assert_eq!(order, vec!(2.0, 1.0, 0.0));
+}
@@ -0,0 +1,173 @@
+extern crate cgmath;
+#[macro_use]
+extern crate slog;
+extern crate calcium_rendering;
+extern crate calcium_rendering_2d;
+extern crate calcium_rendering_software;
+
+use std::f32::{NAN};
+use std::sync::{Arc};
+
+use cgmath::{Vector2, Vector4, Point2};
+use slog::{Logger, Discard};
+
+use calcium_rendering::{Renderer};
+use calcium_rendering::texture::{Texture, PixelFormat};
+use calcium_rendering_2d::render_data::{
+    SpriteBatcher, Sprite, RenderBatch, ShaderMode, UvMode, BlendMode, Rectangle, QuadTransform,
+    affine_translation,
+};
+use calcium_rendering_software::{SoftwareRendererRaw};
+
+fn create_renderer() -> Renderer<SoftwareRendererRaw> {
+    let log = Logger::root(Discard, o!());
+    Renderer::raw_new(SoftwareRendererRaw::new(&log, Vector2::new(4, 4)), log)
+}
+
+fn create_texture(
+    renderer: &mut Renderer<SoftwareRendererRaw>,
+) -> Arc<Texture<SoftwareRendererRaw>> {
+    Texture::new()
+        .from_bytes(vec!(255u8; 4), Vector2::new(1, 1), PixelFormat::Rgba8)
+        .build(renderer).unwrap()
+}
+
+fn rect() -> Rectangle<f32> {
+    Rectangle::new(Point2::new(0.0, 0.0), Point2::new(1.0, 1.0))
+}
+
+/// Creates a sprite that can be recognized in the output by the red channel of its color.
+fn sprite(
+    texture: &Arc<Texture<SoftwareRendererRaw>>, id: f32, depth: f32,
+) -> Sprite<SoftwareRendererRaw> {
+    Sprite::new(texture.clone(), rect(), rect())
+        .with_color(Vector4::new(id, 0.0, 0.0, 1.0))
+        .with_depth(depth)
+}
+
+/// Gets the IDs of the sprites in every batch, in the order they're drawn in.
+fn sprite_ids(batches: &[RenderBatch<SoftwareRendererRaw>]) -> Vec<Vec<f32>> {
+    batches.iter()
+        .map(|batch| batch.vertices.chunks(6).map(|quad| quad[0].color.x).collect())
+        .collect()
+}
+
+#[test]
+fn same_texture_merges() {
+    let mut renderer = create_renderer();
+    let texture = create_texture(&mut renderer);
+
+    let mut batcher = SpriteBatcher::new();
+    batcher.push(sprite(&texture, 0.0, 0.0));
+    batcher.push(sprite(&texture, 1.0, 0.0));
+    batcher.push(sprite(&texture, 2.0, 1.0));
+
+    assert_eq!(sprite_ids(&batcher.into_batches()), vec!(vec!(0.0, 1.0, 2.0)));
+}
+
+#[test]
+fn different_texture_blend_or_uv_mode_splits() {
+    let mut renderer = create_renderer();
+    let texture = create_texture(&mut renderer);
+    let other_texture = create_texture(&mut renderer);
+
+    let mut batcher = SpriteBatcher::new();
+    batcher.push(sprite(&texture, 0.0, 0.0));
+    batcher.push(sprite(&other_texture, 1.0, 0.0));
+    batcher.push(sprite(&other_texture, 2.0, 0.0).with_blend_mode(BlendMode::Additive));
+    batcher.push(sprite(&other_texture, 3.0, 0.0).with_uv_mode(UvMode::YUp));
+    batcher.push(
+        sprite(&other_texture, 4.0, 0.0).with_mode(ShaderMode::Mask(other_texture.clone()))
+    );
+    let batches = batcher.into_batches();
+
+    assert_eq!(
+        sprite_ids(&batches),
+        vec!(vec!(0.0), vec!(1.0), vec!(2.0), vec!(3.0), vec!(4.0))
+    );
+    assert_eq!(batches[2].blend_mode, BlendMode::Additive);
+    assert_eq!(batches[3].uv_mode, UvMode::YUp);
+}
+
+#[test]
+fn order_within_depth_is_stable() {
+    let mut renderer = create_renderer();
+    let texture = create_texture(&mut renderer);
+    let other_texture = create_texture(&mut renderer);
+
+    // Merging the first and last sprite would draw the last one below the middle one
+    let mut batcher = SpriteBatcher::new();
+    batcher.push(sprite(&texture, 1.0, 1.0));
+    batcher.push(sprite(&texture, 2.0, 0.0));
+    batcher.push(sprite(&other_texture, 3.0, 0.0));
+    batcher.push(sprite(&texture, 4.0, 0.0));
+
+    assert_eq!(
+        sprite_ids(&batcher.into_batches()),
+        vec!(vec!(2.0), vec!(3.0), vec!(4.0, 1.0))
+    );
+}
+
+#[test]
+fn nan_depth_goes_last() {
+    let mut renderer = create_renderer();
+    let texture = create_texture(&mut renderer);
+    let other_texture = create_texture(&mut renderer);
+
+    let mut batcher = SpriteBatcher::new();
+    batcher.push(sprite(&texture, 0.0, NAN));
+    batcher.push(sprite(&other_texture, 1.0, 2.0));
+    batcher.push(sprite(&other_texture, 2.0, -1.0));
+    batcher.push(sprite(&texture, 3.0, NAN));
+
+    assert_eq!(
+        sprite_ids(&batcher.into_batches()),
+        vec!(vec!(2.0, 1.0), vec!(0.0, 3.0))
+    );
+}
+
+/// Gets the position and UV of the first vertex of every sprite, the destination's min corner.
+fn first_corners(batches: &[RenderBatch<SoftwareRendererRaw>]) -> Vec<(Point2<f32>, Point2<f32>)> {
+    batches.iter()
+        .flat_map(|batch| batch.vertices.chunks(6).map(|quad| (quad[0].position, quad[0].uv)))
+        .collect()
+}
+
+#[test]
+fn quad_transform_is_applied_when_drawn() {
+    let mut renderer = create_renderer();
+    let texture = create_texture(&mut renderer);
+    let transform = QuadTransform::new().with_flip(true, false).with_scale(Vector2::new(2.0, 2.0));
+
+    // Setting the quad transform twice doesn't undo the flip, and moving the sprite afterwards
+    //  moves the pivot with it
+    let mut sprite = Sprite::new(texture.clone(), rect(), rect())
+        .with_quad_transform(transform.clone())
+        .with_quad_transform(transform);
+    sprite.destination = Rectangle::new(Point2::new(2.0, 2.0), Point2::new(3.0, 3.0));
+
+    let mut batcher = SpriteBatcher::new();
+    batcher.push(sprite);
+    let batches = batcher.into_batches();
+    assert_eq!(first_corners(&batches), vec!((Point2::new(2.0, 2.0), Point2::new(1.0, 0.0))));
+    assert_eq!(batches[0].vertices[3].position, Point2::new(4.0, 4.0));
+}
+
+#[test]
+fn transform_stack_applies_to_pushed_sprites() {
+    let mut renderer = create_renderer();
+    let texture = create_texture(&mut renderer);
+
+    let mut batcher = SpriteBatcher::new();
+    batcher.push_transform(affine_translation(Vector2::new(10.0, 0.0)));
+    batcher.push(sprite(&texture, 0.0, 0.0)
+        .with_quad_transform(QuadTransform::new().with_scale(Vector2::new(2.0, 1.0))));
+    assert!(batcher.pop_transform());
+    assert!(!batcher.pop_transform());
+    batcher.push(sprite(&texture, 1.0, 0.0));
+
+    // The quad transform is applied in the sprite's own space, before the stack's transform
+    let batches = batcher.into_batches();
+    let max_corners: Vec<_> = batches[0].vertices.chunks(6).map(|quad| quad[3].position).collect();
+    assert_eq!(max_corners, vec!(Point2::new(12.0, 1.0), Point2::new(1.0, 1.0)));
+}
@@ -0,0 +1,85 @@
+extern crate cgmath;
+extern crate calcium_rendering_2d;
+
+use std::f32::consts::{FRAC_PI_2};
+
+use cgmath::{Vector2, Point2, Rad};
+
+use calcium_rendering_2d::render_data::{
+    QuadTransform, TransformStack, Rectangle, affine_translation, affine_rotation, affine_scale,
+    transform_point,
+};
+
+fn assert_near(actual: Point2<f32>, expected: Point2<f32>, message: &str) {
+    assert!(
+        (actual.x - expected.x).abs() < 0.001 && (actual.y - expected.y).abs() < 0.001,
+        "{}: {:?} is not {:?}", message, actual, expected
+    );
+}
+
+#[test]
+fn positive_rotation_is_clockwise_with_y_down() {
+    let rotation = affine_rotation(Rad(FRAC_PI_2));
+    assert_near(
+        transform_point(rotation, Point2::new(1.0, 0.0)), Point2::new(0.0, 1.0), "Right"
+    );
+    assert_near(
+        transform_point(rotation, Point2::new(0.0, 1.0)), Point2::new(-1.0, 0.0), "Down"
+    );
+}
+
+#[test]
+fn quad_transform_pivots_around_origin() {
+    // The pivot is the origin relative to the rectangle's minimum corner, it doesn't move
+    let transform = QuadTransform::new()
+        .with_origin(Vector2::new(1.0, 1.0))
+        .with_rotation(Rad(FRAC_PI_2));
+    let matrix = transform.to_matrix(Point2::new(10.0, 10.0));
+    assert_near(transform_point(matrix, Point2::new(11.0, 11.0)), Point2::new(11.0, 11.0), "Pivot");
+    assert_near(transform_point(matrix, Point2::new(12.0, 11.0)), Point2::new(11.0, 12.0), "Right");
+
+    let transform = QuadTransform::new()
+        .with_origin(Vector2::new(1.0, 1.0))
+        .with_scale(Vector2::new(2.0, 3.0));
+    let matrix = transform.to_matrix(Point2::new(10.0, 10.0));
+    assert_near(transform_point(matrix, Point2::new(10.0, 10.0)), Point2::new(9.0, 8.0), "Min");
+}
+
+#[test]
+fn quad_transform_flips_source() {
+    let source = Rectangle::new(Point2::new(0.0, 0.25), Point2::new(0.5, 1.0));
+
+    let flipped = QuadTransform::new().with_flip(true, false).flip_source(source.clone());
+    assert_eq!((flipped.min, flipped.max), (Point2::new(0.5, 0.25), Point2::new(0.0, 1.0)));
+
+    let flipped = QuadTransform::new().with_flip(false, true).flip_source(source);
+    assert_eq!((flipped.min, flipped.max), (Point2::new(0.0, 1.0), Point2::new(0.5, 0.25)));
+}
+
+#[test]
+fn transform_stack_composes_in_parent_space() {
+    let mut stack = TransformStack::new();
+    stack.push(affine_translation(Vector2::new(10.0, 0.0)));
+    stack.push(affine_scale(Vector2::new(2.0, 2.0)));
+    assert_eq!(stack.depth(), 2);
+
+    // The scale happens inside the translated space, so the translation isn't scaled
+    let point = Point2::new(1.0, 1.0);
+    assert_near(transform_point(stack.current(), point), Point2::new(12.0, 2.0), "Both");
+
+    assert!(stack.pop());
+    assert_near(transform_point(stack.current(), point), Point2::new(11.0, 1.0), "Translation");
+    assert!(stack.pop());
+    assert_near(transform_point(stack.current(), point), point, "Identity");
+}
+
+#[test]
+fn transform_stack_reports_underflow() {
+    let mut stack = TransformStack::new();
+    assert!(!stack.pop());
+    assert_eq!(stack.depth(), 0);
+
+    stack.push(affine_translation(Vector2::new(1.0, 0.0)));
+    assert!(stack.pop());
+    assert!(!stack.pop());
+}
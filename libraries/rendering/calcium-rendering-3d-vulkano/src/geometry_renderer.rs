@@ -8,7 +8,7 @@ use vulkano::buffer::{CpuAccessibleBuffer, BufferUsage};
 use vulkano::pipeline::viewport::{Viewport as ViewportVk};
 
 use calcium_rendering::{Error, Viewport, Renderer};
-use calcium_rendering::texture::{Texture};
+use calcium_rendering::texture::{Texture, PixelFormat};
 use calcium_rendering_vulkano::{VulkanoRendererRaw};
 use calcium_rendering_vulkano_shaders::{gbuffer_vs};
 use calcium_rendering_3d::{Camera, RenderWorld, Entity, World3DRenderTarget};
@@ -31,15 +31,15 @@ impl GeometryRenderer {
 
         info!(renderer.log(), "Creating default material textures");
         let default_black = Texture::new()
-            .from_bytes(BLACK_TEXTURE_1PX, Vector2::new(1, 1), false)
+            .from_bytes(BLACK_TEXTURE_1PX, Vector2::new(1, 1), PixelFormat::R8)
             .as_single_channel()
             .build(renderer)?;
         let default_white = Texture::new()
-            .from_bytes(WHITE_TEXTURE_1PX, Vector2::new(1, 1), false)
+            .from_bytes(WHITE_TEXTURE_1PX, Vector2::new(1, 1), PixelFormat::R8)
             .as_single_channel()
             .build(renderer)?;
         let default_normal = Texture::new()
-            .from_bytes(NORMAL_TEXTURE_1PX, Vector2::new(1, 1), true)
+            .from_bytes(NORMAL_TEXTURE_1PX, Vector2::new(1, 1), PixelFormat::Rgba8)
             .as_linear()
             .build(renderer)?;
 
@@ -8,6 +8,8 @@ pub trait World3DRenderer<R: RendererRaw>: Any + Sized {
     type RenderTargetRaw: World3DRenderTargetRaw<R, Self> + Any;
     type MeshRaw: MeshRaw<R> + Any + Send + Sync;
 
+    // TODO: Count the time spent rendering into FrameStats::render_3d_time, this needs render to
+    //  take the Renderer instead of the raw renderer first.
     fn render(
         &mut self,
         world: &RenderWorld<R, Self>, camera: &Camera,
@@ -0,0 +1,121 @@
+use std::fmt::{self, Display, Formatter};
+
+/// Describes what a backend is able to do. Values that depend on the hardware are queried from the
+/// device the backend would render with. Where that isn't possible without a window, they're the
+/// minimum the backend's underlying API guarantees, and `Context::renderer_capabilities` gives
+/// the actual values once a renderer has been created.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Capabilities {
+    /// If world3d renderers can be created.
+    pub world3d: bool,
+
+    /// If simple2d renderers can be created.
+    pub simple2d: bool,
+
+    /// The largest width and height a texture is allowed to have.
+    pub max_texture_size: u32,
+
+    /// If textures can be built as render targets.
+    pub render_to_texture: bool,
+
+    /// If the LinearFloat and SingleChannelFloat texture store formats are supported.
+    pub float_textures: bool,
+
+    /// If the window's frames can be captured, rather than only render target textures.
+    pub window_capture: bool,
+
+    /// If additional windows sharing the same renderer can be created.
+    pub multi_window: bool,
+}
+
+impl Capabilities {
+    /// Capabilities of a backend that can't do anything, for example because it isn't available
+    /// on the current platform.
+    pub fn none() -> Self {
+        Capabilities {
+            world3d: false,
+            simple2d: false,
+            max_texture_size: 0,
+            render_to_texture: false,
+            float_textures: false,
+            window_capture: false,
+            multi_window: false,
+        }
+    }
+
+    /// Checks if a single capability is supported.
+    pub fn supports(&self, capability: Capability) -> bool {
+        match capability {
+            Capability::World3D => self.world3d,
+            Capability::Simple2D => self.simple2d,
+            Capability::MaxTextureSize(size) => self.max_texture_size >= size,
+            Capability::RenderToTexture => self.render_to_texture,
+            Capability::FloatTextures => self.float_textures,
+            Capability::WindowCapture => self.window_capture,
+            Capability::MultiWindow => self.multi_window,
+        }
+    }
+
+    /// Gets all capabilities in the list that aren't supported.
+    pub fn missing(&self, required: &[Capability]) -> Vec<Capability> {
+        required.iter().cloned().filter(|c| !self.supports(*c)).collect()
+    }
+}
+
+/// A single thing a runtime can require from a backend.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Capability {
+    World3D,
+    Simple2D,
+    /// Textures of at least this width and height can be created.
+    MaxTextureSize(u32),
+    RenderToTexture,
+    FloatTextures,
+    WindowCapture,
+    MultiWindow,
+}
+
+impl Display for Capability {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            Capability::World3D => write!(f, "world3d rendering"),
+            Capability::Simple2D => write!(f, "simple2d rendering"),
+            Capability::MaxTextureSize(size) => write!(f, "textures of {}x{}", size, size),
+            Capability::RenderToTexture => write!(f, "rendering to textures"),
+            Capability::FloatTextures => write!(f, "float textures"),
+            Capability::WindowCapture => write!(f, "capturing the window"),
+            Capability::MultiWindow => write!(f, "multiple windows"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Capabilities, Capability};
+
+    #[test]
+    fn none_is_missing_everything() {
+        let required = [Capability::Simple2D, Capability::MaxTextureSize(1)];
+        assert_eq!(Capabilities::none().missing(&required), required.to_vec());
+    }
+
+    #[test]
+    fn missing_only_lists_unsupported() {
+        let capabilities = Capabilities {
+            simple2d: true,
+            render_to_texture: true,
+            max_texture_size: 2048,
+            .. Capabilities::none()
+        };
+        let required = [
+            Capability::Simple2D, Capability::World3D, Capability::MaxTextureSize(2048),
+            Capability::MaxTextureSize(4096), Capability::RenderToTexture,
+        ];
+
+        assert_eq!(
+            capabilities.missing(&required),
+            vec!(Capability::World3D, Capability::MaxTextureSize(4096))
+        );
+        assert!(capabilities.missing(&[]).is_empty());
+    }
+}
@@ -3,7 +3,7 @@ use window::{Window, WindowSettings, AdvancedWindow};
 use input::{Input};
 
 use calcium_rendering::raw::{RendererRaw};
-use calcium_rendering::{Error, Renderer};
+use calcium_rendering::{Error, Renderer, WindowId, RendererSettings};
 
 #[cfg(feature = "2d")]
 use calcium_rendering_2d::raw::{Renderer2DRaw};
@@ -13,6 +13,8 @@ use calcium_rendering_2d::{Renderer2D};
 #[cfg(feature = "3d")]
 use calcium_rendering_3d::{World3DRenderer};
 
+use {Capabilities};
+
 pub trait Context {
     type RendererRaw: RendererRaw;
     type Window: Window + AdvancedWindow;
@@ -23,28 +25,62 @@ pub trait Context {
     #[cfg(feature = "3d")]
     type World3DRenderer: World3DRenderer<Self::RendererRaw>;
 
-    /// Creates a new renderer with an initial window.
+    /// Gets what this backend is able to do. If the backend isn't available on this system, for
+    /// example because there's no device it can render with, nothing is supported.
+    fn capabilities(&self) -> Capabilities;
+
+    /// Gets what this backend is able to do, with the limits of the device the renderer was
+    /// created with.
+    fn renderer_capabilities(&self, _renderer: &Renderer<Self::RendererRaw>) -> Capabilities {
+        self.capabilities()
+    }
+
+    /// Creates a new renderer with an initial window. The renderer settings can be changed later
+    /// using `Renderer::set_settings`.
     fn renderer(
         &self, log: Option<Logger>, window_settings: &WindowSettings,
+        renderer_settings: RendererSettings,
     ) ->  Result<(
         Renderer<Self::RendererRaw>,
         Self::Window,
     ), Error>;
 
-    /// Handles an event for a window, updating the renderers and window as needed. Using this the
-    /// backend can resize its swapchain buffers and make other relevant changes.
+    /// Creates an additional window that shares the renderer, and with it all textures, with the
+    /// initial window. Frames for it are started using `Renderer::start_window_frame`.
+    fn window(
+        &self, renderer: &mut Renderer<Self::RendererRaw>, window_settings: &WindowSettings,
+    ) -> Result<(WindowId, Self::Window), Error>;
+
+    /// Stops rendering to an additional window, this should be done before dropping it.
+    fn close_window(
+        &self,
+        renderer: &mut Renderer<Self::RendererRaw>,
+        window_id: WindowId,
+        window: Self::Window,
+    );
+
+    /// Handles an event for the initial window, updating the renderers and window as needed.
     fn handle_event(
         &self,
         event: &Input,
         renderer: &mut Renderer<Self::RendererRaw>,
         window: &mut Self::Window,
+    ) {
+        self.handle_window_event(event, renderer, WindowId::main(), window)
+    }
+
+    /// Handles an event for any window, updating the renderers and window as needed. Using this
+    /// the backend can resize its swapchain buffers and make other relevant changes.
+    fn handle_window_event(
+        &self,
+        event: &Input,
+        renderer: &mut Renderer<Self::RendererRaw>,
+        window_id: WindowId,
+        window: &mut Self::Window,
     );
 
-    /// Creates a world3d renderer.
-    ///
-    /// Only supported on the following backends:
-    /// - Vulkano
-    /// TODO: Add a system for requesting required features and reject backends that don't have it.
+    /// Creates a world3d renderer. Only supported if the backend's capabilities include world3d,
+    /// use `run_runtime_with` to only pick backends that do.
     /// TODO: Remove WindowRenderer from this initialization, World3DRenderer should create a new
     ///  thing specific to a single window.
     #[cfg(feature = "3d")]
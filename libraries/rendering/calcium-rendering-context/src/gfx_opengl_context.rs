@@ -4,12 +4,14 @@ use slog_stdlog::{StdLog};
 use glutin_window::{GlutinWindow};
 use input::{Input};
 use window::{WindowSettings};
-use gfx::{Encoder};
+use gfx::{Encoder, Device as GfxDevice};
 use gfx_window_glutin::{self};
 use gfx_device_gl::{Device, Factory};
 
 use calcium_rendering::raw::{RawAccess};
-use calcium_rendering::{Renderer, Error, CalciumErrorMappable};
+use calcium_rendering::{
+    Renderer, Error, CalciumErrorMappable, WindowId, RendererSettings, VsyncMode,
+};
 use calcium_rendering_gfx::{GfxRendererRaw, ColorFormat, DepthFormat};
 
 #[cfg(feature = "2d")]
@@ -20,7 +22,7 @@ use calcium_rendering_2d_gfx::{GfxRenderer2DRaw};
 #[cfg(feature = "3d")]
 use unsupported::{UnsupportedWorld3DRenderer};
 
-use {Context};
+use {Context, Capabilities};
 
 pub struct GfxOpenGlContext;
 
@@ -34,17 +36,43 @@ impl Context for GfxOpenGlContext {
     #[cfg(feature = "3d")]
     type World3DRenderer = UnsupportedWorld3DRenderer;
 
+    fn capabilities(&self) -> Capabilities {
+        // OpenGL can't be queried without creating a window with a context first, so this is the
+        //  minimum GL_MAX_TEXTURE_SIZE OpenGL 3 requires
+        capabilities_for(1024)
+    }
+
+    fn renderer_capabilities(
+        &self, renderer: &Renderer<GfxRendererRaw<Device, Factory>>,
+    ) -> Capabilities {
+        let max_texture_size = renderer.raw().device().get_capabilities().max_texture_size;
+        capabilities_for(max_texture_size as u32)
+    }
+
     fn renderer(
         &self, log: Option<Logger>, window_settings: &WindowSettings,
+        mut renderer_settings: RendererSettings,
     ) -> Result<
         (Renderer<GfxRendererRaw<Device, Factory>>, GlutinWindow),
         Error
     > {
         let log = log.unwrap_or(Logger::root(StdLog.fuse(), o!()));
 
+        // OpenGL only lets us pick between vsync on or off when creating the window
+        if renderer_settings.vsync == VsyncMode::Adaptive {
+            warn!(log, "Adaptive vsync not supported by gfx OpenGL backend, using vsync on");
+            renderer_settings.vsync = VsyncMode::On;
+        }
+        if renderer_settings.image_count.is_some() {
+            warn!(log, "Swapchain image count not supported by gfx OpenGL backend, ignoring");
+            renderer_settings.image_count = None;
+        }
+
         let size = window_settings.get_size();
         let size = Vector2::new(size.width, size.height);
-        let window: GlutinWindow = window_settings
+        let window: GlutinWindow = window_settings.clone()
+            .vsync(renderer_settings.vsync == VsyncMode::On)
+            .srgb(renderer_settings.srgb)
             .build()
             .map_platform_err()?;
 
@@ -52,15 +80,36 @@ impl Context for GfxOpenGlContext {
             gfx_window_glutin::init_existing::<ColorFormat, DepthFormat>(&window.window);
         let encoder: Encoder<_, _> = factory.create_command_buffer().into();
 
-        let renderer_raw = GfxRendererRaw::new(&log, device, factory, encoder, main_color, size);
+        let renderer_raw = GfxRendererRaw::new(
+            &log, device, factory, encoder, main_color, size, renderer_settings
+        );
 
         Ok((Renderer::raw_new(renderer_raw, log.clone()), window))
     }
 
-    fn handle_event(
+    fn window(
+        &self,
+        _renderer: &mut Renderer<GfxRendererRaw<Device, Factory>>,
+        _window_settings: &WindowSettings,
+    ) -> Result<(WindowId, GlutinWindow), Error> {
+        // Every OpenGL window has its own context, which the gfx renderer can't share textures
+        //  between
+        Err(Error::Unsupported("The gfx OpenGL backend only supports a single window".into()))
+    }
+
+    fn close_window(
+        &self,
+        _renderer: &mut Renderer<GfxRendererRaw<Device, Factory>>,
+        _window_id: WindowId,
+        _window: GlutinWindow,
+    ) {
+    }
+
+    fn handle_window_event(
         &self,
         event: &Input,
         renderer: &mut Renderer<GfxRendererRaw<Device, Factory>>,
+        _window_id: WindowId,
         window: &mut GlutinWindow,
     ) {
         match event {
@@ -97,3 +146,15 @@ impl Context for GfxOpenGlContext {
         Ok(Renderer2D::raw_new(renderer_raw))
     }
 }
+
+fn capabilities_for(max_texture_size: u32) -> Capabilities {
+    Capabilities {
+        world3d: false,
+        simple2d: cfg!(feature = "2d"),
+        max_texture_size,
+        render_to_texture: true,
+        float_textures: true,
+        window_capture: true,
+        multi_window: false,
+    }
+}
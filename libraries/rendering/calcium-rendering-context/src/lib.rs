@@ -26,6 +26,7 @@ extern crate calcium_rendering_2d_gfx;
 #[cfg(feature = "2d")]
 extern crate calcium_rendering_2d_vulkano;
 
+mod capabilities;
 mod context;
 mod runtime;
 mod unsupported;
@@ -33,12 +34,25 @@ mod unsupported;
 mod gfx_opengl_context;
 mod vulkano_context;
 
+pub use capabilities::{Capabilities, Capability};
 pub use context::{Context};
-pub use runtime::{run_runtime, Runtime};
+pub use runtime::{run_runtime, run_runtime_with, Runtime};
 
-#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Backend {
     Vulkano,
     GfxOpenGl,
     GfxDirectX,
 }
+
+impl Backend {
+    /// Gets what this backend is able to do, without having to create a renderer for it.
+    pub fn capabilities(&self) -> Capabilities {
+        match *self {
+            Backend::Vulkano => ::vulkano_context::VulkanoContext.capabilities(),
+            Backend::GfxOpenGl => ::gfx_opengl_context::GfxOpenGlContext.capabilities(),
+            // Not implemented yet, so it can't do anything
+            Backend::GfxDirectX => Capabilities::none(),
+        }
+    }
+}
@@ -1,6 +1,16 @@
-use calcium_rendering::{Error};
+use std::cell::{Cell};
+use std::rc::{Rc};
 
-use {Backend, Context};
+use slog::{Logger};
+use window::{WindowSettings};
+use input::{Input};
+
+use calcium_rendering::{Error, Renderer, WindowId, RendererSettings};
+
+#[cfg(feature = "2d")]
+use calcium_rendering_2d::{Renderer2D};
+
+use {Backend, Context, Capability, Capabilities};
 
 pub fn run_runtime<R: Runtime>(backend: Backend, runtime: R) -> Result<(), Error> {
     match backend {
@@ -8,10 +18,208 @@ pub fn run_runtime<R: Runtime>(backend: Backend, runtime: R) -> Result<(), Error
             runtime.run(::vulkano_context::VulkanoContext),
         Backend::GfxOpenGl =>
             runtime.run(::gfx_opengl_context::GfxOpenGlContext),
-        Backend::GfxDirectX => unimplemented!(),
+        Backend::GfxDirectX =>
+            Err(Error::Unsupported("The GfxDirectX backend is not implemented".into())),
+    }
+}
+
+/// Runs the runtime with the first backend in the preferences that has all the required
+/// capabilities. If creating the renderer fails, the next backend is tried with a clone of the
+/// runtime. Errors returned after the renderer has been created are returned as-is. If none of the
+/// backends can be used, an error is returned listing why for each backend.
+pub fn run_runtime_with<R: Runtime + Clone>(
+    required: &[Capability], preferences: &[Backend], runtime: R,
+) -> Result<(), Error> {
+    let (candidates, mut rejections) =
+        select_backends(required, preferences, |backend| backend.capabilities());
+
+    for backend in candidates {
+        let initialized = Rc::new(Cell::new(false));
+        let result = run_runtime_tracked(backend, runtime.clone(), initialized.clone());
+
+        match result {
+            Err(error) if !initialized.get() =>
+                rejections.push(format!("{:?} failed to initialize: {}", backend, error)),
+            result => return result,
+        }
+    }
+
+    if rejections.is_empty() {
+        return Err(Error::Unsupported("No backends to pick from were given".into()))
+    }
+
+    Err(Error::Unsupported(format!(
+        "None of the preferred backends are supported: {}", rejections.join("; ")
+    )))
+}
+
+/// Splits the preferred backends into the ones that have all required capabilities, in order of
+/// preference, and descriptions of why the others were rejected.
+fn select_backends<F: Fn(Backend) -> Capabilities>(
+    required: &[Capability], preferences: &[Backend], capabilities: F,
+) -> (Vec<Backend>, Vec<String>) {
+    let mut candidates = Vec::new();
+    let mut rejections = Vec::new();
+
+    for backend in preferences {
+        let missing = capabilities(*backend).missing(required);
+        if missing.is_empty() {
+            candidates.push(*backend);
+            continue
+        }
+
+        let missing: Vec<String> = missing.iter().map(|c| c.to_string()).collect();
+        rejections.push(format!("{:?} does not support {}", backend, missing.join(", ")));
+    }
+
+    (candidates, rejections)
+}
+
+fn run_runtime_tracked<R: Runtime>(
+    backend: Backend, runtime: R, initialized: Rc<Cell<bool>>,
+) -> Result<(), Error> {
+    match backend {
+        Backend::Vulkano => runtime.run(InitTracking {
+            context: ::vulkano_context::VulkanoContext, initialized,
+        }),
+        Backend::GfxOpenGl => runtime.run(InitTracking {
+            context: ::gfx_opengl_context::GfxOpenGlContext, initialized,
+        }),
+        Backend::GfxDirectX =>
+            Err(Error::Unsupported("The GfxDirectX backend is not implemented".into())),
     }
 }
 
 pub trait Runtime {
     fn run<C: Context>(self, context: C) -> Result<(), Error>;
 }
+
+/// Passes everything through to the wrapped context, but keeps track of if the renderer could be
+/// created, so `run_runtime_with` knows if it can try another backend.
+struct InitTracking<C: Context> {
+    context: C,
+    initialized: Rc<Cell<bool>>,
+}
+
+impl<C: Context> Context for InitTracking<C> {
+    type RendererRaw = C::RendererRaw;
+    type Window = C::Window;
+
+    #[cfg(feature = "2d")]
+    type Renderer2DRaw = C::Renderer2DRaw;
+
+    #[cfg(feature = "3d")]
+    type World3DRenderer = C::World3DRenderer;
+
+    fn capabilities(&self) -> Capabilities {
+        self.context.capabilities()
+    }
+
+    fn renderer_capabilities(&self, renderer: &Renderer<Self::RendererRaw>) -> Capabilities {
+        self.context.renderer_capabilities(renderer)
+    }
+
+    fn renderer(
+        &self, log: Option<Logger>, window_settings: &WindowSettings,
+        renderer_settings: RendererSettings,
+    ) -> Result<(Renderer<Self::RendererRaw>, Self::Window), Error> {
+        let result = self.context.renderer(log, window_settings, renderer_settings);
+        if result.is_ok() {
+            self.initialized.set(true);
+        }
+        result
+    }
+
+    fn window(
+        &self, renderer: &mut Renderer<Self::RendererRaw>, window_settings: &WindowSettings,
+    ) -> Result<(WindowId, Self::Window), Error> {
+        self.context.window(renderer, window_settings)
+    }
+
+    fn close_window(
+        &self,
+        renderer: &mut Renderer<Self::RendererRaw>,
+        window_id: WindowId,
+        window: Self::Window,
+    ) {
+        self.context.close_window(renderer, window_id, window)
+    }
+
+    fn handle_window_event(
+        &self,
+        event: &Input,
+        renderer: &mut Renderer<Self::RendererRaw>,
+        window_id: WindowId,
+        window: &mut Self::Window,
+    ) {
+        self.context.handle_window_event(event, renderer, window_id, window)
+    }
+
+    #[cfg(feature = "3d")]
+    fn world3d_renderer(
+        &self,
+        renderer: &mut Renderer<Self::RendererRaw>,
+    ) -> Result<Self::World3DRenderer, Error> {
+        self.context.world3d_renderer(renderer)
+    }
+
+    #[cfg(feature = "2d")]
+    fn simple2d_renderer(
+        &self,
+        renderer: &mut Renderer<Self::RendererRaw>,
+    ) -> Result<Renderer2D<Self::RendererRaw, Self::Renderer2DRaw>, Error> {
+        self.context.simple2d_renderer(renderer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {Backend, Capabilities, Capability};
+    use super::{select_backends};
+
+    fn capabilities(backend: Backend) -> Capabilities {
+        match backend {
+            Backend::Vulkano => Capabilities {
+                world3d: true,
+                simple2d: true,
+                max_texture_size: 4096,
+                .. Capabilities::none()
+            },
+            Backend::GfxOpenGl => Capabilities {
+                simple2d: true,
+                max_texture_size: 1024,
+                .. Capabilities::none()
+            },
+            Backend::GfxDirectX => Capabilities::none(),
+        }
+    }
+
+    #[test]
+    fn keeps_order_of_preference() {
+        let preferences = [Backend::GfxOpenGl, Backend::GfxDirectX, Backend::Vulkano];
+        let (candidates, rejections) =
+            select_backends(&[Capability::Simple2D], &preferences, capabilities);
+
+        assert_eq!(candidates, vec!(Backend::GfxOpenGl, Backend::Vulkano));
+        assert_eq!(rejections.len(), 1);
+        assert!(rejections[0].contains("GfxDirectX"));
+    }
+
+    #[test]
+    fn rejects_backends_missing_capabilities() {
+        let preferences = [Backend::GfxOpenGl, Backend::Vulkano];
+        let required = [Capability::Simple2D, Capability::MaxTextureSize(2048)];
+        let (candidates, rejections) = select_backends(&required, &preferences, capabilities);
+
+        assert_eq!(candidates, vec!(Backend::Vulkano));
+        assert_eq!(rejections, vec!("GfxOpenGl does not support textures of 2048x2048"));
+    }
+
+    #[test]
+    fn no_candidates_without_preferences() {
+        let (candidates, rejections) = select_backends(&[], &[], capabilities);
+
+        assert!(candidates.is_empty());
+        assert!(rejections.is_empty());
+    }
+}
@@ -4,13 +4,13 @@ use slog_stdlog::{StdLog};
 use window::{WindowSettings};
 use input::{Input};
 use winit_window::{self, WinitWindow};
-use vulkano::instance::{Instance};
+use vulkano::instance::{Instance, InstanceExtensions, PhysicalDevice};
 
 use calcium_rendering::raw::{RawAccess};
-use calcium_rendering::{Renderer, Error, CalciumErrorMappable};
+use calcium_rendering::{Renderer, Error, CalciumErrorMappable, WindowId, RendererSettings};
 use calcium_rendering_vulkano::{VulkanoRendererRaw};
 
-use {Context};
+use {Context, Capabilities};
 
 #[cfg(feature = "2d")]
 use calcium_rendering_2d::{Renderer2D};
@@ -32,8 +32,28 @@ impl Context for VulkanoContext {
     #[cfg(feature = "2d")]
     type Renderer2DRaw = VulkanoRenderer2DRaw;
 
+    fn capabilities(&self) -> Capabilities {
+        // We don't need a window to find out what the device can do, only an instance
+        let instance = match Instance::new(None, &InstanceExtensions::none(), None) {
+            Ok(instance) => instance,
+            Err(_) => return Capabilities::none(),
+        };
+
+        // The renderer uses the first device, so that's the one that matters
+        match PhysicalDevice::enumerate(&instance).next() {
+            Some(physical) => capabilities_for(physical.limits().max_image_dimension_2d()),
+            None => Capabilities::none(),
+        }
+    }
+
+    fn renderer_capabilities(&self, renderer: &Renderer<VulkanoRendererRaw>) -> Capabilities {
+        let physical = renderer.raw().device().physical_device();
+        capabilities_for(physical.limits().max_image_dimension_2d())
+    }
+
     fn renderer(
         &self, log: Option<Logger>, window_settings: &WindowSettings,
+        renderer_settings: RendererSettings,
     ) -> Result<(Renderer<VulkanoRendererRaw>, WinitWindow), Error> {
         let log = log.unwrap_or(Logger::root(StdLog.fuse(), o!()));
 
@@ -55,21 +75,46 @@ impl Context for VulkanoContext {
         // Set up the renderer itself
         let renderer = VulkanoRendererRaw::new(
             &log, instance,
-            window.surface.clone(), Vector2::new(size.width, size.height)
+            window.surface.clone(), Vector2::new(size.width, size.height), renderer_settings,
         )?;
 
         Ok((Renderer::raw_new(renderer, log.clone()), window))
     }
 
-    fn handle_event(
+    fn window(
+        &self, renderer: &mut Renderer<VulkanoRendererRaw>, window_settings: &WindowSettings,
+    ) -> Result<(WindowId, WinitWindow), Error> {
+        // The window has to be created on the same instance the renderer's device belongs to
+        let window = WinitWindow::new_vulkano(
+            renderer.raw().instance().clone(), window_settings,
+        );
+        let size = window_settings.get_size();
+
+        let log = renderer.log().clone();
+        let window_id = renderer.raw_mut().add_window(
+            &log, window.surface.clone(), Vector2::new(size.width, size.height)
+        );
+
+        Ok((window_id, window))
+    }
+
+    fn close_window(
+        &self, renderer: &mut Renderer<VulkanoRendererRaw>,
+        window_id: WindowId, _window: WinitWindow,
+    ) {
+        renderer.raw_mut().remove_window(window_id);
+    }
+
+    fn handle_window_event(
         &self,
         event: &Input,
         renderer: &mut Renderer<VulkanoRendererRaw>,
+        window_id: WindowId,
         _window: &mut WinitWindow,
     ) {
         match event {
             &Input::Resize(w, h) =>
-                renderer.raw_mut().queue_resize(Vector2::new(w, h)),
+                renderer.raw_mut().queue_resize(window_id, Vector2::new(w, h)),
             _ => {}
         }
     }
@@ -91,3 +136,15 @@ impl Context for VulkanoContext {
         Ok(Renderer2D::raw_new(renderer_raw))
     }
 }
+
+fn capabilities_for(max_texture_size: u32) -> Capabilities {
+    Capabilities {
+        world3d: cfg!(feature = "3d"),
+        simple2d: cfg!(feature = "2d"),
+        max_texture_size,
+        render_to_texture: true,
+        float_textures: true,
+        window_capture: true,
+        multi_window: true,
+    }
+}
@@ -9,7 +9,7 @@ mod renderer;
 mod texture;
 
 pub use renderer::{GfxRendererRaw, GfxFrameRaw};
-pub use texture::{GfxTextureRaw, GenericView};
+pub use texture::{GfxTextureRaw, GenericView, DynamicTexture};
 
 pub type ColorFormat = ::gfx::format::Rgba8;
 pub type DepthFormat = ::gfx::format::DepthStencil;
@@ -1,10 +1,14 @@
 use slog::{Logger};
 use cgmath::{Vector2};
 use gfx::{Device, Factory, Encoder};
+use gfx::format::{ChannelType};
 use gfx::handle::{RenderTargetView};
+use gfx::memory::{Typed};
+use image::{RgbaImage};
 
 use calcium_rendering::raw::{RendererRaw};
-use calcium_rendering::{Frame};
+use calcium_rendering::texture::{PixelFormat};
+use calcium_rendering::{self, Error, Frame, WindowId, RendererSettings, CalciumErrorMappable};
 
 use {ColorFormat, GfxTextureRaw};
 
@@ -15,6 +19,7 @@ pub struct GfxRendererRaw<D: Device, F: Factory<D::Resources>> {
 
     color_view: RenderTargetView<D::Resources, ColorFormat>,
     size: Vector2<u32>,
+    settings: RendererSettings,
 }
 
 impl<D: Device, F: Factory<D::Resources>> GfxRendererRaw<D, F> {
@@ -23,7 +28,7 @@ impl<D: Device, F: Factory<D::Resources>> GfxRendererRaw<D, F> {
         device: D, factory: F, encoder: Encoder<D::Resources, D::CommandBuffer>,
 
         color_view: RenderTargetView<D::Resources, ColorFormat>,
-        size: Vector2<u32>,
+        size: Vector2<u32>, settings: RendererSettings,
     ) -> Self {
         info!(log, "Creating gfx renderer");
 
@@ -34,6 +39,7 @@ impl<D: Device, F: Factory<D::Resources>> GfxRendererRaw<D, F> {
 
             color_view,
             size,
+            settings,
         }
     }
 
@@ -69,6 +75,11 @@ impl<D: Device, F: Factory<D::Resources>> GfxRendererRaw<D, F> {
         self.color_view = color_view
     }
 
+    /// Submits all commands recorded so far to the device.
+    pub fn flush(&mut self) {
+        self.encoder.flush(&mut self.device);
+    }
+
     pub fn report_resize(&mut self, size: Vector2<u32>) {
         self.size = size;
     }
@@ -82,17 +93,68 @@ impl<D: Device + 'static, F: Factory<D::Resources> + 'static> RendererRaw for Gf
         self.size
     }
 
+    fn window_size(&self, window: WindowId) -> Option<Vector2<u32>> {
+        if window == WindowId::main() { Some(self.size) } else { None }
+    }
+
+    fn settings(&self) -> &RendererSettings {
+        &self.settings
+    }
+
+    fn set_settings(&mut self, settings: RendererSettings, log: &Logger) {
+        // The OpenGL context is created together with the window, which decides its vsync and
+        //  framebuffer format, so we can't change those afterwards
+        if settings != self.settings {
+            warn!(log, "The gfx backend can't change renderer settings after creation, ignoring");
+        }
+    }
+
     fn start_frame(&mut self) -> Frame<Self> {
         self.device.cleanup();
 
         Frame::raw_new(GfxFrameRaw {
             size: self.size
-        })
+        }, WindowId::main())
+    }
+
+    fn start_window_frame(&mut self, window: WindowId) -> Result<Frame<Self>, Error> {
+        if window != WindowId::main() {
+            return Err(Error::Unsupported("The gfx backend only supports a single window".into()))
+        }
+
+        Ok(self.start_frame())
     }
 
     fn finish_frame(&mut self, _frame: Frame<Self>) {
         self.encoder.flush(&mut self.device);
     }
+
+    fn capture_frame(&mut self, _frame: &mut Frame<Self>) -> Result<RgbaImage, Error> {
+        // The window's color target is the back buffer, so this has to happen before the frame is
+        //  finished and the buffers are swapped
+        let texture = self.color_view.raw().get_texture().clone();
+        let info = texture.get_info().to_raw_image_info(ChannelType::Unorm, 0);
+        let size = Vector2::new(info.width as u32, info.height as u32);
+
+        let download = self.factory
+            .create_download_buffer::<[u8; 4]>(size.x as usize * size.y as usize)
+            .map_platform_err()?;
+        self.encoder.copy_texture_to_buffer_raw(&texture, None, info, download.raw(), 0)
+            .map_err(|e| Error::Platform(format!("{:?}", e)))?;
+        self.flush();
+
+        let bytes: Vec<u8> = {
+            let reader = self.factory.read_mapping(&download)
+                .map_err(|e| Error::Platform(format!("{:?}", e)))?;
+
+            // OpenGL gives us the rows bottom to top
+            reader.chunks(size.x as usize).rev()
+                .flat_map(|row| row.iter().flat_map(|p| p.iter().cloned()))
+                .collect()
+        };
+
+        calcium_rendering::image_from_pixels(&bytes, size, PixelFormat::Rgba8)
+    }
 }
 
 pub struct GfxFrameRaw {
@@ -1,132 +1,289 @@
-use std::path::{PathBuf};
-
 use cgmath::{Vector2};
-use image::{self};
-use gfx::{Device, Factory};
-use gfx::texture::{Kind, Size, AaMode};
-use gfx::format::{Rgba8, Srgba8, R8, Unorm};
-use gfx::handle::{ShaderResourceView, RawShaderResourceView};
-use gfx::memory::{Typed};
-
-use calcium_rendering::{Error, CalciumErrorMappable, Renderer};
+use gfx::{Device, Factory, Resources};
+use gfx::texture::{
+    Kind, Size, AaMode, NewImageInfo, SamplerInfo, FilterMethod, WrapMode as GfxWrapMode,
+};
+use gfx::format::{
+    Rgba8, Srgba8, R8, R8_G8, R16, R32, R8_G8_B8_A8, R16_G16_B16_A16, Unorm, Float, Swizzle,
+    TextureFormat, TextureSurface, TextureChannel, ChannelTyped, ChannelType,
+};
+use gfx::handle::{ShaderResourceView, RawShaderResourceView, RenderTargetView, Texture, Sampler};
+use gfx::memory::{self, Typed, Usage};
+use image::{RgbaImage};
+
+use calcium_rendering::{self, Error, CalciumErrorMappable, Renderer};
 use calcium_rendering::raw::{TextureRaw, RawAccess};
-use calcium_rendering::texture::{TextureBuilder, TextureSource, TextureStoreFormat, SampleMode};
+use calcium_rendering::texture::{
+    self, TextureBuilder, TextureStoreFormat, SampleMode, PixelFormat, WrapMode, MipLevel,
+};
+
+use {GfxRendererRaw, ColorFormat};
 
-use {GfxRendererRaw};
+// Additional shorthand types gfx doesn't have by itself
+type R8U = (R8, Unorm);
+type Rg8U = (R8_G8, Unorm);
+type R16U = (R16, Unorm);
+type Rgba16F = (R16_G16_B16_A16, Float);
+type R32F = (R32, Float);
 
+/// A shader resource view of any of the store formats, grouped by what the shader sees.
 pub enum GenericView<D: Device + 'static> {
-    Rgba8(ShaderResourceView<D::Resources, [f32; 4]>),
-    R8(ShaderResourceView<D::Resources, f32>),
+    Rgba(ShaderResourceView<D::Resources, [f32; 4]>),
+    Rg(ShaderResourceView<D::Resources, [f32; 2]>),
+    R(ShaderResourceView<D::Resources, f32>),
 }
 
 impl<D: Device + 'static> GenericView<D> {
     pub fn raw(&self) -> &RawShaderResourceView<D::Resources> {
         match *self {
-            GenericView::Rgba8(ref view) => view.raw(),
-            GenericView::R8(ref view) => view.raw(),
+            GenericView::Rgba(ref view) => view.raw(),
+            GenericView::Rg(ref view) => view.raw(),
+            GenericView::R(ref view) => view.raw(),
         }
     }
 }
 
+/// The underlying texture of a dynamic texture, kept around so it can be updated.
+pub enum DynamicTexture<D: Device + 'static> {
+    Srgba8(Texture<D::Resources, R8_G8_B8_A8>),
+    Rgba8(Texture<D::Resources, R8_G8_B8_A8>),
+    R8(Texture<D::Resources, R8>),
+    Rg8(Texture<D::Resources, R8_G8>),
+    R16(Texture<D::Resources, R16>),
+    Rgba16F(Texture<D::Resources, R16_G16_B16_A16>),
+    R32F(Texture<D::Resources, R32>),
+}
+
 pub struct GfxTextureRaw<D: Device + 'static> {
     pub view: GenericView<D>,
+    /// Set if this texture was built as a render target.
+    pub render_target_view: Option<RenderTargetView<D::Resources, ColorFormat>>,
+    /// Set if this texture was built as a render target, kept around so it can be read back.
+    pub render_target_texture: Option<Texture<D::Resources, R8_G8_B8_A8>>,
+    /// Set if this texture was built as dynamic.
+    pub dynamic_texture: Option<DynamicTexture<D>>,
     pub sample_mode: SampleMode,
+    pub sampler: Sampler<D::Resources>,
+    store_format: TextureStoreFormat,
     size: Vector2<u32>,
 }
 
 impl<D: Device + 'static> GfxTextureRaw<D> {
-    fn from_path<F: Factory<D::Resources> + 'static>(
-        path: &PathBuf, builder: &TextureBuilder<GfxRendererRaw<D, F>>,
-        renderer: &mut Renderer<GfxRendererRaw<D, F>>
-    ) -> Result<Self, Error> {
-        info!(renderer.log(),
-            "Loading texture from file"; "path" => path.display().to_string()
-        );
-
-        // Load in the image file TODO: Find ways to avoid this to_rgba conversion
-        let img = image::open(path).unwrap().to_rgba();
-        let (width, height) = img.dimensions();
-
-        Self::from_bytes(&img, Vector2::new(width, height), true, builder, renderer)
-    }
-
-    fn from_bytes<F: Factory<D::Resources> + 'static>(
-        bytes: &[u8], size: Vector2<u32>, color: bool,
+    fn from_data<F: Factory<D::Resources> + 'static>(
+        data: &[u8], size: Vector2<u32>,
         builder: &TextureBuilder<GfxRendererRaw<D, F>>,
         renderer: &mut Renderer<GfxRendererRaw<D, F>>,
     ) -> Result<Self, Error> {
-        info!(renderer.log(),
-            "Loading texture from bytes"; "width" => size.x, "height" => size.y, "color" => color
-        );
+        if builder.dynamic {
+            return Self::new_dynamic(data, size, builder, renderer)
+        }
 
-        // Convert from the format we have to the format we want
-        // TODO: Avoid this step using the following advice:
-        //  "create an Upload type buffer, map it, fill it up, then issue copy_buffer_to_texture"
-        let pixels = size.x as usize * size.y as usize;
-        let data = if builder.store_format != TextureStoreFormat::SingleChannel {
-            let mut data = vec![4; pixels * 4];
-
-            if !color {
-                // Multi-Channel store, Single-Channel source
-                for i in 0..pixels {
-                    unsafe {
-                        *data.get_unchecked_mut(i*4 + 0) = bytes[i];
-                        *data.get_unchecked_mut(i*4 + 1) = 0;
-                        *data.get_unchecked_mut(i*4 + 2) = 0;
-                        *data.get_unchecked_mut(i*4 + 3) = 1;
-                    }
-                }
-            } else {
-                // Multi-Channel store, Multi-Channel source
-                for i in 0..pixels {
-                    unsafe {
-                        *data.get_unchecked_mut(i*4 + 0) = bytes[i*4 + 0];
-                        *data.get_unchecked_mut(i*4 + 1) = bytes[i*4 + 1];
-                        *data.get_unchecked_mut(i*4 + 2) = bytes[i*4 + 2];
-                        *data.get_unchecked_mut(i*4 + 3) = bytes[i*4 + 3];
-                    }
-                }
-            }
-            data
+        // Mipmaps are generated on the CPU, gfx takes all levels at once
+        let levels = if builder.generate_mipmaps {
+            texture::generate_mipmaps(data, size, builder.store_format)
         } else {
-            let mut data = vec![4; pixels];
-            if color { panic!("Currently unsupported conversion from color to greyscale in bytes"); }
-
-            for i in 0..pixels {
-                unsafe {
-                    *data.get_unchecked_mut(i) = bytes[i];
-                }
-            }
-
-            data
+            vec!(MipLevel { bytes: data.to_vec(), size })
         };
+        let data: Vec<&[u8]> = levels.iter().map(|l| l.bytes.as_slice()).collect();
 
-        // Additional shorthand types gfx doesn't have by itself
-        type R8U = (R8, Unorm);
-
-        // Actually create the gfx texture
+        // Actually create the gfx texture, the data is already in the store format's layout
         let kind = Kind::D2(size.x as Size, size.y as Size, AaMode::Single);
+        let factory = renderer.raw_mut().factory_mut();
         let view = match builder.store_format {
-            TextureStoreFormat::Srgb => GenericView::Rgba8(
-                renderer.raw_mut().factory_mut().create_texture_immutable_u8::<Srgba8>(kind, &[&data])
+            TextureStoreFormat::Srgb => GenericView::Rgba(
+                factory.create_texture_immutable_u8::<Srgba8>(kind, &data)
+                    .map_platform_err()?.1
+                ),
+            TextureStoreFormat::Linear => GenericView::Rgba(
+                factory.create_texture_immutable_u8::<Rgba8>(kind, &data)
+                    .map_platform_err()?.1
+                ),
+            TextureStoreFormat::SingleChannel => GenericView::R(
+                factory.create_texture_immutable_u8::<R8U>(kind, &data)
                     .map_platform_err()?.1
                 ),
-            TextureStoreFormat::Linear => GenericView::Rgba8(
-                renderer.raw_mut().factory_mut().create_texture_immutable_u8::<Rgba8>(kind, &[&data])
+            TextureStoreFormat::TwoChannel => GenericView::Rg(
+                factory.create_texture_immutable_u8::<Rg8U>(kind, &data)
                     .map_platform_err()?.1
                 ),
-            TextureStoreFormat::SingleChannel => GenericView::R8(
-                renderer.raw_mut().factory_mut().create_texture_immutable_u8::<R8U>(kind, &[&data])
+            TextureStoreFormat::SingleChannel16 => GenericView::R(
+                factory.create_texture_immutable_u8::<R16U>(kind, &data)
+                    .map_platform_err()?.1
+                ),
+            TextureStoreFormat::LinearFloat => GenericView::Rgba(
+                factory.create_texture_immutable_u8::<Rgba16F>(kind, &data)
+                    .map_platform_err()?.1
+                ),
+            TextureStoreFormat::SingleChannelFloat => GenericView::R(
+                factory.create_texture_immutable_u8::<R32F>(kind, &data)
                     .map_platform_err()?.1
                 ),
         };
 
         Ok(GfxTextureRaw {
             view,
+            render_target_view: None,
+            render_target_texture: None,
+            dynamic_texture: None,
+            sample_mode: builder.sample_mode,
+            sampler: create_sampler(builder, renderer),
+            store_format: builder.store_format,
+            size,
+        })
+    }
+
+    fn new_render_target<F: Factory<D::Resources> + 'static>(
+        size: Vector2<u32>,
+        builder: &TextureBuilder<GfxRendererRaw<D, F>>,
+        renderer: &mut Renderer<GfxRendererRaw<D, F>>,
+    ) -> Result<Self, Error> {
+        info!(renderer.log(),
+            "Creating render target texture"; "width" => size.x, "height" => size.y
+        );
+
+        // Render targets have to match the format the pipelines render to, so the store format is
+        //  ignored here. We create the texture ourselves rather than through create_render_target,
+        //  so it can also be copied from for capturing.
+        let (texture, view, render_target_view) = {
+            let factory = renderer.raw_mut().factory_mut();
+            let kind = Kind::D2(size.x as Size, size.y as Size, AaMode::Single);
+            let bind = memory::RENDER_TARGET | memory::SHADER_RESOURCE | memory::TRANSFER_SRC;
+            let texture = factory.create_texture::<R8_G8_B8_A8>(
+                kind, 1, bind, Usage::Data, Some(ChannelType::Srgb)
+            ).map_platform_err()?;
+            let view = factory.view_texture_as_shader_resource::<ColorFormat>(
+                &texture, (0, 0), Swizzle::new()
+            ).map_platform_err()?;
+            let render_target_view = factory.view_texture_as_render_target::<ColorFormat>(
+                &texture, 0, None
+            ).map_platform_err()?;
+            (texture, view, render_target_view)
+        };
+
+        // The contents of a new render target are undefined, start out transparent instead
+        renderer.raw_mut().encoder_mut().clear(&render_target_view, [0.0, 0.0, 0.0, 0.0]);
+
+        Ok(GfxTextureRaw {
+            view: GenericView::Rgba(view),
+            render_target_view: Some(render_target_view),
+            render_target_texture: Some(texture),
+            dynamic_texture: None,
             sample_mode: builder.sample_mode,
+            sampler: create_sampler(builder, renderer),
+            store_format: builder.store_format,
             size,
         })
     }
+
+    fn new_dynamic<F: Factory<D::Resources> + 'static>(
+        data: &[u8], size: Vector2<u32>,
+        builder: &TextureBuilder<GfxRendererRaw<D, F>>,
+        renderer: &mut Renderer<GfxRendererRaw<D, F>>,
+    ) -> Result<Self, Error> {
+        if builder.generate_mipmaps {
+            warn!(renderer.log(), "Mipmaps are not generated for dynamic textures");
+        }
+
+        // Create the texture without any data, we upload the initial data the same way as any
+        //  later updates
+        let kind = Kind::D2(size.x as Size, size.y as Size, AaMode::Single);
+        let (view, dynamic_texture) = {
+            let factory = renderer.raw_mut().factory_mut();
+            match builder.store_format {
+                TextureStoreFormat::Srgb => {
+                    let (texture, view) = create_dynamic::<_, _, Srgba8>(factory, kind)?;
+                    (GenericView::Rgba(view), DynamicTexture::Srgba8(texture))
+                },
+                TextureStoreFormat::Linear => {
+                    let (texture, view) = create_dynamic::<_, _, Rgba8>(factory, kind)?;
+                    (GenericView::Rgba(view), DynamicTexture::Rgba8(texture))
+                },
+                TextureStoreFormat::SingleChannel => {
+                    let (texture, view) = create_dynamic::<_, _, R8U>(factory, kind)?;
+                    (GenericView::R(view), DynamicTexture::R8(texture))
+                },
+                TextureStoreFormat::TwoChannel => {
+                    let (texture, view) = create_dynamic::<_, _, Rg8U>(factory, kind)?;
+                    (GenericView::Rg(view), DynamicTexture::Rg8(texture))
+                },
+                TextureStoreFormat::SingleChannel16 => {
+                    let (texture, view) = create_dynamic::<_, _, R16U>(factory, kind)?;
+                    (GenericView::R(view), DynamicTexture::R16(texture))
+                },
+                TextureStoreFormat::LinearFloat => {
+                    let (texture, view) = create_dynamic::<_, _, Rgba16F>(factory, kind)?;
+                    (GenericView::Rgba(view), DynamicTexture::Rgba16F(texture))
+                },
+                TextureStoreFormat::SingleChannelFloat => {
+                    let (texture, view) = create_dynamic::<_, _, R32F>(factory, kind)?;
+                    (GenericView::R(view), DynamicTexture::R32F(texture))
+                },
+            }
+        };
+
+        let texture = GfxTextureRaw {
+            view,
+            render_target_view: None,
+            render_target_texture: None,
+            dynamic_texture: Some(dynamic_texture),
+            sample_mode: builder.sample_mode,
+            sampler: create_sampler(builder, renderer),
+            store_format: builder.store_format,
+            size,
+        };
+        texture.upload(Vector2::new(0, 0), size, data, renderer)?;
+
+        Ok(texture)
+    }
+
+    /// Uploads already converted data into a region of a dynamic texture.
+    fn upload<F: Factory<D::Resources> + 'static>(
+        &self, offset: Vector2<u32>, size: Vector2<u32>, data: &[u8],
+        renderer: &mut Renderer<GfxRendererRaw<D, F>>,
+    ) -> Result<(), Error> {
+        let info = NewImageInfo {
+            xoffset: offset.x as Size,
+            yoffset: offset.y as Size,
+            zoffset: 0,
+            width: size.x as Size,
+            height: size.y as Size,
+            depth: 1,
+            format: (),
+            mipmap: 0,
+        };
+
+        let encoder = renderer.raw_mut().encoder_mut();
+        let result = match *self.dynamic_texture.as_ref().unwrap() {
+            DynamicTexture::Srgba8(ref texture) =>
+                encoder.update_texture::<R8_G8_B8_A8, Srgba8>(
+                    texture, None, info, memory::cast_slice(data)
+                ),
+            DynamicTexture::Rgba8(ref texture) =>
+                encoder.update_texture::<R8_G8_B8_A8, Rgba8>(
+                    texture, None, info, memory::cast_slice(data)
+                ),
+            DynamicTexture::R8(ref texture) =>
+                encoder.update_texture::<R8, R8U>(texture, None, info, data),
+            DynamicTexture::Rg8(ref texture) =>
+                encoder.update_texture::<R8_G8, Rg8U>(
+                    texture, None, info, memory::cast_slice(data)
+                ),
+            DynamicTexture::R16(ref texture) =>
+                encoder.update_texture::<R16, R16U>(
+                    texture, None, info, memory::cast_slice(data)
+                ),
+            DynamicTexture::Rgba16F(ref texture) =>
+                encoder.update_texture::<R16_G16_B16_A16, Rgba16F>(
+                    texture, None, info, memory::cast_slice(data)
+                ),
+            DynamicTexture::R32F(ref texture) =>
+                encoder.update_texture::<R32, R32F>(
+                    texture, None, info, memory::cast_slice(data)
+                ),
+        };
+
+        result.map_err(|e| Error::Platform(format!("{:?}", e)))
+    }
 }
 
 impl<D: Device + 'static, F: Factory<D::Resources> + 'static>
@@ -136,16 +293,107 @@ impl<D: Device + 'static, F: Factory<D::Resources> + 'static>
         builder: TextureBuilder<GfxRendererRaw<D, F>>,
         renderer: &mut Renderer<GfxRendererRaw<D, F>>,
     ) -> Result<Self, Error> {
-        match builder.source {
-            TextureSource::File(ref path) =>
-                Self::from_path(path, &builder, renderer),
-            TextureSource::Bytes { ref bytes, size, color } => {
-                Self::from_bytes(bytes.as_ref(), size, color, &builder, renderer)
-            },
+        // Render targets don't upload any data, so they only need the size
+        if builder.render_target {
+            let size = builder.source.size()?;
+            return Self::new_render_target(size, &builder, renderer)
         }
+
+        let (data, size) = builder.load_pixels()?;
+        Self::from_data(&data, size, &builder, renderer)
     }
 
     fn size(&self) -> Vector2<u32> {
         self.size
     }
+
+    fn update_region(
+        &self, offset: Vector2<u32>, size: Vector2<u32>, bytes: &[u8], format: PixelFormat,
+        renderer: &mut Renderer<GfxRendererRaw<D, F>>,
+    ) -> Result<(), Error> {
+        if self.dynamic_texture.is_none() {
+            return Err(Error::Unsupported("Texture was not built as dynamic".into()))
+        }
+
+        let data = texture::convert_pixels(bytes, format, self.store_format.pixel_format());
+        self.upload(offset, size, &data, renderer)
+    }
+
+    fn capture(
+        &self, renderer: &mut Renderer<GfxRendererRaw<D, F>>,
+    ) -> Result<RgbaImage, Error> {
+        let texture = self.render_target_texture.as_ref().ok_or_else(|| Error::Unsupported(
+            "The gfx backend can only capture render target textures".into()
+        ))?;
+
+        let pixel_count = self.size.x as usize * self.size.y as usize;
+        let download = renderer.raw_mut().factory_mut()
+            .create_download_buffer::<[u8; 4]>(pixel_count)
+            .map_platform_err()?;
+
+        // Copy the texture over and wait for everything rendered to it so far
+        let info = texture.get_info().to_raw_image_info(ChannelType::Unorm, 0);
+        renderer.raw_mut().encoder_mut()
+            .copy_texture_to_buffer_raw(texture.raw(), None, info, download.raw(), 0)
+            .map_err(|e| Error::Platform(format!("{:?}", e)))?;
+        renderer.raw_mut().flush();
+
+        let bytes: Vec<u8> = {
+            let reader = renderer.raw_mut().factory_mut().read_mapping(&download)
+                .map_err(|e| Error::Platform(format!("{:?}", e)))?;
+
+            // OpenGL gives us the rows bottom to top, the same as when capturing the window
+            reader.chunks(self.size.x as usize).rev()
+                .flat_map(|row| row.iter().flat_map(|p| p.iter().cloned()))
+                .collect()
+        };
+
+        calcium_rendering::image_from_pixels(&bytes, self.size, PixelFormat::Rgba8)
+    }
+}
+
+/// Creates an empty texture that can be updated, together with a view of it.
+fn create_dynamic<R: Resources, F: Factory<R>, T: TextureFormat>(
+    factory: &mut F, kind: Kind,
+) -> Result<(Texture<R, T::Surface>, ShaderResourceView<R, T::View>), Error>
+    where T::Surface: TextureSurface, T::Channel: TextureChannel
+{
+    let bind = memory::SHADER_RESOURCE | memory::TRANSFER_DST;
+    let texture = factory.create_texture::<T::Surface>(
+        kind, 1, bind, Usage::Dynamic, Some(<T::Channel as ChannelTyped>::get_channel_type())
+    ).map_platform_err()?;
+    let view = factory.view_texture_as_shader_resource::<T>(
+        &texture, (0, 0), Swizzle::new()
+    ).map_platform_err()?;
+
+    Ok((texture, view))
+}
+
+/// Creates a sampler matching the sampling options of a texture builder.
+fn create_sampler<D: Device + 'static, F: Factory<D::Resources> + 'static>(
+    builder: &TextureBuilder<GfxRendererRaw<D, F>>,
+    renderer: &mut Renderer<GfxRendererRaw<D, F>>,
+) -> Sampler<D::Resources> {
+    let filter = match (builder.sample_mode, builder.anisotropy) {
+        (SampleMode::Linear, Some(level)) if level > 1 => FilterMethod::Anisotropic(level),
+        (SampleMode::Linear, _) => FilterMethod::Trilinear,
+        (SampleMode::Nearest, _) => FilterMethod::Scale,
+    };
+
+    let mut info = SamplerInfo::new(filter, GfxWrapMode::Clamp);
+    info.wrap_mode = (
+        wrap_mode_to_gfx(builder.wrap_u), wrap_mode_to_gfx(builder.wrap_v), GfxWrapMode::Clamp
+    );
+    info.border = builder.border_color.to_rgba().into();
+
+    renderer.raw_mut().factory_mut().create_sampler(info)
+}
+
+fn wrap_mode_to_gfx(wrap_mode: WrapMode) -> GfxWrapMode {
+    match wrap_mode {
+        WrapMode::Clamp => GfxWrapMode::Clamp,
+        WrapMode::Repeat => GfxWrapMode::Tile,
+        WrapMode::MirroredRepeat => GfxWrapMode::Mirror,
+        WrapMode::Border => GfxWrapMode::Border,
+    }
 }
@@ -0,0 +1,22 @@
+extern crate bincode;
+extern crate cgmath;
+extern crate image;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate slog;
+extern crate screenmath;
+extern crate calcium_rendering;
+extern crate calcium_rendering_2d;
+
+pub mod recording;
+mod renderer;
+mod renderer_2d;
+mod replay;
+mod texture;
+
+pub use renderer::{RecordingRendererRaw, RecordingFrameRaw};
+pub use renderer_2d::{RecordingRenderer2DRaw, RecordingRenderer2DTargetRaw};
+pub use replay::{Replayer};
+pub use texture::{RecordingTextureRaw};
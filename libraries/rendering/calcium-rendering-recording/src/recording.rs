@@ -0,0 +1,409 @@
+use std::fs::{File};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+use bincode;
+use cgmath::{Point2};
+use screenmath::{Rectangle};
+
+use calcium_rendering::{Error, CalciumErrorMappable};
+use calcium_rendering::texture::{
+    TextureStoreFormat, SampleMode, PixelFormat, WrapMode, BorderColor,
+};
+use calcium_rendering_2d::render_data::{UvMode, BlendMode};
+
+/// Written at the start of every saved recording, to recognize recording files.
+const MAGIC: [u8; 4] = *b"CRRC";
+
+/// The version of the recording file layout. This has to be increased whenever any of the
+/// recorded types change, so older files are rejected with a clear error instead of failing to
+/// deserialize somewhere in the middle.
+pub const FORMAT_VERSION: u32 = 2;
+
+/// Everything a recording renderer has seen, textures and frames.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Recording {
+    /// All textures created, in order of creation.
+    pub textures: Vec<RecordedTexture>,
+    /// All finished frames, in order.
+    pub frames: Vec<RecordedFrame>,
+}
+
+impl Recording {
+    pub fn new() -> Self {
+        Recording {
+            textures: Vec::new(),
+            frames: Vec::new(),
+        }
+    }
+
+    /// Gets the creation event of a texture. Returns an error if the texture isn't in this
+    /// recording, for example because it was created before the recording was taken.
+    pub fn texture(&self, id: TextureId) -> Result<&RecordedTexture, Error> {
+        self.textures.iter().find(|t| t.id == id).ok_or_else(|| Error::Unsupported(format!(
+            "Texture {} was not created in this recording", id.0
+        )))
+    }
+
+    /// Writes this recording to a file, prefixed with a header containing the format version.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let mut file = BufWriter::new(File::create(path).map_platform_err()?);
+        bincode::serialize_into(&mut file, &(MAGIC, FORMAT_VERSION)).map_platform_err()?;
+        bincode::serialize_into(&mut file, self).map_platform_err()
+    }
+
+    /// Reads a recording previously written using `save`. Returns an error if the file isn't a
+    /// recording, or was saved with a different format version.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let mut file = BufReader::new(File::open(path).map_platform_err()?);
+
+        let (magic, version): ([u8; 4], u32) = bincode::deserialize_from(&mut file)
+            .map_err(|_| Error::Unsupported("File is not a recording".into()))?;
+        if magic != MAGIC {
+            return Err(Error::Unsupported("File is not a recording".into()))
+        }
+        if version != FORMAT_VERSION {
+            return Err(Error::Unsupported(format!(
+                "Recording has format version {}, but only version {} is supported",
+                version, FORMAT_VERSION
+            )))
+        }
+
+        bincode::deserialize_from(&mut file).map_platform_err()
+    }
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TextureId(pub u32);
+
+/// A texture creation event, with everything that was passed to its `TextureBuilder`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RecordedTexture {
+    pub id: TextureId,
+    pub source: RecordedTextureSource,
+    pub size: [u32; 2],
+    pub store_format: RecordedStoreFormat,
+    pub generate_mipmaps: bool,
+    pub sample_mode: RecordedSampleMode,
+    pub wrap_u: RecordedWrapMode,
+    pub wrap_v: RecordedWrapMode,
+    pub anisotropy: Option<u8>,
+    pub border_color: RecordedBorderColor,
+    pub premultiply_alpha: bool,
+    pub render_target: bool,
+    pub dynamic: bool,
+    /// The amount of frames that had been finished when this texture was created.
+    pub created_at_frame: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum RecordedTextureSource {
+    File(PathBuf),
+    Bytes { bytes: Vec<u8>, format: RecordedPixelFormat },
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq)]
+pub enum RecordedStoreFormat {
+    Srgb,
+    Linear,
+    SingleChannel,
+    TwoChannel,
+    SingleChannel16,
+    LinearFloat,
+    SingleChannelFloat,
+}
+
+impl From<TextureStoreFormat> for RecordedStoreFormat {
+    fn from(value: TextureStoreFormat) -> Self {
+        match value {
+            TextureStoreFormat::Srgb => RecordedStoreFormat::Srgb,
+            TextureStoreFormat::Linear => RecordedStoreFormat::Linear,
+            TextureStoreFormat::SingleChannel => RecordedStoreFormat::SingleChannel,
+            TextureStoreFormat::TwoChannel => RecordedStoreFormat::TwoChannel,
+            TextureStoreFormat::SingleChannel16 => RecordedStoreFormat::SingleChannel16,
+            TextureStoreFormat::LinearFloat => RecordedStoreFormat::LinearFloat,
+            TextureStoreFormat::SingleChannelFloat => RecordedStoreFormat::SingleChannelFloat,
+        }
+    }
+}
+
+impl Into<TextureStoreFormat> for RecordedStoreFormat {
+    fn into(self) -> TextureStoreFormat {
+        match self {
+            RecordedStoreFormat::Srgb => TextureStoreFormat::Srgb,
+            RecordedStoreFormat::Linear => TextureStoreFormat::Linear,
+            RecordedStoreFormat::SingleChannel => TextureStoreFormat::SingleChannel,
+            RecordedStoreFormat::TwoChannel => TextureStoreFormat::TwoChannel,
+            RecordedStoreFormat::SingleChannel16 => TextureStoreFormat::SingleChannel16,
+            RecordedStoreFormat::LinearFloat => TextureStoreFormat::LinearFloat,
+            RecordedStoreFormat::SingleChannelFloat => TextureStoreFormat::SingleChannelFloat,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq)]
+pub enum RecordedPixelFormat {
+    R8,
+    Rg8,
+    Rgba8,
+    R16,
+    Rgba16,
+    Rgba16F,
+    R32F,
+    Rgba32F,
+}
+
+impl From<PixelFormat> for RecordedPixelFormat {
+    fn from(value: PixelFormat) -> Self {
+        match value {
+            PixelFormat::R8 => RecordedPixelFormat::R8,
+            PixelFormat::Rg8 => RecordedPixelFormat::Rg8,
+            PixelFormat::Rgba8 => RecordedPixelFormat::Rgba8,
+            PixelFormat::R16 => RecordedPixelFormat::R16,
+            PixelFormat::Rgba16 => RecordedPixelFormat::Rgba16,
+            PixelFormat::Rgba16F => RecordedPixelFormat::Rgba16F,
+            PixelFormat::R32F => RecordedPixelFormat::R32F,
+            PixelFormat::Rgba32F => RecordedPixelFormat::Rgba32F,
+        }
+    }
+}
+
+impl Into<PixelFormat> for RecordedPixelFormat {
+    fn into(self) -> PixelFormat {
+        match self {
+            RecordedPixelFormat::R8 => PixelFormat::R8,
+            RecordedPixelFormat::Rg8 => PixelFormat::Rg8,
+            RecordedPixelFormat::Rgba8 => PixelFormat::Rgba8,
+            RecordedPixelFormat::R16 => PixelFormat::R16,
+            RecordedPixelFormat::Rgba16 => PixelFormat::Rgba16,
+            RecordedPixelFormat::Rgba16F => PixelFormat::Rgba16F,
+            RecordedPixelFormat::R32F => PixelFormat::R32F,
+            RecordedPixelFormat::Rgba32F => PixelFormat::Rgba32F,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq)]
+pub enum RecordedSampleMode {
+    Linear,
+    Nearest,
+}
+
+impl From<SampleMode> for RecordedSampleMode {
+    fn from(value: SampleMode) -> Self {
+        match value {
+            SampleMode::Linear => RecordedSampleMode::Linear,
+            SampleMode::Nearest => RecordedSampleMode::Nearest,
+        }
+    }
+}
+
+impl Into<SampleMode> for RecordedSampleMode {
+    fn into(self) -> SampleMode {
+        match self {
+            RecordedSampleMode::Linear => SampleMode::Linear,
+            RecordedSampleMode::Nearest => SampleMode::Nearest,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq)]
+pub enum RecordedWrapMode {
+    Clamp,
+    Repeat,
+    MirroredRepeat,
+    Border,
+}
+
+impl From<WrapMode> for RecordedWrapMode {
+    fn from(value: WrapMode) -> Self {
+        match value {
+            WrapMode::Clamp => RecordedWrapMode::Clamp,
+            WrapMode::Repeat => RecordedWrapMode::Repeat,
+            WrapMode::MirroredRepeat => RecordedWrapMode::MirroredRepeat,
+            WrapMode::Border => RecordedWrapMode::Border,
+        }
+    }
+}
+
+impl Into<WrapMode> for RecordedWrapMode {
+    fn into(self) -> WrapMode {
+        match self {
+            RecordedWrapMode::Clamp => WrapMode::Clamp,
+            RecordedWrapMode::Repeat => WrapMode::Repeat,
+            RecordedWrapMode::MirroredRepeat => WrapMode::MirroredRepeat,
+            RecordedWrapMode::Border => WrapMode::Border,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq)]
+pub enum RecordedBorderColor {
+    TransparentBlack,
+    OpaqueBlack,
+    OpaqueWhite,
+}
+
+impl From<BorderColor> for RecordedBorderColor {
+    fn from(value: BorderColor) -> Self {
+        match value {
+            BorderColor::TransparentBlack => RecordedBorderColor::TransparentBlack,
+            BorderColor::OpaqueBlack => RecordedBorderColor::OpaqueBlack,
+            BorderColor::OpaqueWhite => RecordedBorderColor::OpaqueWhite,
+        }
+    }
+}
+
+impl Into<BorderColor> for RecordedBorderColor {
+    fn into(self) -> BorderColor {
+        match self {
+            RecordedBorderColor::TransparentBlack => BorderColor::TransparentBlack,
+            RecordedBorderColor::OpaqueBlack => BorderColor::OpaqueBlack,
+            RecordedBorderColor::OpaqueWhite => BorderColor::OpaqueWhite,
+        }
+    }
+}
+
+/// A single frame, from `start_frame` to `finish_frame`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RecordedFrame {
+    pub size: [u32; 2],
+    /// Every texture update made since the previous frame was finished, in the order they were
+    /// made. When replaying, each is applied at the point in the frame it was made at.
+    pub texture_updates: Vec<RecordedTextureUpdate>,
+    /// Every call to `Renderer2D::render` made during this frame.
+    pub renders: Vec<RecordedRender>,
+}
+
+/// A single call to `Texture::update_region`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RecordedTextureUpdate {
+    pub texture: TextureId,
+    pub offset: [u32; 2],
+    pub size: [u32; 2],
+    pub bytes: Vec<u8>,
+    pub format: RecordedPixelFormat,
+    /// The amount of render calls that had been made in the frame when this update was made.
+    /// Updates made between frames are 0, and so come before all of the frame's render calls.
+    pub before_render: usize,
+}
+
+/// A single call to `Renderer2D::render`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RecordedRender {
+    pub clear: bool,
+    /// The texture that was rendered to, or None if this was rendered to the window.
+    pub target: Option<TextureId>,
+    /// The render sets in the order they were drawn, after sorting by layer and sort key.
+    pub sets: Vec<RecordedSet>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RecordedSet {
+    /// The matrix the set's projection and viewport resolved to, in column-major order.
+    pub projection: [[f32; 4]; 4],
+    pub batches: Vec<RecordedBatch>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RecordedBatch {
+    pub mode: RecordedShaderMode,
+    pub uv_mode: RecordedUvMode,
+    pub blend_mode: RecordedBlendMode,
+    pub vertices: Vec<RecordedVertex>,
+    /// The minimum and maximum of the area in pixels the batch was clipped to, after combining
+    /// the set's and batch's clip rectangles. None if it wasn't clipped.
+    pub clip: Option<[[u32; 2]; 2]>,
+}
+
+impl RecordedBatch {
+    /// Returns the bounding rectangles of every quad in this batch, assuming the batch was built
+    /// out of quads using `RenderBatch::push_rectangle`.
+    pub fn rectangles(&self) -> Vec<Rectangle<f32>> {
+        self.vertices.chunks(6).map(|quad| {
+            let mut min = Point2::new(::std::f32::MAX, ::std::f32::MAX);
+            let mut max = Point2::new(::std::f32::MIN, ::std::f32::MIN);
+            for vertex in quad {
+                min.x = min.x.min(vertex.position[0]);
+                min.y = min.y.min(vertex.position[1]);
+                max.x = max.x.max(vertex.position[0]);
+                max.y = max.y.max(vertex.position[1]);
+            }
+            Rectangle::new(min, max)
+        }).collect()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum RecordedShaderMode {
+    Color,
+    Texture(TextureId),
+    Mask(TextureId),
+    /// A custom shader, recorded by its name. Replaying needs the shader to be added to the
+    /// replayer.
+    Custom { shader: String, parameters: Vec<[f32; 4]>, textures: Vec<TextureId> },
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq)]
+pub enum RecordedUvMode {
+    YUp,
+    YDown,
+}
+
+impl From<UvMode> for RecordedUvMode {
+    fn from(value: UvMode) -> Self {
+        match value {
+            UvMode::YUp => RecordedUvMode::YUp,
+            UvMode::YDown => RecordedUvMode::YDown,
+        }
+    }
+}
+
+impl Into<UvMode> for RecordedUvMode {
+    fn into(self) -> UvMode {
+        match self {
+            RecordedUvMode::YUp => UvMode::YUp,
+            RecordedUvMode::YDown => UvMode::YDown,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq)]
+pub enum RecordedBlendMode {
+    Alpha,
+    PremultipliedAlpha,
+    Additive,
+    Multiply,
+    Opaque,
+}
+
+impl From<BlendMode> for RecordedBlendMode {
+    fn from(value: BlendMode) -> Self {
+        match value {
+            BlendMode::Alpha => RecordedBlendMode::Alpha,
+            BlendMode::PremultipliedAlpha => RecordedBlendMode::PremultipliedAlpha,
+            BlendMode::Additive => RecordedBlendMode::Additive,
+            BlendMode::Multiply => RecordedBlendMode::Multiply,
+            BlendMode::Opaque => RecordedBlendMode::Opaque,
+        }
+    }
+}
+
+impl Into<BlendMode> for RecordedBlendMode {
+    fn into(self) -> BlendMode {
+        match self {
+            RecordedBlendMode::Alpha => BlendMode::Alpha,
+            RecordedBlendMode::PremultipliedAlpha => BlendMode::PremultipliedAlpha,
+            RecordedBlendMode::Additive => BlendMode::Additive,
+            RecordedBlendMode::Multiply => BlendMode::Multiply,
+            RecordedBlendMode::Opaque => BlendMode::Opaque,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq)]
+pub struct RecordedVertex {
+    pub position: [f32; 2],
+    pub uv: [f32; 2],
+    pub color: [f32; 4],
+}
@@ -0,0 +1,153 @@
+use slog::{Logger};
+use cgmath::{Vector2};
+use image::{RgbaImage};
+
+use calcium_rendering::raw::{RendererRaw, RawAccess};
+use calcium_rendering::{Error, Frame, WindowId, RendererSettings};
+
+use recording::{Recording, RecordedFrame, RecordedTexture, RecordedTextureUpdate, TextureId};
+use {RecordingTextureRaw};
+
+/// A renderer that doesn't draw anything, but instead records everything it's asked to do. The
+/// resulting recording can be inspected, saved to a file, and replayed on another backend.
+pub struct RecordingRendererRaw {
+    size: Vector2<u32>,
+    recording: Recording,
+    pending_updates: Vec<RecordedTextureUpdate>,
+    settings: RendererSettings,
+    /// Not reset when taking the recording, so textures keep unique IDs across recordings.
+    next_texture_id: u32,
+    renders_in_frame: usize,
+}
+
+impl RecordingRendererRaw {
+    pub fn new(log: &Logger, size: Vector2<u32>) -> Self {
+        info!(log, "Creating recording renderer"; "width" => size.x, "height" => size.y);
+
+        RecordingRendererRaw {
+            size,
+            recording: Recording::new(),
+            pending_updates: Vec::new(),
+            settings: RendererSettings::new(),
+            next_texture_id: 0,
+            renders_in_frame: 0,
+        }
+    }
+
+    /// Gets everything recorded so far.
+    pub fn recording(&self) -> &Recording {
+        &self.recording
+    }
+
+    /// Takes everything recorded so far, leaving an empty recording behind. Textures created
+    /// before this will not be in the new recording, but new textures will never be given the
+    /// same ID as one of them.
+    pub fn take_recording(&mut self) -> Recording {
+        ::std::mem::replace(&mut self.recording, Recording::new())
+    }
+
+    pub fn resize(&mut self, size: Vector2<u32>) {
+        self.size = size;
+    }
+
+    /// Adds a texture creation event to the recording, assigning the texture an ID.
+    pub fn record_texture(&mut self, mut texture: RecordedTexture) -> TextureId {
+        let id = TextureId(self.next_texture_id);
+        self.next_texture_id += 1;
+        texture.id = id;
+        texture.created_at_frame = self.recording.frames.len();
+        self.recording.textures.push(texture);
+        id
+    }
+
+    /// Adds a texture update event to the recording, it will be part of the next finished frame.
+    /// The update is placed after all render calls made so far in the current frame.
+    pub fn record_texture_update(&mut self, mut update: RecordedTextureUpdate) {
+        update.before_render = self.renders_in_frame;
+        self.pending_updates.push(update);
+    }
+
+    /// Counts a render call made in the current frame, so texture updates made after it can be
+    /// replayed after it as well.
+    pub fn record_render(&mut self) {
+        self.renders_in_frame += 1;
+    }
+}
+
+impl RendererRaw for RecordingRendererRaw {
+    type FrameRaw = RecordingFrameRaw;
+    type TextureRaw = RecordingTextureRaw;
+
+    fn size(&self) -> Vector2<u32> {
+        self.size
+    }
+
+    fn window_size(&self, window: WindowId) -> Option<Vector2<u32>> {
+        if window == WindowId::main() { Some(self.size) } else { None }
+    }
+
+    fn settings(&self) -> &RendererSettings {
+        &self.settings
+    }
+
+    fn set_settings(&mut self, settings: RendererSettings, log: &Logger) {
+        debug!(log, "Renderer settings have no effect on the recording renderer");
+        self.settings = settings;
+    }
+
+    fn start_frame(&mut self) -> Frame<Self> {
+        self.renders_in_frame = 0;
+        Frame::raw_new(RecordingFrameRaw {
+            frame: RecordedFrame {
+                size: self.size.into(),
+                texture_updates: Vec::new(),
+                renders: Vec::new(),
+            },
+        }, WindowId::main())
+    }
+
+    fn start_window_frame(&mut self, window: WindowId) -> Result<Frame<Self>, Error> {
+        if window != WindowId::main() {
+            return Err(Error::Unsupported("Recordings only record a single window".into()))
+        }
+
+        Ok(self.start_frame())
+    }
+
+    fn finish_frame(&mut self, mut frame: Frame<Self>) {
+        let empty = RecordedFrame {
+            size: self.size.into(),
+            texture_updates: Vec::new(),
+            renders: Vec::new(),
+        };
+        let mut recorded = ::std::mem::replace(&mut frame.raw_mut().frame, empty);
+        recorded.texture_updates = ::std::mem::replace(&mut self.pending_updates, Vec::new());
+        self.renders_in_frame = 0;
+        self.recording.frames.push(recorded);
+    }
+
+    fn capture_frame(&mut self, _frame: &mut Frame<Self>) -> Result<RgbaImage, Error> {
+        Err(Error::Unsupported(
+            "Recordings don't render anything, replay them on another backend to capture".into()
+        ))
+    }
+}
+
+pub struct RecordingFrameRaw {
+    frame: RecordedFrame,
+}
+
+impl RecordingFrameRaw {
+    pub fn size(&self) -> Vector2<u32> {
+        self.frame.size.into()
+    }
+
+    /// Gets what has been recorded so far in this frame.
+    pub fn frame(&self) -> &RecordedFrame {
+        &self.frame
+    }
+
+    pub fn frame_mut(&mut self) -> &mut RecordedFrame {
+        &mut self.frame
+    }
+}
@@ -0,0 +1,141 @@
+use std::sync::{Arc};
+
+use calcium_rendering::raw::{RawAccess};
+use calcium_rendering::texture::{Texture};
+use calcium_rendering::{Renderer, Error, Frame};
+use calcium_rendering_2d::render_data::{
+    RenderBatch, ShaderMode, RenderData, RenderSet, Shader2D,
+};
+use calcium_rendering_2d::raw::{Renderer2DRaw, Renderer2DTargetRaw};
+use calcium_rendering_2d::{Renderer2DTarget, Renderer2D};
+
+use recording::{TextureId, RecordedRender, RecordedSet, RecordedBatch, RecordedShaderMode, RecordedVertex};
+use {RecordingRendererRaw};
+
+pub struct RecordingRenderer2DRaw {
+}
+
+impl RecordingRenderer2DRaw {
+    pub fn new(renderer: &mut Renderer<RecordingRendererRaw>) -> Result<Self, Error> {
+        info!(renderer.log(), "Creating simple2d renderer");
+        Ok(RecordingRenderer2DRaw {
+        })
+    }
+}
+
+impl Renderer2DRaw<RecordingRendererRaw> for RecordingRenderer2DRaw {
+    type RenderTargetRaw = RecordingRenderer2DTargetRaw;
+
+    fn render(
+        &mut self,
+        data: &RenderData<RecordingRendererRaw>,
+        frame: &mut Frame<RecordingRendererRaw>,
+        render_target: &mut Renderer2DTarget<RecordingRendererRaw, Self>,
+        renderer: &mut Renderer<RecordingRendererRaw>,
+    ) {
+        let size = render_target.size(frame.raw().size());
+        let sets = data.sorted_sets().into_iter()
+            .map(|set| record_set(set, size))
+            .collect();
+
+        frame.raw_mut().frame_mut().renders.push(RecordedRender {
+            clear: render_target.raw.is_clear(),
+            target: render_target.raw.texture(),
+            sets,
+        });
+        renderer.raw_mut().record_render();
+    }
+
+    fn register_shader(
+        &mut self, _shader: &Arc<Shader2D>, _renderer: &mut Renderer<RecordingRendererRaw>,
+    ) -> Result<(), Error> {
+        // Shaders are recorded by name, there's nothing to create for them
+        Ok(())
+    }
+}
+
+fn record_set(
+    set: &RenderSet<RecordingRendererRaw>, size: ::cgmath::Vector2<u32>,
+) -> RecordedSet {
+    RecordedSet {
+        projection: set.to_matrix(size).into(),
+        batches: set.sorted_batches().into_iter()
+            .map(|batch| record_batch(set, batch, size))
+            .collect(),
+    }
+}
+
+fn record_batch(
+    set: &RenderSet<RecordingRendererRaw>, batch: &RenderBatch<RecordingRendererRaw>,
+    size: ::cgmath::Vector2<u32>,
+) -> RecordedBatch {
+    let mode = match batch.mode {
+        ShaderMode::Color => RecordedShaderMode::Color,
+        ShaderMode::Texture(ref texture) => RecordedShaderMode::Texture(texture.raw().id()),
+        ShaderMode::Mask(ref texture) => RecordedShaderMode::Mask(texture.raw().id()),
+        ShaderMode::Custom(ref material) => RecordedShaderMode::Custom {
+            shader: material.shader.name.clone(),
+            parameters: material.parameters.iter().map(|p| (*p).into()).collect(),
+            textures: material.textures.iter().map(|t| t.raw().id()).collect(),
+        },
+    };
+
+    // Indices and instances are recorded expanded, so every recorded batch is a plain triangle
+    //  list regardless of how it was built
+    let vertices = batch.triangle_vertices().iter().map(|v| RecordedVertex {
+        position: v.position.into(),
+        uv: v.uv.into(),
+        color: v.color.into(),
+    }).collect();
+
+    RecordedBatch {
+        mode,
+        uv_mode: batch.uv_mode.into(),
+        blend_mode: batch.blend_mode.into(),
+        vertices,
+        clip: set.scissor(batch, size).map(|s| [s.min.into(), s.max.into()]),
+    }
+}
+
+pub struct RecordingRenderer2DTargetRaw {
+    clear: bool,
+    texture: Option<TextureId>,
+}
+
+impl RecordingRenderer2DTargetRaw {
+    pub fn is_clear(&self) -> bool {
+        self.clear
+    }
+
+    /// The ID of the texture this target renders to, or None if it renders to the window.
+    pub fn texture(&self) -> Option<TextureId> {
+        self.texture
+    }
+}
+
+impl Renderer2DTargetRaw<RecordingRendererRaw, RecordingRenderer2DRaw>
+    for RecordingRenderer2DTargetRaw
+{
+    fn new(
+        clear: bool,
+        _renderer: &Renderer<RecordingRendererRaw>,
+        _simple2d_renderer: &Renderer2D<RecordingRendererRaw, RecordingRenderer2DRaw>,
+    ) -> Self {
+        RecordingRenderer2DTargetRaw {
+            clear,
+            texture: None,
+        }
+    }
+
+    fn new_texture(
+        clear: bool,
+        texture: &Arc<Texture<RecordingRendererRaw>>,
+        _renderer: &Renderer<RecordingRendererRaw>,
+        _simple2d_renderer: &Renderer2D<RecordingRendererRaw, RecordingRenderer2DRaw>,
+    ) -> Result<Self, Error> {
+        Ok(RecordingRenderer2DTargetRaw {
+            clear,
+            texture: Some(texture.raw().id()),
+        })
+    }
+}
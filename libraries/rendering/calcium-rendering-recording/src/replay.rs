@@ -0,0 +1,225 @@
+use std::collections::{HashMap};
+use std::sync::{Arc};
+
+use cgmath::{Matrix4, Vector2, Vector4, Point2};
+
+use calcium_rendering::raw::{RendererRaw};
+use calcium_rendering::texture::{Texture};
+use calcium_rendering::{Error, Renderer};
+use calcium_rendering_2d::render_data::{
+    RenderData, RenderSet, RenderBatch, ShaderMode, DrawVertex, Projection, ClipRect, Rectangle,
+    Shader2D, Material, MATERIAL_PARAMETERS, MATERIAL_TEXTURES,
+};
+use calcium_rendering_2d::raw::{Renderer2DRaw};
+use calcium_rendering_2d::{Renderer2D, Renderer2DTarget};
+
+use recording::{
+    Recording, RecordedFrame, RecordedRender, RecordedShaderMode, RecordedTextureSource,
+    RecordedTextureUpdate, TextureId,
+};
+
+/// Replays recorded frames on any backend. Textures and texture render targets used by the
+/// recording are created on the backend the first time they're needed, and are kept around for
+/// later frames. Custom shaders can't be recorded, so the shaders a recording uses have to be
+/// added using `add_shader`.
+pub struct Replayer<R: RendererRaw, SR: Renderer2DRaw<R>> {
+    textures: HashMap<TextureId, Arc<Texture<R>>>,
+    targets: HashMap<(TextureId, bool), Renderer2DTarget<R, SR>>,
+    shaders: HashMap<String, Arc<Shader2D>>,
+}
+
+impl<R: RendererRaw, SR: Renderer2DRaw<R>> Replayer<R, SR> {
+    pub fn new() -> Self {
+        Replayer {
+            textures: HashMap::new(),
+            targets: HashMap::new(),
+            shaders: HashMap::new(),
+        }
+    }
+
+    /// Adds a custom shader to use for batches recorded with a shader with the same name. The
+    /// shader still has to be registered with the 2D renderer. Returns an error if a shader with
+    /// the same name has already been added, as the recording can't tell them apart.
+    pub fn add_shader(&mut self, shader: Arc<Shader2D>) -> Result<(), Error> {
+        if self.shaders.contains_key(&shader.name) {
+            return Err(Error::Unsupported(format!(
+                "A shader named \"{}\" has already been added", shader.name
+            )))
+        }
+
+        self.shaders.insert(shader.name.clone(), shader);
+        Ok(())
+    }
+
+    /// Renders all 2D render calls recorded in a frame, in a newly started frame on the renderer.
+    /// Render calls that went to the window are rendered to the given render target.
+    pub fn replay_frame(
+        &mut self,
+        frame: &RecordedFrame, recording: &Recording,
+        simple2d_renderer: &mut Renderer2D<R, SR>,
+        render_target: &mut Renderer2DTarget<R, SR>,
+        renderer: &mut Renderer<R>,
+    ) -> Result<(), Error> {
+        // Create all the textures, render data and targets before starting the frame
+        let mut update_textures = Vec::new();
+        for update in &frame.texture_updates {
+            update_textures.push(self.texture(update.texture, recording, renderer)?);
+        }
+
+        let mut all_data = Vec::new();
+        for render in &frame.renders {
+            all_data.push(self.render_data(render, recording, renderer)?);
+
+            if let Some(id) = render.target {
+                if !self.targets.contains_key(&(id, render.clear)) {
+                    let texture = self.texture(id, recording, renderer)?;
+                    let target = Renderer2DTarget::from_texture(
+                        render.clear, texture, renderer, simple2d_renderer
+                    )?;
+                    self.targets.insert((id, render.clear), target);
+                }
+            }
+        }
+
+        // Texture updates are applied in between the render calls they were made in between
+        let mut updates = frame.texture_updates.iter().zip(update_textures.iter()).peekable();
+        let mut backend_frame = renderer.start_frame();
+        for (i, (render, data)) in frame.renders.iter().zip(all_data.iter()).enumerate() {
+            while updates.peek().map(|&(u, _)| u.before_render <= i).unwrap_or(false) {
+                let (update, texture) = updates.next().unwrap();
+                apply_update(update, texture, renderer)?;
+            }
+
+            let target = match render.target {
+                Some(id) => self.targets.get_mut(&(id, render.clear)).unwrap(),
+                None => &mut *render_target,
+            };
+            simple2d_renderer.render(data, &mut backend_frame, target, renderer);
+        }
+        for (update, texture) in updates {
+            apply_update(update, texture, renderer)?;
+        }
+        renderer.finish_frame(backend_frame);
+
+        Ok(())
+    }
+
+    /// Converts a recorded render call back into render data for this backend. The recorded
+    /// projection matrices are used as-is.
+    pub fn render_data(
+        &mut self, render: &RecordedRender, recording: &Recording, renderer: &mut Renderer<R>,
+    ) -> Result<RenderData<R>, Error> {
+        let mut data = RenderData::new();
+
+        for recorded_set in &render.sets {
+            let mut batches = Vec::new();
+
+            for recorded_batch in &recorded_set.batches {
+                let mode = match recorded_batch.mode {
+                    RecordedShaderMode::Color => ShaderMode::Color,
+                    RecordedShaderMode::Texture(id) =>
+                        ShaderMode::Texture(self.texture(id, recording, renderer)?),
+                    RecordedShaderMode::Mask(id) =>
+                        ShaderMode::Mask(self.texture(id, recording, renderer)?),
+                    RecordedShaderMode::Custom { ref shader, ref parameters, ref textures } => {
+                        let shader = self.shaders.get(shader).cloned().ok_or_else(||
+                            Error::Unsupported(format!(
+                                "Shader \"{}\" used by recording has not been added", shader
+                            ))
+                        )?;
+
+                        // Recordings can come from anywhere, so check them before they can go
+                        //  out of the material's bounds
+                        if parameters.len() > MATERIAL_PARAMETERS ||
+                            textures.len() > MATERIAL_TEXTURES {
+                            return Err(Error::Unsupported(format!(
+                                "Batch using shader \"{}\" has {} parameters and {} textures, \
+                                 at most {} and {} are supported",
+                                shader.name, parameters.len(), textures.len(),
+                                MATERIAL_PARAMETERS, MATERIAL_TEXTURES
+                            )))
+                        }
+
+                        let mut material = Material::new(shader);
+                        for (i, parameter) in parameters.iter().enumerate() {
+                            material = material.with_vector(i, Vector4::from(*parameter));
+                        }
+                        for id in textures {
+                            material = material
+                                .with_texture(self.texture(*id, recording, renderer)?);
+                        }
+
+                        ShaderMode::Custom(Arc::new(material))
+                    },
+                };
+
+                let mut batch = RenderBatch::new(mode, recorded_batch.uv_mode.into())
+                    .with_blend_mode(recorded_batch.blend_mode.into());
+                batch.vertices = recorded_batch.vertices.iter().map(|v| DrawVertex::new(
+                    v.position.into(), v.uv.into(), v.color.into(),
+                )).collect();
+                batch.clip = recorded_batch.clip.map(|clip| ClipRect::Pixels(Rectangle::new(
+                    Point2::new(clip[0][0] as f32, clip[0][1] as f32),
+                    Point2::new(clip[1][0] as f32, clip[1][1] as f32),
+                )));
+                batches.push(batch);
+            }
+
+            let projection = Projection::Matrix(Matrix4::from(recorded_set.projection));
+            data.render_sets.push(RenderSet::new(projection, batches));
+        }
+
+        Ok(data)
+    }
+
+    fn texture(
+        &mut self, id: TextureId, recording: &Recording, renderer: &mut Renderer<R>,
+    ) -> Result<Arc<Texture<R>>, Error> {
+        if let Some(texture) = self.textures.get(&id) {
+            return Ok(texture.clone())
+        }
+
+        let recorded = recording.texture(id)?;
+        let builder = match recorded.source {
+            RecordedTextureSource::File(ref path) =>
+                Texture::new().from_file(path.clone()),
+            RecordedTextureSource::Bytes { ref bytes, format } =>
+                Texture::new().from_bytes(
+                    bytes.clone(), Vector2::from(recorded.size), format.into()
+                ),
+        };
+        let mut builder = builder
+            .with_store_format(recorded.store_format.into())
+            .with_sample_mode(recorded.sample_mode.into())
+            .with_wrap_modes(recorded.wrap_u.into(), recorded.wrap_v.into())
+            .with_border_color(recorded.border_color.into());
+        if let Some(anisotropy) = recorded.anisotropy {
+            builder = builder.with_anisotropy(anisotropy);
+        }
+        if recorded.generate_mipmaps {
+            builder = builder.generate_mipmaps();
+        }
+        if recorded.premultiply_alpha {
+            builder = builder.premultiply_alpha();
+        }
+        if recorded.render_target {
+            builder = builder.as_render_target();
+        }
+        if recorded.dynamic {
+            builder = builder.as_dynamic();
+        }
+
+        let texture = builder.build(renderer)?;
+        self.textures.insert(id, texture.clone());
+        Ok(texture)
+    }
+}
+
+fn apply_update<R: RendererRaw>(
+    update: &RecordedTextureUpdate, texture: &Texture<R>, renderer: &mut Renderer<R>,
+) -> Result<(), Error> {
+    texture.update_region(
+        Vector2::from(update.offset), Vector2::from(update.size),
+        &update.bytes, update.format.into(), renderer,
+    )
+}
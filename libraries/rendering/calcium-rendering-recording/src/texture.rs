@@ -0,0 +1,94 @@
+use cgmath::{Vector2};
+use image::{RgbaImage};
+
+use calcium_rendering::{Error, Renderer};
+use calcium_rendering::raw::{TextureRaw, RawAccess};
+use calcium_rendering::texture::{TextureBuilder, TextureSource, PixelFormat};
+
+use recording::{RecordedTexture, RecordedTextureSource, RecordedTextureUpdate, TextureId};
+use {RecordingRendererRaw};
+
+pub struct RecordingTextureRaw {
+    id: TextureId,
+    size: Vector2<u32>,
+    dynamic: bool,
+}
+
+impl RecordingTextureRaw {
+    /// The ID this texture has been given in the recording.
+    pub fn id(&self) -> TextureId {
+        self.id
+    }
+}
+
+impl TextureRaw<RecordingRendererRaw> for RecordingTextureRaw {
+    fn new(
+        builder: TextureBuilder<RecordingRendererRaw>,
+        renderer: &mut Renderer<RecordingRendererRaw>,
+    ) -> Result<Self, Error> {
+        // We don't need the pixels, but we do need to know how big the texture is
+        let size = builder.source.size()?;
+        let source = match builder.source {
+            TextureSource::File(ref path) => RecordedTextureSource::File(path.clone()),
+            TextureSource::Bytes { ref bytes, format, .. } => RecordedTextureSource::Bytes {
+                bytes: bytes.as_ref().to_vec(),
+                format: format.into(),
+            },
+        };
+
+        let id = renderer.raw_mut().record_texture(RecordedTexture {
+            id: TextureId(0),
+            source,
+            size: size.into(),
+            store_format: builder.store_format.into(),
+            generate_mipmaps: builder.generate_mipmaps,
+            sample_mode: builder.sample_mode.into(),
+            wrap_u: builder.wrap_u.into(),
+            wrap_v: builder.wrap_v.into(),
+            anisotropy: builder.anisotropy,
+            border_color: builder.border_color.into(),
+            premultiply_alpha: builder.premultiply_alpha,
+            render_target: builder.render_target,
+            dynamic: builder.dynamic,
+            created_at_frame: 0,
+        });
+
+        Ok(RecordingTextureRaw {
+            id,
+            size,
+            dynamic: builder.dynamic,
+        })
+    }
+
+    fn size(&self) -> Vector2<u32> {
+        self.size
+    }
+
+    fn update_region(
+        &self, offset: Vector2<u32>, size: Vector2<u32>, bytes: &[u8], format: PixelFormat,
+        renderer: &mut Renderer<RecordingRendererRaw>,
+    ) -> Result<(), Error> {
+        if !self.dynamic {
+            return Err(Error::Unsupported("Texture was not built as dynamic".into()))
+        }
+
+        renderer.raw_mut().record_texture_update(RecordedTextureUpdate {
+            texture: self.id,
+            offset: offset.into(),
+            size: size.into(),
+            bytes: bytes.to_vec(),
+            format: format.into(),
+            before_render: 0,
+        });
+
+        Ok(())
+    }
+
+    fn capture(
+        &self, _renderer: &mut Renderer<RecordingRendererRaw>,
+    ) -> Result<RgbaImage, Error> {
+        Err(Error::Unsupported(
+            "Recordings don't render anything, replay them on another backend to capture".into()
+        ))
+    }
+}
@@ -0,0 +1,159 @@
+extern crate cgmath;
+#[macro_use]
+extern crate slog;
+extern crate calcium_rendering;
+extern crate calcium_rendering_2d;
+extern crate calcium_rendering_recording;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::{Write};
+use std::sync::{Arc};
+
+use cgmath::{Vector2};
+use slog::{Logger, Discard};
+
+use calcium_rendering::{Renderer};
+use calcium_rendering::raw::{RawAccess};
+use calcium_rendering::texture::{Texture, PixelFormat};
+use calcium_rendering_2d::{Renderer2D, Renderer2DTarget};
+use calcium_rendering_2d::render_data::{RenderData, Shader2D};
+use calcium_rendering_recording::{RecordingRendererRaw, RecordingRenderer2DRaw, Replayer};
+use calcium_rendering_recording::recording::{
+    Recording, TextureId, RecordedRender, RecordedSet, RecordedBatch, RecordedShaderMode,
+    RecordedUvMode, RecordedBlendMode,
+};
+
+type RecordingReplayer = Replayer<RecordingRendererRaw, RecordingRenderer2DRaw>;
+
+fn create_renderer() -> Renderer<RecordingRendererRaw> {
+    let log = Logger::root(Discard, o!());
+    Renderer::raw_new(RecordingRendererRaw::new(&log, Vector2::new(16, 16)), log)
+}
+
+fn create_texture(renderer: &mut Renderer<RecordingRendererRaw>) -> TextureId {
+    let texture = Texture::new()
+        .from_bytes(vec!(0u8; 4), Vector2::new(1, 1), PixelFormat::Rgba8)
+        .build(renderer).unwrap();
+    texture.raw().id()
+}
+
+#[test]
+fn texture_ids_stay_unique_after_take() {
+    let mut renderer = create_renderer();
+
+    let first = create_texture(&mut renderer);
+    let taken = renderer.raw_mut().take_recording();
+    let second = create_texture(&mut renderer);
+
+    assert_ne!(first, second);
+    assert!(taken.texture(first).is_ok());
+    assert!(taken.texture(second).is_err());
+    assert!(renderer.raw().recording().texture(second).is_ok());
+    assert!(renderer.raw().recording().texture(first).is_err());
+}
+
+#[test]
+fn save_and_load_round_trip() {
+    let mut renderer = create_renderer();
+    create_texture(&mut renderer);
+    let frame = renderer.start_frame();
+    renderer.finish_frame(frame);
+
+    let path = env::temp_dir().join("calcium-recording-round-trip.bin");
+    renderer.raw().recording().save(&path).unwrap();
+    let loaded = Recording::load(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(&loaded, renderer.raw().recording());
+}
+
+#[test]
+fn load_rejects_other_files() {
+    let path = env::temp_dir().join("calcium-recording-not-a-recording.bin");
+    File::create(&path).unwrap().write_all(b"not a recording at all").unwrap();
+    let result = Recording::load(&path);
+    fs::remove_file(&path).unwrap();
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn texture_updates_keep_submission_order() {
+    let mut renderer = create_renderer();
+    let mut simple2d_renderer = Renderer2D::raw_new(
+        RecordingRenderer2DRaw::new(&mut renderer).unwrap()
+    );
+    let mut target = Renderer2DTarget::new(true, &renderer, &simple2d_renderer);
+    let texture = Texture::new()
+        .from_bytes(vec!(0u8; 4), Vector2::new(1, 1), PixelFormat::Rgba8)
+        .as_dynamic()
+        .build(&mut renderer).unwrap();
+    let data = RenderData::new();
+    let update = |renderer: &mut Renderer<RecordingRendererRaw>| texture.update_region(
+        Vector2::new(0, 0), Vector2::new(1, 1), &[255; 4], PixelFormat::Rgba8, renderer
+    ).unwrap();
+
+    update(&mut renderer);
+    let mut frame = renderer.start_frame();
+    simple2d_renderer.render(&data, &mut frame, &mut target, &mut renderer);
+    update(&mut renderer);
+    simple2d_renderer.render(&data, &mut frame, &mut target, &mut renderer);
+    update(&mut renderer);
+    renderer.finish_frame(frame);
+
+    let recording = renderer.raw().recording();
+    let order: Vec<_> = recording.frames[0].texture_updates.iter()
+        .map(|u| u.before_render)
+        .collect();
+    assert_eq!(order, vec!(0, 1, 2));
+}
+
+fn custom_render(parameters: usize, textures: Vec<TextureId>) -> RecordedRender {
+    let batch = RecordedBatch {
+        mode: RecordedShaderMode::Custom {
+            shader: "custom".into(),
+            parameters: vec!([0.0; 4]; parameters),
+            textures,
+        },
+        uv_mode: RecordedUvMode::YDown,
+        blend_mode: RecordedBlendMode::Alpha,
+        vertices: Vec::new(),
+        clip: None,
+    };
+
+    RecordedRender {
+        clear: true,
+        target: None,
+        sets: vec!(RecordedSet {
+            projection: [[0.0; 4]; 4],
+            batches: vec!(batch),
+        }),
+    }
+}
+
+#[test]
+fn replayer_rejects_duplicate_shader_names() {
+    let mut replayer = RecordingReplayer::new();
+
+    assert!(replayer.add_shader(Arc::new(Shader2D::new("custom"))).is_ok());
+    assert!(replayer.add_shader(Arc::new(Shader2D::new("custom"))).is_err());
+    assert!(replayer.add_shader(Arc::new(Shader2D::new("other"))).is_ok());
+}
+
+#[test]
+fn replayer_rejects_too_large_materials() {
+    let mut renderer = create_renderer();
+    let texture = create_texture(&mut renderer);
+    let recording = renderer.raw().recording().clone();
+    let mut replayer = RecordingReplayer::new();
+    replayer.add_shader(Arc::new(Shader2D::new("custom"))).unwrap();
+
+    let fits = custom_render(8, vec!(texture; 4));
+    assert!(replayer.render_data(&fits, &recording, &mut renderer).is_ok());
+
+    let parameters = custom_render(9, Vec::new());
+    assert!(replayer.render_data(&parameters, &recording, &mut renderer).is_err());
+    let textures = custom_render(0, vec!(texture; 5));
+    assert!(replayer.render_data(&textures, &recording, &mut renderer).is_err());
+}
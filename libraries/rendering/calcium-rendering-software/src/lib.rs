@@ -0,0 +1,13 @@
+extern crate cgmath;
+extern crate image;
+#[macro_use]
+extern crate slog;
+extern crate calcium_rendering;
+
+mod pixel_buffer;
+mod renderer;
+mod texture;
+
+pub use pixel_buffer::{PixelBuffer, Sampler};
+pub use renderer::{SoftwareRendererRaw, SoftwareFrameRaw};
+pub use texture::{SoftwareTextureRaw};
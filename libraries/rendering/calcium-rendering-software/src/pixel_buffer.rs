@@ -0,0 +1,244 @@
+use cgmath::{Vector2, Vector4, Point2};
+
+use calcium_rendering::texture::{
+    SampleMode, WrapMode, BorderColor, srgb_to_linear, linear_to_srgb,
+};
+
+/// How a pixel buffer should be sampled, the CPU equivalent of a GPU sampler.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sampler {
+    pub mode: SampleMode,
+    pub wrap_u: WrapMode,
+    pub wrap_v: WrapMode,
+    pub border_color: BorderColor,
+}
+
+impl Sampler {
+    /// Creates a new sampler that clamps to the edges.
+    pub fn new(mode: SampleMode) -> Self {
+        Sampler {
+            mode,
+            wrap_u: WrapMode::Clamp,
+            wrap_v: WrapMode::Clamp,
+            border_color: BorderColor::TransparentBlack,
+        }
+    }
+}
+
+/// A CPU-side image of linear RGBA pixels, used both for textures and for the framebuffer.
+#[derive(Clone, Debug)]
+pub struct PixelBuffer {
+    size: Vector2<u32>,
+    pixels: Vec<Vector4<f32>>,
+}
+
+impl PixelBuffer {
+    /// Creates a new pixel buffer with all pixels set to the given color.
+    pub fn new(size: Vector2<u32>, color: Vector4<f32>) -> Self {
+        PixelBuffer {
+            size,
+            pixels: vec![color; size.x as usize * size.y as usize],
+        }
+    }
+
+    /// Creates a new pixel buffer from RGBA8 bytes, optionally gamma converting them from sRGB to
+    /// linear values.
+    pub fn from_rgba8(bytes: &[u8], size: Vector2<u32>, srgb: bool) -> Self {
+        let decode = |v: u8| {
+            let v = v as f32 / 255.0;
+            if srgb { srgb_to_linear(v) } else { v }
+        };
+
+        let pixels = bytes.chunks(4).map(|c| Vector4::new(
+            decode(c[0]), decode(c[1]), decode(c[2]), c[3] as f32 / 255.0,
+        )).collect();
+
+        PixelBuffer {
+            size,
+            pixels,
+        }
+    }
+
+    /// Creates a new pixel buffer from single-channel bytes. Just like a single-channel texture on
+    /// the GPU, the value ends up in the red channel.
+    pub fn from_r8(bytes: &[u8], size: Vector2<u32>) -> Self {
+        let pixels = bytes.iter().map(|v| Vector4::new(*v as f32 / 255.0, 0.0, 0.0, 1.0)).collect();
+
+        PixelBuffer {
+            size,
+            pixels,
+        }
+    }
+
+    /// Creates a new pixel buffer from linear RGBA float values.
+    pub fn from_rgba_f32(pixels: &[[f32; 4]], size: Vector2<u32>) -> Self {
+        PixelBuffer {
+            size,
+            pixels: pixels.iter().map(|p| Vector4::from(*p)).collect(),
+        }
+    }
+
+    pub fn size(&self) -> Vector2<u32> {
+        self.size
+    }
+
+    pub fn pixels(&self) -> &Vec<Vector4<f32>> {
+        &self.pixels
+    }
+
+    pub fn pixel(&self, position: Vector2<u32>) -> Vector4<f32> {
+        self.pixels[self.index(position)]
+    }
+
+    pub fn set_pixel(&mut self, position: Vector2<u32>, color: Vector4<f32>) {
+        let index = self.index(position);
+        self.pixels[index] = color;
+    }
+
+    /// Copies all pixels of another buffer into this buffer, at the given offset.
+    pub fn copy_from(&mut self, source: &PixelBuffer, offset: Vector2<u32>) {
+        for y in 0..source.size.y {
+            for x in 0..source.size.x {
+                let position = Vector2::new(x, y);
+                self.set_pixel(position + offset, source.pixel(position));
+            }
+        }
+    }
+
+    /// Sets all pixels in this buffer to the given color.
+    pub fn clear(&mut self, color: Vector4<f32>) {
+        for pixel in &mut self.pixels {
+            *pixel = color;
+        }
+    }
+
+    /// Samples the buffer at the given UV coordinates, clamping to the edges.
+    pub fn sample(&self, uv: Point2<f32>, sampler: &Sampler) -> Vector4<f32> {
+        let coords = Vector2::new(uv.x * self.size.x as f32, uv.y * self.size.y as f32);
+
+        match sampler.mode {
+            SampleMode::Nearest =>
+                self.wrapped_pixel(coords.x.floor() as i64, coords.y.floor() as i64, sampler),
+            SampleMode::Linear => {
+                // Pixel centers are at half coordinates, so offset before finding the neighbours
+                let x = coords.x - 0.5;
+                let y = coords.y - 0.5;
+                let (x0, y0) = (x.floor(), y.floor());
+                let (fx, fy) = (x - x0, y - y0);
+                let (x0, y0) = (x0 as i64, y0 as i64);
+
+                let top = self.wrapped_pixel(x0, y0, sampler) * (1.0 - fx) +
+                    self.wrapped_pixel(x0 + 1, y0, sampler) * fx;
+                let bottom = self.wrapped_pixel(x0, y0 + 1, sampler) * (1.0 - fx) +
+                    self.wrapped_pixel(x0 + 1, y0 + 1, sampler) * fx;
+                top * (1.0 - fy) + bottom * fy
+            },
+        }
+    }
+
+    /// Converts the buffer to RGBA8 bytes, gamma converting the color channels to sRGB. This
+    /// matches what ends up on screen with an sRGB window framebuffer.
+    pub fn to_srgba8(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.pixels.len() * 4);
+
+        let encode = |v: f32| (linear_to_srgb(v.max(0.0).min(1.0)) * 255.0).round() as u8;
+
+        for pixel in &self.pixels {
+            bytes.push(encode(pixel.x));
+            bytes.push(encode(pixel.y));
+            bytes.push(encode(pixel.z));
+            bytes.push((pixel.w.max(0.0).min(1.0) * 255.0).round() as u8);
+        }
+
+        bytes
+    }
+
+    /// Converts the buffer to RGBA8 bytes, without any gamma conversion.
+    pub fn to_rgba8(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.pixels.len() * 4);
+
+        for pixel in &self.pixels {
+            for value in &[pixel.x, pixel.y, pixel.z, pixel.w] {
+                bytes.push((value.max(0.0).min(1.0) * 255.0).round() as u8);
+            }
+        }
+
+        bytes
+    }
+
+    fn wrapped_pixel(&self, x: i64, y: i64, sampler: &Sampler) -> Vector4<f32> {
+        let x = wrap(x, self.size.x as i64, sampler.wrap_u);
+        let y = wrap(y, self.size.y as i64, sampler.wrap_v);
+
+        match (x, y) {
+            (Some(x), Some(y)) => self.pixel(Vector2::new(x as u32, y as u32)),
+            _ => Vector4::from(sampler.border_color.to_rgba()),
+        }
+    }
+
+    fn index(&self, position: Vector2<u32>) -> usize {
+        position.y as usize * self.size.x as usize + position.x as usize
+    }
+}
+
+/// Wraps a pixel coordinate into the 0 to size range, or gives None if the border should be used.
+fn wrap(value: i64, size: i64, mode: WrapMode) -> Option<i64> {
+    let modulo = |value: i64, size: i64| ((value % size) + size) % size;
+
+    match mode {
+        WrapMode::Clamp => Some(value.max(0).min(size - 1)),
+        WrapMode::Repeat => Some(modulo(value, size)),
+        WrapMode::MirroredRepeat => {
+            // Every other tile is flipped, so the pattern repeats every two tiles
+            let value = modulo(value, size * 2);
+            Some(if value >= size { size * 2 - 1 - value } else { value })
+        },
+        WrapMode::Border =>
+            if value >= 0 && value < size { Some(value) } else { None },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use calcium_rendering::texture::{WrapMode};
+    use super::{wrap};
+
+    fn wrapped(mode: WrapMode) -> Vec<Option<i64>> {
+        (-4..7).map(|value| wrap(value, 3, mode)).collect()
+    }
+
+    #[test]
+    fn wrap_clamps_to_edges() {
+        assert_eq!(
+            wrapped(WrapMode::Clamp),
+            vec!(Some(0), Some(0), Some(0), Some(0), Some(0), Some(1), Some(2), Some(2), Some(2),
+                Some(2), Some(2))
+        );
+    }
+
+    #[test]
+    fn wrap_repeats_negative_and_positive() {
+        assert_eq!(
+            wrapped(WrapMode::Repeat),
+            vec!(Some(2), Some(0), Some(1), Some(2), Some(0), Some(1), Some(2), Some(0), Some(1),
+                Some(2), Some(0))
+        );
+    }
+
+    #[test]
+    fn wrap_mirrors_every_other_tile() {
+        assert_eq!(
+            wrapped(WrapMode::MirroredRepeat),
+            vec!(Some(2), Some(2), Some(1), Some(0), Some(0), Some(1), Some(2), Some(2), Some(1),
+                Some(0), Some(0))
+        );
+    }
+
+    #[test]
+    fn wrap_uses_border_outside() {
+        assert_eq!(
+            wrapped(WrapMode::Border),
+            vec!(None, None, None, None, Some(0), Some(1), Some(2), None, None, None, None)
+        );
+    }
+}
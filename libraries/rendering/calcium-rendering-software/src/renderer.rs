@@ -0,0 +1,98 @@
+use slog::{Logger};
+use cgmath::{Vector2, Vector4};
+use image::{RgbaImage};
+
+use calcium_rendering::raw::{RendererRaw};
+use calcium_rendering::texture::{PixelFormat};
+use calcium_rendering::{self, Error, Frame, WindowId, RendererSettings};
+
+use {SoftwareTextureRaw, PixelBuffer};
+
+/// A renderer that renders entirely on the CPU into an in-memory framebuffer. This doesn't need a
+/// GPU or a window, which makes it useful for testing the output of rendering code.
+pub struct SoftwareRendererRaw {
+    framebuffer: PixelBuffer,
+    settings: RendererSettings,
+}
+
+impl SoftwareRendererRaw {
+    pub fn new(log: &Logger, size: Vector2<u32>) -> Self {
+        info!(log, "Creating software renderer"; "width" => size.x, "height" => size.y);
+
+        SoftwareRendererRaw {
+            framebuffer: PixelBuffer::new(size, Vector4::new(0.0, 0.0, 0.0, 1.0)),
+            settings: RendererSettings::new(),
+        }
+    }
+
+    /// Gets the framebuffer that frames are rendered into.
+    pub fn framebuffer(&self) -> &PixelBuffer {
+        &self.framebuffer
+    }
+
+    pub fn framebuffer_mut(&mut self) -> &mut PixelBuffer {
+        &mut self.framebuffer
+    }
+
+    /// Resizes the framebuffer, this discards its current contents.
+    pub fn resize(&mut self, size: Vector2<u32>) {
+        self.framebuffer = PixelBuffer::new(size, Vector4::new(0.0, 0.0, 0.0, 1.0));
+    }
+}
+
+impl RendererRaw for SoftwareRendererRaw {
+    type FrameRaw = SoftwareFrameRaw;
+    type TextureRaw = SoftwareTextureRaw;
+
+    fn size(&self) -> Vector2<u32> {
+        self.framebuffer.size()
+    }
+
+    fn window_size(&self, window: WindowId) -> Option<Vector2<u32>> {
+        if window == WindowId::main() { Some(self.framebuffer.size()) } else { None }
+    }
+
+    fn settings(&self) -> &RendererSettings {
+        &self.settings
+    }
+
+    fn set_settings(&mut self, settings: RendererSettings, log: &Logger) {
+        // Nothing is ever presented, so there's nothing for these to change
+        debug!(log, "Renderer settings have no effect on the software renderer");
+        self.settings = settings;
+    }
+
+    fn start_frame(&mut self) -> Frame<Self> {
+        Frame::raw_new(SoftwareFrameRaw {
+            size: self.framebuffer.size(),
+        }, WindowId::main())
+    }
+
+    fn start_window_frame(&mut self, window: WindowId) -> Result<Frame<Self>, Error> {
+        if window != WindowId::main() {
+            return Err(Error::Unsupported("The software renderer only has a single framebuffer".into()))
+        }
+
+        Ok(self.start_frame())
+    }
+
+    fn finish_frame(&mut self, _frame: Frame<Self>) {
+        // Everything has already been drawn straight into the framebuffer
+    }
+
+    fn capture_frame(&mut self, _frame: &mut Frame<Self>) -> Result<RgbaImage, Error> {
+        calcium_rendering::image_from_pixels(
+            &self.framebuffer.to_srgba8(), self.framebuffer.size(), PixelFormat::Rgba8
+        )
+    }
+}
+
+pub struct SoftwareFrameRaw {
+    size: Vector2<u32>,
+}
+
+impl SoftwareFrameRaw {
+    pub fn size(&self) -> Vector2<u32> {
+        self.size
+    }
+}
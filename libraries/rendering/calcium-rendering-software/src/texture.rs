@@ -0,0 +1,168 @@
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use cgmath::{Vector2, Vector4, Point2};
+use image::{RgbaImage};
+
+use calcium_rendering::{self, Error, Renderer};
+use calcium_rendering::raw::{TextureRaw};
+use calcium_rendering::texture::{self, TextureBuilder, TextureStoreFormat, SampleMode, PixelFormat};
+
+use {SoftwareRendererRaw, PixelBuffer, Sampler};
+
+pub struct SoftwareTextureRaw {
+    pixels: RwLock<PixelBuffer>,
+    /// The generated mipmap levels after the base level, empty if mipmaps weren't generated.
+    mipmaps: Vec<PixelBuffer>,
+    size: Vector2<u32>,
+    store_format: TextureStoreFormat,
+    sampler: Sampler,
+    dynamic: bool,
+    render_target: bool,
+}
+
+impl SoftwareTextureRaw {
+    pub fn pixels(&self) -> RwLockReadGuard<PixelBuffer> {
+        self.pixels.read().unwrap()
+    }
+
+    /// Gets the pixels of this texture for reading, or None if they're currently being written
+    /// to, for example because this texture is the current render target.
+    pub fn try_pixels(&self) -> Option<RwLockReadGuard<PixelBuffer>> {
+        self.pixels.try_read().ok()
+    }
+
+    /// Gets the pixels of this texture for writing. Textures are shared, so this is done through
+    /// a lock.
+    pub fn pixels_mut(&self) -> RwLockWriteGuard<PixelBuffer> {
+        self.pixels.write().unwrap()
+    }
+
+    /// Samples the texture, picking the mipmap levels from how much of the texture's UV area a
+    /// single target pixel covers. Adjacent levels are blended, like a GPU sampler with linear
+    /// mipmap filtering. Returns None if the base level is currently being written to.
+    pub fn sample(&self, uv: Point2<f32>, uv_area_per_pixel: f32) -> Option<Vector4<f32>> {
+        let texel_area = uv_area_per_pixel * self.size.x as f32 * self.size.y as f32;
+        let lod = (0.5 * texel_area.log2()).max(0.0).min(self.mipmaps.len() as f32);
+        if self.mipmaps.is_empty() || !lod.is_finite() || lod == 0.0 {
+            return self.try_pixels().map(|pixels| pixels.sample(uv, &self.sampler))
+        }
+
+        let level = lod.floor() as usize;
+        let fraction = lod - level as f32;
+        let low = if level == 0 {
+            self.try_pixels()?.sample(uv, &self.sampler)
+        } else {
+            self.mipmaps[level - 1].sample(uv, &self.sampler)
+        };
+        if fraction == 0.0 {
+            return Some(low)
+        }
+
+        let high = self.mipmaps[level].sample(uv, &self.sampler);
+        Some(low * (1.0 - fraction) + high * fraction)
+    }
+
+    /// The amount of mipmap levels this texture has, including the base level.
+    pub fn mip_levels(&self) -> usize {
+        self.mipmaps.len() + 1
+    }
+
+    pub fn sample_mode(&self) -> SampleMode {
+        self.sampler.mode
+    }
+
+    pub fn sampler(&self) -> &Sampler {
+        &self.sampler
+    }
+
+    /// If this texture was built as a render target, and can be rendered to.
+    pub fn is_render_target(&self) -> bool {
+        self.render_target
+    }
+}
+
+impl TextureRaw<SoftwareRendererRaw> for SoftwareTextureRaw {
+    fn new(
+        builder: TextureBuilder<SoftwareRendererRaw>,
+        renderer: &mut Renderer<SoftwareRendererRaw>,
+    ) -> Result<Self, Error> {
+        let (data, size) = builder.load_pixels()?;
+        let pixels = to_pixel_buffer(&data, size, builder.store_format);
+
+        // Dynamic textures and render targets change after creation, which would make the mipmaps
+        //  go out of date
+        let mipmaps = if !builder.generate_mipmaps {
+            Vec::new()
+        } else if builder.dynamic || builder.render_target {
+            warn!(
+                renderer.log(), "Mipmaps are not generated for dynamic or render target textures"
+            );
+            Vec::new()
+        } else {
+            texture::generate_mipmaps(&data, size, builder.store_format).iter()
+                .skip(1)
+                .map(|level| to_pixel_buffer(&level.bytes, level.size, builder.store_format))
+                .collect()
+        };
+
+        Ok(SoftwareTextureRaw {
+            pixels: RwLock::new(pixels),
+            mipmaps,
+            size,
+            store_format: builder.store_format,
+            sampler: Sampler {
+                mode: builder.sample_mode,
+                wrap_u: builder.wrap_u,
+                wrap_v: builder.wrap_v,
+                border_color: builder.border_color,
+            },
+            dynamic: builder.dynamic,
+            render_target: builder.render_target,
+        })
+    }
+
+    fn size(&self) -> Vector2<u32> {
+        self.size
+    }
+
+    fn update_region(
+        &self, offset: Vector2<u32>, size: Vector2<u32>, bytes: &[u8], format: PixelFormat,
+        _renderer: &mut Renderer<SoftwareRendererRaw>,
+    ) -> Result<(), Error> {
+        if !self.dynamic {
+            return Err(Error::Unsupported("Texture was not built as dynamic".into()))
+        }
+
+        let data = texture::convert_pixels(bytes, format, self.store_format.pixel_format());
+        let region = to_pixel_buffer(&data, size, self.store_format);
+        self.pixels_mut().copy_from(&region, offset);
+
+        Ok(())
+    }
+
+    fn capture(
+        &self, _renderer: &mut Renderer<SoftwareRendererRaw>,
+    ) -> Result<RgbaImage, Error> {
+        let pixels = self.pixels();
+        let bytes = if self.store_format == TextureStoreFormat::Srgb {
+            pixels.to_srgba8()
+        } else {
+            pixels.to_rgba8()
+        };
+
+        calcium_rendering::image_from_pixels(&bytes, self.size, PixelFormat::Rgba8)
+    }
+}
+
+/// Converts data already in the store format's pixel format to what sampling the texture would
+/// give us on the GPU.
+fn to_pixel_buffer(
+    data: &[u8], size: Vector2<u32>, store_format: TextureStoreFormat,
+) -> PixelBuffer {
+    if store_format == TextureStoreFormat::Srgb {
+        PixelBuffer::from_rgba8(data, size, true)
+    } else {
+        let pixels = texture::decode_pixels(data, store_format.pixel_format());
+        PixelBuffer::from_rgba_f32(&pixels, size)
+    }
+}
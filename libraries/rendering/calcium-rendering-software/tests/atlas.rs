@@ -0,0 +1,81 @@
+extern crate cgmath;
+#[macro_use]
+extern crate slog;
+extern crate calcium_rendering;
+extern crate calcium_rendering_software;
+
+use cgmath::{Vector2};
+use slog::{Logger, Discard};
+
+use calcium_rendering::{Renderer, Error};
+use calcium_rendering::atlas::{Atlas};
+use calcium_rendering::texture::{PixelFormat};
+use calcium_rendering_software::{SoftwareRendererRaw};
+
+fn create_renderer() -> Renderer<SoftwareRendererRaw> {
+    let log = Logger::root(Discard, o!());
+    Renderer::raw_new(SoftwareRendererRaw::new(&log, Vector2::new(4, 4)), log.clone())
+}
+
+fn assert_unsupported<T>(result: Result<T, Error>) {
+    match result {
+        Err(Error::Unsupported(_)) => {},
+        Err(error) => panic!("Expected an unsupported error, got {:?}", error),
+        Ok(_) => panic!("Expected an unsupported error, got a result"),
+    }
+}
+
+#[test]
+fn atlas_packs_images_with_padding() {
+    let mut renderer = create_renderer();
+    let atlas = Atlas::new()
+        .add_bytes("a", vec!(255; 2 * 2), Vector2::new(2, 2), PixelFormat::R8)
+        .add_bytes("b", vec!(128; 3), Vector2::new(3, 1), PixelFormat::R8)
+        .with_page_size(Vector2::new(8, 8))
+        .build(&mut renderer).unwrap();
+
+    assert_eq!(atlas.pages().len(), 1);
+    let a = atlas.region("a").unwrap();
+    let b = atlas.region("b").unwrap();
+    assert_eq!(a.size, Vector2::new(2, 2));
+    assert_eq!(b.size, Vector2::new(3, 1));
+
+    // Both regions keep their padding inside the page and away from each other
+    for region in &[a, b] {
+        assert!(region.position.x >= 1 && region.position.y >= 1);
+        assert!(region.position.x + region.size.x < 8 && region.position.y + region.size.y < 8);
+    }
+    let apart = |min: u32, max: u32| min + 2 <= max;
+    assert!(
+        apart(a.position.x + a.size.x, b.position.x) ||
+        apart(b.position.x + b.size.x, a.position.x) ||
+        apart(a.position.y + a.size.y, b.position.y) ||
+        apart(b.position.y + b.size.y, a.position.y)
+    );
+}
+
+#[test]
+fn atlas_rejects_images_larger_than_a_page() {
+    let mut renderer = create_renderer();
+    assert_unsupported(Atlas::new()
+        .add_bytes("a", vec!(0; 7 * 7), Vector2::new(7, 7), PixelFormat::R8)
+        .with_page_size(Vector2::new(8, 8))
+        .build(&mut renderer));
+}
+
+#[test]
+fn atlas_rejects_padding_that_overflows() {
+    let mut renderer = create_renderer();
+    assert_unsupported(Atlas::new()
+        .add_bytes("a", vec!(0; 1), Vector2::new(1, 1), PixelFormat::R8)
+        .with_padding(::std::u32::MAX / 2 + 1)
+        .build(&mut renderer));
+}
+
+#[test]
+fn atlas_rejects_wrong_byte_length() {
+    let mut renderer = create_renderer();
+    assert_unsupported(Atlas::new()
+        .add_bytes("a", vec!(0; 3), Vector2::new(2, 2), PixelFormat::R8)
+        .build(&mut renderer));
+}
@@ -0,0 +1,128 @@
+extern crate cgmath;
+extern crate image;
+#[macro_use]
+extern crate slog;
+extern crate calcium_rendering;
+extern crate calcium_rendering_software;
+
+use std::env;
+use std::fs;
+use std::path::{PathBuf};
+use std::process;
+use std::sync::{Arc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use cgmath::{Vector2};
+use image::{RgbaImage};
+use slog::{Logger, Discard};
+
+use calcium_rendering::{Renderer, Error};
+use calcium_rendering::texture::{Texture, TextureWatcher, TextureLoader, PixelFormat};
+use calcium_rendering_software::{SoftwareRendererRaw};
+
+fn create_renderer() -> Renderer<SoftwareRendererRaw> {
+    let log = Logger::root(Discard, o!());
+    Renderer::raw_new(SoftwareRendererRaw::new(&log, Vector2::new(4, 4)), log.clone())
+}
+
+/// A path in the temporary directory unique to this test run.
+fn temp_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("calcium-rendering-software-{}-{}", process::id(), name))
+}
+
+fn save_pixel(path: &PathBuf, pixel: [u8; 4]) {
+    let image = RgbaImage::from_raw(1, 1, pixel.to_vec()).unwrap();
+    calcium_rendering::save_png(&image, path).unwrap();
+}
+
+/// Polls the loader until it has no pending textures left, returning all failures.
+fn poll_until_done(
+    loader: &mut TextureLoader<SoftwareRendererRaw>, renderer: &mut Renderer<SoftwareRendererRaw>,
+) -> Vec<(PathBuf, Error)> {
+    let start = Instant::now();
+    let mut failures = Vec::new();
+    while loader.pending() != 0 {
+        assert!(start.elapsed() < Duration::from_secs(10), "Textures took too long to load");
+        failures.extend(loader.poll(renderer));
+        thread::sleep(Duration::from_millis(1));
+    }
+    failures
+}
+
+#[test]
+fn loader_resolves_handles() {
+    let mut renderer = create_renderer();
+    let path = temp_path("loaded.png");
+    save_pixel(&path, [10, 20, 30, 255]);
+
+    let placeholder = Texture::new()
+        .from_bytes(vec!(0; 4), Vector2::new(1, 1), PixelFormat::Rgba8)
+        .build(&mut renderer).unwrap();
+    let mut loader = TextureLoader::new(placeholder.clone());
+    let file = loader.load(Texture::new().from_file(path.clone()));
+    let bytes = loader.load(
+        Texture::new().from_bytes(vec!(1, 2, 3, 4), Vector2::new(1, 1), PixelFormat::Rgba8)
+    );
+    assert!(!file.is_loaded());
+    assert!(Arc::ptr_eq(&file.texture(), &placeholder));
+
+    assert!(poll_until_done(&mut loader, &mut renderer).is_empty());
+    fs::remove_file(&path).unwrap();
+
+    assert!(file.is_loaded() && bytes.is_loaded());
+    let pixel = file.texture().capture(&mut renderer).unwrap().get_pixel(0, 0).data;
+    assert_eq!(pixel, [10, 20, 30, 255]);
+}
+
+#[test]
+fn loader_reports_failures() {
+    let mut renderer = create_renderer();
+    let placeholder = Texture::new()
+        .from_bytes(vec!(0; 4), Vector2::new(1, 1), PixelFormat::Rgba8)
+        .build(&mut renderer).unwrap();
+    let mut loader = TextureLoader::new(placeholder.clone());
+
+    let path = temp_path("missing.png");
+    let missing = loader.load(Texture::new().from_file(path.clone()));
+    let wrong_length = loader.load(
+        Texture::new().from_bytes(vec!(0; 3), Vector2::new(1, 1), PixelFormat::Rgba8)
+    );
+
+    let failures = poll_until_done(&mut loader, &mut renderer);
+    assert_eq!(failures.len(), 2);
+    assert!(failures.iter().any(|failure| failure.0 == path));
+
+    // Failed handles keep giving out the placeholder, and tell why they failed
+    for handle in &[missing, wrong_length] {
+        assert!(!handle.is_loaded());
+        assert!(handle.error().is_some());
+        assert!(Arc::ptr_eq(&handle.texture(), &placeholder));
+    }
+}
+
+#[test]
+fn watcher_premultiplies_reloaded_textures() {
+    let mut renderer = create_renderer();
+    let path = temp_path("premultiplied.png");
+    save_pixel(&path, [255, 255, 255, 255]);
+
+    let mut watcher = TextureWatcher::with_interval(Duration::from_millis(0));
+    let texture = watcher.build(
+        Texture::new().from_file(path.clone()).premultiply_alpha(), &mut renderer,
+    ).unwrap();
+    assert!(texture.is_premultiplied_alpha());
+    assert_eq!(texture.capture(&mut renderer).unwrap().get_pixel(0, 0).data, [255, 255, 255, 255]);
+
+    // Removing the file first makes sure the change is seen, even if the file system's
+    //  modification times are too coarse to tell the two writes apart
+    fs::remove_file(&path).unwrap();
+    assert_eq!(watcher.poll(&mut renderer).len(), 1);
+    save_pixel(&path, [255, 255, 255, 128]);
+    assert_eq!(watcher.poll(&mut renderer).len(), 0);
+    fs::remove_file(&path).unwrap();
+
+    // Half of white in linear space, the same as when the texture is first built
+    let pixel = texture.capture(&mut renderer).unwrap().get_pixel(0, 0).data;
+    assert_eq!(pixel, [188, 188, 188, 128]);
+}
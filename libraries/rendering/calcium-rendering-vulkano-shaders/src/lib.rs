@@ -13,4 +13,8 @@ pub mod lighting_fs { include!{concat!(env!("OUT_DIR"), "/shaders/src/lighting_f
 #[allow(dead_code)]
 pub mod simple2d_vs { include!{concat!(env!("OUT_DIR"), "/shaders/src/simple2d_vert.glsl")} }
 #[allow(dead_code)]
+pub mod simple2d_instanced_vs {
+    include!{concat!(env!("OUT_DIR"), "/shaders/src/simple2d_instanced_vert.glsl")}
+}
+#[allow(dead_code)]
 pub mod simple2d_fs { include!{concat!(env!("OUT_DIR"), "/shaders/src/simple2d_frag.glsl")} }
@@ -14,13 +14,21 @@ layout(location = 0) out vec4 o_color;
 const uint MODE_COLOR = 0;
 const uint MODE_TEXTURE = 1;
 const uint MODE_MASK = 2;
+const uint MODE_PREMULTIPLIED = 4;
 
 void main() {
-    if (u_mode.mode == MODE_COLOR) {
-        o_color = f_color;
-    } else if (u_mode.mode == MODE_TEXTURE) {
-        o_color = texture(u_texture, f_uv).rgba * f_color;
-    } else if (u_mode.mode == MODE_MASK) {
-        o_color = vec4(f_color.rgb, texture(u_texture, f_uv).r) * f_color;
+    // Premultiplied alpha blending needs the vertex color to be premultiplied as well
+    vec4 color = f_color;
+    if ((u_mode.mode & MODE_PREMULTIPLIED) != 0u) {
+        color.rgb *= color.a;
+    }
+    uint mode = u_mode.mode & ~MODE_PREMULTIPLIED;
+
+    if (mode == MODE_COLOR) {
+        o_color = color;
+    } else if (mode == MODE_TEXTURE) {
+        o_color = texture(u_texture, f_uv).rgba * color;
+    } else if (mode == MODE_MASK) {
+        o_color = vec4(color.rgb, texture(u_texture, f_uv).r) * color;
     }
 }
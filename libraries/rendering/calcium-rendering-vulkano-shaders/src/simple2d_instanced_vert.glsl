@@ -0,0 +1,27 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+
+layout(set = 0, binding = 0) uniform MatrixData {
+    mat4 total;
+} u_matrix_data;
+
+layout(location = 0) in vec2 v_corner;
+
+layout(location = 1) in vec2 i_position;
+layout(location = 2) in vec2 i_size;
+layout(location = 3) in vec2 i_uv_min;
+layout(location = 4) in vec2 i_uv_max;
+layout(location = 5) in vec4 i_color;
+
+layout(location = 0) out vec2 f_uv;
+layout(location = 1) out vec4 f_color;
+
+out gl_PerVertex {
+    vec4 gl_Position;
+};
+
+void main() {
+    f_uv = mix(i_uv_min, i_uv_max, v_corner);
+    f_color = i_color;
+    gl_Position = u_matrix_data.total * vec4(i_position + i_size * v_corner, 0.0, 1.0);
+}
@@ -10,7 +10,7 @@ mod renderer;
 mod texture;
 mod window_swapchain;
 
-pub use renderer::{VulkanoRendererRaw, VulkanoFrameRaw};
+pub use renderer::{VulkanoRendererRaw, VulkanoWindowRaw, VulkanoFrameRaw};
 pub use texture::{VulkanoTextureRaw};
 pub use window_swapchain::{WindowSwapchain};
 
@@ -1,28 +1,33 @@
+use std::collections::{HashMap};
 use std::sync::{Arc};
 
 use cgmath::{Vector2};
+use image::{RgbaImage};
 use slog::{Logger};
+use vulkano::buffer::{CpuAccessibleBuffer, BufferUsage};
 use vulkano::device::{DeviceExtensions, Device, Queue};
+use vulkano::image::{ImageAccess};
 use vulkano::instance::{Instance, PhysicalDevice};
 use vulkano::sync::{NowFuture, GpuFuture};
-use vulkano::command_buffer::{CommandBufferExecFuture, AutoCommandBuffer};
+use vulkano::command_buffer::{CommandBufferExecFuture, AutoCommandBuffer, AutoCommandBufferBuilder};
 use vulkano::swapchain::{Surface};
 
 use calcium_rendering::raw::{RawAccess, RendererRaw};
-use calcium_rendering::{Error, Frame};
+use calcium_rendering::texture::{PixelFormat};
+use calcium_rendering::{self, Error, Frame, CalciumErrorMappable, WindowId, RendererSettings};
 
 use {VulkanoTextureRaw, WindowSwapchain};
 
 pub struct VulkanoRendererRaw {
+    log: Logger,
     instance: Arc<Instance>,
     device: Arc<Device>,
     graphics_queue: Arc<Queue>,
 
-    size: Vector2<u32>,
-    pub surface: Arc<Surface>,
-    pub swapchain: WindowSwapchain,
-    queued_resize: bool,
+    windows: HashMap<WindowId, VulkanoWindowRaw>,
+    next_window: usize,
     next_frame_id: u64,
+    settings: RendererSettings,
 
     queued_cb_futures: Vec<CommandBufferExecFuture<NowFuture, AutoCommandBuffer>>,
 }
@@ -30,7 +35,7 @@ pub struct VulkanoRendererRaw {
 impl VulkanoRendererRaw {
     pub fn new(
         log: &Logger, instance: Arc<Instance>,
-        surface: Arc<Surface>, size: Vector2<u32>,
+        surface: Arc<Surface>, size: Vector2<u32>, settings: RendererSettings,
     ) -> Result<Self, Error> {
         info!(log, "Creating vulkano renderer");
 
@@ -85,22 +90,66 @@ impl VulkanoRendererRaw {
         // Get the graphics queue we requested
         let graphics_queue = queues.next().unwrap();
 
-        // Create the swapchain we'll have to render to to make things actually show up on screen
-        let swapchain = WindowSwapchain::new(log, &device, &graphics_queue, &surface, size);
-
-        Ok(VulkanoRendererRaw {
+        let mut renderer = VulkanoRendererRaw {
+            log: log.clone(),
             instance: instance.clone(),
             device,
             graphics_queue,
 
-            size,
+            windows: HashMap::new(),
+            next_window: 0,
+            next_frame_id: 0,
+            settings,
+
+            queued_cb_futures: Vec::new(),
+        };
+
+        // The surface we were given becomes the main window
+        renderer.add_window(log, surface, size);
+
+        Ok(renderer)
+    }
+
+    /// Adds a window to render to, creating a swapchain for its surface. The first window added
+    /// is the main window.
+    pub fn add_window(
+        &mut self, log: &Logger, surface: Arc<Surface>, size: Vector2<u32>,
+    ) -> WindowId {
+        let id = WindowId::raw_new(self.next_window);
+        self.next_window += 1;
+        info!(log, "Adding window to vulkano renderer"; "window" => id.index());
+
+        // Create the swapchain we'll have to render to to make things actually show up on screen
+        let swapchain = WindowSwapchain::new(
+            log, &self.device, &self.graphics_queue, &surface, size, &self.settings
+        );
+
+        self.windows.insert(id, VulkanoWindowRaw {
             surface,
             swapchain,
+            size,
             queued_resize: false,
-            next_frame_id: 0,
+            queued_settings: false,
+        });
 
-            queued_cb_futures: Vec::new(),
-        })
+        id
+    }
+
+    /// Removes a window, after which it can't be rendered to anymore. The main window can't be
+    /// removed. Render targets release anything they created for the window the next time
+    /// they're rendered with.
+    pub fn remove_window(&mut self, window: WindowId) {
+        if window != WindowId::main() {
+            self.windows.remove(&window);
+        }
+    }
+
+    pub fn window(&self, window: WindowId) -> Option<&VulkanoWindowRaw> {
+        self.windows.get(&window)
+    }
+
+    pub fn window_mut(&mut self, window: WindowId) -> Option<&mut VulkanoWindowRaw> {
+        self.windows.get_mut(&window)
     }
 
     pub fn instance(&self) -> &Arc<Instance> {
@@ -139,20 +188,56 @@ impl VulkanoRendererRaw {
         future
     }
 
-    pub fn queue_resize(&mut self, size: Vector2<u32>) {
+    /// Copies an image back after the future is done, and waits for it. Returns the image's bytes
+    /// together with a future that can be continued from.
+    pub fn read_image<I: ImageAccess + Send + Sync + 'static>(
+        &mut self, image: I, size: Vector2<u32>, bytes_per_pixel: usize,
+        future: Box<GpuFuture + Send + Sync>,
+    ) -> Result<(Vec<u8>, Box<GpuFuture + Send + Sync>), Error> {
+        let length = size.x as usize * size.y as usize * bytes_per_pixel;
+        let buffer = CpuAccessibleBuffer::<[u8]>::from_iter(
+            self.device.clone(), BufferUsage::all(), (0..length).map(|_| 0u8)
+        ).map_platform_err()?;
+
+        let command_buffer = AutoCommandBufferBuilder::new(
+                self.device.clone(), self.graphics_queue.family()
+            ).map_platform_err()?
+            .copy_image_to_buffer_dimensions(
+                image, buffer.clone(),
+                [0, 0, 0], [size.x, size.y, 1],
+                0, 1, 0
+            ).map_platform_err()?
+            .build().map_platform_err()?;
+
+        // Anything queued up has to be done before we copy, it may have written to the image
+        let future = self.submit_queued_commands(future)
+            .then_execute(self.graphics_queue.clone(), command_buffer).map_platform_err()?
+            .then_signal_fence_and_flush().map_platform_err()?;
+        future.wait(None).map_platform_err()?;
+
+        let bytes = buffer.read().map_platform_err()?.to_vec();
+        Ok((bytes, Box::new(future)))
+    }
+
+    pub fn queue_resize(&mut self, window: WindowId, size: Vector2<u32>) {
         // Limit to at least 1x1 in size, we crash otherwise.
         if size.x <= 0 || size.y <= 0 {
             return
         }
 
+        let window = match self.windows.get_mut(&window) {
+            Some(window) => window,
+            None => return,
+        };
+
         // We can be spammed with resize events many times in the same frame, so defer changing the
         //  swapchain.
-        self.queued_resize = true;
+        window.queued_resize = true;
 
         // We do however want to immediately set the size value as it may be used for 2D geometry
         // location calculations, which would lag behind at least one frame like this if the
         // calculations are done before start_frame.
-        self.size = size;
+        window.size = size;
     }
 }
 
@@ -161,39 +246,126 @@ impl RendererRaw for VulkanoRendererRaw {
     type TextureRaw = VulkanoTextureRaw;
 
     fn size(&self) -> Vector2<u32> {
-        self.size
+        self.windows[&WindowId::main()].size
     }
 
-    fn start_frame(&mut self) -> Frame<Self> {
-        self.swapchain.cleanup_finished_frames();
+    fn window_size(&self, window: WindowId) -> Option<Vector2<u32>> {
+        self.windows.get(&window).map(|w| w.size)
+    }
+
+    fn settings(&self) -> &RendererSettings {
+        &self.settings
+    }
+
+    fn set_settings(&mut self, settings: RendererSettings, log: &Logger) {
+        debug!(log, "Queueing swapchain recreation for new settings");
+        self.settings = settings;
 
-        // Before we render, see if we need to execute a queued resize
-        if self.queued_resize {
-            // Overwrite the size with the actual size we were changed to
-            self.size = self.swapchain.resize(self.size, &self.device, &self.surface);
-            self.queued_resize = false;
+        // Recreating swapchains can't be done in the middle of a frame, so defer it just like
+        //  resizes
+        for window in self.windows.values_mut() {
+            window.queued_settings = true;
         }
+    }
 
-        // Get the image for this frame, along with a future that will let us queue up the order of
-        //  command buffer submissions.
-        let (image_num, future) = self.swapchain.start_frame();
+    fn start_frame(&mut self) -> Frame<Self> {
+        self.start_window_frame(WindowId::main()).unwrap()
+    }
+
+    fn start_window_frame(&mut self, window: WindowId) -> Result<Frame<Self>, Error> {
+        let (image_num, future, size) = {
+            let device = &self.device;
+            let log = &self.log;
+            let graphics_queue = &self.graphics_queue;
+            let settings = &self.settings;
+            let window_raw = self.windows.get_mut(&window).ok_or_else(|| Error::Platform(
+                format!("Window {} does not exist", window.index())
+            ))?;
+
+            window_raw.swapchain.cleanup_finished_frames();
+
+            // Before we render, see if we need to execute a queued resize
+            if window_raw.queued_resize {
+                // Overwrite the size with the actual size we were changed to
+                window_raw.size = window_raw.swapchain.resize(
+                    window_raw.size, device, &window_raw.surface
+                );
+                window_raw.queued_resize = false;
+            }
+
+            // Same for changed settings, which need the swapchain to be recreated
+            if window_raw.queued_settings {
+                window_raw.swapchain.apply_settings(
+                    log, settings, device, graphics_queue, &window_raw.surface
+                );
+                window_raw.queued_settings = false;
+            }
+
+            // Get the image for this frame, along with a future that will let us queue up the
+            //  order of command buffer submissions.
+            let (image_num, future) = window_raw.swapchain.start_frame();
+            (image_num, future, window_raw.size)
+        };
 
         self.next_frame_id += 1;
-        Frame::raw_new(VulkanoFrameRaw {
+        Ok(Frame::raw_new(VulkanoFrameRaw {
             image_num,
             future: Some(future),
             frame_id: self.next_frame_id - 1,
-            size: self.size,
-        })
+            size,
+        }, window))
     }
 
     fn finish_frame(&mut self, mut frame: Frame<Self>) {
-        self.swapchain.finish_frame(
+        // If the window has been removed in the meantime, there's nothing to present to
+        let window = match self.windows.get_mut(&frame.window()) {
+            Some(window) => window,
+            None => return,
+        };
+
+        window.swapchain.finish_frame(
             frame.raw_mut().future.take().unwrap(),
             self.graphics_queue.clone(),
             frame.raw().image_num
         );
     }
+
+    fn capture_frame(&mut self, frame: &mut Frame<Self>) -> Result<RgbaImage, Error> {
+        let image = self.windows.get(&frame.window())
+            .ok_or_else(|| Error::Platform("The frame's window has been removed".into()))?
+            .swapchain.images()[frame.raw().image_num].clone();
+        let size = frame.raw().size;
+        let future = frame.raw_mut().future.take().unwrap();
+
+        let (bytes, future) = self.read_image(image, size, 4, future)?;
+        frame.raw_mut().future = Some(future);
+
+        // The swapchain's images are stored as BGRA
+        calcium_rendering::image_from_pixels(&bgra_to_rgba(bytes), size, PixelFormat::Rgba8)
+    }
+}
+
+/// Swaps the blue and red channels of 4 channel 8-bit pixel data.
+pub(crate) fn bgra_to_rgba(mut bytes: Vec<u8>) -> Vec<u8> {
+    for pixel in bytes.chunks_mut(4) {
+        pixel.swap(0, 2);
+    }
+    bytes
+}
+
+/// The surface and swapchain of a single window the renderer renders to.
+pub struct VulkanoWindowRaw {
+    pub surface: Arc<Surface>,
+    pub swapchain: WindowSwapchain,
+    size: Vector2<u32>,
+    queued_resize: bool,
+    queued_settings: bool,
+}
+
+impl VulkanoWindowRaw {
+    pub fn size(&self) -> Vector2<u32> {
+        self.size
+    }
 }
 
 pub struct VulkanoFrameRaw {
@@ -1,48 +1,158 @@
 use std::sync::{Arc};
-use std::path::{PathBuf};
 
 use cgmath::{Vector2};
-use image::{self};
-use vulkano::format::{Format};
+use image::{RgbaImage};
+use vulkano::format::{Format, ClearValue};
 use vulkano::buffer::{CpuAccessibleBuffer, BufferUsage};
-use vulkano::image::{Dimensions, MipmapsCount, ImageUsage, ImageLayout};
+use vulkano::image::{Dimensions, MipmapsCount, ImageUsage, ImageLayout, ImageViewAccess};
+use vulkano::image::attachment::{AttachmentImage};
 use vulkano::image::immutable::{ImmutableImage};
-use vulkano::sampler::{Sampler, Filter, MipmapMode, SamplerAddressMode};
+use vulkano::sampler::{
+    Sampler, Filter, MipmapMode, SamplerAddressMode, BorderColor as VkBorderColor,
+};
 use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBuffer};
+use vulkano::sync::{self, GpuFuture};
 
 use calcium_rendering::raw::{TextureRaw, RawAccess};
-use calcium_rendering::texture::{TextureSource, TextureBuilder, TextureStoreFormat, SampleMode};
+use calcium_rendering::texture::{
+    self, TextureBuilder, TextureStoreFormat, SampleMode, PixelFormat, WrapMode, BorderColor,
+    MipLevel,
+};
+use renderer::{bgra_to_rgba};
 use {VulkanoRendererRaw};
-use calcium_rendering::{CalciumErrorMappable, Error, Renderer};
+use calcium_rendering::{self, CalciumErrorMappable, Error, Renderer};
 
 pub struct VulkanoTextureRaw {
-    image: Arc<ImmutableImage<Format>>,
+    image: Arc<ImageViewAccess + Send + Sync>,
+    attachment: Option<Arc<AttachmentImage<Format>>>,
+    dynamic_image: Option<Arc<AttachmentImage<Format>>>,
+    store_format: TextureStoreFormat,
     sampler: Arc<Sampler>,
     size: Vector2<u32>,
 }
 
 impl VulkanoTextureRaw {
-    fn from_buffer(
+    fn new_render_target(
+        size: Vector2<u32>,
+        builder: TextureBuilder<VulkanoRendererRaw>,
+        renderer: &mut Renderer<VulkanoRendererRaw>,
+    ) -> Result<Self, Error> {
+        info!(renderer.log(),
+            "Creating render target texture"; "width" => size.x, "height" => size.y
+        );
+
+        // Render targets have to match the format the 2D render passes render to, so the store
+        //  format is ignored here
+        let attachment = AttachmentImage::with_usage(
+            renderer.raw().device().clone(),
+            [size.x, size.y],
+            Format::B8G8R8A8Srgb,
+            ImageUsage {
+                transfer_source: true, transfer_destination: true, sampled: true,
+                color_attachment: true,
+                ..ImageUsage::none()
+            },
+        ).map_platform_err()?;
+
+        // New images have undefined contents, clear it so it starts out transparent black like on
+        //  the other backends
+        let cbb = AutoCommandBufferBuilder::new(
+                renderer.raw().device().clone(), renderer.raw().graphics_queue().family()
+            ).map_platform_err()?
+            .clear_color_image(attachment.clone(), ClearValue::Float([0.0, 0.0, 0.0, 0.0]))
+            .map_platform_err()?;
+        let future = cbb.build().map_platform_err()?
+            .execute(renderer.raw().graphics_queue().clone()).map_platform_err()?;
+        renderer.raw_mut().queue_command_buffer_future(future);
+
+        let sampler = create_sampler(&builder, 0, renderer)?;
+
+        Ok(VulkanoTextureRaw {
+            image: attachment.clone(),
+            attachment: Some(attachment),
+            dynamic_image: None,
+            store_format: builder.store_format,
+            sampler,
+            size,
+        })
+    }
+
+    fn new_dynamic(
         buffer: Arc<CpuAccessibleBuffer<[u8]>>,
         size: Vector2<u32>,
         builder: TextureBuilder<VulkanoRendererRaw>,
         renderer: &mut Renderer<VulkanoRendererRaw>,
     ) -> Result<Self, Error> {
-        // Get the correct format for the srgb parameter we got passed
-        let format = match builder.store_format {
-            TextureStoreFormat::Srgb => Format::R8G8B8A8Srgb,
-            TextureStoreFormat::Linear => Format::R8G8B8A8Unorm,
-            TextureStoreFormat::SingleChannel => Format::R8Unorm,
+        if builder.generate_mipmaps {
+            warn!(renderer.log(), "Mipmaps are not generated for dynamic textures");
+        }
+
+        // Immutable images can't be written to after they've been initialized, so dynamic textures
+        //  use a regular image that we can keep copying into
+        let dynamic_image = AttachmentImage::with_usage(
+            renderer.raw().device().clone(),
+            [size.x, size.y],
+            store_format_to_format(builder.store_format),
+            ImageUsage {
+                transfer_source: true, transfer_destination: true, sampled: true,
+                ..ImageUsage::none()
+            },
+        ).map_platform_err()?;
+
+        let sampler = create_sampler(&builder, 0, renderer)?;
+
+        let texture = VulkanoTextureRaw {
+            image: dynamic_image.clone(),
+            attachment: None,
+            dynamic_image: Some(dynamic_image),
+            store_format: builder.store_format,
+            sampler,
+            size,
         };
+        texture.upload(buffer, Vector2::new(0, 0), size, renderer)?;
 
-        // TODO: I have no idea how these two values interact, it seems 0 is default in some places
-        // for no mipmaps and One in some others.
-        let (mipmap_levels, mipmaps_count) = if builder.generate_mipmaps {
-            let levels = mip_levels(size.x, size.y);
-            (levels, MipmapsCount::Specific(levels))
+        Ok(texture)
+    }
+
+    /// Copies the buffer into a region of a dynamic texture, before the next frame.
+    fn upload(
+        &self, buffer: Arc<CpuAccessibleBuffer<[u8]>>,
+        offset: Vector2<u32>, size: Vector2<u32>,
+        renderer: &mut Renderer<VulkanoRendererRaw>,
+    ) -> Result<(), Error> {
+        let cbb = AutoCommandBufferBuilder::new(
+                renderer.raw().device().clone(), renderer.raw().graphics_queue().family()
+            ).map_platform_err()?
+            .copy_buffer_to_image_dimensions(
+                buffer, self.dynamic_image.as_ref().unwrap().clone(),
+                [offset.x, offset.y, 0], [size.x, size.y, 1],
+                0, 1, 0
+            ).map_platform_err()?;
+
+        let future = cbb.build().map_platform_err()?
+            .execute(renderer.raw().graphics_queue().clone()).map_platform_err()?;
+        renderer.raw_mut().queue_command_buffer_future(future);
+
+        Ok(())
+    }
+
+    fn from_data(
+        data: &[u8],
+        size: Vector2<u32>,
+        builder: TextureBuilder<VulkanoRendererRaw>,
+        renderer: &mut Renderer<VulkanoRendererRaw>,
+    ) -> Result<Self, Error> {
+        // Get the correct format for the srgb parameter we got passed
+        let format = store_format_to_format(builder.store_format);
+
+        // Mipmaps are generated on the CPU rather than by blitting, so they're the same on every
+        //  backend
+        let levels = if builder.generate_mipmaps {
+            texture::generate_mipmaps(data, size, builder.store_format)
         } else {
-            (0, MipmapsCount::One)
+            vec!(MipLevel { bytes: data.to_vec(), size })
         };
+        let mipmap_levels = levels.len() as u32;
 
         // Create the image itself with an initializer
         let dimensions = Dimensions::Dim2d { width: size.x, height: size.y };
@@ -50,98 +160,58 @@ impl VulkanoTextureRaw {
             renderer.raw().device().clone(),
             dimensions,
             format,
-            mipmaps_count,
+            MipmapsCount::Specific(mipmap_levels),
             ImageUsage {
-                transfer_source: true, transfer_destination: true, sampled: true,
+                transfer_destination: true, sampled: true,
                 ..ImageUsage::none()
             },
-            // TODO: Switch between layouts while generating mipmaps then switch to ShaderReadOnlyOptimal
             ImageLayout::ShaderReadOnlyOptimal,
             renderer.raw().device().active_queue_families(),
         ).map_platform_err()?;
         let initializer = Arc::new(initializer);
 
-        // Copy over the base image
+        // Copy over every level
         let mut cbb = AutoCommandBufferBuilder::new(
-                renderer.raw().device().clone(), renderer.raw().graphics_queue().family()
-            ).map_platform_err()?
-            .copy_buffer_to_image_dimensions(
+            renderer.raw().device().clone(), renderer.raw().graphics_queue().family()
+        ).map_platform_err()?;
+        for (level, mip_level) in levels.iter().enumerate() {
+            let buffer = buffer_from_data(&mip_level.bytes, renderer)?;
+            cbb = cbb.copy_buffer_to_image_dimensions(
                 buffer, initializer.clone(),
-                [0, 0, 0], dimensions.width_height_depth(),
+                [0, 0, 0], [mip_level.size.x, mip_level.size.y, 1],
                 0, dimensions.array_layers_with_cube(),
                 // This last one is the target mipmap level
-                0
-            ).unwrap();
-
-        // Now generate all the mipmap levels
-        for level in 1..mipmap_levels {
-            cbb = cbb.blit_image(
-                // Source
-                initializer.clone(),
-                [0, 0, 0],
-                [
-                    (size.x >> (level - 1)) as i32,
-                    (size.y >> (level - 1)) as i32,
-                    1,
-                ],
-                0,
-                level - 1,
-
-                // Destination
-                initializer.clone(),
-                [0, 0, 0],
-                [
-                    (size.x >> level) as i32,
-                    (size.y >> level) as i32,
-                    1,
-                ],
-                0,
-                level,
-
-                1,
-                Filter::Linear,
-            ).unwrap();
+                level as u32
+            ).map_platform_err()?;
         }
 
-        // Submit all those copy and blit commands to be done before the next frame
-        let future = match cbb.build().unwrap().execute(renderer.raw().graphics_queue().clone()) {
-            Ok(f) => f,
-            Err(_) => unreachable!(),
-        };
+        // Submit all those copy commands to be done before the next frame
+        let future = cbb.build().map_platform_err()?
+            .execute(renderer.raw().graphics_queue().clone()).map_platform_err()?;
         renderer.raw_mut().queue_command_buffer_future(future);
 
         // Create a sampler for this texture based on our mipmapping data (if any)
-        let filter = if builder.sample_mode == SampleMode::Linear {
-            Filter::Linear
-        } else {
-            Filter::Nearest
-        };
-        let sampler = Sampler::new(
-            renderer.raw().device().clone(),
-            filter,
-            filter,
-            MipmapMode::Linear,
-            SamplerAddressMode::Repeat,
-            SamplerAddressMode::Repeat,
-            SamplerAddressMode::Repeat,
-            0.0,
-            // TODO: Only enable anisotropic filtering if it's supported
-            // TODO: This is a performance hit, let the user define how strong it should be
-            16.0,
-            0.0, mipmap_levels as f32
-        ).map_platform_err()?;
+        let sampler = create_sampler(&builder, mipmap_levels, renderer)?;
 
         Ok(VulkanoTextureRaw {
             image,
+            attachment: None,
+            dynamic_image: None,
+            store_format: builder.store_format,
             sampler,
             size,
         })
     }
 
-    pub fn image(&self) -> &Arc<ImmutableImage<Format>> {
+    pub fn image(&self) -> &Arc<ImageViewAccess + Send + Sync> {
         &self.image
     }
 
+    /// The image that can be rendered to, if this texture was built as a render target.
+    pub fn attachment(&self) -> Option<&Arc<AttachmentImage<Format>>> {
+        self.attachment.as_ref()
+    }
+
     pub fn sampler(&self) -> &Arc<Sampler> {
         &self.sampler
     }
@@ -151,81 +221,138 @@ impl TextureRaw<VulkanoRendererRaw> for VulkanoTextureRaw {
     fn new(
         builder: TextureBuilder<VulkanoRendererRaw>, renderer: &mut Renderer<VulkanoRendererRaw>
     ) -> Result<Self, Error> {
-        let (buffer, size) = match builder.source {
-            TextureSource::File(ref path) =>
-                buffer_and_size_from_path(path, &builder, renderer)?,
-            TextureSource::Bytes { ref bytes, size, color } => {
-                // TODO: Make these just a warnings and support conversion
-                if color && builder.store_format == TextureStoreFormat::SingleChannel {
-                    panic!("Vulkano backend does not support converting color bytes to single-channel, this needs to be added!")
-                }
-                if !color && builder.store_format != TextureStoreFormat::SingleChannel {
-                    panic!("Vulkano backend does not support converting greyscale bytes to color, this needs to be added!")
-                }
-                (buffer_from_bytes(bytes.as_ref(), size, color, renderer)?, size)
-            },
-        };
+        // Render targets don't upload any data, so they only need the size
+        if builder.render_target {
+            let size = builder.source.size()?;
+            return Self::new_render_target(size, builder, renderer)
+        }
+
+        let (data, size) = builder.load_pixels()?;
 
-        Self::from_buffer(
-            buffer, size, builder, renderer
-        )
+        if builder.dynamic {
+            let buffer = buffer_from_data(&data, renderer)?;
+            return Self::new_dynamic(buffer, size, builder, renderer)
+        }
+
+        Self::from_data(&data, size, builder, renderer)
     }
 
     fn size(&self) -> Vector2<u32> {
         self.size
     }
+
+    fn update_region(
+        &self, offset: Vector2<u32>, size: Vector2<u32>, bytes: &[u8], format: PixelFormat,
+        renderer: &mut Renderer<VulkanoRendererRaw>,
+    ) -> Result<(), Error> {
+        if self.dynamic_image.is_none() {
+            return Err(Error::Unsupported("Texture was not built as dynamic".into()))
+        }
+
+        let data = texture::convert_pixels(bytes, format, self.store_format.pixel_format());
+        let buffer = buffer_from_data(&data, renderer)?;
+
+        self.upload(buffer, offset, size, renderer)
+    }
+
+    fn capture(
+        &self, renderer: &mut Renderer<VulkanoRendererRaw>,
+    ) -> Result<RgbaImage, Error> {
+        let now: Box<GpuFuture + Send + Sync> =
+            Box::new(sync::now(renderer.raw().device().clone()));
+
+        if let Some(ref attachment) = self.attachment {
+            // Render targets are always stored as BGRA
+            let (bytes, _) = renderer.raw_mut().read_image(attachment.clone(), self.size, 4, now)?;
+            return calcium_rendering::image_from_pixels(
+                &bgra_to_rgba(bytes), self.size, PixelFormat::Rgba8
+            )
+        }
+
+        if let Some(ref dynamic_image) = self.dynamic_image {
+            let format = self.store_format.pixel_format();
+            let (bytes, _) = renderer.raw_mut().read_image(
+                dynamic_image.clone(), self.size, format.bytes_per_pixel(), now
+            )?;
+            return calcium_rendering::image_from_pixels(&bytes, self.size, format)
+        }
+
+        Err(Error::Unsupported(
+            "The vulkano backend can only capture render target and dynamic textures".into()
+        ))
+    }
 }
 
-fn buffer_and_size_from_path(
-    path: &PathBuf, builder: &TextureBuilder<VulkanoRendererRaw>,
-    renderer: &mut Renderer<VulkanoRendererRaw>
-) -> Result<(Arc<CpuAccessibleBuffer<[u8]>>, Vector2<u32>), Error> {
-    info!(renderer.log(),
-        "Loading texture from file"; "path" => path.display().to_string()
-    );
-
-    // Load in the image file
-    let img = image::open(path).unwrap().to_rgba();
-    let img_dimensions = img.dimensions();
-    let size = Vector2::new(img_dimensions.0, img_dimensions.1);
-
-    // Load the image data into a buffer
-    let buffer = {
-        let image_data = img.into_raw();
-
-        // If the format is LinearRed, we need to ignore the GBA elements
-        let chunk_size = if builder.store_format != TextureStoreFormat::SingleChannel { 1 } else { 4 };
-        let image_data_iter = image_data.chunks(chunk_size).map(|c| c[0]);
-
-        // TODO: Use staging buffer instead
-        CpuAccessibleBuffer::<[u8]>::from_iter(
-            renderer.raw().device().clone(), BufferUsage::all(), image_data_iter
-        ).unwrap()
+fn store_format_to_format(store_format: TextureStoreFormat) -> Format {
+    match store_format {
+        TextureStoreFormat::Srgb => Format::R8G8B8A8Srgb,
+        TextureStoreFormat::Linear => Format::R8G8B8A8Unorm,
+        TextureStoreFormat::SingleChannel => Format::R8Unorm,
+        TextureStoreFormat::TwoChannel => Format::R8G8Unorm,
+        TextureStoreFormat::SingleChannel16 => Format::R16Unorm,
+        TextureStoreFormat::LinearFloat => Format::R16G16B16A16Sfloat,
+        TextureStoreFormat::SingleChannelFloat => Format::R32Sfloat,
+    }
+}
+
+fn create_sampler(
+    builder: &TextureBuilder<VulkanoRendererRaw>, mipmap_levels: u32,
+    renderer: &Renderer<VulkanoRendererRaw>,
+) -> Result<Arc<Sampler>, Error> {
+    let filter = if builder.sample_mode == SampleMode::Linear {
+        Filter::Linear
+    } else {
+        Filter::Nearest
     };
 
-    Ok((buffer, size))
+    // This used to always repeat with 16x anisotropic filtering, now it follows the builder so
+    //  the defaults match the other backends
+    // Anisotropic filtering is always supported as the renderer requires it, but the maximum
+    //  level depends on the device. A level of 1 means no anisotropic filtering.
+    let max_anisotropy = renderer.raw().device().physical_device().limits()
+        .max_sampler_anisotropy();
+    let anisotropy = match (builder.sample_mode, builder.anisotropy) {
+        (SampleMode::Linear, Some(level)) => (level as f32).max(1.0).min(max_anisotropy),
+        _ => 1.0,
+    };
+
+    let border = border_color_to_vk(builder.border_color);
+    Sampler::new(
+        renderer.raw().device().clone(),
+        filter,
+        filter,
+        MipmapMode::Linear,
+        wrap_mode_to_address_mode(builder.wrap_u, border),
+        wrap_mode_to_address_mode(builder.wrap_v, border),
+        SamplerAddressMode::ClampToEdge,
+        0.0,
+        anisotropy,
+        0.0, mipmap_levels as f32
+    ).map_platform_err()
 }
 
-fn buffer_from_bytes(
-    bytes: &[u8], size: Vector2<u32>, color: bool, renderer: &mut Renderer<VulkanoRendererRaw>
-) -> Result<Arc<CpuAccessibleBuffer<[u8]>>, Error> {
-    info!(renderer.log(),
-        "Loading texture from bytes"; "width" => size.x, "height" => size.y, "color" => color
-    );
-
-    // Load the image data into a buffer
-    let buffer = {
-        let image_data_iter = bytes.iter().map(|v| *v);
-
-        // TODO: Use staging buffer instead
-        CpuAccessibleBuffer::<[u8]>::from_iter(
-            renderer.raw().device().clone(), BufferUsage::all(), image_data_iter
-        ).unwrap()
-    };
+fn wrap_mode_to_address_mode(wrap_mode: WrapMode, border: VkBorderColor) -> SamplerAddressMode {
+    match wrap_mode {
+        WrapMode::Clamp => SamplerAddressMode::ClampToEdge,
+        WrapMode::Repeat => SamplerAddressMode::Repeat,
+        WrapMode::MirroredRepeat => SamplerAddressMode::MirroredRepeat,
+        WrapMode::Border => SamplerAddressMode::ClampToBorder(border),
+    }
+}
 
-    Ok(buffer)
+fn border_color_to_vk(border_color: BorderColor) -> VkBorderColor {
+    match border_color {
+        BorderColor::TransparentBlack => VkBorderColor::FloatTransparentBlack,
+        BorderColor::OpaqueBlack => VkBorderColor::FloatOpaqueBlack,
+        BorderColor::OpaqueWhite => VkBorderColor::FloatOpaqueWhite,
+    }
 }
 
-pub fn mip_levels(width: u32, height: u32) -> u32 {
-    (width as f32).max(height as f32).log2().floor() as u32 + 1
+fn buffer_from_data(
+    data: &[u8], renderer: &mut Renderer<VulkanoRendererRaw>
+) -> Result<Arc<CpuAccessibleBuffer<[u8]>>, Error> {
+    // TODO: Use staging buffer instead
+    CpuAccessibleBuffer::<[u8]>::from_iter(
+        renderer.raw().device().clone(), BufferUsage::all(), data.iter().map(|v| *v)
+    ).map_platform_err()
 }
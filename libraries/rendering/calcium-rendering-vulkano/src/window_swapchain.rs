@@ -4,10 +4,14 @@ use std::cmp::{min, max};
 use slog::{Logger};
 use cgmath::{Vector2};
 use vulkano::device::{Device, Queue};
-use vulkano::swapchain::{Swapchain, SurfaceTransform, Surface};
+use vulkano::swapchain::{
+    Swapchain, SurfaceTransform, Surface, PresentMode, SupportedPresentModes,
+};
 use vulkano::sync::{GpuFuture};
 use vulkano::image::swapchain::{SwapchainImage};
 
+use calcium_rendering::{RendererSettings, VsyncMode};
+
 /// A representation of the buffer(s) renderers have to render to to show up on the target.
 pub struct WindowSwapchain {
     pub swapchain: Arc<Swapchain>,
@@ -22,41 +26,15 @@ pub struct WindowSwapchain {
 impl WindowSwapchain {
     pub fn new(
         log: &Logger,
-        device: &Arc<Device>, graphics_queue: &Arc<Queue>, surface: &Arc<Surface>, size: Vector2<u32>,
+        device: &Arc<Device>, graphics_queue: &Arc<Queue>, surface: &Arc<Surface>,
+        size: Vector2<u32>, settings: &RendererSettings,
     ) -> Self {
         // Now create the swapchain, we need this to actually swap between our back buffer and the
         //  window's front buffer, without it we can't show anything
         debug!(log, "Creating swapchain");
-        let (swapchain, images) = {
-            // Get what the swap chain we want to create would be capable of, we can't request
-            //  anything it can't do
-            let caps = surface.capabilities(device.physical_device()).unwrap();
-
-            // The swap chain's dimensions need to match the window size
-            let dimensions = caps.current_extent.unwrap_or([size.x, size.y]);
-
-            // The present mode is things like vsync and vsync-framerate, right now pick the first
-            //  one, we're sure it will work but it's probably not optimal
-            // TODO: Let the user decide
-            let present = caps.present_modes.iter().next().unwrap();
-
-            // This decides how alpha will be composited by the OS' window manager, we just pick
-            //  the first available option
-            let alpha = caps.supported_composite_alpha.iter().next().unwrap();
-
-            // And finally, chose the internal format that images will have
-            // The swap chain needs to be in SRGB, and this format is guaranteed supported
-            let format = ::vulkano::format::B8G8R8A8Srgb;
-
-            // Finally, actually create the swapchain, with all its color images
-            Swapchain::new(
-                device.clone(), surface.clone(), caps.min_image_count, format,
-                dimensions, 1,
-                caps.supported_usage_flags, graphics_queue,
-                SurfaceTransform::Identity, alpha,
-                present, true, None
-            ).unwrap()
-        };
+        let (swapchain, images) = create_swapchain(
+            log, device, graphics_queue, surface, [size.x, size.y], settings, None
+        );
         debug!(log, "Created swapchain"; "images" => images.len());
 
         WindowSwapchain {
@@ -101,6 +79,22 @@ impl WindowSwapchain {
         size
     }
 
+    /// Recreates the swapchain with new settings, keeping the current size.
+    pub fn apply_settings(
+        &mut self, log: &Logger, settings: &RendererSettings,
+        device: &Arc<Device>, graphics_queue: &Arc<Queue>, surface: &Arc<Surface>,
+    ) {
+        debug!(log, "Recreating swapchain with new settings");
+        let dimensions = self.swapchain.dimensions();
+        let (swapchain, images) = create_swapchain(
+            log, device, graphics_queue, surface, dimensions, settings, Some(&self.swapchain)
+        );
+        self.swapchain = swapchain;
+        self.images = images;
+
+        self.images_id += 1;
+    }
+
     pub fn cleanup_finished_frames(&mut self) {
         self.previous_frame.as_mut().unwrap().cleanup_finished();
     }
@@ -133,3 +127,73 @@ impl WindowSwapchain {
         self.previous_frame = Some(Box::new(future));
     }
 }
+
+fn create_swapchain(
+    log: &Logger,
+    device: &Arc<Device>, graphics_queue: &Arc<Queue>, surface: &Arc<Surface>,
+    dimensions: [u32; 2], settings: &RendererSettings, old: Option<&Arc<Swapchain>>,
+) -> (Arc<Swapchain>, Vec<Arc<SwapchainImage>>) {
+    // Get what the swap chain we want to create would be capable of, we can't request anything
+    //  it can't do
+    let caps = surface.capabilities(device.physical_device()).unwrap();
+
+    // The swap chain's dimensions need to match the window size
+    let dimensions = caps.current_extent.unwrap_or(dimensions);
+
+    // The present mode is things like vsync and vsync-framerate, FIFO is the only one that's
+    //  guaranteed to be supported so we fall back to that if what was asked for isn't available
+    let present = present_mode_for(log, settings.vsync, &caps.present_modes);
+
+    // Use the requested amount of images if we can, or otherwise the closest we can get
+    let mut image_count = settings.image_count.unwrap_or(caps.min_image_count);
+    image_count = max(image_count, caps.min_image_count);
+    if let Some(max_count) = caps.max_image_count {
+        image_count = min(image_count, max_count);
+    }
+    if settings.image_count.map(|c| c != image_count).unwrap_or(false) {
+        warn!(log, "Requested swapchain image count not supported, using closest supported";
+            "requested" => settings.image_count, "actual" => image_count
+        );
+    }
+
+    // This decides how alpha will be composited by the OS' window manager, we just pick the
+    //  first available option
+    let alpha = caps.supported_composite_alpha.iter().next().unwrap();
+
+    // And finally, chose the internal format that images will have
+    // The swap chain needs to be in SRGB, and this format is guaranteed supported
+    // TODO: Support linear swapchains, this needs the render passes to be created for a format
+    //  other than B8G8R8A8Srgb
+    if !settings.srgb {
+        warn!(log, "Linear swapchains are not supported by vulkano backend, using sRGB");
+    }
+    let format = ::vulkano::format::B8G8R8A8Srgb;
+
+    // Finally, actually create the swapchain, with all its color images
+    Swapchain::new(
+        device.clone(), surface.clone(), image_count, format,
+        dimensions, 1,
+        caps.supported_usage_flags, graphics_queue,
+        SurfaceTransform::Identity, alpha,
+        present, true, old
+    ).unwrap()
+}
+
+fn present_mode_for(
+    log: &Logger, vsync: VsyncMode, supported: &SupportedPresentModes,
+) -> PresentMode {
+    let preferred: &[PresentMode] = match vsync {
+        VsyncMode::On => &[PresentMode::Fifo],
+        VsyncMode::Off => &[PresentMode::Immediate, PresentMode::Mailbox],
+        VsyncMode::Adaptive => &[PresentMode::Relaxed],
+    };
+
+    if let Some(mode) = preferred.iter().find(|m| supported.supports(**m)) {
+        return *mode
+    }
+
+    warn!(log, "Requested vsync mode not supported, falling back to vsync on";
+        "requested" => format!("{:?}", vsync)
+    );
+    PresentMode::Fifo
+}
@@ -0,0 +1,333 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{PathBuf};
+use std::sync::{Arc};
+
+use cgmath::{Vector2, Point2};
+use screenmath::{Rectangle};
+
+use {Error, Renderer};
+use atlas::{SkylinePacker};
+use raw::{RendererRaw};
+use texture::{self, Texture, TextureStoreFormat, SampleMode, PixelFormat};
+
+pub struct AtlasBuilder<R: RendererRaw> {
+    /// The images that will be packed into the atlas.
+    pub images: Vec<AtlasImage>,
+
+    /// The size of every page texture in the atlas. Images that don't fit on one page anymore are
+    /// placed on a new page. Defaults to 1024x1024.
+    pub page_size: Vector2<u32>,
+
+    /// How many pixels to pad around every image. The padding is filled by repeating the image's
+    /// edge pixels, so linear sampling at the edge of a region doesn't bleed in neighboring
+    /// images. Defaults to 1.
+    pub padding: u32,
+
+    /// Defines how the page textures should be stored internally. Images are always packed as
+    /// 8-bit RGBA, so formats with more precision than that don't gain anything. Defaults to Srgb.
+    pub store_format: TextureStoreFormat,
+
+    /// How the page textures should be sampled. Defaults to Linear.
+    pub sample_mode: SampleMode,
+
+    _r: ::std::marker::PhantomData<R>,
+}
+
+impl<R: RendererRaw> AtlasBuilder<R> {
+    fn new() -> Self {
+        AtlasBuilder {
+            images: Vec::new(),
+            page_size: Vector2::new(1024, 1024),
+            padding: 1,
+            store_format: TextureStoreFormat::Srgb,
+            sample_mode: SampleMode::Linear,
+            _r: ::std::marker::PhantomData,
+        }
+    }
+
+    pub fn add_file<N: Into<String>, P: Into<PathBuf>>(mut self, name: N, path: P) -> Self {
+        self.images.push(AtlasImage {
+            name: name.into(),
+            source: AtlasImageSource::File(path.into()),
+        });
+        self
+    }
+
+    pub fn add_bytes<N: Into<String>>(
+        mut self, name: N, bytes: Vec<u8>, size: Vector2<u32>, format: PixelFormat,
+    ) -> Self {
+        self.images.push(AtlasImage {
+            name: name.into(),
+            source: AtlasImageSource::Bytes { bytes, size, format },
+        });
+        self
+    }
+
+    pub fn with_page_size(mut self, value: Vector2<u32>) -> Self {
+        self.page_size = value;
+        self
+    }
+
+    pub fn with_padding(mut self, value: u32) -> Self {
+        self.padding = value;
+        self
+    }
+
+    pub fn with_store_format(mut self, value: TextureStoreFormat) -> Self {
+        self.store_format = value;
+        self
+    }
+
+    pub fn with_sample_mode(mut self, value: SampleMode) -> Self {
+        self.sample_mode = value;
+        self
+    }
+
+    pub fn with_linear_sampling(self) -> Self {
+        self.with_sample_mode(SampleMode::Linear)
+    }
+
+    pub fn with_nearest_sampling(self) -> Self {
+        self.with_sample_mode(SampleMode::Nearest)
+    }
+
+    pub fn build(self, renderer: &mut Renderer<R>) -> Result<Atlas<R>, Error> {
+        info!(renderer.log(), "Building texture atlas"; "images" => self.images.len());
+
+        // Regions are looked up by name, so a duplicate would silently replace another image
+        let mut names = HashSet::new();
+        for image in &self.images {
+            if !names.insert(image.name.as_str()) {
+                return Err(Error::Unsupported(format!(
+                    "Atlas image name \"{}\" is used more than once", image.name
+                )))
+            }
+        }
+
+        // Load all images as RGBA so they can be copied into the pages
+        let mut loaded = Vec::new();
+        for image in self.images {
+            let (pixels, size) = load_rgba(image.source)?;
+            loaded.push((image.name, pixels, size));
+        }
+
+        // Packing the tallest images first gives a much flatter skyline
+        loaded.sort_by(|a, b| b.2.y.cmp(&a.2.y));
+
+        let mut pages: Vec<(SkylinePacker, Vec<u8>)> = Vec::new();
+        let mut placed = Vec::new();
+        for (name, pixels, size) in loaded {
+            let padding = self.padding;
+            let padded_size = padded(size, padding).ok_or_else(|| Error::Unsupported(format!(
+                "Atlas image \"{}\" of size {:?} with padding {} is too large",
+                name, size, padding
+            )))?;
+            if padded_size.x > self.page_size.x || padded_size.y > self.page_size.y {
+                return Err(Error::Unsupported(format!(
+                    "Atlas image \"{}\" of size {:?} does not fit in page of size {:?}",
+                    name, size, self.page_size
+                )))
+            }
+
+            // Try the pages we already have first, and start a new page if none of them fit
+            let mut found = None;
+            for (index, page) in pages.iter_mut().enumerate() {
+                if let Some(position) = page.0.pack(padded_size) {
+                    found = Some((index, position));
+                    break
+                }
+            }
+            let (page_index, position) = match found {
+                Some(value) => value,
+                None => {
+                    let mut packer = SkylinePacker::new(self.page_size);
+                    let position = packer.pack(padded_size).unwrap();
+                    let page_bytes =
+                        vec![0u8; self.page_size.x as usize * self.page_size.y as usize * 4];
+                    pages.push((packer, page_bytes));
+                    (pages.len() - 1, position)
+                },
+            };
+
+            let image_position = Point2::new(position.x + self.padding, position.y + self.padding);
+            copy_padded(
+                &pixels, size, &mut pages[page_index].1, self.page_size,
+                image_position, self.padding,
+            );
+            placed.push((name, page_index, image_position, size));
+        }
+
+        // Create the actual textures for all pages
+        let mut page_textures = Vec::new();
+        for (_, page_bytes) in pages {
+            let texture = Texture::new()
+                .from_bytes(page_bytes, self.page_size, PixelFormat::Rgba8)
+                .with_store_format(self.store_format)
+                .with_sample_mode(self.sample_mode)
+                .build(renderer)?;
+            page_textures.push(texture);
+        }
+
+        let page_size = Vector2::new(self.page_size.x as f32, self.page_size.y as f32);
+        let mut regions = HashMap::new();
+        for (name, page, position, size) in placed {
+            let uv = Rectangle::new(
+                Point2::new(position.x as f32 / page_size.x, position.y as f32 / page_size.y),
+                Point2::new(
+                    (position.x + size.x) as f32 / page_size.x,
+                    (position.y + size.y) as f32 / page_size.y,
+                ),
+            );
+
+            regions.insert(name, AtlasRegion {
+                page,
+                texture: page_textures[page].clone(),
+                position,
+                size,
+                uv,
+            });
+        }
+
+        info!(renderer.log(), "Finished building texture atlas"; "pages" => page_textures.len());
+
+        Ok(Atlas {
+            pages: page_textures,
+            regions,
+        })
+    }
+}
+
+pub struct AtlasImage {
+    pub name: String,
+    pub source: AtlasImageSource,
+}
+
+pub enum AtlasImageSource {
+    File(PathBuf),
+    Bytes { bytes: Vec<u8>, size: Vector2<u32>, format: PixelFormat },
+}
+
+/// Many images packed together into one or more textures, so they can be drawn in the same
+/// render batch.
+pub struct Atlas<R: RendererRaw> {
+    pages: Vec<Arc<Texture<R>>>,
+    regions: HashMap<String, AtlasRegion<R>>,
+}
+
+impl<R: RendererRaw> Atlas<R> {
+    pub fn new() -> AtlasBuilder<R> {
+        AtlasBuilder::new()
+    }
+
+    /// The textures the images have been packed into.
+    pub fn pages(&self) -> &Vec<Arc<Texture<R>>> {
+        &self.pages
+    }
+
+    /// Gets the region an image with the given name has been packed into.
+    pub fn region(&self, name: &str) -> Option<&AtlasRegion<R>> {
+        self.regions.get(name)
+    }
+
+    pub fn regions(&self) -> &HashMap<String, AtlasRegion<R>> {
+        &self.regions
+    }
+}
+
+/// Where an image ended up in an atlas.
+pub struct AtlasRegion<R: RendererRaw> {
+    /// The index of the page this image is on.
+    pub page: usize,
+
+    /// The page texture this image is on.
+    pub texture: Arc<Texture<R>>,
+
+    /// The position of the image's top left corner on the page, in pixels.
+    pub position: Point2<u32>,
+
+    /// The size of the image in pixels.
+    pub size: Vector2<u32>,
+
+    /// The UV coordinates of the image on the page texture, with Y pointing down.
+    pub uv: Rectangle<f32>,
+}
+
+impl<R: RendererRaw> AtlasRegion<R> {
+    /// Calculates the UV coordinates of a part of this region, given in pixels relative to the
+    /// top left of the image. This is useful for grid tilesets packed as a single image.
+    pub fn sub_uv(&self, pixels: Rectangle<u32>) -> Rectangle<f32> {
+        let page_size = self.texture.size();
+        let page_size = Vector2::new(page_size.x as f32, page_size.y as f32);
+        let min = self.position + Vector2::new(pixels.min.x, pixels.min.y);
+        let max = self.position + Vector2::new(pixels.max.x, pixels.max.y);
+
+        Rectangle::new(
+            Point2::new(min.x as f32 / page_size.x, min.y as f32 / page_size.y),
+            Point2::new(max.x as f32 / page_size.x, max.y as f32 / page_size.y),
+        )
+    }
+}
+
+impl<R: RendererRaw> Clone for AtlasRegion<R> {
+    fn clone(&self) -> Self {
+        AtlasRegion {
+            page: self.page,
+            texture: self.texture.clone(),
+            position: self.position,
+            size: self.size,
+            uv: self.uv.clone(),
+        }
+    }
+}
+
+fn load_rgba(source: AtlasImageSource) -> Result<(Vec<u8>, Vector2<u32>), Error> {
+    match source {
+        AtlasImageSource::File(path) => {
+            let (bytes, size, format) = texture::load_file(&path)?;
+            Ok((texture::convert_pixels(&bytes, format, PixelFormat::Rgba8), size))
+        },
+        AtlasImageSource::Bytes { bytes, size, format } => {
+            let expected_length = format.bytes_for(size);
+            if bytes.len() != expected_length {
+                return Err(Error::Unsupported(format!(
+                    "Expected {} bytes of atlas image data, got {}", expected_length, bytes.len()
+                )))
+            }
+
+            Ok((texture::convert_pixels(&bytes, format, PixelFormat::Rgba8), size))
+        },
+    }
+}
+
+/// The size of an image with padding on every side, or None if it doesn't fit in a u32.
+fn padded(size: Vector2<u32>, padding: u32) -> Option<Vector2<u32>> {
+    let total = padding.checked_mul(2)?;
+    Some(Vector2::new(size.x.checked_add(total)?, size.y.checked_add(total)?))
+}
+
+/// Copies an image onto a page, and repeats its edge pixels into the padding around it.
+fn copy_padded(
+    source: &[u8], source_size: Vector2<u32>,
+    page: &mut [u8], page_size: Vector2<u32>,
+    position: Point2<u32>, padding: u32,
+) {
+    if source_size.x == 0 || source_size.y == 0 {
+        return
+    }
+
+    let padding = padding as i64;
+    for y in -padding..(source_size.y as i64 + padding) {
+        for x in -padding..(source_size.x as i64 + padding) {
+            let source_x = x.max(0).min(source_size.x as i64 - 1) as usize;
+            let source_y = y.max(0).min(source_size.y as i64 - 1) as usize;
+            let source_index = (source_y * source_size.x as usize + source_x) * 4;
+
+            let page_x = (position.x as i64 + x) as usize;
+            let page_y = (position.y as i64 + y) as usize;
+            let page_index = (page_y * page_size.x as usize + page_x) * 4;
+
+            page[page_index..page_index+4]
+                .copy_from_slice(&source[source_index..source_index+4]);
+        }
+    }
+}
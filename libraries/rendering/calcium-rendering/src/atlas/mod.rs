@@ -0,0 +1,5 @@
+mod atlas;
+mod packer;
+
+pub use self::atlas::{AtlasBuilder, Atlas, AtlasRegion, AtlasImage, AtlasImageSource};
+pub use self::packer::{SkylinePacker};
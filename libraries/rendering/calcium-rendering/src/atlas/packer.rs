@@ -0,0 +1,169 @@
+use std::cmp::{max};
+
+use cgmath::{Vector2, Point2};
+
+/// Packs rectangles into a fixed size area using the skyline bottom-left algorithm. The skyline is
+/// the top edge of everything packed so far, new rectangles are placed on it as low as possible.
+pub struct SkylinePacker {
+    size: Vector2<u32>,
+    /// Segments of the skyline sorted by X, together always covering the full width.
+    skyline: Vec<SkylineSegment>,
+}
+
+struct SkylineSegment {
+    x: u32,
+    y: u32,
+    width: u32,
+}
+
+impl SkylinePacker {
+    pub fn new(size: Vector2<u32>) -> Self {
+        SkylinePacker {
+            size,
+            skyline: vec!(SkylineSegment { x: 0, y: 0, width: size.x }),
+        }
+    }
+
+    pub fn size(&self) -> Vector2<u32> {
+        self.size
+    }
+
+    /// Finds a position for a rectangle of the given size and marks that area as used. Returns
+    /// None if the rectangle doesn't fit anymore.
+    pub fn pack(&mut self, size: Vector2<u32>) -> Option<Point2<u32>> {
+        // Find the segment we can place the rectangle on as low as possible
+        let mut best: Option<(usize, u32)> = None;
+        for index in 0..self.skyline.len() {
+            if let Some(y) = self.fits_at(index, size) {
+                let better = match best {
+                    Some((_, best_y)) => y < best_y,
+                    None => true,
+                };
+                if better {
+                    best = Some((index, y));
+                }
+            }
+        }
+
+        let (index, y) = match best {
+            Some(value) => value,
+            None => return None,
+        };
+
+        let position = Point2::new(self.skyline[index].x, y);
+        self.add_to_skyline(index, position, size);
+        Some(position)
+    }
+
+    /// Checks if a rectangle fits with its left edge on a segment, returns the Y position it
+    /// would end up at.
+    fn fits_at(&self, index: usize, size: Vector2<u32>) -> Option<u32> {
+        let x = self.skyline[index].x;
+        if x + size.x > self.size.x {
+            return None
+        }
+
+        // The rectangle has to rest on the highest segment it spans
+        let mut y = 0;
+        let mut covered = 0;
+        let mut current = index;
+        while covered < size.x {
+            y = max(y, self.skyline[current].y);
+            if y + size.y > self.size.y {
+                return None
+            }
+
+            covered += self.skyline[current].width;
+            current += 1;
+        }
+
+        Some(y)
+    }
+
+    fn add_to_skyline(&mut self, index: usize, position: Point2<u32>, size: Vector2<u32>) {
+        self.skyline.insert(index, SkylineSegment {
+            x: position.x,
+            y: position.y + size.y,
+            width: size.x,
+        });
+
+        // Remove or shrink the segments the new segment now covers
+        let end = position.x + size.x;
+        let current = index + 1;
+        while current < self.skyline.len() {
+            let segment_end = self.skyline[current].x + self.skyline[current].width;
+            if segment_end <= end {
+                self.skyline.remove(current);
+            } else {
+                if self.skyline[current].x < end {
+                    self.skyline[current].width = segment_end - end;
+                    self.skyline[current].x = end;
+                }
+                break
+            }
+        }
+
+        // Merge neighboring segments at the same height
+        let mut current = 0;
+        while current + 1 < self.skyline.len() {
+            if self.skyline[current].y == self.skyline[current + 1].y {
+                self.skyline[current].width += self.skyline[current + 1].width;
+                self.skyline.remove(current + 1);
+            } else {
+                current += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{Vector2, Point2};
+    use super::{SkylinePacker};
+
+    #[test]
+    fn packs_next_to_each_other() {
+        let mut packer = SkylinePacker::new(Vector2::new(8, 8));
+
+        assert_eq!(packer.pack(Vector2::new(4, 2)), Some(Point2::new(0, 0)));
+        assert_eq!(packer.pack(Vector2::new(4, 4)), Some(Point2::new(4, 0)));
+        assert_eq!(packer.pack(Vector2::new(4, 1)), Some(Point2::new(0, 2)));
+    }
+
+    #[test]
+    fn rests_on_highest_spanned_segment() {
+        let mut packer = SkylinePacker::new(Vector2::new(6, 8));
+        packer.pack(Vector2::new(2, 4));
+        packer.pack(Vector2::new(2, 1));
+
+        // At X 0 it would rest on the first rectangle at Y 4, at X 2 on the second at Y 1
+        assert_eq!(packer.pack(Vector2::new(4, 1)), Some(Point2::new(2, 1)));
+    }
+
+    #[test]
+    fn returns_none_when_full() {
+        let mut packer = SkylinePacker::new(Vector2::new(8, 8));
+
+        assert_eq!(packer.pack(Vector2::new(8, 8)), Some(Point2::new(0, 0)));
+        assert_eq!(packer.pack(Vector2::new(1, 1)), None);
+    }
+
+    #[test]
+    fn returns_none_when_too_large() {
+        let mut packer = SkylinePacker::new(Vector2::new(8, 8));
+
+        assert_eq!(packer.pack(Vector2::new(9, 1)), None);
+        assert_eq!(packer.pack(Vector2::new(1, 9)), None);
+        assert_eq!(packer.pack(Vector2::new(8, 8)), Some(Point2::new(0, 0)));
+    }
+
+    #[test]
+    fn fills_area_exactly() {
+        let mut packer = SkylinePacker::new(Vector2::new(4, 4));
+
+        for _ in 0..4 {
+            assert!(packer.pack(Vector2::new(2, 2)).is_some());
+        }
+        assert_eq!(packer.pack(Vector2::new(1, 1)), None);
+    }
+}
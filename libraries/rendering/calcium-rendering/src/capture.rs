@@ -0,0 +1,55 @@
+use std::fs::{File};
+use std::path::{Path};
+
+use cgmath::{Vector2};
+use image::{RgbaImage, ColorType};
+use image::png::{PNGEncoder};
+
+use {Error, CalciumErrorMappable};
+use texture::{self, PixelFormat};
+
+/// Creates an image from captured pixel data. Data in another format than Rgba8 is converted, see
+/// `texture::convert_pixels` for how channels are filled in.
+pub fn image_from_pixels(
+    bytes: &[u8], size: Vector2<u32>, format: PixelFormat,
+) -> Result<RgbaImage, Error> {
+    let expected_length = format.bytes_for(size);
+    if bytes.len() != expected_length {
+        return Err(Error::Unsupported(format!(
+            "Expected {} bytes of captured data, got {}", expected_length, bytes.len()
+        )))
+    }
+
+    let data = texture::convert_pixels(bytes, format, PixelFormat::Rgba8);
+    Ok(RgbaImage::from_raw(size.x, size.y, data).unwrap())
+}
+
+/// Saves a captured image as a PNG file, regardless of the path's extension.
+pub fn save_png<P: AsRef<Path>>(image: &RgbaImage, path: P) -> Result<(), Error> {
+    let file = File::create(path).map_platform_err()?;
+    let (width, height) = image.dimensions();
+    PNGEncoder::new(file).encode(image, width, height, ColorType::RGBA(8)).map_platform_err()
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{Vector2};
+
+    use {Error};
+    use texture::{PixelFormat};
+    use super::{image_from_pixels};
+
+    #[test]
+    fn image_from_pixels_converts_to_rgba8() {
+        let image = image_from_pixels(&[10, 20], Vector2::new(2, 1), PixelFormat::R8).unwrap();
+        assert_eq!(image.get_pixel(1, 0).data, [20, 0, 0, 255]);
+    }
+
+    #[test]
+    fn image_from_pixels_rejects_wrong_length() {
+        match image_from_pixels(&[0; 3], Vector2::new(1, 1), PixelFormat::Rgba8) {
+            Err(Error::Unsupported(_)) => {},
+            other => panic!("Expected an unsupported error, got {:?}", other.map(|_| ())),
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use std::fmt::{self, Display, Formatter};
 use std::error;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Error {
     Platform(String),
     Unsupported(String),
@@ -1,13 +1,24 @@
 extern crate cgmath;
-//#[macro_use]
+extern crate image;
+#[macro_use]
 extern crate slog;
+extern crate screenmath;
 
+pub mod atlas;
 pub mod raw;
 pub mod texture;
+mod capture;
 mod error;
 mod renderer;
+mod settings;
+mod stats;
 mod viewport;
+mod window;
 
+pub use capture::{image_from_pixels, save_png};
 pub use error::{Error, CalciumErrorMappable};
 pub use renderer::{Renderer, Frame};
+pub use settings::{RendererSettings, VsyncMode};
+pub use stats::{FrameStats};
 pub use viewport::{Viewport};
+pub use window::{WindowId};
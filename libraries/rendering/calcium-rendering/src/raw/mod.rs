@@ -1,9 +1,11 @@
 use std::any::{Any};
 
 use cgmath::{Vector2};
+use image::{RgbaImage};
+use slog::{Logger};
 
-use {Error, Frame, Renderer};
-use texture::{TextureBuilder};
+use {Error, Frame, Renderer, WindowId, RendererSettings};
+use texture::{TextureBuilder, PixelFormat};
 
 /// This trait is meant for internal usage, it allows backends to access the raw data behind high
 /// level types.
@@ -16,13 +18,44 @@ pub trait RendererRaw: Any + Sized {
     type FrameRaw: Any;
     type TextureRaw: TextureRaw<Self> + Any + Send + Sync;
 
+    /// The size of the main window.
     fn size(&self) -> Vector2<u32>;
 
+    /// The size of a window, or None if the renderer doesn't have that window.
+    fn window_size(&self, window: WindowId) -> Option<Vector2<u32>>;
+
+    /// The settings currently in use.
+    fn settings(&self) -> &RendererSettings;
+
+    /// Changes the settings, taking effect from the next frame. Settings that can't be honored
+    /// fall back to what the backend supports, which is logged.
+    fn set_settings(&mut self, settings: RendererSettings, log: &Logger);
+
+    /// Starts a frame for the main window.
     fn start_frame(&mut self) -> Frame<Self>;
+
+    /// Starts a frame for any of the renderer's windows.
+    fn start_window_frame(&mut self, window: WindowId) -> Result<Frame<Self>, Error>;
+
+    /// Finishes a frame, presenting it on the window it was started for.
     fn finish_frame(&mut self, frame: Frame<Self>);
+
+    /// Reads back what has been rendered to the frame so far. This waits for all rendering
+    /// submitted to the frame to finish.
+    fn capture_frame(&mut self, frame: &mut Frame<Self>) -> Result<RgbaImage, Error>;
 }
 
 pub trait TextureRaw<R: RendererRaw>: Sized {
     fn new(builder: TextureBuilder<R>, renderer: &mut Renderer<R>) -> Result<Self, Error>;
     fn size(&self) -> Vector2<u32>;
+
+    /// Overwrites a region of the texture's pixels. The region has already been checked to be
+    /// within the texture, and the bytes to match the region's size.
+    fn update_region(
+        &self, offset: Vector2<u32>, size: Vector2<u32>, bytes: &[u8], format: PixelFormat,
+        renderer: &mut Renderer<R>,
+    ) -> Result<(), Error>;
+
+    /// Reads back the texture's pixels. This waits for all rendering to the texture to finish.
+    fn capture(&self, renderer: &mut Renderer<R>) -> Result<RgbaImage, Error>;
 }
@@ -1,11 +1,21 @@
+use std::path::{Path};
+use std::sync::{Arc};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
 use slog::{Logger};
 use cgmath::{Vector2};
+use image::{RgbaImage};
 
+use {Error, WindowId, FrameStats, RendererSettings};
 use raw::{RendererRaw, RawAccess};
 
 pub struct Renderer<R: RendererRaw> {
     raw: R,
     log: Logger,
+
+    stats: FrameStats,
+    last_frame_stats: FrameStats,
+    textures_alive: Arc<AtomicUsize>,
 }
 
 impl<R: RendererRaw> Renderer<R> {
@@ -13,6 +23,10 @@ impl<R: RendererRaw> Renderer<R> {
         Renderer {
             raw,
             log,
+
+            stats: FrameStats::default(),
+            last_frame_stats: FrameStats::default(),
+            textures_alive: Arc::new(AtomicUsize::new(0)),
         }
     }
 
@@ -25,12 +39,71 @@ impl<R: RendererRaw> Renderer<R> {
         self.raw.size()
     }
 
+    /// The size of a window, or None if the renderer doesn't have that window.
+    pub fn window_size(&self, window: WindowId) -> Option<Vector2<u32>> {
+        self.raw.window_size(window)
+    }
+
+    /// The settings currently in use.
+    pub fn settings(&self) -> &RendererSettings {
+        self.raw.settings()
+    }
+
+    /// Changes the settings, taking effect from the next frame. Settings that can't be honored
+    /// fall back to what the backend supports, which is logged.
+    pub fn set_settings(&mut self, settings: RendererSettings) {
+        info!(self.log, "Changing renderer settings"; "settings" => format!("{:?}", settings));
+        self.raw.set_settings(settings, &self.log);
+    }
+
+    /// Starts a frame for the main window.
     pub fn start_frame(&mut self) -> Frame<R> {
         self.raw.start_frame()
     }
 
+    /// Starts a frame for any of the renderer's windows. Window render targets render to the
+    /// window of the frame they're used with.
+    pub fn start_window_frame(&mut self, window: WindowId) -> Result<Frame<R>, Error> {
+        self.raw.start_window_frame(window)
+    }
+
     pub fn finish_frame(&mut self, frame: Frame<R>) {
-        self.raw.finish_frame(frame)
+        self.raw.finish_frame(frame);
+
+        // Everything counted since the previous finished frame belongs to this frame
+        let mut stats = ::std::mem::replace(&mut self.stats, FrameStats::default());
+        stats.textures_alive = self.textures_alive.load(Ordering::SeqCst);
+        self.last_frame_stats = stats;
+    }
+
+    /// Gets the stats of the last finished frame.
+    pub fn frame_stats(&self) -> &FrameStats {
+        &self.last_frame_stats
+    }
+
+    /// Gets the stats of the frame currently being built, for backends and renderers to count
+    /// their work into. Work done outside of a frame, like creating textures, is counted towards
+    /// the next finished frame.
+    pub fn stats_mut(&mut self) -> &mut FrameStats {
+        &mut self.stats
+    }
+
+    pub(crate) fn textures_alive(&self) -> &Arc<AtomicUsize> {
+        &self.textures_alive
+    }
+
+    /// Captures what has been rendered to the frame so far. To capture the finished frame, call
+    /// this after all rendering and right before `finish_frame`.
+    pub fn capture_frame(&mut self, frame: &mut Frame<R>) -> Result<RgbaImage, Error> {
+        self.raw.capture_frame(frame)
+    }
+
+    /// Captures what has been rendered to the frame so far, and saves it as a PNG file.
+    pub fn save_frame_png<P: AsRef<Path>>(
+        &mut self, frame: &mut Frame<R>, path: P,
+    ) -> Result<(), Error> {
+        let image = self.capture_frame(frame)?;
+        ::save_png(&image, path)
     }
 }
 
@@ -42,14 +115,21 @@ impl<R: RendererRaw> RawAccess<R> for Renderer<R> {
 
 pub struct Frame<R: RendererRaw> {
     raw: R::FrameRaw,
+    window: WindowId,
 }
 
 impl<R: RendererRaw> Frame<R> {
-    pub fn raw_new(raw: R::FrameRaw) -> Self {
+    pub fn raw_new(raw: R::FrameRaw, window: WindowId) -> Self {
         Frame {
             raw,
+            window,
         }
     }
+
+    /// The window this frame will be presented on.
+    pub fn window(&self) -> WindowId {
+        self.window
+    }
 }
 
 impl<R: RendererRaw> RawAccess<R::FrameRaw> for Frame<R> {
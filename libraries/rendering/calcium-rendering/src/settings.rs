@@ -0,0 +1,57 @@
+/// Settings for how a renderer presents frames. Backends fall back to the closest thing they
+/// support when a setting can't be honored, and log when they do.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RendererSettings {
+    /// How presenting frames should be synchronized with the display. Defaults to On.
+    pub vsync: VsyncMode,
+
+    /// How many images the window's swapchain should have, or None to let the backend decide.
+    /// More images can give smoother frame rates at the cost of latency. Defaults to None.
+    pub image_count: Option<u32>,
+
+    /// If the window's framebuffer should be sRGB, converting linear values written to it.
+    /// Defaults to true.
+    pub srgb: bool,
+}
+
+impl RendererSettings {
+    pub fn new() -> Self {
+        RendererSettings {
+            vsync: VsyncMode::On,
+            image_count: None,
+            srgb: true,
+        }
+    }
+
+    pub fn with_vsync(mut self, value: VsyncMode) -> Self {
+        self.vsync = value;
+        self
+    }
+
+    pub fn with_image_count(mut self, value: u32) -> Self {
+        self.image_count = Some(value);
+        self
+    }
+
+    pub fn with_srgb(mut self, value: bool) -> Self {
+        self.srgb = value;
+        self
+    }
+}
+
+impl Default for RendererSettings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VsyncMode {
+    /// Frames are presented in sync with the display, never tearing.
+    On,
+    /// Frames are presented as soon as they're done, which may tear.
+    Off,
+    /// Frames are presented in sync with the display, unless they're late, then they're presented
+    /// right away which may tear.
+    Adaptive,
+}
@@ -0,0 +1,33 @@
+use std::time::{Duration};
+
+/// Counters of the work done for a frame. Backends and the renderers built on top of them count
+/// their work into the stats of the frame currently being built.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FrameStats {
+    /// The amount of 2D render sets rendered.
+    pub render_sets: usize,
+
+    /// The amount of 2D render batches rendered.
+    pub batches: usize,
+
+    /// The amount of draw calls submitted by the backend.
+    pub draw_calls: usize,
+
+    /// The amount of vertices submitted for rendering.
+    pub vertices: usize,
+
+    /// The amount of textures created.
+    pub textures_created: usize,
+
+    /// The amount of textures that still existed when the frame was finished.
+    pub textures_alive: usize,
+
+    /// The amount of bytes uploaded to buffers and textures.
+    pub bytes_uploaded: usize,
+
+    /// The CPU time spent in 2D rendering.
+    pub render_2d_time: Duration,
+
+    /// The CPU time spent in 3D rendering.
+    pub render_3d_time: Duration,
+}
@@ -0,0 +1,310 @@
+use std::fs::{File};
+use std::io::{BufReader};
+use std::path::{Path};
+
+use cgmath::{Vector2};
+use image::{self};
+use image::hdr::{HDRDecoder};
+
+use {Error, CalciumErrorMappable};
+use texture::{PixelFormat, TextureStoreFormat};
+
+/// Loads an image file, returning its pixels in the format closest to the file's own. Radiance
+/// HDR files are loaded as Rgba32F, everything else as Rgba8.
+pub fn load_file(path: &Path) -> Result<(Vec<u8>, Vector2<u32>, PixelFormat), Error> {
+    let is_hdr = path.extension()
+        .map(|e| e.to_string_lossy().to_lowercase() == "hdr")
+        .unwrap_or(false);
+
+    if is_hdr {
+        let file = File::open(path).map_platform_err()?;
+        let decoder = HDRDecoder::new(BufReader::new(file)).map_platform_err()?;
+        let metadata = decoder.metadata();
+        let pixels: Vec<[f32; 4]> = decoder.read_image_hdr().map_platform_err()?
+            .into_iter()
+            .map(|p| [p.data[0], p.data[1], p.data[2], 1.0])
+            .collect();
+
+        let size = Vector2::new(metadata.width, metadata.height);
+        Ok((encode_pixels(&pixels, PixelFormat::Rgba32F), size, PixelFormat::Rgba32F))
+    } else {
+        let image = image::open(path).map_platform_err()?.to_rgba();
+        let (width, height) = image.dimensions();
+        Ok((image.into_raw(), Vector2::new(width, height), PixelFormat::Rgba8))
+    }
+}
+
+/// Converts pixel data from one format to another. Values are converted per channel without
+/// changing color space. Channels missing from the source are filled in as 0, except for alpha
+/// which is filled in as 1. Channels missing from the target are dropped, so a single channel
+/// target gets the source's red channel.
+pub fn convert_pixels(bytes: &[u8], from: PixelFormat, to: PixelFormat) -> Vec<u8> {
+    if from == to {
+        return bytes.to_vec()
+    }
+
+    encode_pixels(&decode_pixels(bytes, from), to)
+}
+
+/// Multiplies the color channels of pixel data in a store format's pixel format by their alpha.
+/// Srgb data is multiplied in linear color space, so it blends the same as linear data. Formats
+/// without an alpha channel are returned as-is.
+pub fn premultiply_alpha(bytes: &[u8], store_format: TextureStoreFormat) -> Vec<u8> {
+    let format = store_format.pixel_format();
+    if format.channels() != 4 {
+        return bytes.to_vec()
+    }
+
+    let srgb = store_format == TextureStoreFormat::Srgb;
+    let pixels: Vec<[f32; 4]> = decode_pixels(bytes, format).into_iter().map(|p| {
+        let mut value = p;
+        for channel in 0..3 {
+            value[channel] = if srgb {
+                linear_to_srgb(srgb_to_linear(p[channel]) * p[3])
+            } else {
+                p[channel] * p[3]
+            };
+        }
+        value
+    }).collect();
+
+    encode_pixels(&pixels, format)
+}
+
+/// Decodes pixel data into RGBA float values.
+pub fn decode_pixels(bytes: &[u8], format: PixelFormat) -> Vec<[f32; 4]> {
+    let channels = format.channels();
+    let channel_bytes = format.bytes_per_channel();
+
+    bytes.chunks(format.bytes_per_pixel()).map(|pixel| {
+        let mut value = [0.0, 0.0, 0.0, 1.0];
+        for channel in 0..channels {
+            let start = channel * channel_bytes;
+            value[channel] = decode_channel(&pixel[start..start+channel_bytes], format);
+        }
+        value
+    }).collect()
+}
+
+/// Encodes RGBA float values into pixel data. Normalized formats are clamped to 0 to 1.
+pub fn encode_pixels(pixels: &[[f32; 4]], format: PixelFormat) -> Vec<u8> {
+    let channels = format.channels();
+    let mut bytes = Vec::with_capacity(pixels.len() * format.bytes_per_pixel());
+
+    for pixel in pixels {
+        for channel in 0..channels {
+            encode_channel(pixel[channel], format, &mut bytes);
+        }
+    }
+
+    bytes
+}
+
+fn decode_channel(bytes: &[u8], format: PixelFormat) -> f32 {
+    match format {
+        PixelFormat::R8 | PixelFormat::Rg8 | PixelFormat::Rgba8 =>
+            bytes[0] as f32 / 255.0,
+        PixelFormat::R16 | PixelFormat::Rgba16 =>
+            read_u16(bytes) as f32 / 65535.0,
+        PixelFormat::Rgba16F =>
+            f16_to_f32(read_u16(bytes)),
+        PixelFormat::R32F | PixelFormat::Rgba32F =>
+            f32::from_bits(
+                bytes[0] as u32 | (bytes[1] as u32) << 8 |
+                (bytes[2] as u32) << 16 | (bytes[3] as u32) << 24
+            ),
+    }
+}
+
+fn encode_channel(value: f32, format: PixelFormat, bytes: &mut Vec<u8>) {
+    match format {
+        PixelFormat::R8 | PixelFormat::Rg8 | PixelFormat::Rgba8 =>
+            bytes.push((value.max(0.0).min(1.0) * 255.0).round() as u8),
+        PixelFormat::R16 | PixelFormat::Rgba16 =>
+            write_u16((value.max(0.0).min(1.0) * 65535.0).round() as u16, bytes),
+        PixelFormat::Rgba16F =>
+            write_u16(f32_to_f16(value), bytes),
+        PixelFormat::R32F | PixelFormat::Rgba32F => {
+            let bits = value.to_bits();
+            bytes.extend_from_slice(&[
+                bits as u8, (bits >> 8) as u8, (bits >> 16) as u8, (bits >> 24) as u8
+            ]);
+        },
+    }
+}
+
+fn read_u16(bytes: &[u8]) -> u16 {
+    bytes[0] as u16 | (bytes[1] as u16) << 8
+}
+
+fn write_u16(value: u16, bytes: &mut Vec<u8>) {
+    bytes.push(value as u8);
+    bytes.push((value >> 8) as u8);
+}
+
+fn f16_to_f32(value: u16) -> f32 {
+    let sign = if value & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((value >> 10) & 0x1f) as i32;
+    let mantissa = (value & 0x3ff) as f32;
+
+    let magnitude = match exponent {
+        // Subnormal values
+        0 => mantissa * 2.0f32.powi(-24),
+        0x1f => if mantissa == 0.0 { ::std::f32::INFINITY } else { ::std::f32::NAN },
+        _ => (1.0 + mantissa / 1024.0) * 2.0f32.powi(exponent - 15),
+    };
+
+    sign * magnitude
+}
+
+fn f32_to_f16(value: f32) -> u16 {
+    if value.is_nan() {
+        return 0x7e00
+    }
+
+    let sign = if value.is_sign_negative() { 0x8000 } else { 0 };
+    let magnitude = value.abs();
+
+    if magnitude >= 65520.0 {
+        // Too large to represent, this rounds to infinity
+        sign | 0x7c00
+    } else if magnitude < 2.0f32.powi(-14) {
+        // Subnormal values, rounding up to the smallest normal value gives the right bits as well
+        sign | (magnitude / 2.0f32.powi(-24)).round() as u16
+    } else {
+        let bits = magnitude.to_bits();
+        let exponent = ((bits >> 23) & 0xff) as i32 - 127 + 15;
+        let mantissa = bits & 0x7f_ffff;
+
+        // Round the mantissa to 10 bits, a carry correctly bumps the exponent
+        let rounded = ((exponent as u32) << 10) + ((mantissa + 0x1000) >> 13);
+        sign | rounded as u16
+    }
+}
+
+/// Converts a single sRGB encoded color channel in the 0.0 to 1.0 range to linear color space.
+pub fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Converts a single linear color channel in the 0.0 to 1.0 range to sRGB encoding.
+pub fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use texture::{PixelFormat, TextureStoreFormat};
+    use super::{
+        convert_pixels, decode_pixels, encode_pixels, premultiply_alpha, f16_to_f32, f32_to_f16,
+    };
+
+    #[test]
+    fn converts_between_channel_counts() {
+        // Missing color channels become 0 and missing alpha 1, extra channels are dropped
+        assert_eq!(
+            convert_pixels(&[10, 20], PixelFormat::R8, PixelFormat::Rgba8),
+            vec!(10, 0, 0, 255,  20, 0, 0, 255)
+        );
+        assert_eq!(
+            convert_pixels(&[10, 20, 30, 40], PixelFormat::Rgba8, PixelFormat::Rg8),
+            vec!(10, 20)
+        );
+        assert_eq!(
+            convert_pixels(&[10, 20, 30, 40], PixelFormat::Rgba8, PixelFormat::R8),
+            vec!(10)
+        );
+    }
+
+    #[test]
+    fn converts_between_precisions() {
+        assert_eq!(
+            convert_pixels(&[255, 0], PixelFormat::R8, PixelFormat::R16), vec!(255, 255, 0, 0)
+        );
+        assert_eq!(convert_pixels(&[0x00, 0x80], PixelFormat::R16, PixelFormat::R8), vec!(128));
+
+        let floats = convert_pixels(&[0, 51, 255, 255], PixelFormat::Rgba8, PixelFormat::Rgba32F);
+        assert_eq!(decode_pixels(&floats, PixelFormat::Rgba32F), vec!([0.0, 0.2, 1.0, 1.0]));
+    }
+
+    #[test]
+    fn encoding_clamps_normalized_formats() {
+        let pixels = [[-1.0, 0.5, 2.0, 1.0]];
+        assert_eq!(encode_pixels(&pixels, PixelFormat::Rgba8), vec!(0, 128, 255, 255));
+        assert_eq!(
+            decode_pixels(&encode_pixels(&pixels, PixelFormat::Rgba32F), PixelFormat::Rgba32F),
+            pixels.to_vec()
+        );
+    }
+
+    #[test]
+    fn decode_and_encode_round_trip() {
+        let formats = [
+            PixelFormat::R8, PixelFormat::Rg8, PixelFormat::Rgba8, PixelFormat::R16,
+            PixelFormat::Rgba16, PixelFormat::Rgba16F, PixelFormat::R32F, PixelFormat::Rgba32F,
+        ];
+
+        for format in &formats {
+            let bytes: Vec<u8> = (0..format.bytes_per_pixel() * 2)
+                .map(|i| (i * 7 % 64) as u8)
+                .collect();
+            let decoded = decode_pixels(&bytes, *format);
+            assert_eq!(decoded.len(), 2, "{:?}", format);
+            assert_eq!(encode_pixels(&decoded, *format), bytes, "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn f16_values() {
+        let values = [
+            (0.0, 0x0000), (-0.0, 0x8000), (1.0, 0x3c00), (-2.0, 0xc000), (0.5, 0x3800),
+            (65504.0, 0x7bff),
+            // The smallest normal and subnormal values
+            (6.1035156e-5, 0x0400), (5.9604645e-8, 0x0001),
+        ];
+        for &(value, bits) in &values {
+            assert_eq!(f32_to_f16(value), bits, "{}", value);
+            assert_eq!(f16_to_f32(bits), value, "{:x}", bits);
+        }
+    }
+
+    #[test]
+    fn f16_special_values() {
+        assert_eq!(f32_to_f16(100000.0), 0x7c00);
+        assert_eq!(f32_to_f16(-100000.0), 0xfc00);
+        assert_eq!(f16_to_f32(0x7c00), ::std::f32::INFINITY);
+        assert!(f16_to_f32(f32_to_f16(::std::f32::NAN)).is_nan());
+
+        // Rounds to the nearest value, 1 + 1/2048 is halfway and rounds up
+        assert_eq!(f32_to_f16(1.0 + 1.0 / 4096.0), 0x3c00);
+        assert_eq!(f32_to_f16(1.0 + 3.0 / 4096.0), 0x3c01);
+    }
+
+    #[test]
+    fn premultiplies_linear_data() {
+        let bytes = vec!(255, 128, 0, 128,  10, 20, 30, 255);
+        let premultiplied = premultiply_alpha(&bytes, TextureStoreFormat::Linear);
+        assert_eq!(premultiplied, vec!(128, 64, 0, 128,  10, 20, 30, 255));
+    }
+
+    #[test]
+    fn premultiplies_srgb_data_in_linear_space() {
+        let bytes = vec!(255, 255, 255, 128,  255, 0, 0, 0);
+        let premultiplied = premultiply_alpha(&bytes, TextureStoreFormat::Srgb);
+        assert_eq!(premultiplied, vec!(188, 188, 188, 128,  0, 0, 0, 0));
+    }
+
+    #[test]
+    fn keeps_data_without_alpha() {
+        let bytes = vec!(255, 128, 0);
+        assert_eq!(premultiply_alpha(&bytes, TextureStoreFormat::SingleChannel), bytes);
+    }
+}
@@ -0,0 +1,270 @@
+use std::collections::{HashMap};
+use std::panic;
+use std::path::{PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::sync::mpsc::{self, Sender, Receiver};
+use std::thread;
+
+use cgmath::{Vector2};
+
+use {Error, Renderer};
+use raw::{RendererRaw};
+use texture::{self, Texture, TextureBuilder, TextureSource, PixelFormat};
+
+const DEFAULT_WORKERS: usize = 2;
+
+type LoadJob = (usize, PathBuf);
+type LoadResult = (usize, Result<(Vec<u8>, Vector2<u32>, PixelFormat), Error>);
+
+/// Loads textures from files in the background. Files are decoded on worker threads, and the
+/// decoded textures are created on the renderer when `poll` is called. Until then, handles give
+/// out a placeholder texture instead.
+pub struct TextureLoader<R: RendererRaw> {
+    placeholder: Arc<Texture<R>>,
+    job_sender: Sender<LoadJob>,
+    result_sender: Sender<LoadResult>,
+    result_receiver: Receiver<LoadResult>,
+    pending: HashMap<usize, PendingTexture<R>>,
+    next_id: usize,
+}
+
+struct PendingTexture<R: RendererRaw> {
+    builder: TextureBuilder<'static, R>,
+    path: PathBuf,
+    handle: TextureHandle<R>,
+}
+
+impl<R: RendererRaw> TextureLoader<R> {
+    /// Creates a new loader with the default amount of worker threads.
+    pub fn new(placeholder: Arc<Texture<R>>) -> Self {
+        Self::with_workers(placeholder, DEFAULT_WORKERS)
+    }
+
+    pub fn with_workers(placeholder: Arc<Texture<R>>, workers: usize) -> Self {
+        let (job_sender, job_receiver) = mpsc::channel::<LoadJob>();
+        let (result_sender, result_receiver) = mpsc::channel();
+
+        // All workers take jobs from the same queue, they stop once the loader is dropped
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+        for _ in 0..workers.max(1) {
+            let job_receiver = job_receiver.clone();
+            let result_sender = result_sender.clone();
+            thread::spawn(move || {
+                loop {
+                    // The lock only guards taking a job from the queue, a panic while holding it
+                    //  can't leave the receiver in a bad state, so a poisoned lock is still usable
+                    let job = job_receiver.lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner())
+                        .recv();
+                    let (id, path) = match job {
+                        Ok(job) => job,
+                        Err(_) => break,
+                    };
+
+                    // A panicking decoder shouldn't take the worker down with it, or the texture
+                    //  would never finish loading
+                    let result = panic::catch_unwind(|| texture::load_file(&path))
+                        .unwrap_or_else(|_| Err(Error::Platform(format!(
+                            "Decoding texture \"{}\" panicked", path.display()
+                        ))));
+                    if result_sender.send((id, result)).is_err() {
+                        break
+                    }
+                }
+            });
+        }
+
+        TextureLoader {
+            placeholder,
+            job_sender,
+            result_sender,
+            result_receiver,
+            pending: HashMap::new(),
+            next_id: 0,
+        }
+    }
+
+    /// The texture new handles give out while they're loading.
+    pub fn placeholder(&self) -> &Arc<Texture<R>> {
+        &self.placeholder
+    }
+
+    pub fn set_placeholder(&mut self, placeholder: Arc<Texture<R>>) {
+        self.placeholder = placeholder;
+    }
+
+    /// The amount of textures that haven't been created yet.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Starts loading a texture in the background. Builders with a file source are decoded on a
+    /// worker thread, builders with a bytes source are created on the next `poll`.
+    pub fn load(&mut self, builder: TextureBuilder<'static, R>) -> TextureHandle<R> {
+        let placeholder = self.placeholder.clone();
+        self.load_with_placeholder(builder, placeholder)
+    }
+
+    /// Starts loading a texture in the background, using a different placeholder than the
+    /// loader's own until it's done.
+    pub fn load_with_placeholder(
+        &mut self, builder: TextureBuilder<'static, R>, placeholder: Arc<Texture<R>>,
+    ) -> TextureHandle<R> {
+        let handle = TextureHandle {
+            state: Arc::new(RwLock::new(TextureHandleState::Loading(placeholder))),
+        };
+
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let path = match builder.source {
+            TextureSource::File(ref path) => {
+                // If all workers are gone, report that through the same channel the workers would
+                if self.job_sender.send((id, path.clone())).is_err() {
+                    let error = Error::Platform("Texture loader workers have stopped".into());
+                    self.result_sender.send((id, Err(error))).unwrap();
+                }
+                path.clone()
+            },
+            TextureSource::Bytes { .. } => PathBuf::new(),
+        };
+
+        self.pending.insert(id, PendingTexture {
+            builder,
+            path,
+            handle: handle.clone(),
+        });
+
+        handle
+    }
+
+    /// Creates all textures that have finished decoding, and resolves their handles. Returns the
+    /// paths of the textures that failed to load this poll, together with why. Failed handles
+    /// keep giving out their placeholder.
+    pub fn poll(&mut self, renderer: &mut Renderer<R>) -> Vec<(PathBuf, Error)> {
+        let mut decoded = Vec::new();
+        while let Ok(result) = self.result_receiver.try_recv() {
+            decoded.push(result);
+        }
+
+        // Bytes sources don't need decoding, they're ready right away
+        let ready_bytes: Vec<usize> = self.pending.iter()
+            .filter(|&(_, p)| if let TextureSource::Bytes { .. } = p.builder.source {
+                true
+            } else {
+                false
+            })
+            .map(|(id, _)| *id)
+            .collect();
+
+        let mut failures = Vec::new();
+        for id in ready_bytes {
+            let pending = self.pending.remove(&id).unwrap();
+            let result = pending.builder.build(renderer);
+            Self::resolve(&pending.handle, pending.path, result, renderer, &mut failures);
+        }
+
+        for (id, result) in decoded {
+            let PendingTexture { mut builder, path, handle } = match self.pending.remove(&id) {
+                Some(pending) => pending,
+                None => continue,
+            };
+
+            let result = result.and_then(|(bytes, size, format)| {
+                builder.source = TextureSource::Bytes {
+                    bytes: bytes.into(),
+                    size,
+                    format,
+                };
+                builder.build(renderer)
+            });
+            Self::resolve(&handle, path, result, renderer, &mut failures);
+        }
+
+        failures
+    }
+
+    fn resolve(
+        handle: &TextureHandle<R>, path: PathBuf, result: Result<Arc<Texture<R>>, Error>,
+        renderer: &Renderer<R>, failures: &mut Vec<(PathBuf, Error)>,
+    ) {
+        let mut state = handle.state.write().unwrap();
+        let new_state = match result {
+            Ok(texture) => {
+                info!(renderer.log(),
+                    "Finished loading texture"; "path" => path.display().to_string()
+                );
+                TextureHandleState::Loaded(texture)
+            },
+            Err(error) => {
+                warn!(renderer.log(),
+                    "Failed to load texture";
+                    "path" => path.display().to_string(), "error" => error.to_string()
+                );
+                failures.push((path, error.clone()));
+                TextureHandleState::Failed(state.texture(), error)
+            },
+        };
+        *state = new_state;
+    }
+}
+
+/// A texture that may still be loading. Until it's done, the placeholder texture is given out.
+pub struct TextureHandle<R: RendererRaw> {
+    state: Arc<RwLock<TextureHandleState<R>>>,
+}
+
+enum TextureHandleState<R: RendererRaw> {
+    Loading(Arc<Texture<R>>),
+    Loaded(Arc<Texture<R>>),
+    Failed(Arc<Texture<R>>, Error),
+}
+
+impl<R: RendererRaw> TextureHandleState<R> {
+    fn texture(&self) -> Arc<Texture<R>> {
+        match *self {
+            TextureHandleState::Loading(ref texture) |
+            TextureHandleState::Loaded(ref texture) |
+            TextureHandleState::Failed(ref texture, _) => texture.clone(),
+        }
+    }
+}
+
+impl<R: RendererRaw> TextureHandle<R> {
+    /// Creates a handle that's already been loaded.
+    pub fn loaded(texture: Arc<Texture<R>>) -> Self {
+        TextureHandle {
+            state: Arc::new(RwLock::new(TextureHandleState::Loaded(texture))),
+        }
+    }
+
+    /// Gets the loaded texture, or the placeholder if it hasn't been loaded.
+    pub fn texture(&self) -> Arc<Texture<R>> {
+        self.state.read().unwrap().texture()
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        if let TextureHandleState::Loaded(_) = *self.state.read().unwrap() {
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Gets the reason this texture failed to load, if it did.
+    pub fn error(&self) -> Option<Error> {
+        if let TextureHandleState::Failed(_, ref error) = *self.state.read().unwrap() {
+            Some(error.clone())
+        } else {
+            None
+        }
+    }
+}
+
+impl<R: RendererRaw> Clone for TextureHandle<R> {
+    fn clone(&self) -> Self {
+        TextureHandle {
+            state: self.state.clone(),
+        }
+    }
+}
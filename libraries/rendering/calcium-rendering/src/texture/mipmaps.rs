@@ -0,0 +1,174 @@
+use cgmath::{Vector2};
+
+use texture::{TextureStoreFormat, decode_pixels, encode_pixels};
+use texture::conversion::{srgb_to_linear, linear_to_srgb};
+
+/// A single level of a mipmap chain.
+pub struct MipLevel {
+    pub bytes: Vec<u8>,
+    pub size: Vector2<u32>,
+}
+
+/// The amount of levels a full mipmap chain for a texture of the given size has, including the
+/// base level.
+pub fn mip_level_count(size: Vector2<u32>) -> u32 {
+    let largest = size.x.max(size.y).max(1);
+    32 - largest.leading_zeros()
+}
+
+/// The size of a level in the mipmap chain of a texture of the given size.
+pub fn mip_level_size(size: Vector2<u32>, level: u32) -> Vector2<u32> {
+    Vector2::new((size.x >> level).max(1), (size.y >> level).max(1))
+}
+
+/// Generates a full mipmap chain on the CPU from pixel data in the store format's pixel format.
+/// The first level is the original data. Every level is a box filtered version of the level
+/// before it. Srgb data is filtered in linear color space, so colors don't get darker with every
+/// level.
+pub fn generate_mipmaps(
+    bytes: &[u8], size: Vector2<u32>, store_format: TextureStoreFormat,
+) -> Vec<MipLevel> {
+    let pixel_format = store_format.pixel_format();
+    let srgb = store_format == TextureStoreFormat::Srgb;
+
+    // Work with linear float values for all levels, converting back at the end of each level
+    let mut pixels = decode_pixels(bytes, pixel_format);
+    if srgb {
+        for pixel in &mut pixels {
+            for channel in 0..3 {
+                pixel[channel] = srgb_to_linear(pixel[channel]);
+            }
+        }
+    }
+
+    let mut levels = vec!(MipLevel { bytes: bytes.to_vec(), size });
+    let mut level_size = size;
+    for level in 1..mip_level_count(size) {
+        let next_size = mip_level_size(size, level);
+        pixels = downsample(&pixels, level_size, next_size);
+        level_size = next_size;
+
+        let bytes = if srgb {
+            let encoded: Vec<[f32; 4]> = pixels.iter()
+                .map(|p| [linear_to_srgb(p[0]), linear_to_srgb(p[1]), linear_to_srgb(p[2]), p[3]])
+                .collect();
+            encode_pixels(&encoded, pixel_format)
+        } else {
+            encode_pixels(&pixels, pixel_format)
+        };
+
+        levels.push(MipLevel { bytes, size: level_size });
+    }
+
+    levels
+}
+
+/// Halves an image using a box filter over the area each target pixel covers. For odd sizes that
+/// area isn't aligned to source pixels, so the edge pixels are weighted by how much of them is
+/// covered instead of being dropped. Axes that are already 1 pixel stay 1 pixel.
+fn downsample(
+    pixels: &[[f32; 4]], size: Vector2<u32>, target_size: Vector2<u32>,
+) -> Vec<[f32; 4]> {
+    let weights_x: Vec<_> = (0..target_size.x)
+        .map(|x| footprint(x, size.x, target_size.x))
+        .collect();
+    let weights_y: Vec<_> = (0..target_size.y)
+        .map(|y| footprint(y, size.y, target_size.y))
+        .collect();
+
+    let mut target = Vec::with_capacity((target_size.x * target_size.y) as usize);
+    for row_weights in &weights_y {
+        for column_weights in &weights_x {
+            let mut value = [0.0; 4];
+            for &(sy, wy) in row_weights {
+                for &(sx, wx) in column_weights {
+                    let sample = pixels[(sy * size.x + sx) as usize];
+                    for channel in 0..4 {
+                        value[channel] += sample[channel] * wx * wy;
+                    }
+                }
+            }
+            target.push(value);
+        }
+    }
+
+    target
+}
+
+/// The source pixels a target pixel covers along one axis, with how much each one contributes.
+fn footprint(target: u32, size: u32, target_size: u32) -> Vec<(u32, f32)> {
+    let scale = size as f32 / target_size as f32;
+    let start = target as f32 * scale;
+    let end = (target + 1) as f32 * scale;
+
+    (start.floor() as u32..(end.ceil() as u32).min(size))
+        .map(|source| {
+            let covered = end.min((source + 1) as f32) - start.max(source as f32);
+            (source, covered / scale)
+        })
+        .filter(|&(_, weight)| weight > 0.0)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{Vector2};
+    use texture::{TextureStoreFormat};
+    use super::{mip_level_count, mip_level_size, generate_mipmaps, downsample};
+
+    #[test]
+    fn level_count_and_sizes() {
+        assert_eq!(mip_level_count(Vector2::new(1, 1)), 1);
+        assert_eq!(mip_level_count(Vector2::new(8, 2)), 4);
+        assert_eq!(mip_level_count(Vector2::new(5, 3)), 3);
+        assert_eq!(mip_level_size(Vector2::new(8, 2), 2), Vector2::new(2, 1));
+        assert_eq!(mip_level_size(Vector2::new(5, 3), 1), Vector2::new(2, 1));
+        assert_eq!(mip_level_size(Vector2::new(5, 3), 2), Vector2::new(1, 1));
+    }
+
+    #[test]
+    fn generates_full_chain() {
+        let bytes = vec!(128u8; 6 * 4 * 4);
+        let levels = generate_mipmaps(&bytes, Vector2::new(6, 4), TextureStoreFormat::Linear);
+
+        let sizes: Vec<_> = levels.iter().map(|l| l.size).collect();
+        assert_eq!(sizes, vec!(Vector2::new(6, 4), Vector2::new(3, 2), Vector2::new(1, 1)));
+        for level in &levels {
+            assert_eq!(level.bytes.len() as u32, level.size.x * level.size.y * 4);
+            assert!(level.bytes.iter().all(|&b| b == 128));
+        }
+    }
+
+    #[test]
+    fn odd_sizes_include_the_last_column() {
+        let pixels = vec!([0.0, 0.0, 0.0, 1.0], [0.0, 0.0, 0.0, 1.0], [1.0, 1.0, 1.0, 1.0]);
+        let target = downsample(&pixels, Vector2::new(3, 1), Vector2::new(1, 1));
+
+        assert!((target[0][0] - 1.0 / 3.0).abs() < 0.0001);
+        assert!((target[0][3] - 1.0).abs() < 0.0001);
+    }
+
+    #[test]
+    fn odd_sizes_weight_shared_pixels() {
+        let pixels = vec!(
+            [1.0, 0.0, 0.0, 1.0], [0.0, 1.0, 0.0, 1.0], [0.0, 0.0, 1.0, 1.0],
+            [0.0, 0.0, 0.0, 1.0], [0.0, 0.0, 0.0, 1.0],
+        );
+        let target = downsample(&pixels, Vector2::new(5, 1), Vector2::new(2, 1));
+
+        // The middle pixel is split evenly between both target pixels
+        assert!((target[0][0] - 0.4).abs() < 0.0001);
+        assert!((target[0][2] - 0.2).abs() < 0.0001);
+        assert!((target[1][2] - 0.2).abs() < 0.0001);
+        assert!((target[1][1]).abs() < 0.0001);
+    }
+
+    #[test]
+    fn srgb_is_filtered_in_linear_space() {
+        let bytes = vec!(0, 0, 0, 255, 255, 255, 255, 255);
+        let levels = generate_mipmaps(&bytes, Vector2::new(2, 1), TextureStoreFormat::Srgb);
+
+        // Half of full white in linear space is 188 in sRGB, not 128
+        assert_eq!(levels[1].bytes, vec!(188, 188, 188, 255));
+    }
+}
@@ -1,14 +1,60 @@
+mod conversion;
+mod loader;
+mod mipmaps;
 mod texture;
+mod watcher;
 
+pub use self::conversion::{
+    load_file, convert_pixels, decode_pixels, encode_pixels, premultiply_alpha, srgb_to_linear,
+    linear_to_srgb,
+};
+pub use self::loader::{TextureLoader, TextureHandle};
+pub use self::mipmaps::{MipLevel, mip_level_count, mip_level_size, generate_mipmaps};
 pub use self::texture::{TextureBuilder, Texture};
+pub use self::watcher::{TextureWatcher};
 
 use std::path::{PathBuf};
 
 use cgmath::{Vector2};
 
+use {Error};
+
 pub enum TextureSource<'a> {
     File(PathBuf),
-    Bytes { bytes: TextureBytes<'a>, size: Vector2<u32>, color: bool },
+    Bytes { bytes: TextureBytes<'a>, size: Vector2<u32>, format: PixelFormat },
+}
+
+impl<'a> TextureSource<'a> {
+    /// Gets the size of this source's pixels, without converting them. Files still have to be
+    /// decoded to find out their size.
+    pub fn size(&self) -> Result<Vector2<u32>, Error> {
+        match *self {
+            TextureSource::File(ref path) => Ok(load_file(path)?.1),
+            TextureSource::Bytes { size, .. } => Ok(size),
+        }
+    }
+
+    /// Loads the pixels of this source, converted to the given pixel format. Backends use this
+    /// with their store format's pixel format to get data they can upload directly.
+    pub fn load_as(&self, format: PixelFormat) -> Result<(Vec<u8>, Vector2<u32>), Error> {
+        match *self {
+            TextureSource::File(ref path) => {
+                let (bytes, size, file_format) = load_file(path)?;
+                Ok((convert_pixels(&bytes, file_format, format), size))
+            },
+            TextureSource::Bytes { ref bytes, size, format: source_format } => {
+                let bytes = bytes.as_ref();
+                let expected_length = source_format.bytes_for(size);
+                if bytes.len() != expected_length {
+                    return Err(Error::Unsupported(format!(
+                        "Expected {} bytes of texture data, got {}", expected_length, bytes.len()
+                    )))
+                }
+
+                Ok((convert_pixels(bytes, source_format, format), size))
+            },
+        }
+    }
 }
 
 pub enum TextureBytes<'a> {
@@ -51,6 +97,78 @@ pub enum TextureStoreFormat {
     /// roughness/metallic/ambient occlusion maps. From textures with more than one channel the
     /// red channel will be used. This will be interpreted in linear color space.
     SingleChannel,
+
+    /// Only the red and green channels of this texture will be stored, interpreted in linear
+    /// color space. This is useful for data like flow maps or two-component normal maps.
+    TwoChannel,
+
+    /// Like SingleChannel, but stored with 16 bits of precision. This is useful for height fields.
+    SingleChannel16,
+
+    /// Stores all channels as 16-bit floats in linear color space, allowing values outside of the
+    /// 0 to 1 range. This should be used for HDR data, like environment maps.
+    LinearFloat,
+
+    /// Only a single channel stored as a 32-bit float. This is useful for data textures that need
+    /// full precision.
+    SingleChannelFloat,
+}
+
+impl TextureStoreFormat {
+    /// The pixel format data in this store format is uploaded as.
+    pub fn pixel_format(&self) -> PixelFormat {
+        match *self {
+            TextureStoreFormat::Srgb => PixelFormat::Rgba8,
+            TextureStoreFormat::Linear => PixelFormat::Rgba8,
+            TextureStoreFormat::SingleChannel => PixelFormat::R8,
+            TextureStoreFormat::TwoChannel => PixelFormat::Rg8,
+            TextureStoreFormat::SingleChannel16 => PixelFormat::R16,
+            TextureStoreFormat::LinearFloat => PixelFormat::Rgba16F,
+            TextureStoreFormat::SingleChannelFloat => PixelFormat::R32F,
+        }
+    }
+}
+
+/// The layout of raw pixel data. Values wider than a byte are little endian. Unsigned normalized
+/// formats map their full range to 0 to 1, float formats are used as-is.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PixelFormat {
+    R8,
+    Rg8,
+    Rgba8,
+    R16,
+    Rgba16,
+    Rgba16F,
+    R32F,
+    Rgba32F,
+}
+
+impl PixelFormat {
+    pub fn channels(&self) -> usize {
+        match *self {
+            PixelFormat::R8 | PixelFormat::R16 | PixelFormat::R32F => 1,
+            PixelFormat::Rg8 => 2,
+            PixelFormat::Rgba8 | PixelFormat::Rgba16 | PixelFormat::Rgba16F |
+            PixelFormat::Rgba32F => 4,
+        }
+    }
+
+    pub fn bytes_per_channel(&self) -> usize {
+        match *self {
+            PixelFormat::R8 | PixelFormat::Rg8 | PixelFormat::Rgba8 => 1,
+            PixelFormat::R16 | PixelFormat::Rgba16 | PixelFormat::Rgba16F => 2,
+            PixelFormat::R32F | PixelFormat::Rgba32F => 4,
+        }
+    }
+
+    pub fn bytes_per_pixel(&self) -> usize {
+        self.channels() * self.bytes_per_channel()
+    }
+
+    /// The amount of bytes an image of the given size in this format takes up.
+    pub fn bytes_for(&self, size: Vector2<u32>) -> usize {
+        size.x as usize * size.y as usize * self.bytes_per_pixel()
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -58,3 +176,36 @@ pub enum SampleMode {
     Linear,
     Nearest,
 }
+
+/// What happens when a texture is sampled outside of the 0 to 1 UV range.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WrapMode {
+    /// Uses the closest edge pixel.
+    Clamp,
+    /// Tiles the texture.
+    Repeat,
+    /// Tiles the texture, flipping it every other tile.
+    MirroredRepeat,
+    /// Uses the texture's border color.
+    Border,
+}
+
+/// The color sampled outside of a texture with `WrapMode::Border`. Only a few colors are
+/// supported, as not all backends can use arbitrary border colors.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BorderColor {
+    TransparentBlack,
+    OpaqueBlack,
+    OpaqueWhite,
+}
+
+impl BorderColor {
+    /// The RGBA value of this color.
+    pub fn to_rgba(&self) -> [f32; 4] {
+        match *self {
+            BorderColor::TransparentBlack => [0.0, 0.0, 0.0, 0.0],
+            BorderColor::OpaqueBlack => [0.0, 0.0, 0.0, 1.0],
+            BorderColor::OpaqueWhite => [1.0, 1.0, 1.0, 1.0],
+        }
+    }
+}
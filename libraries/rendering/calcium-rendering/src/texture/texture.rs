@@ -1,11 +1,16 @@
-use std::path::{PathBuf};
+use std::path::{Path, PathBuf};
 use std::sync::{Arc};
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use cgmath::{Vector2};
+use image::{RgbaImage};
 
 use {Error, Renderer};
 use raw::{TextureRaw, RawAccess, RendererRaw};
-use texture::{TextureSource, TextureStoreFormat, SampleMode, TextureBytes};
+use texture::{
+    self, TextureSource, TextureStoreFormat, SampleMode, TextureBytes, PixelFormat, WrapMode,
+    BorderColor,
+};
 
 pub struct TextureBuilder<'a, R: RendererRaw> {
     /// Where to get the pixel data for this texture from. Defaults to a 1px black texture.
@@ -14,12 +19,44 @@ pub struct TextureBuilder<'a, R: RendererRaw> {
     /// Defines how the texture should be stored internally. Defaults to Srgb.
     pub store_format: TextureStoreFormat,
 
-    /// If set to true, mipmaps will be generated and used for this texture.
+    /// If set to true, mipmaps will be generated and used for this texture. Mipmaps are generated
+    /// using `texture::generate_mipmaps`, so they're the same on every backend.
     pub generate_mipmaps: bool,
 
     /// How this texture should be sampled, mipmapping will be applied on top of this if applicable.
     pub sample_mode: SampleMode,
 
+    /// What happens when sampling outside of the texture horizontally. Defaults to Clamp on every
+    /// backend. The Vulkan backend used to always repeat, textures that tile, like most 3D
+    /// materials, need `with_repeat`.
+    pub wrap_u: WrapMode,
+
+    /// What happens when sampling outside of the texture vertically. Defaults to Clamp.
+    pub wrap_v: WrapMode,
+
+    /// The maximum level of anisotropic filtering to apply when sampling at an angle, or None to
+    /// not apply any. Backends will limit this to what the hardware supports. Only used with
+    /// linear sampling. Defaults to None, the Vulkan backend used to always apply 16x.
+    pub anisotropy: Option<u8>,
+
+    /// The color sampled outside of the texture when using `WrapMode::Border`. Defaults to
+    /// TransparentBlack.
+    pub border_color: BorderColor,
+
+    /// If set to true, the color channels will be multiplied by alpha when the texture is built,
+    /// for use with `BlendMode::PremultipliedAlpha`. This isn't applied to data passed to
+    /// `Texture::update_region`. Defaults to false.
+    pub premultiply_alpha: bool,
+
+    /// If set to true, the texture can be rendered to, for example by a 2D render target. The
+    /// backend may pick its own internal format for these textures. Defaults to false.
+    pub render_target: bool,
+
+    /// If set to true, the texture's pixels can be overwritten after it's been built using
+    /// `Texture::update_region`. Backends store these textures in a way that makes frequent
+    /// updates cheap, but mipmaps will not be generated for them. Defaults to false.
+    pub dynamic: bool,
+
     _r: ::std::marker::PhantomData<R>,
 }
 
@@ -31,11 +68,18 @@ impl<'a, R: RendererRaw> TextureBuilder<'a, R> {
             source: TextureSource::Bytes {
                 bytes: BLACK_TEXTURE_1PX.into(),
                 size: Vector2::new(1, 1),
-                color: false,
+                format: PixelFormat::R8,
             },
             store_format: TextureStoreFormat::Srgb,
             generate_mipmaps: false,
             sample_mode: SampleMode::Linear,
+            wrap_u: WrapMode::Clamp,
+            wrap_v: WrapMode::Clamp,
+            anisotropy: None,
+            border_color: BorderColor::TransparentBlack,
+            premultiply_alpha: false,
+            render_target: false,
+            dynamic: false,
             _r: ::std::marker::PhantomData,
         }
     }
@@ -47,12 +91,12 @@ impl<'a, R: RendererRaw> TextureBuilder<'a, R> {
     }
 
     pub fn from_bytes<B: Into<TextureBytes<'a>>>(
-        mut self, bytes: B, size: Vector2<u32>, color: bool,
+        mut self, bytes: B, size: Vector2<u32>, format: PixelFormat,
     ) -> Self {
         self.source = TextureSource::Bytes {
             bytes: bytes.into(),
             size,
-            color,
+            format,
         };
         self
     }
@@ -74,6 +118,22 @@ impl<'a, R: RendererRaw> TextureBuilder<'a, R> {
         self.with_store_format(TextureStoreFormat::SingleChannel)
     }
 
+    pub fn as_two_channel(self) -> Self {
+        self.with_store_format(TextureStoreFormat::TwoChannel)
+    }
+
+    pub fn as_single_channel_16(self) -> Self {
+        self.with_store_format(TextureStoreFormat::SingleChannel16)
+    }
+
+    pub fn as_linear_float(self) -> Self {
+        self.with_store_format(TextureStoreFormat::LinearFloat)
+    }
+
+    pub fn as_single_channel_float(self) -> Self {
+        self.with_store_format(TextureStoreFormat::SingleChannelFloat)
+    }
+
     pub fn generate_mipmaps(mut self) -> Self {
         self.generate_mipmaps = true;
         self
@@ -92,14 +152,94 @@ impl<'a, R: RendererRaw> TextureBuilder<'a, R> {
         self.with_sample_mode(SampleMode::Nearest)
     }
 
+    /// Sets the wrap mode for both axes.
+    pub fn with_wrap_mode(self, value: WrapMode) -> Self {
+        self.with_wrap_modes(value, value)
+    }
+
+    pub fn with_wrap_modes(mut self, u: WrapMode, v: WrapMode) -> Self {
+        self.wrap_u = u;
+        self.wrap_v = v;
+        self
+    }
+
+    pub fn with_repeat(self) -> Self {
+        self.with_wrap_mode(WrapMode::Repeat)
+    }
+
+    pub fn with_anisotropy(mut self, value: u8) -> Self {
+        self.anisotropy = Some(value);
+        self
+    }
+
+    pub fn with_border_color(mut self, value: BorderColor) -> Self {
+        self.border_color = value;
+        self
+    }
+
+    pub fn premultiply_alpha(mut self) -> Self {
+        self.premultiply_alpha = true;
+        self
+    }
+
+    pub fn as_render_target(mut self) -> Self {
+        self.render_target = true;
+        self
+    }
+
+    pub fn as_dynamic(mut self) -> Self {
+        self.dynamic = true;
+        self
+    }
+
+    /// Loads the pixels of the source in the store format's pixel format, with alpha premultiplied
+    /// if requested. Backends use this to get the data they upload.
+    pub fn load_pixels(&self) -> Result<(Vec<u8>, Vector2<u32>), Error> {
+        let (data, size) = self.source.load_as(self.store_format.pixel_format())?;
+
+        if self.premultiply_alpha {
+            Ok((texture::premultiply_alpha(&data, self.store_format), size))
+        } else {
+            Ok((data, size))
+        }
+    }
+
     pub fn build(self, renderer: &mut Renderer<R>) -> Result<Arc<Texture<R>>, Error> {
+        match self.source {
+            TextureSource::File(ref path) => info!(renderer.log(),
+                "Loading texture from file"; "path" => path.display().to_string()
+            ),
+            TextureSource::Bytes { size, format, .. } => info!(renderer.log(),
+                "Loading texture from bytes";
+                "width" => size.x, "height" => size.y, "format" => format!("{:?}", format)
+            ),
+        }
+
+        let render_target = self.render_target;
+        let dynamic = self.dynamic;
+        let store_format = self.store_format;
+        let premultiplied_alpha = self.premultiply_alpha;
+        let pixel_format = self.store_format.pixel_format();
         let raw = R::TextureRaw::new(self, renderer)?;
-        Ok(Arc::new(Texture { raw }))
+
+        // Render targets start out without any data being uploaded
+        let bytes = if render_target { 0 } else { pixel_format.bytes_for(raw.size()) };
+        renderer.stats_mut().textures_created += 1;
+        renderer.stats_mut().bytes_uploaded += bytes;
+
+        let alive = renderer.textures_alive().clone();
+        alive.fetch_add(1, Ordering::SeqCst);
+
+        Ok(Arc::new(Texture { raw, alive, dynamic, store_format, premultiplied_alpha }))
     }
 }
 
 pub struct Texture<R: RendererRaw> {
-    raw: R::TextureRaw
+    raw: R::TextureRaw,
+    alive: Arc<AtomicUsize>,
+    dynamic: bool,
+    store_format: TextureStoreFormat,
+    premultiplied_alpha: bool,
 }
 
 impl<R: RendererRaw> Texture<R> {
@@ -110,6 +250,74 @@ impl<R: RendererRaw> Texture<R> {
     pub fn size(&self) -> Vector2<u32> {
         self.raw.size()
     }
+
+    /// If this texture was built with `as_dynamic`, and can be updated using `update_region`.
+    pub fn is_dynamic(&self) -> bool {
+        self.dynamic
+    }
+
+    /// The format this texture was built to store its pixels in.
+    pub fn store_format(&self) -> TextureStoreFormat {
+        self.store_format
+    }
+
+    /// If this texture was built with `premultiply_alpha`. Pixels given to `update_region` are
+    /// not premultiplied, so they should be premultiplied already to match.
+    pub fn is_premultiplied_alpha(&self) -> bool {
+        self.premultiplied_alpha
+    }
+
+    /// Overwrites a region of this texture's pixels in place. The bytes are laid out the same way
+    /// as for `TextureBuilder::from_bytes`, but only cover the region. The texture needs to have
+    /// been built with `as_dynamic`.
+    pub fn update_region(
+        &self, offset: Vector2<u32>, size: Vector2<u32>, bytes: &[u8], format: PixelFormat,
+        renderer: &mut Renderer<R>,
+    ) -> Result<(), Error> {
+        let texture_size = self.size();
+        let fits = |offset: u32, size: u32, texture_size: u32| offset.checked_add(size)
+            .map(|end| end <= texture_size)
+            .unwrap_or(false);
+        if !fits(offset.x, size.x, texture_size.x) || !fits(offset.y, size.y, texture_size.y) {
+            return Err(Error::Unsupported(format!(
+                "Region {:?} at {:?} does not fit in texture of size {:?}",
+                size, offset, texture_size
+            )))
+        }
+
+        let expected_length = format.bytes_for(size);
+        if bytes.len() != expected_length {
+            return Err(Error::Unsupported(format!(
+                "Expected {} bytes of texture data, got {}", expected_length, bytes.len()
+            )))
+        }
+
+        self.raw.update_region(offset, size, bytes, format, renderer)?;
+        renderer.stats_mut().bytes_uploaded += bytes.len();
+
+        Ok(())
+    }
+
+    /// Reads back this texture's pixels, for example after rendering to it as a render target.
+    /// Pixels are given as they're stored, so Srgb textures give sRGB values and linear textures
+    /// give linear values.
+    pub fn capture(&self, renderer: &mut Renderer<R>) -> Result<RgbaImage, Error> {
+        self.raw.capture(renderer)
+    }
+
+    /// Reads back this texture's pixels, and saves them as a PNG file.
+    pub fn save_png<P: AsRef<Path>>(
+        &self, path: P, renderer: &mut Renderer<R>,
+    ) -> Result<(), Error> {
+        let image = self.capture(renderer)?;
+        ::save_png(&image, path)
+    }
+}
+
+impl<R: RendererRaw> Drop for Texture<R> {
+    fn drop(&mut self) {
+        self.alive.fetch_sub(1, Ordering::SeqCst);
+    }
 }
 
 impl<R: RendererRaw> RawAccess<R::TextureRaw> for Texture<R> {
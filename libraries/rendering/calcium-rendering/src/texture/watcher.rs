@@ -0,0 +1,164 @@
+use std::fs;
+use std::path::{PathBuf};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant, SystemTime};
+
+use cgmath::{Vector2};
+
+use {Error, Renderer};
+use raw::{RendererRaw};
+use texture::{self, Texture, TextureBuilder, TextureSource};
+
+/// Watches the files textures were loaded from, and reloads the textures in place when the files
+/// change. This is meant for development, so changes to assets show up without restarting.
+///
+/// Watched textures are updated in place, so every `Arc` referencing them sees the new pixels.
+/// To be able to do this, textures are built as dynamic, which means mipmaps are not generated
+/// for them. Textures built with `premultiply_alpha` have their alpha premultiplied again when
+/// reloaded. Files are only checked when `poll` is called, so everything stays on the thread the
+/// watcher is polled from.
+pub struct TextureWatcher<R: RendererRaw> {
+    watched: Vec<WatchedTexture<R>>,
+    interval: Duration,
+    last_poll: Option<Instant>,
+}
+
+struct WatchedTexture<R: RendererRaw> {
+    path: PathBuf,
+    texture: Weak<Texture<R>>,
+    modified: Option<SystemTime>,
+}
+
+impl<R: RendererRaw> TextureWatcher<R> {
+    /// Creates a new watcher that checks files at most twice a second.
+    pub fn new() -> Self {
+        Self::with_interval(Duration::from_millis(500))
+    }
+
+    /// Creates a new watcher that checks files at most once every interval.
+    pub fn with_interval(interval: Duration) -> Self {
+        TextureWatcher {
+            watched: Vec::new(),
+            interval,
+            last_poll: None,
+        }
+    }
+
+    /// Builds a texture from a file source, and starts watching its file.
+    pub fn build(
+        &mut self, builder: TextureBuilder<R>, renderer: &mut Renderer<R>,
+    ) -> Result<Arc<Texture<R>>, Error> {
+        let path = match builder.source {
+            TextureSource::File(ref path) => path.clone(),
+            TextureSource::Bytes { .. } => return Err(Error::Unsupported(
+                "Only textures with a file source can be watched".into()
+            )),
+        };
+
+        let texture = builder.as_dynamic().build(renderer)?;
+        self.watch(path, &texture)?;
+
+        Ok(texture)
+    }
+
+    /// Starts watching a file for an existing texture. The texture needs to have been built as
+    /// dynamic, otherwise an error is returned. Textures stop being watched once they're dropped.
+    pub fn watch<P: Into<PathBuf>>(
+        &mut self, path: P, texture: &Arc<Texture<R>>,
+    ) -> Result<(), Error> {
+        if !texture.is_dynamic() {
+            return Err(Error::Unsupported(
+                "Only textures built as dynamic can be watched".into()
+            ))
+        }
+
+        let path = path.into();
+        let modified = modified_time(&path);
+
+        self.watched.push(WatchedTexture {
+            path,
+            texture: Arc::downgrade(texture),
+            modified,
+        });
+
+        Ok(())
+    }
+
+    /// The amount of textures currently being watched.
+    pub fn watched(&self) -> usize {
+        self.watched.len()
+    }
+
+    /// Checks all watched files for changes, and reloads the textures of any that changed.
+    /// Returns the paths of the textures that failed to reload, together with why. A failed
+    /// reload keeps the old pixels, and is tried again the next time the file changes.
+    pub fn poll(&mut self, renderer: &mut Renderer<R>) -> Vec<(PathBuf, Error)> {
+        let mut failures = Vec::new();
+
+        // Don't hit the file system every single frame
+        let now = Instant::now();
+        if let Some(last_poll) = self.last_poll {
+            if now.duration_since(last_poll) < self.interval {
+                return failures
+            }
+        }
+        self.last_poll = Some(now);
+
+        // Stop watching textures that have been dropped
+        self.watched.retain(|w| w.texture.upgrade().is_some());
+
+        for watched in &mut self.watched {
+            let modified = modified_time(&watched.path);
+            if modified == watched.modified {
+                continue
+            }
+            watched.modified = modified;
+
+            let texture = watched.texture.upgrade().unwrap();
+            match reload(&watched.path, &texture, renderer) {
+                Ok(()) => info!(renderer.log(),
+                    "Reloaded texture"; "path" => watched.path.display().to_string()
+                ),
+                Err(error) => {
+                    warn!(renderer.log(),
+                        "Failed to reload texture";
+                        "path" => watched.path.display().to_string(),
+                        "error" => error.to_string()
+                    );
+                    failures.push((watched.path.clone(), error));
+                },
+            }
+        }
+
+        failures
+    }
+}
+
+fn reload<R: RendererRaw>(
+    path: &PathBuf, texture: &Texture<R>, renderer: &mut Renderer<R>,
+) -> Result<(), Error> {
+    let (bytes, size, format) = texture::load_file(path)?;
+
+    // The texture's storage can't be resized in place
+    if size != texture.size() {
+        return Err(Error::Unsupported(format!(
+            "Texture changed size from {:?} to {:?}, this can't be reloaded in place",
+            texture.size(), size
+        )))
+    }
+
+    // Premultiplying happens in the store format, the same as when the texture was built
+    if texture.is_premultiplied_alpha() {
+        let store_format = texture.store_format();
+        let pixel_format = store_format.pixel_format();
+        let bytes = texture::convert_pixels(&bytes, format, pixel_format);
+        let bytes = texture::premultiply_alpha(&bytes, store_format);
+        texture.update_region(Vector2::new(0, 0), size, &bytes, pixel_format, renderer)
+    } else {
+        texture.update_region(Vector2::new(0, 0), size, &bytes, format, renderer)
+    }
+}
+
+fn modified_time(path: &PathBuf) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
@@ -0,0 +1,19 @@
+/// Identifies one of the windows a renderer renders to. A renderer always has at least its main
+/// window, additional windows can be created through the backend.
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub struct WindowId(usize);
+
+impl WindowId {
+    /// The window the renderer was created with.
+    pub fn main() -> Self {
+        WindowId(0)
+    }
+
+    pub fn raw_new(index: usize) -> Self {
+        WindowId(index)
+    }
+
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
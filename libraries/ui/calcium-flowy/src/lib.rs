@@ -1,7 +1,6 @@
 extern crate calcium_rendering;
 extern crate calcium_rendering_2d;
 extern crate flowy;
-extern crate image;
 extern crate rusttype;
 extern crate cgmath;
 
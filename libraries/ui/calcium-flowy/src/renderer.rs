@@ -4,37 +4,31 @@ use std::collections::{HashMap};
 use cgmath::{Vector2, Vector4, Point2};
 use rusttype::gpu_cache::{Cache};
 use rusttype::{Font, Scale};
-use image::{GrayImage, GenericImage, ImageBuffer, Luma};
 use calcium_rendering::{Renderer, Error};
 use calcium_rendering::raw::{RendererRaw};
-use calcium_rendering::texture::{Texture};
-use calcium_rendering_2d::render_data::{RenderBatch, ShaderMode, Rectangle, UvMode};
+use calcium_rendering::texture::{Texture, PixelFormat};
+use calcium_rendering_2d::render_data::{RenderBatch, ShaderMode, Rectangle, UvMode, ClipRect};
 
 use flowy::{Ui, ElementId, ElementCursorState, Element};
 
 pub struct FlowyRenderer<R: RendererRaw> {
     glyph_cache: Cache,
-    glyph_image: GrayImage,
     glyph_texture: Arc<Texture<R>>,
     text_cache: HashMap<ElementId, RenderBatch<R>>,
 }
 
 impl<R: RendererRaw> FlowyRenderer<R> {
     pub fn new(renderer: &mut Renderer<R>) -> Result<Self, Error> {
-        // TODO: This can go down to 0.1 again instead of 1.0 when the texture is overwritten
-        // instead of replaced.
-        let glyph_cache = Cache::new(512, 512, 1.0, 1.0);
-        let glyph_image = GrayImage::from_raw(512, 512, vec![0u8; 512*512]).unwrap();
+        let glyph_cache = Cache::new(512, 512, 0.1, 0.1);
         let glyph_texture = Texture::new()
-            // We will never use this initial texture, so just use something cheap
-            .from_bytes(vec![0u8; 8*8], Vector2::new(8, 8), false)
+            .from_bytes(vec![0u8; 512*512], Vector2::new(512, 512), PixelFormat::R8)
             .as_single_channel()
             .with_nearest_sampling()
+            .as_dynamic()
             .build(renderer)?;
 
         Ok(FlowyRenderer {
             glyph_cache,
-            glyph_image,
             glyph_texture,
             text_cache: HashMap::new(),
         })
@@ -58,13 +52,6 @@ impl<R: RendererRaw> FlowyRenderer<R> {
         // Draw all the elements recursively starting at the root
         self.render_element(ui.elements.root_id(), ui, &mut batcher, renderer)?;
 
-        // Make sure all cached batches have the same text texture, this will only matter for the
-        //  next frame, but it should clean up some stale textures.
-        // TODO: Add something to Texture that just overwrites its data.
-        for entry in &mut self.text_cache {
-            entry.1.mode = ShaderMode::Mask(self.glyph_texture.clone());
-        }
-
         batcher.finish();
         Ok(())
     }
@@ -80,7 +67,7 @@ impl<R: RendererRaw> FlowyRenderer<R> {
             render_element_text(
                 &ui.fonts,
                 element_id, element,
-                &mut self.glyph_cache, &mut self.glyph_image, &mut self.glyph_texture,
+                &mut self.glyph_cache, &self.glyph_texture,
                 batcher, &mut self.text_cache, renderer
             )?;
         }
@@ -127,7 +114,7 @@ fn render_element_box<R: RendererRaw>(element: &Element, batcher: &mut Batcher<R
 fn render_element_text<R: RendererRaw>(
     fonts: &Vec<Font>,
     id: ElementId, element: &mut Element,
-    glyph_cache: &mut Cache, glyph_image: &mut GrayImage, glyph_texture: &mut Arc<Texture<R>>,
+    glyph_cache: &mut Cache, glyph_texture: &Arc<Texture<R>>,
     batcher: &mut Batcher<R>, text_cache: &mut HashMap<ElementId, RenderBatch<R>>,
     renderer: &mut Renderer<R>,
 ) -> Result<(), Error> {
@@ -138,7 +125,7 @@ fn render_element_text<R: RendererRaw>(
         let font = fonts.get(element.style().text_font.0).expect("Unable to find font on element");
         batcher.next_batch(retrieve_or_create_batch(
             id, element, font,
-            glyph_cache, glyph_image, glyph_texture,
+            glyph_cache, glyph_texture,
             text_cache, renderer,
         )?);
 
@@ -152,7 +139,7 @@ fn render_element_text<R: RendererRaw>(
 
 fn retrieve_or_create_batch<R: RendererRaw>(
     id: ElementId, element: &mut Element, font: &Font,
-    glyph_cache: &mut Cache, glyph_image: &mut GrayImage, glyph_texture: &mut Arc<Texture<R>>,
+    glyph_cache: &mut Cache, glyph_texture: &Arc<Texture<R>>,
     text_cache: &mut HashMap<ElementId, RenderBatch<R>>, renderer: &mut Renderer<R>,
 ) -> Result<RenderBatch<R>, Error> {
     let container = element.positioning().container.clone();
@@ -170,7 +157,7 @@ fn retrieve_or_create_batch<R: RendererRaw>(
     // Couldn't find something in the cache, generate a new batch
     let batch = generate_text_batch(
         element, font,
-        glyph_cache, glyph_image, glyph_texture,
+        glyph_cache, glyph_texture,
         renderer,
     )?;
 
@@ -184,7 +171,7 @@ fn retrieve_or_create_batch<R: RendererRaw>(
 
 fn generate_text_batch<R: RendererRaw>(
     element: &mut Element, font: &Font,
-    glyph_cache: &mut Cache, glyph_image: &mut GrayImage, glyph_texture: &mut Arc<Texture<R>>,
+    glyph_cache: &mut Cache, glyph_texture: &Arc<Texture<R>>,
     renderer: &mut Renderer<R>,
 ) -> Result<RenderBatch<R>, Error> {
     // If the text size is too small, we can't render anything
@@ -205,37 +192,23 @@ fn generate_text_batch<R: RendererRaw>(
         glyph_cache.queue_glyph(0, glyph.clone());
     }
 
-    // Now see if we need to create a new glyph cache
-    let mut changed = false;
+    // Upload any newly cached glyphs into the glyph texture, we can't return errors from inside
+    //  the callback so keep track of the first one
+    let mut upload_result = Ok(());
     glyph_cache.cache_queued(|rect, data| {
-        // Create an image from the data we got
-        // TODO: See if we can avoid copying all pixel data to create the image
-        let new_glyphs_subimage: ImageBuffer<Luma<u8>, Vec<u8>> = ImageBuffer::from_raw(
-            rect.width(), rect.height(), data.into()
-        ).unwrap();
-
-        // Copy the data into the full glyphs image
-        glyph_image.copy_from(&new_glyphs_subimage, rect.min.x, rect.min.y);
-        changed = true;
+        if upload_result.is_ok() {
+            upload_result = glyph_texture.update_region(
+                Vector2::new(rect.min.x, rect.min.y), Vector2::new(rect.width(), rect.height()),
+                data, PixelFormat::R8, renderer,
+            );
+        }
     }).unwrap();
+    upload_result?;
 
-    // If the image has actually changed, update the texture. This is done afterwards because
-    //  the cache_queued callback may be called multiple times
-    // TODO: Move this to the end of creating the render batch so it's only done once, this is
-    // more complex than just simply copy-paste, as the batch already has the wrong texture so the
-    // batch needs to be generated later or its texture needs to be replaced.
-    if changed {
-        // Upload the glyphs into a texture
-        // TODO: Check if we need to convert from sRGB to Linear, calcium takes Linear here
-        *glyph_texture = Texture::new()
-            .from_bytes(glyph_image.as_ref(), Vector2::new(512, 512), false)
-            .as_single_channel()
-            .with_nearest_sampling()
-            .build(renderer)?;
-    }
-
-    // Set the texture in the render batch
-    let mut batch = RenderBatch::new(ShaderMode::Mask(glyph_texture.clone()), UvMode::YDown);
+    // Set the texture in the render batch, and make sure text doesn't spill out of its element
+    let container = element.positioning().container.clone();
+    let mut batch = RenderBatch::new(ShaderMode::Mask(glyph_texture.clone()), UvMode::YDown)
+        .with_clip(ClipRect::Projected(container));
 
     // Actually render the text
     let c = element.style().text_color;
@@ -37,30 +37,40 @@ impl<R: RendererRaw, WR: World3DRenderer<R>> ViewportView<R, WR> {
             .with_base_color(Texture::new()
                 .from_file("./assets/texture.png")
                 .generate_mipmaps()
+                .with_repeat()
+                .with_anisotropy(16)
                 .build(renderer)?
             )
             .with_normal_map(Texture::new()
                 .from_file("./assets/texture_normal.png")
                 .as_linear()
                 .generate_mipmaps()
+                .with_repeat()
+                .with_anisotropy(16)
                 .build(renderer)?
             )
             .with_metallic_map(Texture::new()
                 .from_file("./assets/texture_metallic.png")
                 .as_single_channel()
                 .generate_mipmaps()
+                .with_repeat()
+                .with_anisotropy(16)
                 .build(renderer)?
             )
             .with_roughness_map(Texture::new()
                 .from_file("./assets/texture_roughness.png")
                 .as_single_channel()
                 .generate_mipmaps()
+                .with_repeat()
+                .with_anisotropy(16)
                 .build(renderer)?
             )
             .with_ambient_occlusion_map(Texture::new()
                 .from_file("./assets/texture_ambientOcclusion.png")
                 .as_single_channel()
                 .generate_mipmaps()
+                .with_repeat()
+                .with_anisotropy(16)
                 .build(renderer)?
             );
 
@@ -69,12 +79,16 @@ impl<R: RendererRaw, WR: World3DRenderer<R>> ViewportView<R, WR> {
             .with_base_color(Texture::new()
                 .from_file("./assets/human.png")
                 .generate_mipmaps()
+                .with_repeat()
+                .with_anisotropy(16)
                 .build(renderer)?
             )
             .with_ambient_occlusion_map(Texture::new()
                 .from_file("./assets/human_ambientOcclusion.png")
                 .as_single_channel()
                 .generate_mipmaps()
+                .with_repeat()
+                .with_anisotropy(16)
                 .build(renderer)?
             );
         let model = Model::load(renderer, "./assets/human.obj", 1.0);
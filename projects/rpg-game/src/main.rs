@@ -25,7 +25,7 @@ use slog_async::{Async};
 use slog_term::{CompactFormat, TermDecorator};
 
 use calcium_rendering::{Error};
-use calcium_rendering_context::{Backend};
+use calcium_rendering_context::{Backend, Capability};
 
 fn main() {
     // Set up the logger
@@ -50,8 +50,10 @@ fn init_logger() -> Logger {
 
 fn run_game(log: &Logger) -> Result<(), Error> {
     // TODO: Read in from configuration and UI
-    let backend = Backend::GfxOpenGl;
+    let preferences = [Backend::GfxOpenGl, Backend::Vulkano];
 
-    // Run the game's runtime with the appropriate backends
-    calcium_rendering_context::run_runtime(backend, runtime::StaticRuntime { log: log.clone() })
+    // Run the game's runtime with the first backend that can render it
+    calcium_rendering_context::run_runtime_with(
+        &[Capability::Simple2D], &preferences, runtime::StaticRuntime { log: log.clone() }
+    )
 }
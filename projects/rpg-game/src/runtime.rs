@@ -1,9 +1,8 @@
 //#![allow(dead_code)]
 
-use std::sync::{Arc};
 use std::path::{PathBuf};
 
-use cgmath::{Vector2, Point2};
+use cgmath::{Vector2, Point2, Vector4};
 use input::{Input, Button, Key, ButtonState, ButtonArgs};
 use window::{Window, WindowSettings};
 use slog::{Logger};
@@ -15,9 +14,12 @@ use rusttype::{FontCollection};
 use tiled;
 
 use calcium_game::{LoopTimer};
-use calcium_rendering::{Error};
-use calcium_rendering::texture::{Texture};
-use calcium_rendering_2d::render_data::{RenderBatch, ShaderMode, Rectangle, Projection, RenderData, RenderSet, UvMode};
+use calcium_rendering::{Error, RendererSettings};
+use calcium_rendering::atlas::{Atlas, AtlasRegion};
+use calcium_rendering::texture::{Texture, TextureLoader, PixelFormat};
+use calcium_rendering_2d::render_data::{
+    Rectangle, Projection, RenderData, RenderSet, SpriteBatcher,
+};
 use calcium_rendering_2d::{Renderer2DTarget};
 use calcium_rendering_context::{Runtime, Context};
 use calcium_rendering::raw::{RendererRaw};
@@ -27,8 +29,8 @@ use view::{MapRenderer};
 
 struct FriendlyUnit<R: RendererRaw> {
     name: String,
-    tex: Arc<Texture<R>>,
-    selecttex: Arc<Texture<R>>,
+    sprite: AtlasRegion<R>,
+    selection: AtlasRegion<R>,
     position: Point2<f32>,
     size: Vector2<f32>,
     speed: f32,
@@ -38,8 +40,8 @@ struct FriendlyUnit<R: RendererRaw> {
 }
 
 impl <R: RendererRaw> FriendlyUnit<R> {
-    pub fn new(name: String, tex: Arc<Texture<R>>, selecttex: Arc<Texture<R>>, position: Point2<f32>, size: Vector2<f32>, speed: f32) -> FriendlyUnit<R> {
-        FriendlyUnit {name: name, tex: tex, selecttex: selecttex, position: position, size: size, speed: speed, selected: false, tabrel: 0.0}
+    pub fn new(name: String, sprite: AtlasRegion<R>, selection: AtlasRegion<R>, position: Point2<f32>, size: Vector2<f32>, speed: f32) -> FriendlyUnit<R> {
+        FriendlyUnit {name: name, sprite: sprite, selection: selection, position: position, size: size, speed: speed, selected: false, tabrel: 0.0}
     }
 
     pub fn update(&mut self, delta: f32, selected: bool, pinput: &PlayerInput) {
@@ -58,25 +60,21 @@ impl <R: RendererRaw> FriendlyUnit<R> {
             if pinput.d {self.position.x += self.speed * delta;}
         }
     }
-    pub fn render(&mut self, batches: &mut Vec<RenderBatch<R>>) {
-        //let mut batches = Vec::new();
-        let mut normaltexture = RenderBatch::new(
-            ShaderMode::Texture(self.tex.clone()), UvMode::YDown
-        );
-        normaltexture.push_rectangle_full_texture(
+    pub fn render(&mut self, sprites: &mut SpriteBatcher<R>) {
+        sprites.push_sprite(
+            self.sprite.texture.clone(),
             // position is centered in the texture
-            Rectangle::new(self.position + -self.size/2.0, self.position + self.size/2.0)
+            Rectangle::new(self.position + -self.size/2.0, self.position + self.size/2.0),
+            self.sprite.uv.clone(), Vector4::new(1.0, 1.0, 1.0, 1.0), 0.0
         );
-        batches.push(normaltexture);
 
+        // The selection is drawn over its own unit, sprites with the same depth keep their order
         if self.selected {
-            let mut selectiontexture = RenderBatch::new(
-                ShaderMode::Texture(self.selecttex.clone()), UvMode::YDown
-            );
-            selectiontexture.push_rectangle_full_texture(
-                Rectangle::new(self.position + -self.size, self.position + self.size)
+            sprites.push_sprite(
+                self.selection.texture.clone(),
+                Rectangle::new(self.position + -self.size, self.position + self.size),
+                self.selection.uv.clone(), Vector4::new(1.0, 1.0, 1.0, 1.0), 0.0
             );
-            batches.push(selectiontexture);
         }
     }
     pub fn get_position(&mut self) -> Point2<f32> {
@@ -95,6 +93,7 @@ struct PlayerInput {
     pub tab: bool,
 }
 
+#[derive(Clone)]
 pub struct StaticRuntime {
     pub log: Logger,
 }
@@ -106,7 +105,7 @@ impl Runtime for StaticRuntime {
         // Set up everything we need to render
         let window_settings = WindowSettings::new("RPG Game", [1280, 720]);
         let (mut renderer, mut window) =
-            context.renderer(Some(self.log.clone()), &window_settings)?;
+            context.renderer(Some(self.log.clone()), &window_settings, RendererSettings::new())?;
         let mut simple2d_renderer = context.simple2d_renderer(&mut renderer)?;
         let mut simple2d_render_target = Renderer2DTarget::new(
             true, &renderer, &simple2d_renderer
@@ -135,28 +134,44 @@ impl Runtime for StaticRuntime {
             fpso.set_text(format!("test text"));
         }
 
+        // Show what the renderer is doing in the corner
+        let stats = Element::new(Style {
+            position: Position::Relative(Point2::new(0.0, 0.0), SideH::Left, SideV::Top),
+            size: Size::units(400.0, 14.0),
+            text_color: Srgb::new(1.0, 1.0, 1.0).into(),
+            text_size: 14.0,
+            .. Style::new()
+        });
+        let stats_id = ui.elements.add_child(stats, root_id);
+
         // Units data
-        let friendly_texture = Texture::new()
-            .from_file("./assets/friendly.png")
+        let units_atlas = Atlas::new()
+            .add_file("friendly", "./assets/friendly.png")
+            .add_file("selection", "./assets/selection.png")
+            .with_page_size(Vector2::new(256, 256))
             .with_nearest_sampling()
             .build(&mut renderer)?;
-        let selection_texture = Texture::new()
-            .from_file("./assets/selection.png")
-            .with_nearest_sampling()
+        let friendly_sprite = units_atlas.region("friendly").unwrap();
+        let selection_sprite = units_atlas.region("selection").unwrap();
+
+        // Textures that aren't needed right away are loaded in the background
+        let placeholder = Texture::new()
+            .from_bytes(vec![0u8, 0, 0, 0], Vector2::new(1, 1), PixelFormat::Rgba8)
             .build(&mut renderer)?;
+        let mut texture_loader = TextureLoader::new(placeholder);
 
         // Set up the game map's tiles
         let map_path = PathBuf::from("./assets/test_map.tmx");
         let tmap = tiled::parse_file(&map_path).unwrap();
         let map = Map::new(&tmap, &self.log);
-        let map_renderer = MapRenderer::new(&tmap, &map_path, &mut renderer)?;
+        let map_renderer = MapRenderer::new(&tmap, &map_path, &mut texture_loader);
 
         let mut players_units = Vec::new();
 
-        let alfred = FriendlyUnit::new(String::from("Alfred"), friendly_texture.clone(), selection_texture.clone(), Point2::new(200.0,200.0), Vector2::new(32.0,32.0), 256.0 );
-        let bertil = FriendlyUnit::new(String::from("Bertil"), friendly_texture.clone(), selection_texture.clone(), Point2::new(300.0,300.0), Vector2::new(32.0,32.0), 256.0 );
-        let carl = FriendlyUnit::new(String::from("Carl"), friendly_texture.clone(), selection_texture.clone(), Point2::new(400.0,400.0), Vector2::new(32.0,32.0), 256.0 );
-        let dagobert = FriendlyUnit::new(String::from("Dagobert"), friendly_texture.clone(), selection_texture.clone(), Point2::new(300.0,500.0), Vector2::new(32.0,32.0), 256.0 );
+        let alfred = FriendlyUnit::new(String::from("Alfred"), friendly_sprite.clone(), selection_sprite.clone(), Point2::new(200.0,200.0), Vector2::new(32.0,32.0), 256.0 );
+        let bertil = FriendlyUnit::new(String::from("Bertil"), friendly_sprite.clone(), selection_sprite.clone(), Point2::new(300.0,300.0), Vector2::new(32.0,32.0), 256.0 );
+        let carl = FriendlyUnit::new(String::from("Carl"), friendly_sprite.clone(), selection_sprite.clone(), Point2::new(400.0,400.0), Vector2::new(32.0,32.0), 256.0 );
+        let dagobert = FriendlyUnit::new(String::from("Dagobert"), friendly_sprite.clone(), selection_sprite.clone(), Point2::new(300.0,500.0), Vector2::new(32.0,32.0), 256.0 );
 
         players_units.push(alfred);
         players_units.push(bertil);
@@ -174,6 +189,11 @@ impl Runtime for StaticRuntime {
         while !window.should_close() {
             let delta = timer.tick();
 
+            // Finish any textures that are done loading, we can't run without them
+            if let Some((_, error)) = texture_loader.poll(&mut renderer).into_iter().next() {
+                return Err(error)
+            }
+
             // Handle input
             while let Some(event) = window.poll_event() {
                 // Let the context handle anything needed
@@ -210,6 +230,16 @@ impl Runtime for StaticRuntime {
                 fpso.set_text(players_units[selected_unit].get_name().clone());
             }
 
+            {
+                let stats = renderer.frame_stats();
+                ui.elements[stats_id].set_text(format!(
+                    "Draws: {}, Vertices: {}, Textures: {}, 2D: {:.2}ms",
+                    stats.draw_calls, stats.vertices, stats.textures_alive,
+                    stats.render_2d_time.as_secs() as f32 * 1_000.0 +
+                        stats.render_2d_time.subsec_nanos() as f32 / 1_000_000.0,
+                ));
+            }
+
             // TODO: kill this
             tabrelease -= delta;
             if tabrelease <= 0.0 && tab_pressed {
@@ -237,9 +267,11 @@ impl Runtime for StaticRuntime {
             map_renderer.render(&map, &mut world_batches, camera_size);
 
             // Render the player units
+            let mut sprites = SpriteBatcher::new();
             for unit in &mut players_units {
-                unit.render(&mut world_batches);
+                unit.render(&mut sprites);
             }
+            sprites.drain_into(&mut world_batches);
 
             // Submit the world render data
             //let camera = Camera::new(32.0, Point2::new(0.0, 0.0));
@@ -1,9 +1,7 @@
-use std::sync::{Arc};
 use std::path::{PathBuf};
 
 use calcium_rendering::raw::{RendererRaw};
-use calcium_rendering::texture::{Texture};
-use calcium_rendering::{Renderer, Error};
+use calcium_rendering::texture::{Texture, TextureLoader, TextureHandle};
 use calcium_rendering_2d::render_data::{RenderBatch, ShaderMode, Rectangle, UvMode};
 use cgmath::{Vector2, Vector4, Point2};
 use tiled::{Map as TMap};
@@ -11,14 +9,14 @@ use tiled::{Map as TMap};
 use model::{Map};
 
 pub struct MapRenderer<R: RendererRaw> {
-    tileset_texture: Arc<Texture<R>>,
+    tileset_texture: TextureHandle<R>,
     tileset_first_gid: u32,
     tileset_tiles_amount: Vector2<u32>,
     tileset_uv_per_tile: Vector2<f32>,
 }
 
 impl<R: RendererRaw> MapRenderer<R> {
-    pub fn new(map: &TMap, map_path: &PathBuf, renderer: &mut Renderer<R>) -> Result<Self, Error> {
+    pub fn new(map: &TMap, map_path: &PathBuf, texture_loader: &mut TextureLoader<R>) -> Self {
         // Load in the map and validate that we can render using it
         if map.tilesets.len() != 1 {
             panic!("Only one tileset per map is supported");
@@ -34,11 +32,11 @@ impl<R: RendererRaw> MapRenderer<R> {
         full_image_source.pop();
         full_image_source.push(&image.source);
 
-        // Finally, load in the texture
-        let texture = Texture::new()
+        // Finally, start loading in the texture
+        let texture = texture_loader.load(Texture::new()
             .from_file(full_image_source)
             .with_nearest_sampling()
-            .build(renderer)?;
+        );
 
         // Calculate all the data we need about this texture to render tiles from it
         let tileset_first_gid = tileset.first_gid;
@@ -51,19 +49,19 @@ impl<R: RendererRaw> MapRenderer<R> {
             1.0 / tileset_tiles_amount.y as f32
         );
 
-        Ok(MapRenderer {
+        MapRenderer {
             tileset_texture: texture,
             tileset_first_gid,
             tileset_tiles_amount,
             tileset_uv_per_tile,
-        })
+        }
     }
 
     pub fn render(
         &self, map: &Map, batches: &mut Vec<RenderBatch<R>>, camera_size: Vector2<f32>
     ) {
         let mut batch = RenderBatch::new(
-            ShaderMode::Texture(self.tileset_texture.clone()), UvMode::YDown
+            ShaderMode::Texture(self.tileset_texture.texture()), UvMode::YDown
         );
 
         let last_pos = Vector2::new(
@@ -88,8 +86,8 @@ impl<R: RendererRaw> MapRenderer<R> {
                         tile / self.tileset_tiles_amount.x,
                     ).cast();
 
-                    // Add the tile to be rendered
-                    batch.push_rectangle(
+                    // Add the tile to be rendered, tiles are all the same quad so they're instanced
+                    batch.push_instance(
                         Rectangle::new(
                             position * 32.0,
                             (position + Vector2::new(1.0, 1.0)) * 32.0
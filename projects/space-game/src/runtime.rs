@@ -1,10 +1,10 @@
 use slog::{Logger};
 use window::{WindowSettings, Window};
 use input::{Input, Button, ButtonArgs, ButtonState, Key};
-use cgmath::{Vector2};
+use cgmath::{Vector2, Point2};
 
-use calcium_rendering::{Error};
-use calcium_rendering_2d::render_data::{RenderData};
+use calcium_rendering::{Error, RendererSettings};
+use calcium_rendering_2d::render_data::{RenderData, Camera};
 use calcium_rendering_2d::{Renderer2DTarget};
 use calcium_rendering_context::{Context, Runtime};
 use calcium_game::{LoopTimer};
@@ -23,7 +23,7 @@ impl Runtime for StaticRuntime {
         // Set up everything we need to render
         let window_settings = WindowSettings::new("Space Game", [1280, 720]);
         let (mut renderer, mut window) =
-            context.renderer(Some(self.log.clone()), &window_settings)?;
+            context.renderer(Some(self.log.clone()), &window_settings, RendererSettings::new())?;
         let mut simple2d_renderer = context.simple2d_renderer(&mut renderer)?;
         let mut simple2d_render_target = Renderer2DTarget::new(
             true, &renderer, &simple2d_renderer
@@ -36,6 +36,7 @@ impl Runtime for StaticRuntime {
         // Set up views
         let background_view = BackgroundView::new(&mut renderer)?;
         let tile_structure_view = TileStructureView::new(&mut renderer)?;
+        let camera = Camera::new(32.0 * 2.0, Point2::new(50.0, 50.0));
 
         let mut _right_pressed = false;
 
@@ -67,7 +68,9 @@ impl Runtime for StaticRuntime {
             let mut render_data = RenderData::new();
 
             background_view.render(&mut render_data, &mut renderer);
-            tile_structure_view.render(&tile_structure, &mut render_data);
+            tile_structure_view.render(
+                &tile_structure, &camera, renderer.size(), &mut render_data
+            );
 
             // Finally do the 2D rendering itself
             let mut frame = renderer.start_frame();
@@ -7,6 +7,8 @@ use calcium_rendering::texture::{Texture};
 use calcium_rendering::{Renderer, Error};
 use calcium_rendering_2d::render_data::{RenderData, RenderBatch, ShaderMode, Rectangle, RenderSet, Projection, UvMode};
 
+use view::{LAYER_BACKGROUND};
+
 pub struct BackgroundView<R: RendererRaw> {
     texture: Arc<Texture<R>>,
 }
@@ -46,7 +48,8 @@ impl<R: RendererRaw> BackgroundView<R> {
             Point2::from_vec(half_target_size + rect_size * 0.5),
         ));
 
-        let background_set = RenderSet::new(Projection::Pixels, vec!(background_batch));
+        let background_set = RenderSet::new(Projection::Pixels, vec!(background_batch))
+            .with_layer(LAYER_BACKGROUND);
         render_data.render_sets.push(background_set);
     }
 }
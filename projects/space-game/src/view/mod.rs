@@ -3,3 +3,7 @@ mod tile_structure;
 
 pub use self::background::{BackgroundView};
 pub use self::tile_structure::{TileStructureView};
+
+/// The layers views render on, so they can be rendered in any order.
+pub const LAYER_BACKGROUND: i32 = 0;
+pub const LAYER_TILES: i32 = 1;
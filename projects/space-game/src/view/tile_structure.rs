@@ -8,6 +8,7 @@ use calcium_rendering::{Renderer, Error};
 use calcium_rendering_2d::render_data::{RenderData, RenderBatch, ShaderMode, Rectangle, RenderSet, Projection, Camera, UvMode};
 
 use model::{TileStructure};
+use view::{LAYER_TILES};
 
 pub struct TileStructureView<R: RendererRaw> {
     texture: Arc<Texture<R>>,
@@ -42,7 +43,8 @@ impl<R: RendererRaw> TileStructureView<R> {
 
     pub fn render(
         &self,
-        structure: &TileStructure, render_data: &mut RenderData<R>
+        structure: &TileStructure, camera: &Camera, target_size: Vector2<u32>,
+        render_data: &mut RenderData<R>,
     ) {
         let mut tiles_batch = RenderBatch::new(
             ShaderMode::Texture(self.texture.clone()), UvMode::YUp
@@ -54,9 +56,18 @@ impl<R: RendererRaw> TileStructureView<R> {
             tile / self.tileset_tiles_amount.x,
         ).cast();
 
+        // Only render the tiles the camera can see
+        let bounds = camera.visible_bounds(target_size);
+        let size = structure.size();
+        let clamp = |value: f32, max: u32| (value.floor().max(0.0) as u32).min(max);
+        let start = Vector2::new(clamp(bounds.min.x, size.x), clamp(bounds.min.y, size.y));
+        let end = Vector2::new(
+            clamp(bounds.max.x + 1.0, size.x), clamp(bounds.max.y + 1.0, size.y),
+        );
+
         // Render the tiles
-        for y in 0..structure.size().y {
-            for x in 0..structure.size().x {
+        for y in start.y..end.y {
+            for x in start.x..end.x {
                 let tile_position = Point2::new(x, y).cast();
 
                 if !structure.tile_at(tile_position).unwrap().has_floor() {
@@ -85,8 +96,8 @@ impl<R: RendererRaw> TileStructureView<R> {
         }
 
         // Submit the rendering set
-        let camera = Camera::new(32.0 * 2.0, Point2::new(50.0, 50.0));
-        let background_set = RenderSet::new(Projection::Camera(camera), vec!(tiles_batch));
-        render_data.render_sets.push(background_set);
+        let tiles_set = RenderSet::new(Projection::Camera(camera.clone()), vec!(tiles_batch))
+            .with_layer(LAYER_TILES);
+        render_data.render_sets.push(tiles_set);
     }
 }